@@ -0,0 +1,83 @@
+//! How far the upstream forecast was from what happened. The archive keeps
+//! the forecast for each settlement period as it stood at several lead
+//! times, then the actual once it is published; comparing the two shows how
+//! much to trust a forecast made hours or days ahead.
+
+use crate::model::{FORECAST_HORIZON, IntensityPoint};
+use chrono::TimeDelta;
+use serde::Serialize;
+
+/// Start of each lead time bucket, in hours ahead of the period. The archive
+/// keeps the earliest forecast it saw in each, so buckets hold forecasts made
+/// from their start to the next one's, up to 48 hours.
+pub const LEAD_BUCKETS: [i64; 4] = [0, 6, 12, 24];
+
+/// The bucket a forecast made `lead` before its period belongs in, or `None`
+/// beyond 48 hours. Forecasts for the period already under way count as 0.
+pub fn lead_bucket(lead: TimeDelta) -> Option<i64> {
+    if lead >= FORECAST_HORIZON {
+        return None;
+    }
+    let hours = lead.num_hours().max(0);
+    LEAD_BUCKETS
+        .into_iter()
+        .rev()
+        .find(|&bucket| bucket <= hours)
+}
+
+/// A stored forecast and the actual intensity of its period
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ForecastOutcome {
+    /// The forecast's [`LEAD_BUCKETS`] entry
+    pub lead_hours: i64,
+    pub forecast: i32,
+    pub actual: i32,
+}
+
+/// Forecast error over a set of periods, in gCO₂/kWh
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub struct ForecastError {
+    pub periods: usize,
+    /// Mean absolute error
+    pub mae: f64,
+    /// Mean absolute percentage error, against the actual; periods with an
+    /// actual of 0 are left out of it. `None` if all of them were.
+    pub mape: Option<f64>,
+    /// Mean of forecast minus actual: positive when the forecast runs high
+    pub bias: f64,
+}
+
+impl ForecastError {
+    /// Error of `(forecast, actual)` pairs, or `None` for none
+    pub fn of(pairs: impl IntoIterator<Item = (i32, i32)>) -> Option<ForecastError> {
+        let (mut periods, mut absolute, mut signed) = (0usize, 0.0, 0.0);
+        let (mut percentage_periods, mut percentage) = (0usize, 0.0);
+        for (forecast, actual) in pairs {
+            let error = (forecast - actual) as f64;
+            periods += 1;
+            absolute += error.abs();
+            signed += error;
+            if actual != 0 {
+                percentage_periods += 1;
+                percentage += (error / actual as f64).abs() * 100.0;
+            }
+        }
+
+        (periods > 0).then(|| ForecastError {
+            periods,
+            mae: absolute / periods as f64,
+            mape: (percentage_periods > 0).then(|| percentage / percentage_periods as f64),
+            bias: signed / periods as f64,
+        })
+    }
+
+    /// Error of the forecast each point was published with, over the points
+    /// that also have an actual
+    pub fn of_points(points: &[IntensityPoint]) -> Option<ForecastError> {
+        ForecastError::of(
+            points
+                .iter()
+                .filter_map(|point| Some((point.forecast?, point.actual?))),
+        )
+    }
+}
@@ -0,0 +1,50 @@
+//! A national figure rolled up from the regions, as a cross-check on the
+//! reported one. The API publishes no regional demand to weight by, so it's
+//! a plain mean of the regions; regions with more demand pull the real
+//! national figure their way, which is why the two differ.
+
+use crate::model::RegionalSnapshot;
+
+/// Region ids that cover GB between them, without overlap. 15 to 17 are
+/// England, Scotland and Wales as a whole, so they're left out.
+const DNO_REGIONS: std::ops::RangeInclusive<u32> = 1..=14;
+
+/// The regions' national estimate beside the reported national figure, in
+/// gCO₂/kWh
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct NationalEstimate {
+    /// Mean of the regions' figures
+    pub estimate: f64,
+    pub reported: i32,
+    /// Regions with a figure, out of the 14
+    pub regions: usize,
+}
+
+impl NationalEstimate {
+    /// Roll `snapshot`'s regions up and compare with `reported`, or `None`
+    /// if no region has a figure
+    pub fn of(snapshot: &RegionalSnapshot, reported: i32) -> Option<NationalEstimate> {
+        let figures: Vec<i32> = snapshot
+            .regions
+            .iter()
+            .filter(|region| DNO_REGIONS.contains(&region.region_id))
+            .filter_map(|region| region.intensity.best())
+            .collect();
+        (!figures.is_empty()).then(|| NationalEstimate {
+            estimate: figures.iter().sum::<i32>() as f64 / figures.len() as f64,
+            reported,
+            regions: figures.len(),
+        })
+    }
+
+    /// Estimate minus reported: negative when the regions come out greener
+    pub fn difference(&self) -> f64 {
+        self.estimate - self.reported as f64
+    }
+
+    /// [`difference`](Self::difference) as a percentage of the reported
+    /// figure, or `None` if that's 0
+    pub fn difference_percent(&self) -> Option<f64> {
+        (self.reported != 0).then(|| self.difference() / self.reported as f64 * 100.0)
+    }
+}
@@ -0,0 +1,562 @@
+//! Alert rules for `carbon alert`: conditions on the intensity, its band and
+//! the generation mix, each routed to named channels. Rules are read from a
+//! TOML file:
+//!
+//! ```toml
+//! [channels.slack]
+//! type = "webhook"
+//! url = "https://hooks.slack.com/services/..."
+//! template = '{"text": "{{message}}"}'
+//!
+//! [[rules]]
+//! name = "Clean and sunny"
+//! when = 'index == "very low" and solar_perc > 20'
+//! channels = ["slack", "stdout"]
+//! ```
+//!
+//! Slack and Discord channels (`type = "slack"` or `"discord"` with the
+//! webhook's `url`) post the message with the band as a coloured square and
+//! a sparkline of the next six hours' bands.
+//!
+//! Conditions compare `intensity` (gCO₂/kWh), `index` (the band, ordered
+//! from "very low" to "very high") or `<fuel>_perc` (that fuel's share of
+//! generation, e.g. `wind_perc`) with `<`, `<=`, `>`, `>=`, `==` or `!=`.
+//! A comparison can be required to hold for several periods in a row
+//! (`intensity < 100 for 2 periods`), and comparisons are combined with `and`
+//! and `or`, `and` binding tighter.
+
+use crate::model::{FuelSource, IntensityIndex, IntensityPoint};
+use crate::webhook::fill_template;
+use chrono::{DateTime, SecondsFormat, TimeDelta, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, VecDeque};
+
+/// Channel every config has without declaring it: print to standard output
+pub const STDOUT_CHANNEL: &str = "stdout";
+
+/// Message used when a rule doesn't give its own
+const DEFAULT_MESSAGE: &str = "{{rule}}: grid is {{index}} at {{intensity}} gCO₂/kWh";
+
+/// Slack and Discord text used when the channel doesn't give its own
+pub const DEFAULT_CHAT_TEXT: &str = "{{emoji}} {{message}}\nNext 6 hours: {{sparkline}}";
+
+/// How far ahead the sparkline in Slack and Discord messages looks
+pub const CHAT_OUTLOOK: TimeDelta = TimeDelta::hours(6);
+
+/// Contents of the rules file
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AlertConfig {
+    #[serde(default)]
+    pub channels: BTreeMap<String, ChannelConfig>,
+    pub rules: Vec<RuleConfig>,
+}
+
+/// Where a rule's alerts go
+#[derive(Clone, Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase", deny_unknown_fields)]
+pub enum ChannelConfig {
+    /// Print the message
+    Stdout,
+    /// POST to a URL: `template` filled in as for messages plus
+    /// `{{message}}`, or the [`AlertEvent`] as JSON
+    Webhook {
+        url: String,
+        template: Option<String>,
+    },
+    /// Post to a Slack incoming webhook: `template` filled in as for
+    /// [`AlertEvent::render_chat`], by default the band's emoji, the message
+    /// and a sparkline of the next six hours
+    Slack {
+        url: String,
+        template: Option<String>,
+    },
+    /// Post to a Discord webhook, as for Slack
+    Discord {
+        url: String,
+        template: Option<String>,
+    },
+    /// Send an email through an SMTP server
+    Email {
+        server: String,
+        /// Defaults to 587 (STARTTLS), or 25 with `tls = false`
+        port: Option<u16>,
+        /// STARTTLS, on by default
+        #[serde(default = "default_true")]
+        tls: bool,
+        username: Option<String>,
+        /// Environment variable holding the password, so it stays out of the
+        /// file
+        password_env: Option<String>,
+        from: String,
+        to: Vec<String>,
+    },
+    /// Publish the [`AlertEvent`] as JSON to an MQTT topic
+    Mqtt {
+        #[serde(default = "default_broker")]
+        broker: String,
+        #[serde(default = "default_mqtt_port")]
+        port: u16,
+        topic: String,
+        username: Option<String>,
+        password_env: Option<String>,
+    },
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_broker() -> String {
+    "localhost".to_string()
+}
+
+fn default_mqtt_port() -> u16 {
+    1883
+}
+
+/// One `[[rules]]` entry
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RuleConfig {
+    pub name: String,
+    /// The condition, e.g. `intensity < 100 for 2 periods`
+    pub when: String,
+    /// Names from `[channels]`, or `stdout`
+    pub channels: Vec<String>,
+    /// Message template with `{{rule}}`, `{{intensity}}`, `{{index}}`,
+    /// `{{timestamp}}` and `{{region}}` placeholders
+    pub message: Option<String>,
+}
+
+impl AlertConfig {
+    /// Parse the file's contents and check every rule: its condition parses
+    /// and its channels exist
+    pub fn parse(contents: &str) -> Result<(AlertConfig, Vec<Rule>), String> {
+        let config: AlertConfig = toml::from_str(contents).map_err(|e| e.to_string())?;
+        if config.rules.is_empty() {
+            return Err("no [[rules]] defined".to_string());
+        }
+
+        let rules = config
+            .rules
+            .iter()
+            .map(|rule| {
+                let condition = rule
+                    .when
+                    .parse()
+                    .map_err(|e| format!("rule '{name}': {e}", name = rule.name))?;
+                if let Some(unknown) = rule.channels.iter().find(|channel| {
+                    *channel != STDOUT_CHANNEL && !config.channels.contains_key(*channel)
+                }) {
+                    return Err(format!(
+                        "rule '{name}': no channel named '{unknown}'",
+                        name = rule.name
+                    ));
+                }
+                Ok(Rule {
+                    config: rule.clone(),
+                    condition,
+                    holding: false,
+                })
+            })
+            .collect::<Result<_, String>>()?;
+
+        Ok((config, rules))
+    }
+}
+
+/// One period's figures, national or for a region
+#[derive(Clone, Debug, PartialEq)]
+pub struct Reading {
+    /// Start of the settlement period
+    pub from: DateTime<Utc>,
+    pub intensity: i32,
+    pub index: Option<IntensityIndex>,
+    pub generation_mix: Vec<FuelSource>,
+    /// Region short name, `None` for national
+    pub region: Option<String>,
+}
+
+impl Reading {
+    /// A fuel's share of generation; fuels not in the mix count as 0%
+    fn fuel_perc(&self, fuel: &str) -> f64 {
+        self.generation_mix
+            .iter()
+            .find(|source| source.fuel == fuel)
+            .map_or(0.0, |source| source.perc)
+    }
+}
+
+/// Recent readings, one per settlement period, newest last. Polling more
+/// than once a period replaces that period's reading rather than counting
+/// it twice towards `for N periods`.
+#[derive(Debug, Default)]
+pub struct History {
+    readings: VecDeque<Reading>,
+    capacity: usize,
+}
+
+impl History {
+    /// Enough history for the longest `for N periods` in `rules`
+    pub fn for_rules(rules: &[Rule]) -> History {
+        History {
+            readings: VecDeque::new(),
+            capacity: rules
+                .iter()
+                .map(|rule| rule.condition.periods())
+                .max()
+                .unwrap_or(1),
+        }
+    }
+
+    pub fn push(&mut self, reading: Reading) {
+        if self
+            .readings
+            .back()
+            .is_some_and(|last| last.from == reading.from)
+        {
+            self.readings.pop_back();
+        }
+        self.readings.push_back(reading);
+        while self.readings.len() > self.capacity {
+            self.readings.pop_front();
+        }
+    }
+
+    pub fn latest(&self) -> Option<&Reading> {
+        self.readings.back()
+    }
+}
+
+/// A rule with its parsed condition and whether it held last time
+#[derive(Clone, Debug)]
+pub struct Rule {
+    pub config: RuleConfig,
+    pub condition: Condition,
+    holding: bool,
+}
+
+impl Rule {
+    /// Check the rule against the history, returning the alert if its
+    /// condition has just started to hold. It fires once on the way in and
+    /// can fire again only after the condition has stopped holding.
+    pub fn update(&mut self, history: &History) -> Option<AlertEvent> {
+        let holds = self.condition.holds(history);
+        let fired = holds && !self.holding;
+        self.holding = holds;
+        if !fired {
+            return None;
+        }
+
+        let reading = history.latest()?;
+        let mut event = AlertEvent {
+            rule: self.config.name.clone(),
+            message: String::new(),
+            intensity: reading.intensity,
+            index: reading.index,
+            timestamp: reading.from,
+            region: reading.region.clone(),
+        };
+        event.message = event.render(self.config.message.as_deref().unwrap_or(DEFAULT_MESSAGE));
+        Some(event)
+    }
+}
+
+/// What channels are sent when a rule fires; the JSON body for webhook and
+/// MQTT channels
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct AlertEvent {
+    pub rule: String,
+    pub message: String,
+    pub intensity: i32,
+    pub index: Option<IntensityIndex>,
+    /// Start of the settlement period that triggered the rule
+    pub timestamp: DateTime<Utc>,
+    /// Region short name, `None` for national
+    pub region: Option<String>,
+}
+
+impl AlertEvent {
+    /// Fill a template's `{{rule}}`, `{{message}}`, `{{intensity}}`,
+    /// `{{index}}`, `{{timestamp}}` (RFC 3339) and `{{region}}` ("national"
+    /// when not regional) placeholders
+    pub fn render(&self, template: &str) -> String {
+        let values = [
+            ("rule", self.rule.clone()),
+            ("message", self.message.clone()),
+            ("intensity", self.intensity.to_string()),
+            (
+                "index",
+                self.index
+                    .map_or("unknown", IntensityIndex::as_str)
+                    .to_string(),
+            ),
+            (
+                "timestamp",
+                self.timestamp.to_rfc3339_opts(SecondsFormat::Secs, true),
+            ),
+            (
+                "region",
+                self.region
+                    .clone()
+                    .unwrap_or_else(|| "national".to_string()),
+            ),
+        ];
+        fill_template(template, &values)
+    }
+
+    /// Fill a Slack or Discord text template: [`render`](Self::render)'s
+    /// placeholders plus `{{emoji}}`, the band as a coloured square, and
+    /// `{{sparkline}}`, a square per period of `outlook`
+    pub fn render_chat(&self, template: &str, outlook: &[IntensityPoint]) -> String {
+        let values = [
+            ("emoji", band_emoji(self.index).to_string()),
+            ("sparkline", emoji_sparkline(outlook)),
+        ];
+        fill_template(&self.render(template), &values)
+    }
+}
+
+/// A coloured square for a band, green for very low through to purple for
+/// very high
+pub fn band_emoji(index: Option<IntensityIndex>) -> &'static str {
+    match index {
+        Some(IntensityIndex::VeryLow) => "🟩",
+        Some(IntensityIndex::Low) => "🟨",
+        Some(IntensityIndex::Moderate) => "🟧",
+        Some(IntensityIndex::High) => "🟥",
+        Some(IntensityIndex::VeryHigh) => "🟪",
+        None => "⬜",
+    }
+}
+
+/// One [`band_emoji`] per period, oldest first. Periods without a published
+/// band are banded by their intensity.
+pub fn emoji_sparkline(points: &[IntensityPoint]) -> String {
+    points
+        .iter()
+        .map(|point| {
+            let index = point
+                .index
+                .unwrap_or_else(|| IntensityIndex::for_intensity(point.intensity));
+            band_emoji(Some(index))
+        })
+        .collect()
+}
+
+/// A rule's condition: comparisons joined by `or`, each a group joined by
+/// `and`
+#[derive(Clone, Debug, PartialEq)]
+pub struct Condition {
+    any_of: Vec<Vec<Comparison>>,
+}
+
+impl Condition {
+    pub fn holds(&self, history: &History) -> bool {
+        self.any_of
+            .iter()
+            .any(|all_of| all_of.iter().all(|comparison| comparison.holds(history)))
+    }
+
+    /// The most periods any comparison needs
+    fn periods(&self) -> usize {
+        self.any_of
+            .iter()
+            .flatten()
+            .map(|comparison| comparison.periods)
+            .max()
+            .unwrap_or(1)
+    }
+}
+
+/// `metric op value`, optionally `for N periods`
+#[derive(Clone, Debug, PartialEq)]
+struct Comparison {
+    metric: Metric,
+    op: Op,
+    value: Value,
+    /// Consecutive periods the comparison must hold for, at least 1
+    periods: usize,
+}
+
+impl Comparison {
+    fn holds(&self, history: &History) -> bool {
+        let readings = &history.readings;
+        readings.len() >= self.periods
+            && readings
+                .iter()
+                .rev()
+                .take(self.periods)
+                .all(|reading| self.holds_for(reading))
+    }
+
+    fn holds_for(&self, reading: &Reading) -> bool {
+        match (&self.metric, &self.value) {
+            (Metric::Intensity, Value::Number(value)) => {
+                self.op.compare(&(reading.intensity as f64), value)
+            }
+            (Metric::FuelPerc(fuel), Value::Number(value)) => {
+                self.op.compare(&reading.fuel_perc(fuel), value)
+            }
+            (Metric::Index, Value::Index(value)) => reading
+                .index
+                .is_some_and(|index| self.op.compare(&index, value)),
+            // Ruled out when parsing
+            _ => false,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Metric {
+    Intensity,
+    Index,
+    /// Share of generation from this fuel, in percent
+    FuelPerc(String),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Op {
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+    Equal,
+    NotEqual,
+}
+
+impl Op {
+    fn compare<T: PartialOrd>(self, left: &T, right: &T) -> bool {
+        match self {
+            Op::Less => left < right,
+            Op::LessOrEqual => left <= right,
+            Op::Greater => left > right,
+            Op::GreaterOrEqual => left >= right,
+            Op::Equal => left == right,
+            Op::NotEqual => left != right,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Value {
+    Number(f64),
+    Index(IntensityIndex),
+}
+
+impl std::str::FromStr for Condition {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let tokens = tokenize(value)?;
+        let mut tokens = tokens.iter().map(String::as_str).peekable();
+
+        let mut any_of = Vec::new();
+        let mut all_of = Vec::new();
+        loop {
+            all_of.push(parse_comparison(&mut tokens)?);
+            match tokens.next().map(str::to_ascii_lowercase).as_deref() {
+                None => break,
+                Some("and") => {}
+                Some("or") => any_of.push(std::mem::take(&mut all_of)),
+                Some(other) => return Err(format!("expected 'and' or 'or', found '{other}'")),
+            }
+        }
+        any_of.push(all_of);
+
+        Ok(Condition { any_of })
+    }
+}
+
+/// One comparison from the front of `tokens`
+fn parse_comparison<'a>(
+    tokens: &mut std::iter::Peekable<impl Iterator<Item = &'a str>>,
+) -> Result<Comparison, String> {
+    let metric = match tokens.next() {
+        Some("intensity") => Metric::Intensity,
+        Some("index") => Metric::Index,
+        Some(name) => match name.strip_suffix("_perc") {
+            Some(fuel) if !fuel.is_empty() => Metric::FuelPerc(fuel.to_string()),
+            _ => {
+                return Err(format!(
+                    "unknown metric '{name}': expected intensity, index or <fuel>_perc"
+                ));
+            }
+        },
+        None => return Err("expected a comparison".to_string()),
+    };
+    let op = match tokens.next() {
+        Some("<") => Op::Less,
+        Some("<=") => Op::LessOrEqual,
+        Some(">") => Op::Greater,
+        Some(">=") => Op::GreaterOrEqual,
+        Some("==") => Op::Equal,
+        Some("!=") => Op::NotEqual,
+        Some(other) => return Err(format!("unknown operator '{other}'")),
+        None => return Err("expected an operator".to_string()),
+    };
+    let value = tokens.next().ok_or("expected a value")?;
+    let value = match metric {
+        Metric::Index => Value::Index(value.parse()?),
+        _ => Value::Number(
+            value
+                .trim_end_matches('%')
+                .parse()
+                .map_err(|_| format!("expected a number, found '{value}'"))?,
+        ),
+    };
+
+    let mut periods = 1;
+    if tokens
+        .next_if(|token| token.eq_ignore_ascii_case("for"))
+        .is_some()
+    {
+        periods = tokens
+            .next()
+            .and_then(|count| count.parse().ok())
+            .filter(|&count| count > 0)
+            .ok_or("expected a number of periods after 'for'")?;
+        tokens.next_if(|token| matches!(*token, "period" | "periods"));
+    }
+
+    Ok(Comparison {
+        metric,
+        op,
+        value,
+        periods,
+    })
+}
+
+/// Split a condition into words, operators and quoted strings (quotes
+/// removed)
+fn tokenize(value: &str) -> Result<Vec<String>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = value.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c == '"' || c == '\'' {
+            chars.next();
+            let quoted: String = chars.by_ref().take_while(|&next| next != c).collect();
+            tokens.push(quoted);
+        } else if "<>=!".contains(c) {
+            let mut op = String::new();
+            while let Some(next) = chars.next_if(|next| "<>=!".contains(*next)) {
+                op.push(next);
+            }
+            tokens.push(op);
+        } else if c.is_alphanumeric() || "_.%-".contains(c) {
+            let mut word = String::new();
+            while let Some(next) =
+                chars.next_if(|next| next.is_alphanumeric() || "_.%-".contains(*next))
+            {
+                word.push(next);
+            }
+            tokens.push(word);
+        } else {
+            return Err(format!("unexpected '{c}'"));
+        }
+    }
+    Ok(tokens)
+}
@@ -0,0 +1,45 @@
+//! Shields.io-style SVG badges, for embedding live grid status in READMEs and
+//! wikis
+
+use crate::chart::escape_xml;
+
+// Badge colours, as shields.io uses for the label and for a missing value
+const BADGE_LABEL: &str = "#555";
+pub const BADGE_UNAVAILABLE: &str = "#9f9f9f";
+
+// Average advance of the badge's 11px Verdana, in pixels
+const BADGE_CHAR_WIDTH: f64 = 7.0;
+
+/// Flat two-part badge in the shields.io layout: grey label on the left,
+/// coloured message on the right
+pub fn render_badge(label: &str, message: &str, color: &str) -> String {
+    // Text is measured roughly, as the badge font's average advance plus
+    // padding; shields.io does the same with real glyph widths
+    let text_width = |text: &str| text.chars().count() as f64 * BADGE_CHAR_WIDTH + 10.0;
+    let label_width = text_width(label);
+    let message_width = text_width(message);
+    let width = label_width + message_width;
+    let (label, message) = (escape_xml(label), escape_xml(message));
+
+    format!(
+        r##"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="20" role="img" aria-label="{label}: {message}">
+    <title>{label}: {message}</title>
+    <linearGradient id="s" x2="0" y2="100%"><stop offset="0" stop-color="#bbb" stop-opacity=".1"/><stop offset="1" stop-opacity=".1"/></linearGradient>
+    <clipPath id="r"><rect width="{width}" height="20" rx="3" fill="#fff"/></clipPath>
+    <g clip-path="url(#r)">
+        <rect width="{label_width}" height="20" fill="{label_color}"/>
+        <rect x="{label_width}" width="{message_width}" height="20" fill="{color}"/>
+        <rect width="{width}" height="20" fill="url(#s)"/>
+    </g>
+    <g fill="#fff" text-anchor="middle" font-family="Verdana,Geneva,DejaVu Sans,sans-serif" font-size="11">
+        <text x="{label_x}" y="15" fill="#010101" fill-opacity=".3">{label}</text>
+        <text x="{label_x}" y="14">{label}</text>
+        <text x="{message_x}" y="15" fill="#010101" fill-opacity=".3">{message}</text>
+        <text x="{message_x}" y="14">{message}</text>
+    </g>
+</svg>"##,
+        label_color = BADGE_LABEL,
+        label_x = label_width / 2.0,
+        message_x = label_width + message_width / 2.0
+    )
+}
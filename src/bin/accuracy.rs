@@ -0,0 +1,10 @@
+//! Standalone `accuracy` binary, the same as `carbon accuracy`
+
+use carbon_vibe::cli::{self, accuracy::AccuracyOptions};
+
+#[tokio::main]
+async fn main() {
+    let options: AccuracyOptions = cli::parse_args("accuracy");
+    cli::init_tracing();
+    cli::exit_on_error(cli::accuracy::run(options).await);
+}
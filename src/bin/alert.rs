@@ -0,0 +1,10 @@
+//! Standalone `alert` binary, the same as `carbon alert`
+
+use carbon_vibe::cli::{self, alert::AlertOptions};
+
+#[tokio::main]
+async fn main() {
+    let options: AlertOptions = cli::parse_args("alert");
+    cli::init_tracing();
+    cli::exit_on_error(cli::alert::run(options).await);
+}
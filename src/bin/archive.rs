@@ -0,0 +1,10 @@
+//! Standalone `archive` binary, the same as `carbon archive`
+
+use carbon_vibe::cli::{self, archive::ArchiveOptions};
+
+#[tokio::main]
+async fn main() {
+    let options: ArchiveOptions = cli::parse_args("archive");
+    cli::init_tracing();
+    cli::exit_on_error(cli::archive::run(options).await);
+}
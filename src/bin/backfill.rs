@@ -0,0 +1,10 @@
+//! Standalone `backfill` binary, the same as `carbon backfill`
+
+use carbon_vibe::cli::{self, backfill::BackfillOptions};
+
+#[tokio::main]
+async fn main() {
+    let options: BackfillOptions = cli::parse_args("backfill");
+    cli::init_tracing();
+    cli::exit_on_error(cli::backfill::run(options).await);
+}
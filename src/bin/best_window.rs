@@ -0,0 +1,10 @@
+//! Standalone `best-window` binary, the same as `carbon best-window`
+
+use carbon_vibe::cli::{self, best_window::BestWindowOptions};
+
+#[tokio::main]
+async fn main() {
+    let options: BestWindowOptions = cli::parse_args("best-window");
+    cli::init_tracing();
+    cli::exit_on_error(cli::best_window::run(options).await);
+}
@@ -0,0 +1,10 @@
+//! Standalone `calendar` binary, the same as `carbon calendar`
+
+use carbon_vibe::cli::{self, calendar::CalendarOptions};
+
+#[tokio::main]
+async fn main() {
+    let options: CalendarOptions = cli::parse_args("calendar");
+    cli::init_tracing();
+    cli::exit_on_error(cli::calendar::run(options).await);
+}
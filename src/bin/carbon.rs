@@ -0,0 +1,10 @@
+//! `carbon`: every command-line tool as one binary with subcommands
+
+use carbon_vibe::cli;
+
+#[tokio::main]
+async fn main() {
+    let cli = cli::parse_cli();
+    cli::init_tracing();
+    cli::exit_on_error(cli.command.run().await);
+}
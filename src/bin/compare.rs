@@ -0,0 +1,10 @@
+//! Standalone `compare` binary, the same as `carbon compare`
+
+use carbon_vibe::cli::{self, compare::CompareOptions};
+
+#[tokio::main]
+async fn main() {
+    let options: CompareOptions = cli::parse_args("compare");
+    cli::init_tracing();
+    cli::exit_on_error(cli::compare::run(options).await);
+}
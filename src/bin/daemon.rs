@@ -0,0 +1,10 @@
+//! Standalone `daemon` binary, the same as `carbon daemon`
+
+use carbon_vibe::cli::{self, daemon::DaemonOptions};
+
+#[tokio::main]
+async fn main() {
+    let options: DaemonOptions = cli::parse_args("daemon");
+    cli::init_tracing();
+    cli::exit_on_error(cli::daemon::run(options).await);
+}
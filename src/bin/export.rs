@@ -0,0 +1,10 @@
+//! Standalone `export` binary, the same as `carbon export`
+
+use carbon_vibe::cli::{self, export::ExportOptions};
+
+#[tokio::main]
+async fn main() {
+    let options: ExportOptions = cli::parse_args("export");
+    cli::init_tracing();
+    cli::exit_on_error(cli::export::run(options).await);
+}
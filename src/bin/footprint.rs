@@ -0,0 +1,10 @@
+//! Standalone `footprint` binary, the same as `carbon footprint`
+
+use carbon_vibe::cli::{self, footprint::FootprintOptions};
+
+#[tokio::main]
+async fn main() {
+    let options: FootprintOptions = cli::parse_args("footprint");
+    cli::init_tracing();
+    cli::exit_on_error(cli::footprint::run(options).await);
+}
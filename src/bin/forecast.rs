@@ -0,0 +1,10 @@
+//! Standalone `forecast` binary, the same as `carbon forecast`
+
+use carbon_vibe::cli::{self, forecast::ForecastOptions};
+
+#[tokio::main]
+async fn main() {
+    let options: ForecastOptions = cli::parse_args("forecast");
+    cli::init_tracing();
+    cli::exit_on_error(cli::forecast::run(options).await);
+}
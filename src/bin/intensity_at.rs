@@ -0,0 +1,10 @@
+//! Standalone `intensity-at` binary, the same as `carbon intensity-at`
+
+use carbon_vibe::cli::{self, intensity_at::IntensityAtOptions};
+
+#[tokio::main]
+async fn main() {
+    let options: IntensityAtOptions = cli::parse_args("intensity-at");
+    cli::init_tracing();
+    cli::exit_on_error(cli::intensity_at::run(options).await);
+}
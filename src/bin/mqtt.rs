@@ -0,0 +1,10 @@
+//! Standalone `mqtt` binary, the same as `carbon mqtt`
+
+use carbon_vibe::cli::{self, mqtt::MqttOptions};
+
+#[tokio::main]
+async fn main() {
+    let options: MqttOptions = cli::parse_args("mqtt");
+    cli::init_tracing();
+    cli::exit_on_error(cli::mqtt::run(options).await);
+}
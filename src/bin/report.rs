@@ -0,0 +1,10 @@
+//! Standalone `report` binary, the same as `carbon report`
+
+use carbon_vibe::cli::{self, report::ReportOptions};
+
+#[tokio::main]
+async fn main() {
+    let options: ReportOptions = cli::parse_args("report");
+    cli::init_tracing();
+    cli::exit_on_error(cli::report::run(options).await);
+}
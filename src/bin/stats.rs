@@ -0,0 +1,10 @@
+//! Standalone `stats` binary, the same as `carbon stats`
+
+use carbon_vibe::cli::{self, stats::StatsOptions};
+
+#[tokio::main]
+async fn main() {
+    let options: StatsOptions = cli::parse_args("stats");
+    cli::init_tracing();
+    cli::exit_on_error(cli::stats::run(options).await);
+}
@@ -0,0 +1,10 @@
+//! Standalone `today` binary, the same as `carbon today`
+
+use carbon_vibe::cli::{self, today::TodayOptions};
+
+#[tokio::main]
+async fn main() {
+    let options: TodayOptions = cli::parse_args("today");
+    cli::init_tracing();
+    cli::exit_on_error(cli::today::run(options).await);
+}
@@ -0,0 +1,10 @@
+//! Standalone `tui` binary, the same as `carbon tui`
+
+use carbon_vibe::cli::{self, tui::TuiOptions};
+
+#[tokio::main]
+async fn main() {
+    let options: TuiOptions = cli::parse_args("tui");
+    cli::init_tracing();
+    cli::exit_on_error(cli::tui::run(options).await);
+}
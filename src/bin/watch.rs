@@ -0,0 +1,10 @@
+//! Standalone `watch` binary, the same as `carbon watch`
+
+use carbon_vibe::cli::{self, watch::WatchOptions};
+
+#[tokio::main]
+async fn main() {
+    let options: WatchOptions = cli::parse_args("watch");
+    cli::init_tracing();
+    cli::exit_on_error(cli::watch::run(options).await);
+}
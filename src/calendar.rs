@@ -0,0 +1,144 @@
+//! iCalendar (RFC 5545) export of forecast low-carbon windows, for
+//! `carbon calendar` and the dashboard's `/calendar.ics`, so "green hours"
+//! can be overlaid on an ordinary calendar
+
+use crate::model::IntensityPoint;
+use crate::schedule::Window;
+use crate::units;
+use chrono::{DateTime, Utc};
+
+/// Threshold in gCO₂/kWh used when none is given
+pub const DEFAULT_BELOW: i32 = 100;
+
+/// Longest content line in octets before it's folded onto the next
+const MAX_LINE_OCTETS: usize = 75;
+
+/// Each run of consecutive periods with intensity below `below`, oldest
+/// first. `points` must be oldest first; a gap in the data ends a window.
+pub fn low_carbon_windows(points: &[IntensityPoint], below: i32) -> Vec<Window> {
+    let mut windows = Vec::new();
+    let mut run: Vec<&IntensityPoint> = Vec::new();
+    for point in points {
+        let continues = run.last().is_some_and(|last| last.to == point.from);
+        if point.intensity >= below || !continues {
+            windows.extend(window(&run));
+            run.clear();
+        }
+        if point.intensity < below {
+            run.push(point);
+        }
+    }
+    windows.extend(window(&run));
+    windows
+}
+
+fn window(run: &[&IntensityPoint]) -> Option<Window> {
+    let (first, last) = (run.first()?, run.last()?);
+    Some(Window {
+        from: first.from,
+        to: last.to,
+        average: run.iter().map(|p| p.intensity as f64).sum::<f64>() / run.len() as f64,
+    })
+}
+
+/// A calendar with an event per window. Events are marked free so they
+/// don't block out time, and their UIDs depend only on start time, threshold
+/// and area, so a re-import or refreshed subscription updates them in place.
+pub fn to_ics(
+    windows: &[Window],
+    below: i32,
+    area: Option<&str>,
+    generated: DateTime<Utc>,
+) -> String {
+    let area_name = area.unwrap_or("GB");
+    let uid_area: String = area_name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_lowercase()
+            } else {
+                '-'
+            }
+        })
+        .collect();
+
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        "PRODID:-//carbon-vibe//Low-carbon windows//EN".to_string(),
+        "CALSCALE:GREGORIAN".to_string(),
+        "METHOD:PUBLISH".to_string(),
+        format!(
+            "X-WR-CALNAME:{name}",
+            name = escape_text(&format!("Green hours ({area_name})"))
+        ),
+    ];
+    for window in windows {
+        lines.extend([
+            "BEGIN:VEVENT".to_string(),
+            format!(
+                "UID:{start}-below-{below}-{uid_area}@carbon-vibe",
+                start = ics_time(window.from)
+            ),
+            format!("DTSTAMP:{stamp}", stamp = ics_time(generated)),
+            format!("DTSTART:{start}", start = ics_time(window.from)),
+            format!("DTEND:{end}", end = ics_time(window.to)),
+            format!(
+                "SUMMARY:{summary}",
+                summary = escape_text(&format!(
+                    "Green hours: {average} {unit}",
+                    average = units::figure(window.average),
+                    unit = units::label()
+                ))
+            ),
+            format!(
+                "DESCRIPTION:{description}",
+                description = escape_text(&format!(
+                    "{area_name} grid forecast below {limit} {unit} throughout, averaging {average}.",
+                    limit = units::figure(below),
+                    unit = units::label(),
+                    average = units::figure(window.average)
+                ))
+            ),
+            "TRANSP:TRANSPARENT".to_string(),
+            "END:VEVENT".to_string(),
+        ]);
+    }
+    lines.push("END:VCALENDAR".to_string());
+
+    lines
+        .iter()
+        .map(|line| format!("{line}\r\n", line = fold(line)))
+        .collect()
+}
+
+/// UTC date-time in the iCalendar basic format, e.g. 20250626T133000Z
+fn ics_time(time: DateTime<Utc>) -> String {
+    time.format("%Y%m%dT%H%M%SZ").to_string()
+}
+
+/// Escape a TEXT value's backslashes, separators and newlines
+fn escape_text(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+/// Split a content line longer than 75 octets, continuing each fold with a
+/// space and never splitting a UTF-8 character
+fn fold(line: &str) -> String {
+    let mut folded = String::with_capacity(line.len());
+    let mut octets = 0;
+    for c in line.chars() {
+        if octets + c.len_utf8() > MAX_LINE_OCTETS {
+            folded.push_str("\r\n ");
+            // The leading space counts towards the continuation line
+            octets = 1;
+        }
+        folded.push(c);
+        octets += c.len_utf8();
+    }
+    folded
+}
@@ -0,0 +1,861 @@
+//! SVG charts for the web dashboard: the intensity timeline, the generation
+//! mix pie, treemap and stacked history, and the legend's sparklines. Colours are
+//! CSS custom properties (`var(--grid)`) from [`crate::theme`], resolved by
+//! the page or, for rasterised images, by [`crate::theme::Theme::resolve`].
+//! Times are labelled in the display zone and intensities in the display
+//! unit.
+
+use crate::accuracy::ForecastError;
+use crate::cli::timezone::local;
+use crate::mix::{FuelSourceWithIntensity, UNKNOWN_FUEL};
+use crate::model::{GenerationMixPeriod, IntensityIndex, IntensityPoint};
+use crate::theme::{UNKNOWN_FUEL_COLOR, fuel_color};
+use crate::units;
+use chrono::{DateTime, TimeDelta, Utc};
+
+// Legend sparkline size in pixels
+pub const SPARKLINE_WIDTH: f64 = 60.0;
+pub const SPARKLINE_HEIGHT: f64 = 16.0;
+
+// How much of the generation mix history the legend sparklines cover
+pub const SPARKLINE_HISTORY: TimeDelta = TimeDelta::hours(12);
+
+// The dashboard's timeline chart, in pixels
+pub const CHART_SIZE: (f64, f64) = (500.0, 180.0);
+
+// `/treemap.svg`'s default size, in pixels
+pub const TREEMAP_SIZE: (f64, f64) = (500.0, 300.0);
+
+// Room a treemap label needs: about 7px a character at 11px bold, on two
+// lines, inside a 6px margin
+const TREEMAP_LABEL_CHAR_WIDTH: f64 = 7.0;
+const TREEMAP_LABEL_HEIGHT: f64 = 34.0;
+const TREEMAP_LABEL_MARGIN: f64 = 6.0;
+
+// Roughly one x-axis label every 2 hours on the 24-hour chart
+const X_AXIS_TARGET_TICKS: usize = 12;
+
+/// Centred, triangular-weighted moving average over `window` points: each
+/// point counts for more the nearer it is to the middle, e.g. 1, 2, 3, 2, 1
+/// for a window of 5, so the trend follows turns sooner than a plain mean
+/// without jumping at each period. An even window reaches one point further
+/// ahead than back. Near the ends of the series the weights that fall off
+/// the end are dropped and the rest renormalised, so the output has the
+/// same length as the input and the trend line spans the whole chart.
+pub fn moving_average(values: &[i32], window: usize) -> Vec<f64> {
+    if window == 0 {
+        return Vec::new();
+    }
+
+    let half_before = (window - 1) / 2;
+    let half_after = window / 2;
+    // The middle point's weight; each step away counts one less
+    let peak = half_after + 1;
+
+    (0..values.len())
+        .map(|i| {
+            let start = i.saturating_sub(half_before);
+            let end = (i + half_after + 1).min(values.len());
+            let (sum, weights) = (start..end).fold((0.0, 0.0), |(sum, weights), j| {
+                let weight = (peak - i.abs_diff(j)) as f64;
+                (sum + weight * values[j] as f64, weights + weight)
+            });
+            sum / weights
+        })
+        .collect()
+}
+
+/// X-axis ticks for the timeline: `(position, label)` pairs where position is
+/// the fraction (0.0-1.0) along the axis. Roughly `target_count` ticks are
+/// placed on evenly spaced points and labelled with that point's own time, so
+/// labels always agree with the data whatever the window length. Windows
+/// longer than a day include the weekday in the label.
+pub fn x_axis_ticks(points: &[DateTime<Utc>], target_count: usize) -> Vec<(f64, String)> {
+    if points.is_empty() || target_count == 0 {
+        return Vec::new();
+    }
+
+    let span = points[points.len() - 1] - points[0];
+    let label_format = if span > chrono::Duration::hours(24) {
+        "%a %H:%M"
+    } else {
+        "%H:%M"
+    };
+
+    let step = points.len().div_ceil(target_count).max(1);
+    let last_index = (points.len() - 1).max(1) as f64;
+
+    (0..points.len())
+        .step_by(step)
+        .map(|i| {
+            (
+                i as f64 / last_index,
+                local(points[i]).format(label_format).to_string(),
+            )
+        })
+        .collect()
+}
+
+/// The timeline's vertical scale: the data's range, or a fixed domain from
+/// `--y-min`/`--y-max` so charts drawn at different times can share a
+/// scale, mapped onto `height` pixels below `top`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct YScale {
+    pub min: f64,
+    pub max: f64,
+    top: f64,
+    height: f64,
+}
+
+impl YScale {
+    /// `None` when the domain is empty, e.g. a flat series without a fixed
+    /// bound, or `y_min` at or above `y_max`
+    pub fn new(values: &[i32], y_min: Option<f64>, y_max: Option<f64>, top: f64, height: f64) -> Option<Self> {
+        let min = y_min.unwrap_or(*values.iter().min().unwrap_or(&0) as f64);
+        let max = y_max.unwrap_or(*values.iter().max().unwrap_or(&100) as f64);
+        (max > min).then_some(YScale { min, max, top, height })
+    }
+
+    /// Where `value` sits, with values outside a fixed domain clamped to the
+    /// chart edge
+    pub fn y(&self, value: f64) -> f64 {
+        self.unclamped_y(value.clamp(self.min, self.max))
+    }
+
+    /// As [`YScale::y`] but free to fall outside the chart, for axis labels
+    /// rounded past the domain
+    pub fn unclamped_y(&self, value: f64) -> f64 {
+        self.top + self.height - (value - self.min) / (self.max - self.min) * self.height
+    }
+}
+
+pub fn render_intensity_chart(
+    timeline_points: &[IntensityPoint],
+    moving_average_window: usize,
+    y_min: Option<f64>,
+    y_max: Option<f64>,
+    (width, height): (f64, f64),
+) -> String {
+    if timeline_points.is_empty() {
+        return String::new();
+    }
+
+    let margin_left = 50.0;
+    let margin_right = 20.0;
+    let margin_top = 20.0;
+    let margin_bottom = 40.0;
+    let chart_width = width - margin_left - margin_right;
+    let chart_height = height - margin_top - margin_bottom;
+
+    let intensities: Vec<i32> = timeline_points.iter().map(|p| p.intensity).collect();
+    let Some(scale) = YScale::new(&intensities, y_min, y_max, margin_top, chart_height) else {
+        return String::new();
+    };
+    let (min_intensity, max_intensity) = (scale.min, scale.max);
+
+    // Generate path data
+    let mut path_data = String::new();
+    let mut forecast_path_data = String::new();
+
+    for (i, point) in timeline_points.iter().enumerate() {
+        let x = margin_left + (i as f64 / (timeline_points.len() - 1) as f64) * chart_width;
+        let y = scale.y(point.intensity as f64);
+
+        if i == 0 {
+            if point.is_forecast() {
+                forecast_path_data = format!("M {x} {y}", x = x, y = y);
+            } else {
+                path_data = format!("M {x} {y}", x = x, y = y);
+            }
+        } else if point.is_forecast() {
+            if forecast_path_data.is_empty() {
+                // Start forecast path from last historical point
+                if let Some(prev_point) = timeline_points.get(i - 1) {
+                    let prev_x = margin_left
+                        + ((i - 1) as f64 / (timeline_points.len() - 1) as f64) * chart_width;
+                    let prev_y = scale.y(prev_point.intensity as f64);
+                    forecast_path_data = format!("M {prev_x} {prev_y} L {x} {y}", prev_x = prev_x, prev_y = prev_y, x = x, y = y);
+                } else {
+                    forecast_path_data = format!("M {x} {y}", x = x, y = y);
+                }
+            } else {
+                forecast_path_data.push_str(&format!(" L {x} {y}", x = x, y = y));
+            }
+        } else {
+            path_data.push_str(&format!(" L {x} {y}", x = x, y = y));
+        }
+    }
+
+    // What was forecast for periods that now have an actual, dotted over the
+    // actual line so misses show, with the error over them in the corner
+    let mut published_forecast_path_data = String::new();
+    let mut previous_had_forecast = false;
+    for (i, point) in timeline_points.iter().enumerate() {
+        let (Some(forecast), Some(_)) = (point.forecast, point.actual) else {
+            previous_had_forecast = false;
+            continue;
+        };
+        let x = margin_left + (i as f64 / (timeline_points.len() - 1) as f64) * chart_width;
+        let y = scale.y(forecast as f64);
+        let command = if previous_had_forecast { " L" } else { " M" };
+        published_forecast_path_data.push_str(&format!("{command} {x} {y}", command = command, x = x, y = y));
+        previous_had_forecast = true;
+    }
+    let forecast_error = ForecastError::of_points(timeline_points)
+        .map(|error| {
+            format!(
+                "<text class=\"forecast-error\" x=\"{x}\" y=\"{y}\" font-family=\"Arial, sans-serif\" font-size=\"10\" style=\"fill: var(--axis-label)\">Forecast off by {mae} {unit} on average<title>Mean absolute error of the forecast over {periods} periods with actuals{mape}</title></text>",
+                x = margin_left,
+                y = margin_top - 6.0,
+                mae = units::figure(error.mae),
+                unit = units::label(),
+                periods = error.periods,
+                mape = error.mape.map(|mape| format!(" ({mape:.1}%)")).unwrap_or_default()
+            )
+        })
+        .unwrap_or_default();
+
+    // Moving average overlay (only worth drawing when it actually smooths)
+    let mut moving_average_path_data = String::new();
+    if moving_average_window > 1 {
+        for (i, average) in moving_average(&intensities, moving_average_window)
+            .into_iter()
+            .enumerate()
+        {
+            let x = margin_left + (i as f64 / (timeline_points.len() - 1) as f64) * chart_width;
+            let y = scale.y(average);
+            let command = if i == 0 { "M" } else { " L" };
+            moving_average_path_data.push_str(&format!("{command} {x} {y}", command = command, x = x, y = y));
+        }
+    }
+
+    // Background shaded by band, clipped to the plotted range
+    let mut band_shading = String::new();
+    for (i, band) in IntensityIndex::ALL.into_iter().enumerate() {
+        let lower = (band.lower_bound() as f64).max(min_intensity);
+        let upper = IntensityIndex::ALL
+            .get(i + 1)
+            .map_or(max_intensity, |next| (next.lower_bound() as f64).min(max_intensity));
+        if upper <= lower {
+            continue;
+        }
+        let (top, bottom) = (scale.y(upper), scale.y(lower));
+        band_shading.push_str(&format!(
+            "<rect x=\"{x}\" y=\"{y}\" width=\"{width}\" height=\"{height}\" style=\"fill: var(--band-{band})\" opacity=\"0.12\"><title>{name} ({lower}–{upper} {unit})</title></rect>",
+            x = margin_left,
+            y = top,
+            width = chart_width,
+            height = bottom - top,
+            band = band.as_str().replace(' ', "-"),
+            name = band,
+            lower = units::figure(lower),
+            upper = units::figure(upper),
+            unit = units::label()
+        ));
+    }
+
+    // Invisible hover targets on each point, so the browser shows its time
+    // and value as a tooltip
+    let mut point_targets = String::new();
+    for (i, point) in timeline_points.iter().enumerate() {
+        let x = margin_left + (i as f64 / (timeline_points.len() - 1) as f64) * chart_width;
+        let y = scale.y(point.intensity as f64);
+        point_targets.push_str(&format!(
+            "<circle cx=\"{x}\" cy=\"{y}\" r=\"5\" fill=\"transparent\"><title>{from}–{to}: {intensity} {unit}{kind}</title></circle>",
+            x = x,
+            y = y,
+            from = local(point.from).format("%H:%M"),
+            to = local(point.to).format("%H:%M"),
+            intensity = units::figure(point.intensity),
+            unit = units::label(),
+            kind = match (point.actual, point.forecast) {
+                (Some(_), Some(forecast)) => format!(" (forecast {forecast})", forecast = units::figure(forecast)),
+                _ if point.is_forecast() => " (forecast)".to_string(),
+                _ => String::new(),
+            }
+        ));
+    }
+
+    // Find current time marker
+    let now = chrono::Utc::now();
+    let current_index = timeline_points
+        .iter()
+        .position(|p| p.from > now)
+        .unwrap_or(timeline_points.len() / 2);
+
+    let current_x =
+        margin_left + (current_index as f64 / (timeline_points.len() - 1) as f64) * chart_width;
+
+    // Dot and label on the period containing now, when the timeline covers it
+    let current_value = timeline_points
+        .iter()
+        .position(|p| p.from <= now && now < p.to)
+        .map(|i| {
+            let point = &timeline_points[i];
+            let x = margin_left + (i as f64 / (timeline_points.len() - 1) as f64) * chart_width;
+            let y = scale.y(point.intensity as f64);
+            // Label on whichever side of the dot has room
+            let (label_x, anchor) = if x > margin_left + chart_width / 2.0 {
+                (x - 8.0, "end")
+            } else {
+                (x + 8.0, "start")
+            };
+            format!(
+                "<circle class=\"current-value\" cx=\"{x}\" cy=\"{y}\" r=\"4\" style=\"fill: var(--now-marker); stroke: var(--chart-background)\" stroke-width=\"1.5\"><title>Now: {intensity} {unit}</title></circle>
+            <text x=\"{label_x}\" y=\"{label_y}\" font-family=\"Arial, sans-serif\" font-size=\"11\" font-weight=\"bold\" style=\"fill: var(--axis-title)\" text-anchor=\"{anchor}\">{intensity}</text>",
+                x = x,
+                y = y,
+                label_x = label_x,
+                label_y = (y - 6.0).max(margin_top + 10.0),
+                anchor = anchor,
+                intensity = units::figure(point.intensity),
+                unit = units::label()
+            )
+        })
+        .unwrap_or_default();
+
+    // Calculate Y-axis labels (every 20 units, rounded), stepping through
+    // round figures in the display unit
+    let factor = units::display_unit().factor();
+    let y_step = ((max_intensity - min_intensity) * factor / 4.0).ceil().max(20.0);
+    let y_start = (min_intensity * factor / y_step).floor() * y_step;
+    let y_end = (max_intensity * factor / y_step).ceil() * y_step;
+
+    // Generate Y-axis labels
+    let mut y_labels = String::new();
+    let mut y_grid_lines = String::new();
+    let mut current_y_value = y_start;
+    while current_y_value <= y_end {
+        let y_pos = scale.unclamped_y(current_y_value / factor);
+
+        // Y-axis label
+        y_labels.push_str(&format!(
+            "<text x=\"{x}\" y=\"{y}\" font-family=\"Arial, sans-serif\" font-size=\"10\" style=\"fill: var(--axis-label)\" text-anchor=\"end\">{value}</text>",
+            x = margin_left - 5.0,
+            y = y_pos + 3.0,
+            value = current_y_value as i32
+        ));
+
+        // Horizontal grid line
+        y_grid_lines.push_str(&format!(
+            "<line x1=\"{x1}\" y1=\"{y1}\" x2=\"{x2}\" y2=\"{y2}\" style=\"stroke: var(--grid)\" stroke-width=\"1\"/>",
+            x1 = margin_left,
+            y1 = y_pos,
+            x2 = margin_left + chart_width,
+            y2 = y_pos
+        ));
+
+        current_y_value += y_step;
+    }
+
+    // Generate X-axis markers from the points' own timestamps
+    let mut x_labels = String::new();
+    let mut x_grid_lines = String::new();
+
+    let times: Vec<DateTime<Utc>> = timeline_points.iter().map(|p| p.from).collect();
+    for (position, time_label) in x_axis_ticks(&times, X_AXIS_TARGET_TICKS) {
+        let x_pos = margin_left + position * chart_width;
+
+        // X-axis label
+        x_labels.push_str(&format!(
+            "<text x=\"{x}\" y=\"{y}\" font-family=\"Arial, sans-serif\" font-size=\"9\" style=\"fill: var(--axis-label)\" text-anchor=\"middle\">{time_label}</text>",
+            x = x_pos,
+            y = height - 5.0,
+            time_label = time_label
+        ));
+
+        // Vertical grid line
+        x_grid_lines.push_str(&format!(
+            "<line x1=\"{x1}\" y1=\"{y1}\" x2=\"{x2}\" y2=\"{y2}\" style=\"stroke: var(--grid)\" stroke-width=\"1\" opacity=\"0.5\"/>",
+            x1 = x_pos,
+            y1 = margin_top,
+            x2 = x_pos,
+            y2 = margin_top + chart_height
+        ));
+    }
+
+    format!(
+        "<svg width=\"{width}\" height=\"{height}\" viewBox=\"0 0 {width} {height}\">
+            <!-- Background -->
+            <rect x=\"0\" y=\"0\" width=\"{width}\" height=\"{height}\" style=\"fill: var(--chart-background)\" rx=\"5\"/>
+            
+            <!-- Chart area -->
+            <rect x=\"{chart_x}\" y=\"{chart_y}\" width=\"{chart_width}\" height=\"{chart_height}\" style=\"fill: var(--plot-background); stroke: var(--plot-border)\" stroke-width=\"1\"/>
+            
+            <!-- Intensity bands -->
+            {band_shading}
+            
+            <!-- Grid lines -->
+            {y_grid_lines}
+            {x_grid_lines}
+            
+            <!-- Historical data -->
+            <path d=\"{path_data}\" style=\"stroke: var(--actual-line)\" stroke-width=\"2\" fill=\"none\"/>
+            
+            <!-- Forecast data -->
+            <path d=\"{forecast_path_data}\" style=\"stroke: var(--forecast-line)\" stroke-width=\"2\" fill=\"none\" stroke-dasharray=\"5,5\"/>
+            
+            <!-- Forecast for periods since published -->
+            <path class=\"published-forecast\" d=\"{published_forecast_path_data}\" style=\"stroke: var(--forecast-line)\" stroke-width=\"1.5\" fill=\"none\" stroke-dasharray=\"2,3\" opacity=\"0.8\"/>
+            {forecast_error}
+            
+            <!-- Moving average trend -->
+            <path d=\"{moving_average_path_data}\" style=\"stroke: var(--trend-line)\" stroke-width=\"2\" fill=\"none\" opacity=\"0.8\"/>
+            
+            <!-- Current time marker -->
+            <line x1=\"{current_x}\" y1=\"{marker_y1}\" x2=\"{current_x}\" y2=\"{marker_y2}\" style=\"stroke: var(--now-marker)\" stroke-width=\"2\"/>
+            
+            <!-- Point tooltips -->
+            {point_targets}
+            
+            <!-- Current value -->
+            {current_value}
+            
+            <!-- Y-axis labels -->
+            {y_labels}
+            
+            <!-- X-axis labels -->
+            {x_labels}
+            
+            <!-- Axis labels -->
+            <text x=\"{time_label_x}\" y=\"{time_label_y}\" font-family=\"Arial, sans-serif\" font-size=\"11\" style=\"fill: var(--axis-title)\" text-anchor=\"middle\">Time</text>
+            <text x=\"{y_axis_label_x}\" y=\"{y_axis_label_y}\" font-family=\"Arial, sans-serif\" font-size=\"11\" style=\"fill: var(--axis-title)\" text-anchor=\"middle\" transform=\"rotate(-90 {y_axis_label_x} {y_axis_label_y})\">{y_axis_unit}</text>
+        </svg>",
+        width = width,
+        height = height,
+        chart_x = margin_left,
+        chart_y = margin_top,
+        chart_width = chart_width,
+        chart_height = chart_height,
+        band_shading = band_shading,
+        y_grid_lines = y_grid_lines,
+        x_grid_lines = x_grid_lines,
+        path_data = path_data,
+        forecast_path_data = forecast_path_data,
+        published_forecast_path_data = published_forecast_path_data.trim_start(),
+        forecast_error = forecast_error,
+        moving_average_path_data = moving_average_path_data,
+        current_x = current_x,
+        marker_y1 = margin_top,
+        marker_y2 = margin_top + chart_height,
+        point_targets = point_targets,
+        current_value = current_value,
+        y_labels = y_labels,
+        x_labels = x_labels,
+        time_label_x = width / 2.0,
+        time_label_y = height - 15.0,
+        y_axis_label_x = 15.0,
+        y_axis_label_y = height / 2.0,
+        y_axis_unit = units::label()
+    )
+}
+
+/// One fuel's share of generation over time, oldest first. Periods where the
+/// fuel is not reported count as 0%.
+pub fn fuel_series(generation_history: &[GenerationMixPeriod], fuel: &str) -> Vec<f64> {
+    generation_history
+        .iter()
+        .map(|period| {
+            period
+                .generation_mix
+                .iter()
+                .find(|source| source.fuel == fuel)
+                .map_or(0.0, |source| source.perc)
+        })
+        .collect()
+}
+
+/// The periods of `generation_history`, oldest first, that begin within
+/// `span` of the end of the latest
+pub fn trailing_periods(generation_history: &[GenerationMixPeriod], span: TimeDelta) -> &[GenerationMixPeriod] {
+    let Some(latest) = generation_history.last() else {
+        return generation_history;
+    };
+    let start = latest.to - span;
+    &generation_history[generation_history.partition_point(|period| period.from < start)..]
+}
+
+/// SVG path for a sparkline scaled to fill `width` x `height`. A flat series
+/// is drawn through the middle.
+pub fn sparkline_path(values: &[f64], width: f64, height: f64) -> String {
+    if values.len() < 2 {
+        return String::new();
+    }
+
+    let min = values.iter().copied().fold(f64::INFINITY, f64::min);
+    let max = values.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    let range = max - min;
+
+    values
+        .iter()
+        .enumerate()
+        .map(|(i, value)| {
+            let x = i as f64 / (values.len() - 1) as f64 * width;
+            let y = if range > 0.0 {
+                height - (value - min) / range * height
+            } else {
+                height / 2.0
+            };
+            let command = if i == 0 { "M" } else { "L" };
+            format!("{command} {x:.1} {y:.1}", command = command, x = x, y = y)
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Colour for the `i`th fuel of `generation_mix`. Fuels without a factor
+/// are in the unknown colour, as they are in the stacked chart's history.
+pub fn mix_color(generation_mix: &[FuelSourceWithIntensity], i: usize) -> String {
+    if generation_mix[i].fuel == UNKNOWN_FUEL {
+        UNKNOWN_FUEL_COLOR.to_string()
+    } else {
+        fuel_color(i)
+    }
+}
+
+/// Slices of the generation mix pie, labelled where they're big enough, for
+/// a 500×500 viewBox
+pub fn render_pie_chart(generation_mix: &[FuelSourceWithIntensity]) -> String {
+    let total: f64 = generation_mix.iter().map(|f| f.perc).sum();
+    let mut start_angle = 0.0;
+    let mut elements = String::new();
+
+    for (i, fuel) in generation_mix.iter().enumerate() {
+        let percentage = fuel.perc / total;
+        let angle = percentage * 2.0 * std::f64::consts::PI;
+        let end_angle = start_angle + angle;
+
+        // Skip very small segments for labels but still draw them
+        let show_label = fuel.perc >= 0.5;
+
+        let center_x = 250.0;
+        let center_y = 250.0;
+        let radius = 150.0;
+
+        let x1 = center_x + radius * start_angle.cos();
+        let y1 = center_y + radius * start_angle.sin();
+        let x2 = center_x + radius * end_angle.cos();
+        let y2 = center_y + radius * end_angle.sin();
+
+        let large_arc = if angle > std::f64::consts::PI { 1 } else { 0 };
+
+        // Create pie segment path
+        let path = format!(
+            "M {center_x} {center_y} L {x1} {y1} A {radius} {radius} 0 {large_arc} 1 {x2} {y2} Z",
+            center_x = center_x,
+            center_y = center_y,
+            x1 = x1,
+            y1 = y1,
+            radius = radius,
+            large_arc = large_arc,
+            x2 = x2,
+            y2 = y2
+        );
+
+        let color = mix_color(generation_mix, i);
+
+        // Add pie segment
+        elements.push_str(&format!(
+            r#"<path d="{path}" style="fill: {color}; stroke: var(--slice-border)" stroke-width="2" />"#,
+            path = path,
+            color = color
+        ));
+
+        // Add label only for segments that are large enough
+        if show_label {
+            // Calculate label position (middle of arc, closer to the pie)
+            let mid_angle = start_angle + angle / 2.0;
+            let label_radius = 175.0; // Closer to the pie edge
+
+            let label_x = center_x + label_radius * mid_angle.cos();
+            let label_y = center_y + label_radius * mid_angle.sin();
+
+            // Center-align all text
+            let text_anchor = "middle";
+
+            // Add label text (closer to pie, no connecting line)
+            elements.push_str(&format!(
+                "<text x=\"{label_x}\" y=\"{label_y}\" text-anchor=\"{text_anchor}\" font-family=\"Arial, sans-serif\" font-size=\"11\" font-weight=\"bold\" style=\"fill: var(--slice-label)\">{fuel_name}</text>",
+                label_x = label_x,
+                label_y = label_y - 2.0,
+                text_anchor = text_anchor,
+                fuel_name = fuel.fuel
+            ));
+
+            // Add percentage on a second line
+            elements.push_str(&format!(
+                "<text x=\"{label_x}\" y=\"{label_y}\" text-anchor=\"{text_anchor}\" font-family=\"Arial, sans-serif\" font-size=\"10\" style=\"fill: var(--slice-label-secondary)\">{percentage:.1}%</text>",
+                label_x = label_x,
+                label_y = label_y + 10.0,
+                text_anchor = text_anchor,
+                percentage = fuel.perc
+            ));
+        }
+
+        start_angle = end_angle;
+    }
+
+    elements
+}
+
+/// Stacked area chart of each fuel's share over the mix history, oldest on
+/// the left. Fuels take the colour they have in the pie and legend; any not
+/// in the current mix (e.g. `imports` when split by interconnector) are grey.
+pub fn render_mix_history_chart(
+    generation_mix: &[FuelSourceWithIntensity],
+    generation_history: &[GenerationMixPeriod],
+) -> String {
+    if generation_history.len() < 2 {
+        return String::new();
+    }
+
+    let width = 500.0;
+    let height = 200.0;
+    let margin_left = 50.0;
+    let margin_right = 20.0;
+    let margin_top = 20.0;
+    let margin_bottom = 40.0;
+    let chart_width = width - margin_left - margin_right;
+    let chart_height = height - margin_top - margin_bottom;
+
+    // Every fuel seen in the window, in the current mix's order first so the
+    // stack matches the pie
+    let mut fuels: Vec<&str> = generation_mix.iter().map(|fuel| fuel.fuel.as_str()).collect();
+    for period in generation_history {
+        for source in &period.generation_mix {
+            if !fuels.contains(&source.fuel.as_str()) {
+                fuels.push(&source.fuel);
+            }
+        }
+    }
+    fuels.retain(|fuel| fuel_series(generation_history, fuel).iter().any(|&perc| perc > 0.0));
+
+    // Published shares don't always sum to exactly 100, so scale each period
+    let totals: Vec<f64> = generation_history
+        .iter()
+        .map(|period| period.generation_mix.iter().map(|source| source.perc).sum())
+        .collect();
+
+    let last_index = (generation_history.len() - 1) as f64;
+    let x_at = |i: usize| margin_left + i as f64 / last_index * chart_width;
+    let y_at = |share: f64| margin_top + chart_height - share / 100.0 * chart_height;
+
+    let mut areas = String::new();
+    let mut baseline = vec![0.0; generation_history.len()];
+    for fuel in &fuels {
+        let color = generation_mix
+            .iter()
+            .position(|source| source.fuel == *fuel)
+            .map_or_else(|| UNKNOWN_FUEL_COLOR.to_string(), |i| mix_color(generation_mix, i));
+
+        let top: Vec<f64> = fuel_series(generation_history, fuel)
+            .iter()
+            .zip(&totals)
+            .zip(&baseline)
+            .map(|((perc, total), below)| {
+                below + if *total > 0.0 { perc / total * 100.0 } else { 0.0 }
+            })
+            .collect();
+
+        // Along the top of this fuel's band, then back along the bottom
+        let upper = top
+            .iter()
+            .enumerate()
+            .map(|(i, share)| format!("{x:.1},{y:.1}", x = x_at(i), y = y_at(*share)));
+        let lower = baseline
+            .iter()
+            .enumerate()
+            .rev()
+            .map(|(i, share)| format!("{x:.1},{y:.1}", x = x_at(i), y = y_at(*share)));
+        areas.push_str(&format!(
+            r#"<polygon points="{points}" style="fill: {color}; stroke: var(--slice-border)" stroke-width="0.5"><title>{fuel}</title></polygon>"#,
+            points = upper.chain(lower).collect::<Vec<_>>().join(" "),
+            color = color,
+            fuel = fuel
+        ));
+
+        baseline = top;
+    }
+
+    let mut y_labels = String::new();
+    for share in [0, 25, 50, 75, 100] {
+        y_labels.push_str(&format!(
+            "<text x=\"{x}\" y=\"{y}\" font-family=\"Arial, sans-serif\" font-size=\"10\" style=\"fill: var(--axis-label)\" text-anchor=\"end\">{share}%</text>",
+            x = margin_left - 5.0,
+            y = y_at(share as f64) + 3.0,
+            share = share
+        ));
+    }
+
+    let times: Vec<DateTime<Utc>> = generation_history.iter().map(|period| period.from).collect();
+    let mut x_labels = String::new();
+    for (position, time_label) in x_axis_ticks(&times, X_AXIS_TARGET_TICKS / 2) {
+        x_labels.push_str(&format!(
+            "<text x=\"{x}\" y=\"{y}\" font-family=\"Arial, sans-serif\" font-size=\"9\" style=\"fill: var(--axis-label)\" text-anchor=\"middle\">{time_label}</text>",
+            x = margin_left + position * chart_width,
+            y = margin_top + chart_height + 15.0,
+            time_label = time_label
+        ));
+    }
+
+    format!(
+        "<svg width=\"{width}\" height=\"{height}\" viewBox=\"0 0 {width} {height}\">
+            <!-- Background -->
+            <rect x=\"0\" y=\"0\" width=\"{width}\" height=\"{height}\" style=\"fill: var(--chart-background)\" rx=\"5\"/>
+
+            <!-- Stacked fuel shares -->
+            {areas}
+            <rect x=\"{chart_x}\" y=\"{chart_y}\" width=\"{chart_width}\" height=\"{chart_height}\" fill=\"none\" style=\"stroke: var(--plot-border)\" stroke-width=\"1\"/>
+
+            <!-- Y-axis labels -->
+            {y_labels}
+
+            <!-- X-axis labels -->
+            {x_labels}
+        </svg>",
+        width = width,
+        height = height,
+        areas = areas,
+        chart_x = margin_left,
+        chart_y = margin_top,
+        chart_width = chart_width,
+        chart_height = chart_height,
+        y_labels = y_labels,
+        x_labels = x_labels
+    )
+}
+
+/// A rectangle in SVG coordinates
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Rect {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+/// Split `bounds` into one tile per value, in order, with areas in
+/// proportion to the values, by the squarified algorithm (Bruls, Huizing and
+/// van Wijk): tiles are laid in strips along the shorter side of the space
+/// left, and a strip takes the next value only while that keeps its tiles
+/// closer to square. Values sorted largest first give the squarest tiles.
+/// Values of 0 or less get an empty tile.
+pub fn squarify(values: &[f64], bounds: Rect) -> Vec<Rect> {
+    let empty = Rect { width: 0.0, height: 0.0, ..bounds };
+    let mut tiles = vec![empty; values.len()];
+    let laid_out: Vec<usize> = (0..values.len()).filter(|&i| values[i] > 0.0).collect();
+    let total: f64 = laid_out.iter().map(|&i| values[i]).sum();
+    if total <= 0.0 || bounds.width <= 0.0 || bounds.height <= 0.0 {
+        return tiles;
+    }
+    let scale = bounds.width * bounds.height / total;
+    let area = |i: usize| values[i] * scale;
+
+    // The worst aspect ratio in a strip of `areas` along a side of `side`
+    let worst = |areas: &[usize], side: f64| {
+        let sum: f64 = areas.iter().map(|&i| area(i)).sum();
+        areas
+            .iter()
+            .map(|&i| {
+                let ratio = side * side * area(i) / (sum * sum);
+                ratio.max(1.0 / ratio)
+            })
+            .fold(0.0, f64::max)
+    };
+
+    let mut free = bounds;
+    let mut start = 0;
+    while start < laid_out.len() {
+        let side = free.width.min(free.height);
+        let mut end = start + 1;
+        while end < laid_out.len() && worst(&laid_out[start..=end], side) <= worst(&laid_out[start..end], side) {
+            end += 1;
+        }
+
+        let strip = &laid_out[start..end];
+        let strip_area: f64 = strip.iter().map(|&i| area(i)).sum();
+        if free.width >= free.height {
+            // A column down the left of the space left
+            let width = strip_area / free.height;
+            let mut y = free.y;
+            for &i in strip {
+                let height = area(i) / width;
+                tiles[i] = Rect { x: free.x, y, width, height };
+                y += height;
+            }
+            free.x += width;
+            free.width -= width;
+        } else {
+            // A row along the top
+            let height = strip_area / free.width;
+            let mut x = free.x;
+            for &i in strip {
+                let width = area(i) / height;
+                tiles[i] = Rect { x, y: free.y, width, height };
+                x += width;
+            }
+            free.y += height;
+            free.height -= height;
+        }
+        start = end;
+    }
+
+    tiles
+}
+
+/// The generation mix as a squarified treemap filling `(width, height)`,
+/// largest fuel top left, each tile in its pie colour and labelled where
+/// there's room. Empty if there's no mix.
+pub fn render_mix_treemap(generation_mix: &[FuelSourceWithIntensity], (width, height): (f64, f64)) -> String {
+    if generation_mix.iter().all(|fuel| fuel.perc <= 0.0) {
+        return String::new();
+    }
+
+    let mut order: Vec<usize> = (0..generation_mix.len()).collect();
+    order.sort_by(|&a, &b| generation_mix[b].perc.total_cmp(&generation_mix[a].perc));
+    let values: Vec<f64> = order.iter().map(|&i| generation_mix[i].perc).collect();
+    let tiles = squarify(&values, Rect { x: 0.0, y: 0.0, width, height });
+
+    let mut elements = String::new();
+    for (&i, tile) in order.iter().zip(&tiles) {
+        if tile.width <= 0.0 || tile.height <= 0.0 {
+            continue;
+        }
+        let fuel = &generation_mix[i];
+        let percentage = format!("{perc:.1}%", perc = fuel.perc);
+        elements.push_str(&format!(
+            r#"<rect x="{x:.1}" y="{y:.1}" width="{width:.1}" height="{height:.1}" style="fill: {color}; stroke: var(--slice-border)" stroke-width="2"><title>{fuel}: {percentage}</title></rect>"#,
+            x = tile.x,
+            y = tile.y,
+            width = tile.width,
+            height = tile.height,
+            color = mix_color(generation_mix, i),
+            fuel = escape_xml(&fuel.fuel)
+        ));
+
+        let label_width = fuel.fuel.chars().count().max(percentage.len()) as f64 * TREEMAP_LABEL_CHAR_WIDTH;
+        if tile.width >= label_width + 2.0 * TREEMAP_LABEL_MARGIN && tile.height >= TREEMAP_LABEL_HEIGHT {
+            // Outlined in the border colour, so it reads on light and dark
+            // tiles alike
+            elements.push_str(&format!(
+                r#"<text x="{x:.1}" y="{y:.1}" font-family="Arial, sans-serif" font-size="11" font-weight="bold" paint-order="stroke" stroke-width="3" style="fill: var(--slice-label); stroke: var(--slice-border)">{fuel}<tspan x="{x:.1}" dy="13" font-weight="normal">{percentage}</tspan></text>"#,
+                x = tile.x + TREEMAP_LABEL_MARGIN,
+                y = tile.y + TREEMAP_LABEL_MARGIN + 9.0,
+                fuel = escape_xml(&fuel.fuel)
+            ));
+        }
+    }
+
+    format!(
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="{height}" viewBox="0 0 {width} {height}">{elements}</svg>"#
+    )
+}
+
+/// Escape text for use in XML content and attribute values
+pub fn escape_xml(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
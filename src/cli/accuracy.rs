@@ -0,0 +1,147 @@
+//! `accuracy`: how close the upstream forecast came to the actual intensity
+//! over the last N days, as mean absolute error, mean absolute percentage
+//! error and bias. From the API this is the forecast each period was
+//! published with; an archive also breaks it down by how far ahead the
+//! forecast was made (see `archive record`).
+
+use crate::CarbonError;
+use crate::accuracy::{ForecastError, LEAD_BUCKETS};
+use crate::cli::ClientArgs;
+use crate::output::{OutputFormat, Record, render_all};
+use crate::store::Store;
+use crate::units;
+use chrono::{TimeDelta, Utc};
+use serde::Serialize;
+use tracing::trace;
+
+#[derive(Debug, clap::Args)]
+pub struct AccuracyOptions {
+    /// Cover the last N days
+    #[arg(long, value_parser = clap::value_parser!(i64).range(1..=365), default_value = "14")]
+    days: i64,
+    /// Read forecasts and actuals from this archive database (see `archive
+    /// record`) instead of the API, adding the error by lead time
+    #[arg(long, value_name = "PATH")]
+    db: Option<String>,
+    /// Output format
+    #[arg(long, visible_alias = "output", value_enum, default_value = "text")]
+    format: OutputFormat,
+    #[command(flatten)]
+    client: ClientArgs,
+}
+
+/// Error of one kind of forecast
+#[derive(Debug, Serialize)]
+struct AccuracyRow {
+    /// Hours ahead the forecasts were made, from this to the next bucket;
+    /// `None` for the forecast each period was published with
+    lead_hours: Option<i64>,
+    periods: usize,
+    /// In the display unit
+    #[serde(serialize_with = "units::serialize_f64")]
+    mae: f64,
+    mape: Option<f64>,
+    /// In the display unit
+    #[serde(serialize_with = "units::serialize_f64")]
+    bias: f64,
+}
+
+impl AccuracyRow {
+    fn new(lead_hours: Option<i64>, ForecastError { periods, mae, mape, bias }: ForecastError) -> Self {
+        AccuracyRow { lead_hours, periods, mae, mape, bias }
+    }
+
+    fn label(&self) -> String {
+        let Some(lead_hours) = self.lead_hours else {
+            return "Final forecast".to_string();
+        };
+        let next = LEAD_BUCKETS
+            .into_iter()
+            .find(|&bucket| bucket > lead_hours)
+            .unwrap_or(48);
+        format!("{lead_hours}-{next}h ahead")
+    }
+}
+
+impl Record for AccuracyRow {
+    const CSV_HEADER: &'static [&'static str] = &["lead_hours", "periods", "mae", "mape", "bias"];
+
+    fn csv_fields(&self) -> Vec<String> {
+        vec![
+            self.lead_hours.map(|v| v.to_string()).unwrap_or_default(),
+            self.periods.to_string(),
+            format!("{mae:.1}", mae = units::convert(self.mae)),
+            self
+                .mape
+                .map(|v| format!("{v:.1}"))
+                .unwrap_or_default(),
+            format!("{bias:.1}", bias = units::convert(self.bias)),
+        ]
+    }
+
+    fn text(&self) -> String {
+        let mape = self
+            .mape
+            .map(|v| format!("{v:.1}%"))
+            .unwrap_or_else(|| "n/a".to_string());
+        format!(
+            "{label:<16} MAE {mae:5.1} {unit}, MAPE {mape:>6}, bias {bias:+.1} over {periods} periods",
+            label = self.label(),
+            mae = units::convert(self.mae),
+            unit = units::label(),
+            bias = units::convert(self.bias),
+            periods = self.periods
+        )
+    }
+}
+
+pub async fn run(options: AccuracyOptions) -> Result<(), CarbonError> {
+    let to = Utc::now();
+    let from = to - TimeDelta::days(options.days);
+
+    let (points, outcomes) = match &options.db {
+        Some(db) => {
+            let store = Store::open(db).map_err(|source| CarbonError::OpenArchive {
+                path: db.into(),
+                source,
+            })?;
+            (
+                store.intensity_between(from, to)?,
+                store.forecast_outcomes_between(from, to)?,
+            )
+        }
+        None => {
+            let points = options
+                .client
+                .client()?
+                .intensity_range_for(None, from, to)
+                .await?;
+            (points, Vec::new())
+        }
+    };
+    trace!("Parsed response data: {:?}", points);
+
+    let mut rows: Vec<AccuracyRow> = ForecastError::of_points(&points)
+        .map(|error| AccuracyRow::new(None, error))
+        .into_iter()
+        .collect();
+    rows.extend(LEAD_BUCKETS.into_iter().filter_map(|lead_hours| {
+        let error = ForecastError::of(
+            outcomes
+                .iter()
+                .filter(|outcome| outcome.lead_hours == lead_hours)
+                .map(|outcome| (outcome.forecast, outcome.actual)),
+        )?;
+        Some(AccuracyRow::new(Some(lead_hours), error))
+    }));
+
+    if rows.is_empty() {
+        return Err(CarbonError::EmptyData(format!(
+            "forecasts with actuals for the last {days} days",
+            days = options.days
+        )));
+    }
+    print!("{output}", output = render_all(&rows, options.format)?);
+
+    Ok(())
+}
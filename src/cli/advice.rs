@@ -0,0 +1,285 @@
+//! `advice`: whether to start an appliance now or wait for a greener time,
+//! for the profiles in config.toml's `[appliances]`. The same answer is
+//! served by the dashboard at `/api/advice/<name>`.
+
+use crate::CarbonError;
+use crate::cli::config::{APPLIANCES_TABLE, Config};
+use crate::cli::footprint::{COMPARE_WITHIN, Comparison, Run, compare, describe_grams, run_at};
+use crate::cli::timezone::local;
+use crate::cli::{ClientArgs, RegionArgs, usage_error};
+use crate::model::{FORECAST_HORIZON, IntensityPoint};
+use crate::output::{OutputFormat, Record, render_one};
+use crate::schedule::{describe_duration, greenest_window, parse_duration_arg};
+use crate::settlement::SETTLEMENT_PERIOD;
+use crate::units;
+use chrono::{DateTime, SecondsFormat, TimeDelta, Utc};
+use serde::{Deserialize, Deserializer, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use tracing::trace;
+use utoipa::ToSchema;
+
+// Savings smaller than this are inside the forecast's usual error, so not
+// worth waiting for
+const WORTH_WAITING_PERCENT: f64 = 5.0;
+
+/// Profiles by name, e.g. "washing-machine"
+pub type Appliances = BTreeMap<String, Appliance>;
+
+/// One `[appliances.<name>]` table
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Appliance {
+    /// Energy used by one cycle, in kWh
+    pub kwh: f64,
+    /// How long a cycle runs, e.g. "2h" [default: half an hour]
+    #[serde(default = "default_duration", deserialize_with = "duration")]
+    pub duration: TimeDelta,
+    /// How long the start can wait, e.g. "12h" for an overnight wash
+    /// [default: 24h]
+    #[serde(default = "default_within", deserialize_with = "duration")]
+    pub within: TimeDelta,
+}
+
+impl Appliance {
+    /// A cycle must use some energy and finish inside the forecast
+    pub fn check(&self) -> Result<(), String> {
+        if !(self.kwh > 0.0 && self.kwh.is_finite()) {
+            return Err(format!("kwh must be a positive number, not {kwh}", kwh = self.kwh));
+        }
+        if self.duration + self.within > FORECAST_HORIZON {
+            return Err(format!(
+                "duration and within must add up to at most the {hours}h forecast",
+                hours = FORECAST_HORIZON.num_hours()
+            ));
+        }
+        Ok(())
+    }
+}
+
+fn default_duration() -> TimeDelta {
+    SETTLEMENT_PERIOD
+}
+
+fn default_within() -> TimeDelta {
+    COMPARE_WITHIN
+}
+
+/// A duration written as on the command line, e.g. "2h" or "90m"
+fn duration<'de, D: Deserializer<'de>>(deserializer: D) -> Result<TimeDelta, D::Error> {
+    let value = String::deserialize(deserializer)?;
+    parse_duration_arg(&value).map_err(serde::de::Error::custom)
+}
+
+/// The appliances in the config file, if there is one
+pub fn load_appliances() -> Result<Appliances, CarbonError> {
+    match Config::load().map_err(usage_error)? {
+        Some(config) => config.appliances().map_err(usage_error),
+        None => Ok(Appliances::new()),
+    }
+}
+
+/// Whether to start now
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) enum Verdict {
+    /// Now is the greenest time, or close enough to it
+    RunNow,
+    /// A later start saves enough to be worth waiting for
+    Wait,
+}
+
+impl fmt::Display for Verdict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Verdict::RunNow => "run now",
+            Verdict::Wait => "wait",
+        })
+    }
+}
+
+/// Starting an appliance now against the greenest start in its window
+#[derive(Debug, Serialize, ToSchema)]
+pub(crate) struct Advice {
+    /// The profile's name, e.g. "washing-machine"
+    appliance: String,
+    verdict: Verdict,
+    energy_kwh: f64,
+    /// How long the start could wait, in hours
+    #[serde(rename = "within_hours", serialize_with = "serialize_hours")]
+    #[schema(value_type = f64)]
+    within: TimeDelta,
+    /// Starting now
+    now: Run,
+    /// Starting at the greenest time in the window, and what that saves
+    greenest: Comparison,
+}
+
+fn serialize_hours<S: serde::Serializer>(duration: &TimeDelta, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_f64(duration.num_minutes() as f64 / 60.0)
+}
+
+/// Advice for `appliance` started at `now`, from intensity `points` that
+/// cover the rest of its window
+pub(crate) fn advise(
+    name: &str,
+    appliance: &Appliance,
+    points: &[IntensityPoint],
+    now: DateTime<Utc>,
+) -> Result<Advice, CarbonError> {
+    let run = run_at(points, now, appliance.duration, appliance.kwh)
+        .ok_or_else(|| CarbonError::EmptyData("intensity for the current period".into()))?;
+    let candidates: Vec<_> = points
+        .iter()
+        .filter(|p| p.to > now && p.to <= now + appliance.within + appliance.duration)
+        .cloned()
+        .collect();
+    let window = greenest_window(&candidates, appliance.duration).ok_or_else(|| {
+        CarbonError::EmptyData(format!(
+            "forecast long enough to fit {duration} in the next {within}",
+            duration = describe_duration(appliance.duration),
+            within = describe_duration(appliance.within)
+        ))
+    })?;
+    let greenest = compare(&run, &window, &candidates, appliance.kwh);
+    // A window starting in the current period is as good as starting now
+    let verdict = if window.from > now && greenest.saving_percent >= WORTH_WAITING_PERCENT {
+        Verdict::Wait
+    } else {
+        Verdict::RunNow
+    };
+
+    Ok(Advice {
+        appliance: name.to_string(),
+        verdict,
+        energy_kwh: appliance.kwh,
+        within: appliance.within,
+        now: run,
+        greenest,
+    })
+}
+
+impl Record for Advice {
+    const CSV_HEADER: &'static [&'static str] = &[
+        "appliance",
+        "verdict",
+        "energy_kwh",
+        "from",
+        "intensity",
+        "emissions_g",
+        "greenest_from",
+        "greenest_to",
+        "greenest_intensity",
+        "greenest_emissions_g",
+        "saving_g",
+        "saving_percent",
+    ];
+
+    fn csv_fields(&self) -> Vec<String> {
+        let best = &self.greenest.best;
+        vec![
+            self.appliance.clone(),
+            self.verdict.to_string(),
+            self.energy_kwh.to_string(),
+            self.now.from.to_rfc3339_opts(SecondsFormat::Secs, true),
+            format!("{intensity:.1}", intensity = units::convert(self.now.intensity)),
+            format!("{emissions:.1}", emissions = self.now.emissions_g),
+            best.from.to_rfc3339_opts(SecondsFormat::Secs, true),
+            best.to.to_rfc3339_opts(SecondsFormat::Secs, true),
+            format!("{intensity:.1}", intensity = units::convert(best.intensity)),
+            format!("{emissions:.1}", emissions = best.emissions_g),
+            format!("{saving:.1}", saving = self.greenest.saving_g),
+            format!("{percent:.1}", percent = self.greenest.saving_percent),
+        ]
+    }
+
+    fn text(&self) -> String {
+        let name = self.appliance.replace('-', " ");
+        let best = &self.greenest.best;
+        match self.verdict {
+            Verdict::Wait => format!(
+                "Wait: run the {name} {from} to {to} at {intensity} {unit} for {emissions}, \
+                 {saving} ({percent:.0}%) less than starting now at {now_intensity} {unit} ({now_emissions})",
+                from = local(best.from).format("%a %d %b %H:%M"),
+                to = local(best.to).format("%H:%M %Z"),
+                intensity = units::figure(best.intensity),
+                unit = units::label(),
+                emissions = describe_grams(best.emissions_g),
+                saving = describe_grams(self.greenest.saving_g),
+                percent = self.greenest.saving_percent,
+                now_intensity = units::figure(self.now.intensity),
+                now_emissions = describe_grams(self.now.emissions_g)
+            ),
+            Verdict::RunNow => {
+                let mut text = format!(
+                    "Run now: the {name} at {intensity} {unit}{band} emits {emissions}",
+                    intensity = units::figure(self.now.intensity),
+                    unit = units::label(),
+                    band = self.now.index.map(|index| format!(" ({index})")).unwrap_or_default(),
+                    emissions = describe_grams(self.now.emissions_g)
+                );
+                if self.greenest.saving_g > 0.0 {
+                    text.push_str(&format!(
+                        ", and waiting up to {within} would save only {saving} ({percent:.0}%)",
+                        within = describe_duration(self.within),
+                        saving = describe_grams(self.greenest.saving_g),
+                        percent = self.greenest.saving_percent
+                    ));
+                } else {
+                    text.push_str(&format!(
+                        "; nothing greener is forecast in the next {within}",
+                        within = describe_duration(self.within)
+                    ));
+                }
+                text
+            }
+        }
+    }
+}
+
+#[derive(Debug, clap::Args)]
+pub struct AdviceOptions {
+    /// Appliance to advise on, as named in config.toml's
+    /// `[appliances.<name>]`, e.g. washing-machine
+    appliance: String,
+    /// Output format
+    #[arg(long, visible_alias = "output", value_enum, default_value = "text")]
+    format: OutputFormat,
+    #[command(flatten)]
+    region: RegionArgs,
+    #[command(flatten)]
+    client: ClientArgs,
+}
+
+pub async fn run(options: AdviceOptions) -> Result<(), CarbonError> {
+    let appliances = load_appliances()?;
+    let appliance = find(&appliances, &options.appliance)?;
+
+    let now = Utc::now();
+    let points = options
+        .client
+        .client()?
+        .intensity_between_for(options.region.selector().as_ref(), now, now + appliance.within + appliance.duration)
+        .await?;
+    trace!("Parsed response data: {:?}", points);
+
+    let advice = advise(&options.appliance, appliance, &points, now)?;
+    print!("{output}", output = render_one(&advice, options.format)?);
+    Ok(())
+}
+
+/// The profile called `name`, or a usage error naming those there are
+pub(crate) fn find<'a>(appliances: &'a Appliances, name: &str) -> Result<&'a Appliance, CarbonError> {
+    appliances.get(name).ok_or_else(|| {
+        if appliances.is_empty() {
+            usage_error(format!(
+                "No appliances configured; add an [{APPLIANCES_TABLE}.{name}] table with its kwh to config.toml"
+            ))
+        } else {
+            usage_error(format!(
+                "Unknown appliance '{name}'; configured: {names}",
+                names = appliances.keys().map(String::as_str).collect::<Vec<_>>().join(", ")
+            ))
+        }
+    })
+}
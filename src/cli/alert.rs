@@ -0,0 +1,378 @@
+//! `alert`: poll the grid and send an alert whenever a rule from the rules
+//! file starts to hold, to standard output, webhooks, Slack, Discord, email
+//! or MQTT (see
+//! [`crate::alert`] for the file format)
+
+use crate::alert::{
+    AlertConfig, AlertEvent, CHAT_OUTLOOK, ChannelConfig, DEFAULT_CHAT_TEXT, History, Reading,
+    Rule, STDOUT_CHANNEL,
+};
+use crate::cli::{ClientArgs, PollTicker, RegionArgs};
+use crate::model::{IntensityPoint, RegionSelector};
+use crate::schedule::parse_duration_arg;
+use crate::units;
+use crate::webhook::WebhookPublisher;
+use crate::{CarbonClient, CarbonError};
+use chrono::{DateTime, TimeDelta, Utc};
+use lettre::message::Mailbox;
+use lettre::message::header::ContentType;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use rumqttc::{AsyncClient, QoS};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tracing::{info, instrument, warn};
+
+// Pause before reconnecting after the connection to a broker fails
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+#[derive(Debug, clap::Args)]
+pub struct AlertOptions {
+    /// Rules file
+    #[arg(long, value_name = "PATH", default_value = "alerts.toml")]
+    rules: PathBuf,
+    /// Check the rules file and exit
+    #[arg(long)]
+    check: bool,
+    /// How often to check the rules
+    #[arg(long, value_parser = parse_duration_arg, default_value = "30m")]
+    interval: TimeDelta,
+    #[command(flatten)]
+    region: RegionArgs,
+    #[command(flatten)]
+    client: ClientArgs,
+}
+
+/// A configured destination for alerts, connected and ready to send
+enum Channel {
+    Stdout,
+    Webhook {
+        publisher: WebhookPublisher,
+        template: Option<String>,
+    },
+    /// Slack or Discord: the text goes in the JSON body's `field`
+    Chat {
+        publisher: WebhookPublisher,
+        template: Option<String>,
+        field: &'static str,
+    },
+    Email {
+        mailer: AsyncSmtpTransport<Tokio1Executor>,
+        from: Mailbox,
+        to: Vec<Mailbox>,
+    },
+    Mqtt {
+        mqtt: AsyncClient,
+        topic: String,
+    },
+}
+
+impl Channel {
+    /// Set up a channel from its config. Addresses and credentials are
+    /// checked here so mistakes show at startup; brokers and mail servers
+    /// are only contacted when sending.
+    fn connect(name: &str, config: &ChannelConfig) -> Result<Channel, String> {
+        let password = |env: &Option<String>| -> Result<Option<String>, String> {
+            env.as_ref()
+                .map(|env| {
+                    std::env::var(env).map_err(|_| {
+                        format!("channel '{name}': environment variable {env} is not set")
+                    })
+                })
+                .transpose()
+        };
+
+        let channel = match config {
+            ChannelConfig::Stdout => Channel::Stdout,
+            ChannelConfig::Webhook { url, template } => Channel::Webhook {
+                publisher: WebhookPublisher::new(vec![url.clone()], None),
+                template: template.clone(),
+            },
+            ChannelConfig::Slack { url, template } => Channel::Chat {
+                publisher: WebhookPublisher::new(vec![url.clone()], None),
+                template: template.clone(),
+                field: "text",
+            },
+            ChannelConfig::Discord { url, template } => Channel::Chat {
+                publisher: WebhookPublisher::new(vec![url.clone()], None),
+                template: template.clone(),
+                field: "content",
+            },
+            ChannelConfig::Email {
+                server,
+                port,
+                tls,
+                username,
+                password_env,
+                from,
+                to,
+            } => {
+                let credentials = match username {
+                    Some(username) => Some(Credentials::new(
+                        username.clone(),
+                        password(password_env)?.unwrap_or_default(),
+                    )),
+                    None => None,
+                };
+                let mailer = smtp_transport(server, *port, *tls, credentials)
+                    .map_err(|e| format!("channel '{name}': {e}"))?;
+                let mailbox = |address: &String| {
+                    address
+                        .parse::<Mailbox>()
+                        .map_err(|e| format!("channel '{name}': address '{address}': {e}"))
+                };
+                Channel::Email {
+                    mailer,
+                    from: mailbox(from)?,
+                    to: to.iter().map(mailbox).collect::<Result<_, _>>()?,
+                }
+            }
+            ChannelConfig::Mqtt {
+                broker,
+                port,
+                topic,
+                username,
+                password_env,
+            } => {
+                let mut mqtt_options =
+                    rumqttc::MqttOptions::new(format!("carbon-vibe-alert-{name}"), broker, *port);
+                mqtt_options.set_keep_alive(Duration::from_secs(60));
+                if let Some(username) = username {
+                    let password = password(password_env)?.unwrap_or_default();
+                    mqtt_options.set_credentials(username, password);
+                }
+                let (mqtt, mut event_loop) = AsyncClient::new(mqtt_options, 16);
+                // The event loop does the network I/O and reconnects
+                let broker = format!("{broker}:{port}");
+                tokio::spawn(async move {
+                    loop {
+                        if let Err(e) = event_loop.poll().await {
+                            warn!("MQTT connection to {broker} failed: {e}");
+                            tokio::time::sleep(RECONNECT_DELAY).await;
+                        }
+                    }
+                });
+                Channel::Mqtt {
+                    mqtt,
+                    topic: topic.clone(),
+                }
+            }
+        };
+        Ok(channel)
+    }
+
+    fn is_chat(&self) -> bool {
+        matches!(self, Channel::Chat { .. })
+    }
+
+    /// Send the alert, with `outlook` for chat sparklines. Failures are
+    /// logged and don't stop other channels.
+    async fn send(&self, name: &str, event: &AlertEvent, outlook: &[IntensityPoint]) {
+        let json = || serde_json::to_string(event).unwrap_or_default();
+        match self {
+            Channel::Stdout => println!("{message}", message = event.message),
+            Channel::Webhook {
+                publisher,
+                template,
+            } => {
+                let body = match template {
+                    Some(template) => event.render(template),
+                    None => json(),
+                };
+                publisher.send(&body).await;
+            }
+            Channel::Chat {
+                publisher,
+                template,
+                field,
+            } => {
+                let text =
+                    event.render_chat(template.as_deref().unwrap_or(DEFAULT_CHAT_TEXT), outlook);
+                publisher
+                    .send(&serde_json::json!({ *field: text }).to_string())
+                    .await;
+            }
+            Channel::Email { mailer, from, to } => {
+                let mut message = Message::builder()
+                    .from(from.clone())
+                    .subject(format!("Carbon alert: {rule}", rule = event.rule))
+                    .header(ContentType::TEXT_PLAIN);
+                for recipient in to {
+                    message = message.to(recipient.clone());
+                }
+                let sent = match message.body(event.message.clone()) {
+                    Ok(message) => mailer.send(message).await.map_err(|e| e.to_string()),
+                    Err(e) => Err(e.to_string()),
+                };
+                match sent {
+                    Ok(_) => info!("Emailed alert '{rule}' via {name}", rule = event.rule),
+                    Err(e) => warn!("Email channel {name} failed: {e}"),
+                }
+            }
+            Channel::Mqtt { mqtt, topic } => {
+                if let Err(e) = mqtt.publish(topic, QoS::AtLeastOnce, false, json()).await {
+                    warn!("MQTT channel {name} failed: {e}");
+                }
+            }
+        }
+    }
+}
+
+/// A transport through the SMTP `server`: STARTTLS on `port`, by default
+/// 587, or without `tls` plain text, by default on port 25
+pub(crate) fn smtp_transport(
+    server: &str,
+    port: Option<u16>,
+    tls: bool,
+    credentials: Option<Credentials>,
+) -> Result<AsyncSmtpTransport<Tokio1Executor>, lettre::transport::smtp::Error> {
+    let mut mailer = if tls {
+        AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(server)?.port(port.unwrap_or(587))
+    } else {
+        AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(server).port(port.unwrap_or(25))
+    };
+    if let Some(credentials) = credentials {
+        mailer = mailer.credentials(credentials);
+    }
+    Ok(mailer.build())
+}
+
+/// Read and check a rules file
+pub(crate) fn load_rules(path: &Path) -> Result<(AlertConfig, Vec<Rule>), CarbonError> {
+    let invalid = |message: String| CarbonError::AlertRules {
+        path: path.to_path_buf(),
+        message,
+    };
+    let contents = std::fs::read_to_string(path).map_err(|e| invalid(e.to_string()))?;
+    AlertConfig::parse(&contents).map_err(invalid)
+}
+
+pub async fn run(options: AlertOptions) -> Result<(), CarbonError> {
+    let (config, mut rules) = load_rules(&options.rules)?;
+    if options.check {
+        println!(
+            "{rules} rules and {channels} channels OK",
+            rules = rules.len(),
+            channels = config.channels.len()
+        );
+        return Ok(());
+    }
+
+    let mut channels = HashMap::from([(STDOUT_CHANNEL.to_string(), Channel::Stdout)]);
+    for (name, channel) in &config.channels {
+        let channel =
+            Channel::connect(name, channel).map_err(|message| CarbonError::AlertRules {
+                path: options.rules.clone(),
+                message,
+            })?;
+        channels.insert(name.clone(), channel);
+    }
+
+    let client = options.client.client()?;
+    let region = options.region.selector();
+    let mut history = History::for_rules(&rules);
+
+    let mut ticker = PollTicker::new(options.interval)?;
+    loop {
+        ticker.tick().await;
+        let reading = match fetch_reading(&client, region.as_ref()).await {
+            Ok(reading) => reading,
+            Err(e) => {
+                warn!("Failed to fetch intensity: {e}");
+                continue;
+            }
+        };
+        info!(
+            "Intensity {intensity} {unit} ({index:?})",
+            intensity = units::figure(reading.intensity),
+            unit = units::label(),
+            index = reading.index
+        );
+        history.push(reading);
+
+        // Fetched once per check, and only if a chat channel needs it
+        let mut outlook: Option<Vec<IntensityPoint>> = None;
+        for rule in &mut rules {
+            let Some(event) = rule.update(&history) else {
+                continue;
+            };
+            info!("Rule '{name}' fired", name = event.rule);
+            let rule_channels: Vec<(&String, &Channel)> = rule
+                .config
+                .channels
+                .iter()
+                .filter_map(|name| channels.get(name).map(|channel| (name, channel)))
+                .collect();
+            if outlook.is_none() && rule_channels.iter().any(|(_, channel)| channel.is_chat()) {
+                outlook = Some(fetch_outlook(&client, region.as_ref(), event.timestamp).await);
+            }
+            for (name, channel) in rule_channels {
+                channel
+                    .send(name, &event, outlook.as_deref().unwrap_or_default())
+                    .await;
+            }
+        }
+    }
+}
+
+/// The next [`CHAT_OUTLOOK`] of periods from `from` for chat sparklines. A
+/// failure is logged and leaves the sparkline empty rather than holding up
+/// the alert.
+async fn fetch_outlook(
+    client: &CarbonClient,
+    region: Option<&RegionSelector>,
+    from: DateTime<Utc>,
+) -> Vec<IntensityPoint> {
+    match client
+        .intensity_between_for(region, from, from + CHAT_OUTLOOK)
+        .await
+    {
+        Ok(mut points) => {
+            points.retain(|point| point.from >= from && point.from < from + CHAT_OUTLOOK);
+            points
+        }
+        Err(e) => {
+            warn!("Failed to fetch the outlook for chat channels: {e}");
+            Vec::new()
+        }
+    }
+}
+
+/// Intensity, band and generation mix, national or for the selected region
+#[instrument(skip(client))]
+async fn fetch_reading(
+    client: &CarbonClient,
+    region: Option<&RegionSelector>,
+) -> Result<Reading, CarbonError> {
+    match region {
+        Some(region) => {
+            let reading = client.regional_reading(region).await?;
+            let region = reading.region;
+            let intensity = region.intensity.best().ok_or_else(|| {
+                CarbonError::EmptyData(format!(
+                    "intensity for {region}",
+                    region = region.short_name
+                ))
+            })?;
+            Ok(Reading {
+                from: reading.from,
+                intensity,
+                index: region.intensity.index,
+                generation_mix: region.generation_mix,
+                region: Some(region.short_name),
+            })
+        }
+        None => {
+            let period = client.current_period().await?;
+            let generation_mix = client.generation_mix().await?;
+            Ok(Reading {
+                from: period.from,
+                intensity: period.intensity,
+                index: period.index,
+                generation_mix,
+                region: None,
+            })
+        }
+    }
+}
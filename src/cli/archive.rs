@@ -0,0 +1,359 @@
+use crate::{CarbonClient, CarbonError};
+use crate::cli::failures::FailureArgs;
+use crate::cli::seed::SeedArgs;
+use crate::cli::timezone::local;
+use crate::cli::{ClientArgs, PollTicker, usage_error};
+use crate::model::{IntensityPoint, MAX_INTENSITY_RANGE, parse_datetime_arg};
+use crate::output::{OutputFormat, Record, render_all, render_one};
+use crate::schedule::parse_duration_arg;
+use crate::store::{ArchiveSummary, DEFAULT_DB_PATH, Gap, Store};
+use chrono::{DateTime, SecondsFormat, TimeDelta, Utc};
+use clap::Subcommand;
+use serde::Serialize;
+use tracing::{info, instrument};
+
+// Window queried when neither --hours nor --from/--to is given
+const DEFAULT_HOURS: i64 = 24;
+
+// How far back each recording pass re-fetches, so periods first saved as
+// forecasts are overwritten once their actuals are published
+const REFRESH_WINDOW: TimeDelta = TimeDelta::hours(24);
+
+/// Record history into a local SQLite database and query it
+#[derive(Debug, clap::Args)]
+pub struct ArchiveOptions {
+    /// Database file
+    #[arg(long, global = true, value_name = "PATH", default_value = DEFAULT_DB_PATH)]
+    db: String,
+    #[command(subcommand)]
+    command: ArchiveCommand,
+    #[command(flatten)]
+    client: ClientArgs,
+}
+
+#[derive(Debug, Subcommand)]
+enum ArchiveCommand {
+    /// Save each new settlement period until interrupted
+    Record {
+        /// How often to check for new data
+        #[arg(long, value_parser = parse_duration_arg, default_value = "30m")]
+        interval: TimeDelta,
+        /// How far back to fetch when the archive is empty, at most 336h
+        #[arg(long, value_parser = parse_duration_arg, default_value = "24h")]
+        backfill: TimeDelta,
+        /// Run a single pass and exit, e.g. from cron
+        #[arg(long)]
+        once: bool,
+        #[command(flatten)]
+        failures: FailureArgs,
+        #[command(flatten)]
+        seed: SeedArgs,
+    },
+    /// Stored intensity for each settlement period
+    Intensity(QueryArgs),
+    /// Stored generation mix, one row per fuel and period
+    Mix(QueryArgs),
+    /// Runs of failed recording passes, where periods may be missing
+    Gaps(QueryArgs),
+    /// How much the archive holds
+    Stats {
+        /// Output format
+        #[arg(long, visible_alias = "output", value_enum, default_value = "text")]
+        format: OutputFormat,
+    },
+}
+
+/// Range and format for the query commands. Unlike `history` there is no
+/// 14-day limit: the archive is local.
+#[derive(Debug, clap::Args)]
+struct QueryArgs {
+    /// Cover the last N hours [default: 24]
+    #[arg(long, value_parser = clap::value_parser!(i64).range(1..), conflicts_with_all = ["from", "to"])]
+    hours: Option<i64>,
+    /// Start of the range, e.g. 2025-06-26T14:00Z
+    #[arg(long, value_parser = parse_datetime_arg, requires = "to")]
+    from: Option<DateTime<Utc>>,
+    /// End of the range
+    #[arg(long, value_parser = parse_datetime_arg, requires = "from")]
+    to: Option<DateTime<Utc>>,
+    /// Output format
+    #[arg(long, visible_alias = "output", value_enum, default_value = "text")]
+    format: OutputFormat,
+}
+
+impl QueryArgs {
+    fn range(&self) -> Result<(DateTime<Utc>, DateTime<Utc>), CarbonError> {
+        let (from, to) = match (self.from, self.to) {
+            (Some(from), Some(to)) => (from, to),
+            _ => {
+                let now = Utc::now();
+                let from = TimeDelta::try_hours(self.hours.unwrap_or(DEFAULT_HOURS))
+                    .and_then(|hours| now.checked_sub_signed(hours))
+                    .unwrap_or(DateTime::<Utc>::MIN_UTC);
+                (from, now)
+            }
+        };
+
+        if from >= to {
+            return Err(usage_error(format!(
+                "--from ({from}) must be before --to ({to})",
+                from = from.format("%Y-%m-%d %H:%M"),
+                to = to.format("%Y-%m-%d %H:%M")
+            )));
+        }
+
+        Ok((from, to))
+    }
+}
+
+/// One archived settlement period of intensity
+#[derive(Debug, Serialize)]
+struct IntensityRow {
+    #[serde(flatten)]
+    point: IntensityPoint,
+}
+
+impl Record for IntensityRow {
+    const CSV_HEADER: &'static [&'static str] = &["from", "to", "intensity", "actual", "forecast", "index"];
+
+    fn csv_fields(&self) -> Vec<String> {
+        vec![
+            self.point.from.to_rfc3339_opts(SecondsFormat::Secs, true),
+            self.point.to.to_rfc3339_opts(SecondsFormat::Secs, true),
+            self.point.intensity.to_string(),
+            self.point.actual.map(|v| v.to_string()).unwrap_or_default(),
+            self.point.forecast.map(|v| v.to_string()).unwrap_or_default(),
+            self.point.index.map(|index| index.to_string()).unwrap_or_default(),
+        ]
+    }
+
+    fn text(&self) -> String {
+        format!(
+            "{from}: {intensity}{marker}",
+            from = local(self.point.from).format("%Y-%m-%d %H:%M"),
+            intensity = self.point.intensity,
+            marker = if self.point.is_forecast() { " (forecast)" } else { "" }
+        )
+    }
+}
+
+/// One fuel's share in one archived settlement period
+#[derive(Debug, Serialize)]
+struct MixRow {
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+    fuel: String,
+    perc: f64,
+}
+
+impl Record for MixRow {
+    const CSV_HEADER: &'static [&'static str] = &["from", "to", "fuel", "perc"];
+
+    fn csv_fields(&self) -> Vec<String> {
+        vec![
+            self.from.to_rfc3339_opts(SecondsFormat::Secs, true),
+            self.to.to_rfc3339_opts(SecondsFormat::Secs, true),
+            self.fuel.clone(),
+            self.perc.to_string(),
+        ]
+    }
+
+    fn text(&self) -> String {
+        format!(
+            "{from}: {fuel} {perc:.1}%",
+            from = local(self.from).format("%Y-%m-%d %H:%M"),
+            fuel = self.fuel,
+            perc = self.perc
+        )
+    }
+}
+
+/// A run of failed recording passes
+#[derive(Debug, Serialize)]
+struct GapRow {
+    #[serde(flatten)]
+    gap: Gap,
+}
+
+impl Record for GapRow {
+    const CSV_HEADER: &'static [&'static str] = &["from", "to", "failures", "error"];
+
+    fn csv_fields(&self) -> Vec<String> {
+        vec![
+            self.gap.from.to_rfc3339_opts(SecondsFormat::Secs, true),
+            self.gap.to.to_rfc3339_opts(SecondsFormat::Secs, true),
+            self.gap.failures.to_string(),
+            self.gap.error.clone(),
+        ]
+    }
+
+    fn text(&self) -> String {
+        format!(
+            "{from} to {to}: {failures} failed passes, the last with: {error}",
+            from = local(self.gap.from).format("%Y-%m-%d %H:%M"),
+            to = local(self.gap.to).format("%Y-%m-%d %H:%M"),
+            failures = self.gap.failures,
+            error = self.gap.error
+        )
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct Stats {
+    #[serde(flatten)]
+    summary: ArchiveSummary,
+}
+
+impl Record for Stats {
+    const CSV_HEADER: &'static [&'static str] = &["intensity_periods", "generation_periods", "earliest", "latest"];
+
+    fn csv_fields(&self) -> Vec<String> {
+        let timestamp = |t: Option<DateTime<Utc>>| {
+            t.map(|t| t.to_rfc3339_opts(SecondsFormat::Secs, true)).unwrap_or_default()
+        };
+        vec![
+            self.summary.intensity_periods.to_string(),
+            self.summary.generation_periods.to_string(),
+            timestamp(self.summary.earliest),
+            timestamp(self.summary.latest),
+        ]
+    }
+
+    fn text(&self) -> String {
+        let span = match (self.summary.earliest, self.summary.latest) {
+            (Some(earliest), Some(latest)) => format!(
+                "{earliest} to {latest}",
+                earliest = local(earliest).format("%Y-%m-%d %H:%M %Z"),
+                latest = local(latest).format("%Y-%m-%d %H:%M %Z")
+            ),
+            _ => "empty".to_string(),
+        };
+        format!(
+            "Intensity periods: {intensity}\nGeneration mix periods: {generation}\nCovers: {span}",
+            intensity = self.summary.intensity_periods,
+            generation = self.summary.generation_periods
+        )
+    }
+}
+
+pub async fn run(options: ArchiveOptions) -> Result<(), CarbonError> {
+    if let ArchiveCommand::Record { backfill, .. } = options.command
+        && backfill > MAX_INTENSITY_RANGE
+    {
+        return Err(usage_error(format!(
+            "--backfill can be at most {hours}h, the API maximum",
+            hours = MAX_INTENSITY_RANGE.num_hours()
+        )));
+    }
+
+    let mut store = Store::open(&options.db).map_err(|source| CarbonError::OpenArchive {
+        path: options.db.clone().into(),
+        source,
+    })?;
+
+    match options.command {
+        ArchiveCommand::Record { interval, backfill, once, failures, seed } => {
+            seed.seed(&mut store)?;
+            record(&options.client.client()?, &mut store, interval, backfill, once, failures).await?
+        }
+        ArchiveCommand::Intensity(query) => {
+            let (from, to) = query.range()?;
+            let rows: Vec<_> = store
+                .intensity_between(from, to)?
+                .into_iter()
+                .map(|point| IntensityRow { point })
+                .collect();
+            print!("{output}", output = render_all(&rows, query.format)?);
+        }
+        ArchiveCommand::Mix(query) => {
+            let (from, to) = query.range()?;
+            let rows: Vec<_> = store
+                .generation_between(from, to)?
+                .into_iter()
+                .flat_map(|period| {
+                    period.generation_mix.into_iter().map(move |source| MixRow {
+                        from: period.from,
+                        to: period.to,
+                        fuel: source.fuel,
+                        perc: source.perc,
+                    })
+                })
+                .collect();
+            print!("{output}", output = render_all(&rows, query.format)?);
+        }
+        ArchiveCommand::Gaps(query) => {
+            let (from, to) = query.range()?;
+            let rows: Vec<_> = store.gaps_between(from, to)?.into_iter().map(|gap| GapRow { gap }).collect();
+            print!("{output}", output = render_all(&rows, query.format)?);
+        }
+        ArchiveCommand::Stats { format } => {
+            let stats = Stats { summary: store.summary()? };
+            print!("{output}", output = render_one(&stats, format)?);
+        }
+    }
+
+    Ok(())
+}
+
+/// Save new periods every `interval` until interrupted, or once with `once`.
+/// Failed passes are marked as a gap and retried on the next tick, until
+/// `--max-failures` in a row.
+async fn record(
+    client: &CarbonClient,
+    store: &mut Store,
+    interval: TimeDelta,
+    backfill: TimeDelta,
+    once: bool,
+    failures: FailureArgs,
+) -> Result<(), CarbonError> {
+    if once {
+        return record_pass(client, store, backfill).await;
+    }
+
+    let mut ticker = PollTicker::new(interval)?;
+    let mut failures = failures.count();
+    loop {
+        ticker.tick().await;
+        let at = Utc::now();
+        let result = record_pass(client, store, backfill).await;
+        failures.record(store, at, result)?;
+    }
+}
+
+#[instrument(skip(client, store))]
+async fn record_pass(
+    client: &CarbonClient,
+    store: &mut Store,
+    backfill: TimeDelta,
+) -> Result<(), CarbonError> {
+    let now = Utc::now();
+    let from = record_from(store.latest_intensity()?, now, backfill);
+
+    let points = client.intensity_between(from, now).await?;
+    let periods = client.generation_between(from, now).await?;
+    let forecasts = client.forecast_48h(now).await?;
+    let saved_points = store.record_intensity(&points)?;
+    let saved_periods = store.record_generation(&periods)?;
+    let saved_forecasts = store.record_forecasts(now, &forecasts)?;
+    info!(
+        "Saved {saved_points} intensity and {saved_periods} generation mix periods from {from}, \
+         and {saved_forecasts} forecasts"
+    );
+
+    Ok(())
+}
+
+/// Start of a recording pass given the newest saved period: from there
+/// (re-fetching recent ones for their actuals), or `backfill` ago for an
+/// empty archive, never reaching back past what the API will serve
+pub(crate) fn record_from(
+    latest: Option<DateTime<Utc>>,
+    now: DateTime<Utc>,
+    backfill: TimeDelta,
+) -> DateTime<Utc> {
+    match latest {
+        Some(latest) => latest.min(now - REFRESH_WINDOW),
+        None => now - backfill,
+    }
+    .max(now - MAX_INTENSITY_RANGE)
+}
@@ -0,0 +1,125 @@
+//! `backfill`: fill the local archive with history going back years,
+//! walking back from now a window at a time and checkpointing as it goes so
+//! an interrupted run carries on from where it stopped
+
+use crate::{CarbonClient, CarbonError};
+use crate::cli::timezone::local;
+use crate::cli::{ClientArgs, parse_seconds, usage_error};
+use crate::model::{MAX_INTENSITY_RANGE, parse_datetime_arg};
+use crate::settlement::{SETTLEMENT_PERIOD, floor};
+use crate::store::{DEFAULT_DB_PATH, Store};
+use chrono::{DateTime, Utc};
+use std::time::Duration;
+use tracing::info;
+
+/// Checkpoint holding the start of the oldest window saved so far
+const CHECKPOINT: &str = "backfill";
+
+#[derive(Debug, clap::Args)]
+pub struct BackfillOptions {
+    /// How far back to fill, e.g. 2018-01-01
+    #[arg(long, value_name = "TIME", value_parser = parse_datetime_arg)]
+    since: DateTime<Utc>,
+    /// Database file, which `archive` can query
+    #[arg(long, value_name = "PATH", default_value = DEFAULT_DB_PATH)]
+    db: String,
+    /// Seconds to wait between windows, to go easy on the API
+    #[arg(long, value_name = "SECONDS", value_parser = parse_seconds, default_value = "1")]
+    pause: Duration,
+    /// Start again from now rather than from the saved progress
+    #[arg(long)]
+    restart: bool,
+    #[command(flatten)]
+    client: ClientArgs,
+}
+
+/// What a backfill run saved
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct BackfillSummary {
+    pub windows: usize,
+    pub intensity_periods: usize,
+    pub generation_periods: usize,
+}
+
+pub async fn run(options: BackfillOptions) -> Result<(), CarbonError> {
+    let now = Utc::now();
+    if options.since >= now {
+        return Err(usage_error("--since must be in the past"));
+    }
+
+    let mut store = Store::open(&options.db).map_err(|source| CarbonError::OpenArchive {
+        path: options.db.clone().into(),
+        source,
+    })?;
+    if options.restart {
+        store.clear_checkpoint(CHECKPOINT)?;
+    }
+
+    let client = options.client.client()?;
+    let summary = backfill(
+        &client,
+        &mut store,
+        options.since,
+        floor(now, SETTLEMENT_PERIOD),
+        options.pause,
+    )
+    .await?;
+    println!(
+        "Backfilled to {since}: {intensity} intensity and {generation} generation mix periods \
+         in {windows} window{s}",
+        since = local(options.since).format("%Y-%m-%d %H:%M %Z"),
+        intensity = summary.intensity_periods,
+        generation = summary.generation_periods,
+        windows = summary.windows,
+        s = if summary.windows == 1 { "" } else { "s" }
+    );
+
+    Ok(())
+}
+
+/// Save every period from `since` up to `until` into `store`, newest first
+/// in [`MAX_INTENSITY_RANGE`] windows, pausing `pause` between them. After
+/// each window the checkpoint moves back to its start, and a later run
+/// resumes from there rather than `until`, so only a window cut short is
+/// fetched again.
+pub async fn backfill(
+    client: &CarbonClient,
+    store: &mut Store,
+    since: DateTime<Utc>,
+    until: DateTime<Utc>,
+    pause: Duration,
+) -> Result<BackfillSummary, CarbonError> {
+    let mut summary = BackfillSummary::default();
+    let mut to = match store.checkpoint(CHECKPOINT)? {
+        Some(reached) => {
+            info!("Resuming from {reached}");
+            reached
+        }
+        None => until,
+    };
+
+    while to > since {
+        if summary.windows > 0 {
+            tokio::time::sleep(pause).await;
+        }
+        let from = (to - MAX_INTENSITY_RANGE).max(since);
+        // The API also returns the periods overlapping each end
+        let mut points = client.intensity_between(from, to).await?;
+        points.retain(|point| point.from >= from && point.from < to);
+        let mut periods = client.generation_between(from, to).await?;
+        periods.retain(|period| period.from >= from && period.from < to);
+
+        summary.intensity_periods += store.record_intensity(&points)?;
+        summary.generation_periods += store.record_generation(&periods)?;
+        store.set_checkpoint(CHECKPOINT, from)?;
+        summary.windows += 1;
+        info!(
+            "Saved {from} to {to}: {points} intensity and {periods} generation mix periods",
+            points = points.len(),
+            periods = periods.len()
+        );
+        to = from;
+    }
+
+    Ok(summary)
+}
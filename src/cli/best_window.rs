@@ -0,0 +1,182 @@
+use crate::{CarbonClient, CarbonError};
+use crate::cli::timezone::local;
+use crate::cli::{ClientArgs, PriceArgs, usage_error};
+use crate::model::{FORECAST_HORIZON, IntensityPoint};
+use crate::octopus::OctopusClient;
+use crate::output::{OutputFormat, Record, render_one};
+use crate::schedule::{
+    PricedWindow, Window, cheap_green_window, describe_duration, greenest_window, parse_duration_arg,
+};
+use crate::settlement::SETTLEMENT_PERIOD;
+use crate::units;
+use chrono::{DateTime, SecondsFormat, TimeDelta, Utc};
+use serde::Serialize;
+use tracing::{instrument, trace};
+
+/// A [`Window`] as shown, with its average in the display unit
+#[derive(Debug, Serialize)]
+struct BestWindow {
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+    #[serde(serialize_with = "units::serialize_f64")]
+    average: f64,
+}
+
+impl From<Window> for BestWindow {
+    fn from(Window { from, to, average }: Window) -> Self {
+        BestWindow { from, to, average }
+    }
+}
+
+impl Record for BestWindow {
+    const CSV_HEADER: &'static [&'static str] = &["from", "to", "average"];
+
+    fn csv_fields(&self) -> Vec<String> {
+        vec![
+            self.from.to_rfc3339_opts(SecondsFormat::Secs, true),
+            self.to.to_rfc3339_opts(SecondsFormat::Secs, true),
+            format!("{average:.1}", average = units::convert(self.average)),
+        ]
+    }
+
+    fn text(&self) -> String {
+        format!(
+            "{from} to {to} at {average} {unit} on average",
+            from = local(self.from).format("%a %d %b %H:%M"),
+            to = local(self.to).format("%H:%M %Z"),
+            average = units::figure(self.average),
+            unit = units::label()
+        )
+    }
+}
+
+/// The best window for carbon and Agile price together
+#[derive(Debug, Serialize)]
+struct CheapGreenWindow {
+    #[serde(flatten)]
+    window: BestWindow,
+    /// Mean price over the window in pence/kWh
+    average_price: f64,
+}
+
+impl From<PricedWindow> for CheapGreenWindow {
+    fn from(PricedWindow { window, average_price }: PricedWindow) -> Self {
+        CheapGreenWindow { window: window.into(), average_price }
+    }
+}
+
+impl Record for CheapGreenWindow {
+    const CSV_HEADER: &'static [&'static str] = &["from", "to", "average", "average_price"];
+
+    fn csv_fields(&self) -> Vec<String> {
+        let mut fields = self.window.csv_fields();
+        fields.push(format!("{price:.2}", price = self.average_price));
+        fields
+    }
+
+    fn text(&self) -> String {
+        format!(
+            "{from} to {to} at {average} {unit} and {price:.1}p/kWh on average",
+            from = local(self.window.from).format("%a %d %b %H:%M"),
+            to = local(self.window.to).format("%H:%M %Z"),
+            average = units::figure(self.window.average),
+            unit = units::label(),
+            price = self.average_price
+        )
+    }
+}
+
+#[derive(Debug, clap::Args)]
+pub struct BestWindowOptions {
+    /// How long the job runs, e.g. 3h, 90m or 1h30m
+    #[arg(long, value_parser = parse_duration_arg)]
+    duration: TimeDelta,
+    /// How far ahead to look, at most 48h
+    #[arg(long, value_parser = parse_duration_arg, default_value = "24h")]
+    within: TimeDelta,
+    /// Output format
+    #[arg(long, visible_alias = "output", value_enum, default_value = "text")]
+    format: OutputFormat,
+    #[command(flatten)]
+    prices: PriceArgs,
+    #[command(flatten)]
+    client: ClientArgs,
+}
+
+pub async fn run(options: BestWindowOptions) -> Result<(), CarbonError> {
+    let BestWindowOptions { duration, within, format, prices, client } = options;
+    if within > FORECAST_HORIZON {
+        return Err(usage_error(format!(
+            "--within can be at most {hours}h, the length of the forecast",
+            hours = FORECAST_HORIZON.num_hours()
+        )));
+    }
+    if duration > within {
+        return Err(usage_error("--duration must fit inside --within"));
+    }
+
+    if let Some(octopus) = prices.octopus(&client) {
+        let window = find_cheap_green_window(&client.client()?, &octopus, duration, within, prices.price_weight())
+            .await?
+            .ok_or_else(|| CarbonError::EmptyData("priced forecast long enough to fit a window of that length".into()))?;
+
+        if format == OutputFormat::Text {
+            print!("Cheap and green {duration} window: ", duration = describe_duration(duration));
+        }
+        print!("{output}", output = render_one(&CheapGreenWindow::from(window), format)?);
+        return Ok(());
+    }
+
+    let window = find_best_window(&client.client()?, duration, within)
+        .await?
+        .ok_or_else(|| CarbonError::EmptyData("forecast long enough to fit a window of that length".into()))?;
+
+    if format == OutputFormat::Text {
+        print!("Greenest {duration} window: ", duration = describe_duration(duration));
+    }
+    print!("{output}", output = render_one(&BestWindow::from(window), format)?);
+
+    Ok(())
+}
+
+#[instrument(skip(client))]
+async fn find_best_window(
+    client: &CarbonClient,
+    duration: TimeDelta,
+    within: TimeDelta,
+) -> Result<Option<Window>, CarbonError> {
+    let candidates = fetch_candidates(client, within).await?;
+
+    Ok(greenest_window(&candidates, duration))
+}
+
+#[instrument(skip(client, octopus))]
+async fn find_cheap_green_window(
+    client: &CarbonClient,
+    octopus: &OctopusClient,
+    duration: TimeDelta,
+    within: TimeDelta,
+    price_weight: f64,
+) -> Result<Option<PricedWindow>, CarbonError> {
+    let now = Utc::now();
+    let candidates = fetch_candidates(client, within).await?;
+    // Agile prices are only published up to 11pm tomorrow, so a window can't
+    // run past the last one
+    let rates = octopus.unit_rates(now - SETTLEMENT_PERIOD, now + within).await?;
+    trace!("Unit rates: {:?}", rates);
+
+    Ok(cheap_green_window(&candidates, &rates, duration, price_weight))
+}
+
+/// Forecast periods a window could use: the one in progress can still be
+/// used; anything ending after the search horizon can't
+async fn fetch_candidates(client: &CarbonClient, within: TimeDelta) -> Result<Vec<IntensityPoint>, CarbonError> {
+    let now = Utc::now();
+    let points = client.forecast_48h(now).await?;
+    trace!("Parsed response data: {:?}", points);
+
+    Ok(points
+        .into_iter()
+        .filter(|p| p.to > now && p.to <= now + within)
+        .collect())
+}
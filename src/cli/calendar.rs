@@ -0,0 +1,72 @@
+//! `calendar`: an iCalendar file with an event for each forecast window
+//! below a threshold, to overlay "green hours" on a normal calendar
+
+use crate::CarbonError;
+use crate::calendar::{DEFAULT_BELOW, low_carbon_windows, to_ics};
+use crate::cli::{ClientArgs, RegionArgs};
+use crate::units;
+use chrono::{TimeDelta, Utc};
+use std::path::PathBuf;
+use tracing::trace;
+
+#[derive(Debug, clap::Args)]
+pub struct CalendarOptions {
+    /// Include periods forecast below this many gCO₂/kWh
+    #[arg(long, value_name = "GCO2_PER_KWH", default_value_t = DEFAULT_BELOW)]
+    below: i32,
+    /// Cover the next N days; the forecast reaches 2
+    #[arg(long, value_parser = clap::value_parser!(i64).range(1..=2), default_value = "2")]
+    days: i64,
+    /// File to write; standard output if not given
+    #[arg(long, value_name = "PATH")]
+    out: Option<PathBuf>,
+    #[command(flatten)]
+    region: RegionArgs,
+    #[command(flatten)]
+    client: ClientArgs,
+}
+
+pub async fn run(options: CalendarOptions) -> Result<(), CarbonError> {
+    let now = Utc::now();
+    let to = now + TimeDelta::days(options.days);
+    let client = options.client.client()?;
+    // Regional events are named after the region
+    let (area, points) = match options.region.selector() {
+        Some(region) => {
+            let entry = client.regional_between(&region, now, to).await?;
+            let points = entry
+                .data
+                .iter()
+                .filter_map(|period| period.to_intensity_point())
+                .collect();
+            (Some(entry.short_name), points)
+        }
+        None => (None, client.intensity_between(now, to).await?),
+    };
+    trace!("Parsed response data: {:?}", points);
+
+    let windows = low_carbon_windows(&points, options.below);
+    let ics = to_ics(&windows, options.below, area.as_deref(), now);
+
+    match options.out {
+        Some(path) => {
+            std::fs::write(&path, ics).map_err(|e| {
+                CarbonError::io(
+                    format!("Failed to write '{path}'", path = path.display()),
+                    e,
+                )
+            })?;
+            println!(
+                "Wrote {count} window{s} below {below} {unit} to {path}",
+                count = windows.len(),
+                s = if windows.len() == 1 { "" } else { "s" },
+                below = units::figure(options.below),
+                unit = units::label(),
+                path = path.display()
+            );
+        }
+        None => print!("{ics}"),
+    }
+
+    Ok(())
+}
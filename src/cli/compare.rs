@@ -0,0 +1,110 @@
+//! `compare`: current intensity in every region side by side, greenest
+//! first
+
+use crate::{CarbonClient, CarbonError};
+use crate::cli::{ClientArgs, region_id_parser};
+use crate::model::{IntensityIndex, RegionalSnapshot};
+use crate::output::{OutputFormat, Record, render_all};
+use crate::units;
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::Serialize;
+use tracing::{instrument, trace};
+
+/// Characters in the longest text-mode bar
+const BAR_WIDTH: usize = 30;
+
+/// One region's current figure
+#[derive(Debug, Serialize)]
+struct RegionRow {
+    region_id: u32,
+    region: String,
+    /// The actual value where measured, otherwise the forecast
+    #[serde(serialize_with = "units::serialize_option")]
+    intensity: Option<i32>,
+    index: Option<IntensityIndex>,
+    /// Start of the settlement period
+    timestamp: DateTime<Utc>,
+}
+
+impl Record for RegionRow {
+    const CSV_HEADER: &'static [&'static str] = &["region_id", "region", "intensity", "index", "timestamp"];
+
+    fn csv_fields(&self) -> Vec<String> {
+        vec![
+            self.region_id.to_string(),
+            self.region.clone(),
+            self.intensity.map(units::field).unwrap_or_default(),
+            self.index.map(|index| index.to_string()).unwrap_or_default(),
+            self.timestamp.to_rfc3339_opts(SecondsFormat::Secs, true),
+        ]
+    }
+
+    fn text(&self) -> String {
+        format!(
+            "{region_id:>2}  {region:<24} {intensity:>8}  {index:<9}",
+            region_id = self.region_id,
+            region = self.region,
+            intensity = self.intensity.map_or("-".to_string(), units::figure),
+            index = self.index.map_or("unknown", IntensityIndex::as_str)
+        )
+    }
+}
+
+#[derive(Debug, clap::Args)]
+pub struct CompareOptions {
+    /// Only these region ids, e.g. 1,6,13 [default: all 17]
+    #[arg(long, value_name = "IDS", value_delimiter = ',', value_parser = region_id_parser())]
+    regions: Vec<u32>,
+    /// Output format
+    #[arg(long, visible_alias = "output", value_enum, default_value = "text")]
+    format: OutputFormat,
+    #[command(flatten)]
+    client: ClientArgs,
+}
+
+pub async fn run(options: CompareOptions) -> Result<(), CarbonError> {
+    let format = options.format;
+    let rows = fetch_regions(&options.client.client()?, &options.regions).await?;
+
+    if format != OutputFormat::Text {
+        print!("{output}", output = render_all(&rows, format)?);
+        return Ok(());
+    }
+
+    // Bars are scaled to the highest figure shown
+    let max = rows.iter().filter_map(|row| row.intensity).max().unwrap_or(0).max(1);
+    println!("Id  Region                   {unit:>8}  Index", unit = units::label());
+    for row in &rows {
+        let bar = row
+            .intensity
+            .map_or(0, |intensity| (intensity.max(0) as usize * BAR_WIDTH).div_ceil(max as usize));
+        println!("{text}  {bar}", text = row.text(), bar = "█".repeat(bar));
+    }
+
+    Ok(())
+}
+
+/// Rows for the regions asked for, or all of them, from the lowest intensity
+/// to the highest
+#[instrument(skip(client))]
+async fn fetch_regions(client: &CarbonClient, region_ids: &[u32]) -> Result<Vec<RegionRow>, CarbonError> {
+    let snapshot = client.regional_intensities().await?;
+    trace!("Parsed response data: {:?}", snapshot);
+
+    Ok(region_rows(&snapshot, region_ids))
+}
+
+fn region_rows(snapshot: &RegionalSnapshot, region_ids: &[u32]) -> Vec<RegionRow> {
+    snapshot
+        .by_intensity()
+        .into_iter()
+        .filter(|region| region_ids.is_empty() || region_ids.contains(&region.region_id))
+        .map(|region| RegionRow {
+            region_id: region.region_id,
+            region: region.short_name.clone(),
+            intensity: region.intensity.best(),
+            index: region.intensity.index,
+            timestamp: snapshot.from,
+        })
+        .collect()
+}
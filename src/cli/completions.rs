@@ -0,0 +1,25 @@
+//! `completions`: a shell completion script for `carbon`, generated from the
+//! same argument definitions it parses, so it's always in step with them
+
+use crate::CarbonError;
+use crate::cli::Cli;
+use clap::CommandFactory;
+use clap_complete::Shell;
+use std::io::Write;
+
+#[derive(Debug, clap::Args)]
+pub struct CompletionsOptions {
+    /// Shell to complete for
+    #[arg(value_enum)]
+    shell: Shell,
+}
+
+pub fn run(options: CompletionsOptions) -> Result<(), CarbonError> {
+    // Generated into memory first, as writing to a closed pipe would panic
+    // inside clap_complete
+    let mut script = Vec::new();
+    clap_complete::generate(options.shell, &mut Cli::command(), "carbon", &mut script);
+    std::io::stdout()
+        .write_all(&script)
+        .map_err(|e| CarbonError::io("Failed to write the completion script", e))
+}
@@ -0,0 +1,365 @@
+//! Defaults from `~/.config/carbon-vibe/config.toml`, shared by every
+//! command. Keys are long option names; top-level keys apply to every command
+//! taking that option and a `[command]` table applies to one command only:
+//!
+//! ```toml
+//! postcode = "SW1A"
+//! format = "json"
+//!
+//! [web]
+//! bind = "0.0.0.0:3000"
+//! cache-ttl = 600
+//!
+//! [watch]
+//! below = 100
+//! webhook = ["http://homeassistant.local:8123/api/webhook/carbon"]
+//!
+//! [appliances.washing-machine]
+//! kwh = 1.2
+//! duration = "2h"
+//! within = "12h"
+//! ```
+//!
+//! `[appliances.<name>]` tables aren't options but profiles for `advice`
+//! and the dashboard's `/api/advice/<name>` (see [`Appliance`]).
+//!
+//! Any of these can also be set in the environment, as `CARBON_VIBE_` and
+//! the option name in capitals with underscores, e.g. `CARBON_VIBE_BIND` or
+//! `CARBON_VIBE_CACHE_TTL`, so a container needs no file. Repeated options
+//! take a comma-separated list.
+//!
+//! Settings become the options' defaults, so anything given on the command
+//! line still wins, then the environment, then the config file.
+
+use crate::cli::advice::{Appliance, Appliances};
+use clap::ArgAction;
+use clap::builder::ArgPredicate;
+use clap::error::ErrorKind;
+use std::collections::HashSet;
+use std::ffi::OsString;
+use std::path::PathBuf;
+use toml::{Table, Value};
+
+/// Overrides the config file location
+pub const CONFIG_ENV: &str = "CARBON_VIBE_CONFIG";
+
+/// Prefix of the environment variables that set options
+pub const ENV_PREFIX: &str = "CARBON_VIBE_";
+
+/// Table of appliance profiles, which isn't a command's
+pub const APPLIANCES_TABLE: &str = "appliances";
+
+/// Options that pick a region; setting one in a narrower scope replaces the
+/// other from a wider one
+const REGION_OPTIONS: [&str; 2] = ["postcode", "region"];
+
+pub struct Config {
+    path: PathBuf,
+    table: Table,
+}
+
+impl Config {
+    /// `$CARBON_VIBE_CONFIG`, or `config.toml` under `$XDG_CONFIG_HOME` (by
+    /// default `~/.config`) in `carbon-vibe/`
+    pub fn path() -> Option<PathBuf> {
+        if let Some(path) = std::env::var_os(CONFIG_ENV) {
+            return Some(PathBuf::from(path));
+        }
+        let config_home = std::env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+        Some(config_home.join("carbon-vibe").join("config.toml"))
+    }
+
+    /// Load the config file. A missing file at the default location is not
+    /// an error; one named by `$CARBON_VIBE_CONFIG` must exist.
+    pub fn load() -> Result<Option<Config>, String> {
+        let Some(path) = Config::path() else {
+            return Ok(None);
+        };
+        let contents = match std::fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(e)
+                if e.kind() == std::io::ErrorKind::NotFound
+                    && std::env::var_os(CONFIG_ENV).is_none() =>
+            {
+                return Ok(None);
+            }
+            Err(e) => return Err(format!("Failed to read {path}: {e}", path = path.display())),
+        };
+        Config::parse(path, &contents).map(Some)
+    }
+
+    /// A config file's contents, read from `path`
+    pub fn parse(path: PathBuf, contents: &str) -> Result<Config, String> {
+        let table = contents
+            .parse::<Table>()
+            .map_err(|e| format!("Invalid config file {path}: {e}", path = path.display()))?;
+        Ok(Config { path, table })
+    }
+
+    /// Apply the settings to `command` as defaults, under those from the
+    /// environment, checking every key names an option (or a command's
+    /// table) that exists. `section` selects the `[command]` table for a
+    /// single-purpose binary such as `current`; for the `carbon` binary each
+    /// subcommand picks up its own table.
+    pub fn apply(
+        &self,
+        command: clap::Command,
+        section: Option<&str>,
+        env: &[(String, Value)],
+    ) -> Result<clap::Command, String> {
+        self.check()?;
+
+        let global = self.settings(None);
+        Ok(apply_scoped(command, section, |name| {
+            merge(&merge(&global, &self.settings(Some(name))), env)
+        }))
+    }
+
+    /// The `[appliances.<name>]` profiles, each checked
+    pub fn appliances(&self) -> Result<Appliances, String> {
+        let error = |message: String| format!("{path}: {message}", path = self.path.display());
+        let Some(table) = self.table.get(APPLIANCES_TABLE) else {
+            return Ok(Appliances::new());
+        };
+        let appliances: Appliances = table
+            .clone()
+            .try_into()
+            .map_err(|e| error(format!("Invalid [{APPLIANCES_TABLE}]: {e}")))?;
+        for (name, appliance) in &appliances {
+            Appliance::check(appliance).map_err(|message| error(format!("[{APPLIANCES_TABLE}.{name}]: {message}")))?;
+        }
+        Ok(appliances)
+    }
+
+    /// Top-level settings, or those in the `[name]` table
+    fn settings(&self, section: Option<&str>) -> Vec<(String, Value)> {
+        let table = match section {
+            Some(name) => match self.table.get(name) {
+                Some(Value::Table(table)) => table,
+                _ => return Vec::new(),
+            },
+            None => &self.table,
+        };
+        table
+            .iter()
+            .filter(|(_, value)| !value.is_table())
+            .map(|(key, value)| (key.replace('_', "-"), value.clone()))
+            .collect()
+    }
+
+    /// Reject keys that no command has an option for, so typos don't
+    /// silently do nothing. Checked against every command, since one file
+    /// serves them all.
+    fn check(&self) -> Result<(), String> {
+        let error = |message: String| format!("{path}: {message}", path = self.path.display());
+        let mut commands = <super::Cli as clap::CommandFactory>::command();
+        commands.build();
+
+        self.appliances()?;
+        for (key, value) in &self.table {
+            if key == APPLIANCES_TABLE {
+                continue;
+            }
+            let Value::Table(table) = value else {
+                check_key(&commands, &key.replace('_', "-")).map_err(error)?;
+                continue;
+            };
+            let command = commands
+                .find_subcommand(key)
+                .ok_or_else(|| error(format!("Unknown command [{key}]")))?;
+            for key in table.keys() {
+                check_key(command, &key.replace('_', "-")).map_err(error)?;
+            }
+        }
+
+        for scope in [self.settings(None)]
+            .into_iter()
+            .chain(self.table.keys().map(|key| self.settings(Some(key))))
+        {
+            if REGION_OPTIONS
+                .iter()
+                .all(|option| scope.iter().any(|(key, _)| key == option))
+            {
+                return Err(error("Set postcode or region, not both".to_string()));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Settings from `CARBON_VIBE_*` variables in `vars`. Variables that don't
+/// name an option are ignored rather than rejected, since container
+/// platforms add their own under an app's name, e.g. Kubernetes'
+/// `CARBON_VIBE_SERVICE_HOST`.
+pub fn env_settings(vars: impl IntoIterator<Item = (OsString, OsString)>) -> Vec<(String, Value)> {
+    let mut commands = <super::Cli as clap::CommandFactory>::command();
+    commands.build();
+
+    let mut settings: Vec<(String, Value)> = vars
+        .into_iter()
+        .filter_map(|(name, value)| {
+            let name = name.into_string().ok()?;
+            if name == CONFIG_ENV {
+                return None;
+            }
+            let key = name.strip_prefix(ENV_PREFIX)?.to_ascii_lowercase().replace('_', "-");
+            let value = value.into_string().ok()?;
+            let arg = find_long(&commands, &key)?;
+            let value = if matches!(arg.get_action(), ArgAction::Append) {
+                Value::Array(value.split(',').map(|item| Value::String(item.trim().to_string())).collect())
+            } else {
+                Value::String(value)
+            };
+            Some((key, value))
+        })
+        .collect();
+    // The same order whatever order the environment is in
+    settings.sort_by(|(a, _), (b, _)| a.cmp(b));
+    settings
+}
+
+/// The option with this long name in a command or any of its subcommands
+fn find_long<'a>(command: &'a clap::Command, key: &str) -> Option<&'a clap::Arg> {
+    command
+        .get_arguments()
+        .find(|arg| arg.get_long() == Some(key))
+        .or_else(|| command.get_subcommands().find_map(|subcommand| find_long(subcommand, key)))
+}
+
+fn check_key(command: &clap::Command, key: &str) -> Result<(), String> {
+    let mut known = HashSet::new();
+    long_names(command, &mut known);
+    if known.contains(key) {
+        Ok(())
+    } else {
+        Err(format!("Unknown setting '{key}'"))
+    }
+}
+
+/// Long option names of a command and all its subcommands
+fn long_names(command: &clap::Command, names: &mut HashSet<String>) {
+    names.extend(
+        command
+            .get_arguments()
+            .filter_map(|arg| arg.get_long())
+            .map(str::to_string),
+    );
+    for subcommand in command.get_subcommands() {
+        long_names(subcommand, names);
+    }
+}
+
+/// Command-specific settings over the top-level ones. Picking a region in a
+/// command's table replaces a region picked the other way at the top level.
+fn merge(global: &[(String, Value)], section: &[(String, Value)]) -> Vec<(String, Value)> {
+    let section_has_region = section
+        .iter()
+        .any(|(key, _)| REGION_OPTIONS.contains(&key.as_str()));
+    global
+        .iter()
+        .filter(|(key, _)| {
+            let overridden = section.iter().any(|(section_key, _)| section_key == key);
+            let region_replaced = section_has_region && REGION_OPTIONS.contains(&key.as_str());
+            !overridden && !region_replaced
+        })
+        .chain(section)
+        .cloned()
+        .collect()
+}
+
+/// Apply the settings `scope` gives for a command's name: to `command`
+/// itself when it's the single-purpose binary `section`, otherwise to each
+/// of its subcommands
+fn apply_scoped(
+    mut command: clap::Command,
+    section: Option<&str>,
+    scope: impl Fn(&str) -> Vec<(String, Value)>,
+) -> clap::Command {
+    match section {
+        Some(name) => apply_settings(command, &scope(name)),
+        None => {
+            let names: Vec<String> = command
+                .get_subcommands()
+                .map(|subcommand| subcommand.get_name().to_string())
+                .collect();
+            for name in names {
+                let settings = scope(&name);
+                command = command.mut_subcommand(name, |subcommand| apply_settings(subcommand, &settings));
+            }
+            command
+        }
+    }
+}
+
+/// Set each matching option's default, here and in nested subcommands
+fn apply_settings(mut command: clap::Command, settings: &[(String, Value)]) -> clap::Command {
+    let mut defaulted = Vec::new();
+    for (key, value) in settings {
+        let Some(id) = command
+            .get_arguments()
+            .find(|arg| arg.get_long() == Some(key))
+            .map(|arg| arg.get_id().clone())
+        else {
+            continue;
+        };
+        let values = default_values(value);
+        command = command.mut_arg(&id, |arg| {
+            let arg = arg.default_values(values).required(false);
+            // Given on the command line, either region option replaces a
+            // configured default for the other
+            match key.as_str() {
+                "postcode" => arg.default_value_if("region", ArgPredicate::IsPresent, None),
+                "region" => arg.default_value_if("postcode", ArgPredicate::IsPresent, None),
+                _ => arg,
+            }
+        });
+        defaulted.push(id);
+    }
+
+    // A configured default satisfies a required group, e.g. `watch`'s
+    // thresholds. Groups only list members declared on the arguments once
+    // built, so look at a built copy.
+    let mut built = command.clone();
+    built.build();
+    let groups: Vec<_> = built
+        .get_groups()
+        .filter(|group| group.get_args().any(|arg| defaulted.contains(arg)))
+        .map(|group| group.get_id().clone())
+        .collect();
+    for group in groups {
+        command = command.mut_group(group, |group| group.required(false));
+    }
+
+    let names: Vec<String> = command
+        .get_subcommands()
+        .map(|subcommand| subcommand.get_name().to_string())
+        .collect();
+    for name in names {
+        command = command.mut_subcommand(name, |subcommand| apply_settings(subcommand, settings));
+    }
+    command
+}
+
+/// A TOML value as command-line strings; arrays give repeated values
+fn default_values(value: &Value) -> Vec<String> {
+    match value {
+        Value::String(value) => vec![value.clone()],
+        Value::Array(values) => values.iter().flat_map(default_values).collect(),
+        other => vec![other.to_string()],
+    }
+}
+
+/// Apply the config file and the `CARBON_VIBE_*` environment to `command`,
+/// exiting like a usage error if the file can't be used
+pub fn with_config(command: clap::Command, section: Option<&str>) -> clap::Command {
+    let env = env_settings(std::env::vars_os());
+    let applied = Config::load().and_then(|config| match config {
+        Some(config) => config.apply(command.clone(), section, &env),
+        None => Ok(apply_scoped(command, section, |_| env.clone())),
+    });
+    applied.unwrap_or_else(|message| {
+        clap::Error::raw(ErrorKind::InvalidValue, format!("{message}\n")).exit()
+    })
+}
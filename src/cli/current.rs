@@ -0,0 +1,320 @@
+use crate::{CarbonClient, CarbonError};
+use crate::aggregate::NationalEstimate;
+use crate::cli::{ClientArgs, RegionArgs, regional_client, usage_error};
+use crate::context::{CONTEXT_WINDOW, IntensityContext};
+use crate::electricitymaps::ElectricityMapsClient;
+use crate::model::{IntensityIndex, RegionSelector};
+use crate::output::{OutputFormat, Record, render_one};
+use crate::provider::GridDataProvider;
+use crate::store::{DEFAULT_DB_PATH, Store};
+use crate::units;
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::Serialize;
+use tracing::{trace, instrument, warn};
+
+/// The figure `current` reports, national or regional
+#[derive(Debug, Serialize)]
+struct CurrentReading {
+    /// The actual value where measured, otherwise the forecast
+    #[serde(serialize_with = "units::serialize")]
+    intensity: i32,
+    #[serde(serialize_with = "units::serialize_option")]
+    actual: Option<i32>,
+    #[serde(serialize_with = "units::serialize_option")]
+    forecast: Option<i32>,
+    index: Option<IntensityIndex>,
+    /// Start of the settlement period
+    timestamp: DateTime<Utc>,
+    /// With --context: how it ranks among the archive's past year
+    #[serde(skip_serializing_if = "Option::is_none")]
+    context: Option<IntensityContext>,
+    /// With --postcode or --region: the region the figure is for
+    #[serde(skip_serializing_if = "Option::is_none")]
+    region: Option<ResolvedRegion>,
+}
+
+/// The region a regional reading is for, beside the postcode it was looked
+/// up by
+#[derive(Debug, Serialize)]
+struct ResolvedRegion {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    postcode: Option<String>,
+    /// The API's region id, e.g. 13 for London
+    region_id: u32,
+    /// e.g. "South England"
+    name: String,
+    /// The distribution network operator's area, e.g. "SSE South"
+    dno_region: Option<String>,
+}
+
+impl ResolvedRegion {
+    /// e.g. "RG10 in South England (region 12, SSE South)"
+    fn describe(&self) -> String {
+        let region = match &self.dno_region {
+            Some(dno_region) => format!("{name} (region {id}, {dno_region})", name = self.name, id = self.region_id),
+            None => format!("{name} (region {id})", name = self.name, id = self.region_id),
+        };
+        match &self.postcode {
+            Some(postcode) => format!("{postcode} in {region}"),
+            None => region,
+        }
+    }
+}
+
+impl Record for CurrentReading {
+    const CSV_HEADER: &'static [&'static str] = &[
+        "intensity",
+        "actual",
+        "forecast",
+        "index",
+        "timestamp",
+        "greener_than",
+        "dirtier_than",
+        "postcode",
+        "region_id",
+        "region",
+    ];
+
+    fn csv_fields(&self) -> Vec<String> {
+        vec![
+            units::field(self.intensity),
+            self.actual.map(units::field).unwrap_or_default(),
+            self.forecast.map(units::field).unwrap_or_default(),
+            self.index.map(|index| index.to_string()).unwrap_or_default(),
+            // Same form as the JSON output, e.g. 2025-06-26T14:30:00Z
+            self.timestamp.to_rfc3339_opts(SecondsFormat::Secs, true),
+            self.context.map(|context| context.greener_than.to_string()).unwrap_or_default(),
+            self.context.map(|context| context.dirtier_than.to_string()).unwrap_or_default(),
+            self.region.as_ref().and_then(|region| region.postcode.clone()).unwrap_or_default(),
+            self.region.as_ref().map(|region| region.region_id.to_string()).unwrap_or_default(),
+            self.region.as_ref().map(|region| region.name.clone()).unwrap_or_default(),
+        ]
+    }
+
+    fn text(&self) -> String {
+        let text = match self.index {
+            Some(index) => format!("{intensity} ({index})", intensity = units::figure(self.intensity)),
+            None => units::figure(self.intensity),
+        };
+        let text = match &self.region {
+            Some(region) => format!("{text} for {region}", region = region.describe()),
+            None => text,
+        };
+        match self.context {
+            Some(context) => format!("{text}, {context}", context = context.describe(self.timestamp)),
+            None => text,
+        }
+    }
+}
+
+/// With --aggregate region: the national figure rolled up from the regions
+/// beside the reported one
+#[derive(Debug, Serialize)]
+struct RegionalRollup {
+    #[serde(serialize_with = "units::serialize")]
+    reported: i32,
+    /// Mean of the regions' figures
+    #[serde(serialize_with = "units::serialize_tenth")]
+    estimate: f64,
+    /// Estimate minus reported
+    #[serde(serialize_with = "units::serialize_tenth")]
+    difference: f64,
+    difference_percent: Option<f64>,
+    /// Regions with a figure, out of the 14
+    regions: usize,
+    /// Start of the national settlement period
+    timestamp: DateTime<Utc>,
+}
+
+impl RegionalRollup {
+    fn new(estimate: NationalEstimate, timestamp: DateTime<Utc>) -> RegionalRollup {
+        RegionalRollup {
+            reported: estimate.reported,
+            estimate: estimate.estimate,
+            difference: estimate.difference(),
+            difference_percent: estimate.difference_percent().map(units::round_tenth),
+            regions: estimate.regions,
+            timestamp,
+        }
+    }
+}
+
+impl Record for RegionalRollup {
+    const CSV_HEADER: &'static [&'static str] =
+        &["reported", "estimate", "difference", "difference_percent", "regions", "timestamp"];
+
+    fn csv_fields(&self) -> Vec<String> {
+        vec![
+            units::field(self.reported),
+            format!("{estimate:.1}", estimate = units::convert(self.estimate)),
+            format!("{difference:.1}", difference = units::convert(self.difference)),
+            self.difference_percent.map(|percent| percent.to_string()).unwrap_or_default(),
+            self.regions.to_string(),
+            self.timestamp.to_rfc3339_opts(SecondsFormat::Secs, true),
+        ]
+    }
+
+    fn text(&self) -> String {
+        let direction = if self.difference < 0.0 { "below" } else { "above" };
+        let percent = self
+            .difference_percent
+            .map(|percent| format!(" ({percent:.0}%)", percent = percent.abs()))
+            .unwrap_or_default();
+        format!(
+            "{regions} regions average {estimate} {unit} against {reported} reported: {difference}{percent} {direction}",
+            regions = self.regions,
+            estimate = units::figure(self.estimate),
+            unit = units::label(),
+            reported = units::figure(self.reported),
+            difference = units::figure(self.difference.abs()),
+        )
+    }
+}
+
+/// How --aggregate rolls the national figure up
+#[derive(Clone, Copy, Debug, PartialEq, clap::ValueEnum)]
+enum Aggregate {
+    /// The mean of the 14 regions, unweighted as the API gives no regional
+    /// demand
+    Region,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct CurrentOptions {
+    #[command(flatten)]
+    region: RegionArgs,
+    /// Report the marginal intensity, what one more kWh used now would emit,
+    /// rather than the average (--provider electricitymaps only)
+    #[arg(long, conflicts_with_all = ["postcode", "region"])]
+    marginal: bool,
+    /// Also say how the figure ranks among the past year in the archive,
+    /// e.g. "greener than 71% of the past year" (national figures only)
+    #[arg(long, conflicts_with_all = ["postcode", "region", "marginal"])]
+    context: bool,
+    /// Database file to rank against, filled by `archive record` or
+    /// `backfill`
+    #[arg(long, value_name = "PATH", default_value = DEFAULT_DB_PATH, requires = "context")]
+    db: String,
+    /// Compare the reported national figure with one rolled up from the
+    /// regions, as a cross-check (--provider carbon-intensity only)
+    #[arg(long, value_enum, conflicts_with_all = ["postcode", "region", "marginal", "context"])]
+    aggregate: Option<Aggregate>,
+    /// Output format
+    #[arg(long, visible_alias = "output", value_enum, default_value = "text")]
+    format: OutputFormat,
+    #[command(flatten)]
+    client: ClientArgs,
+}
+
+pub async fn run(options: CurrentOptions) -> Result<(), CarbonError> {
+    let client = options.client.provider()?;
+    // The archive holds the Carbon Intensity API's national figures
+    if options.context && client.carbon_intensity().is_none() {
+        return Err(usage_error("--context only works with --provider carbon-intensity"));
+    }
+    if let Some(Aggregate::Region) = options.aggregate {
+        let client = client
+            .carbon_intensity()
+            .ok_or_else(|| usage_error("--aggregate only works with --provider carbon-intensity"))?;
+        let rollup = fetch_regional_rollup(client).await?;
+        print!("{output}", output = render_one(&rollup, options.format)?);
+        return Ok(());
+    }
+    let mut reading = match options.region.selector() {
+        Some(region) => fetch_regional_intensity(regional_client(&client)?, &region).await?,
+        None if options.marginal => {
+            let client = client
+                .electricity_maps()
+                .ok_or_else(|| usage_error("--marginal only works with --provider electricitymaps"))?;
+            fetch_marginal_intensity(client).await?
+        }
+        None => fetch_carbon_intensity(&client).await?,
+    };
+    if options.context {
+        let store = Store::open(&options.db).map_err(|source| CarbonError::OpenArchive {
+            path: options.db.clone().into(),
+            source,
+        })?;
+        let history = store.intensity_between(reading.timestamp - CONTEXT_WINDOW, reading.timestamp)?;
+        reading.context = IntensityContext::of(reading.intensity, &history);
+        if reading.context.is_none() {
+            warn!(
+                "{db} holds less than a day before now to compare against; fill it with `archive record` or `backfill`",
+                db = options.db
+            );
+        }
+    }
+    print!("{output}", output = render_one(&reading, options.format)?);
+
+    Ok(())
+}
+
+#[instrument(skip(client))]
+async fn fetch_carbon_intensity(client: &impl GridDataProvider) -> Result<CurrentReading, CarbonError> {
+    let period = client.current_period().await?;
+    trace!("Current period: {:?}", period);
+    
+    Ok(CurrentReading {
+        intensity: period.intensity,
+        actual: period.actual,
+        forecast: period.forecast,
+        index: period.index,
+        timestamp: period.from,
+        context: None,
+        region: None,
+    })
+}
+
+#[instrument(skip(client))]
+async fn fetch_regional_rollup(client: &CarbonClient) -> Result<RegionalRollup, CarbonError> {
+    let (national, regional) = tokio::join!(client.current_period(), client.regional_intensities());
+    let (national, regional) = (national?, regional?);
+    trace!("National period: {:?}", national);
+
+    let estimate = NationalEstimate::of(&regional, national.intensity)
+        .ok_or_else(|| CarbonError::EmptyData("regional intensities".into()))?;
+    Ok(RegionalRollup::new(estimate, national.from))
+}
+
+#[instrument(skip(client))]
+async fn fetch_marginal_intensity(client: &ElectricityMapsClient) -> Result<CurrentReading, CarbonError> {
+    let period = client.marginal_period().await?;
+    trace!("Marginal period: {:?}", period);
+
+    Ok(CurrentReading {
+        intensity: period.intensity,
+        actual: period.actual,
+        forecast: period.forecast,
+        index: period.index,
+        timestamp: period.from,
+        context: None,
+        region: None,
+    })
+}
+
+#[instrument(skip(client))]
+async fn fetch_regional_intensity(client: &CarbonClient, region: &RegionSelector) -> Result<CurrentReading, CarbonError> {
+    let reading = client.regional_reading(region).await?;
+    trace!("Resolved region: {:?}", reading);
+    
+    let region = reading.region;
+    let intensity = region
+        .intensity
+        .best()
+        .ok_or_else(|| CarbonError::EmptyData(format!("intensity for {region}", region = region.short_name)))?;
+    
+    Ok(CurrentReading {
+        intensity,
+        actual: region.intensity.actual,
+        forecast: region.intensity.forecast,
+        index: region.intensity.index,
+        timestamp: reading.from,
+        context: None,
+        region: Some(ResolvedRegion {
+            postcode: reading.postcode,
+            region_id: region.region_id,
+            name: region.short_name,
+            dno_region: region.dno_region,
+        }),
+    })
+}
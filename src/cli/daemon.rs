@@ -0,0 +1,47 @@
+//! `daemon`: poll the API into the local archive at each settlement period
+//! and serve the dashboard and JSON API from the archive, so page loads never
+//! depend on upstream being up
+
+use crate::CarbonError;
+use crate::cli::failures::FailureArgs;
+use crate::cli::seed::SeedArgs;
+use crate::cli::usage_error;
+use crate::cli::web::{self, DashboardOptions};
+use crate::model::MAX_INTENSITY_RANGE;
+use crate::schedule::parse_duration_arg;
+use crate::store::{DEFAULT_DB_PATH, Store};
+use chrono::TimeDelta;
+
+/// Options for `daemon`: the archive plus everything `web` takes
+#[derive(Debug, clap::Args)]
+pub struct DaemonOptions {
+    /// Database file, which `archive` can query
+    #[arg(long, value_name = "PATH", default_value = DEFAULT_DB_PATH)]
+    db: String,
+    /// How far back to fetch when the archive is empty, at most 336h
+    #[arg(long, value_parser = parse_duration_arg, default_value = "24h")]
+    backfill: TimeDelta,
+    #[command(flatten)]
+    failures: FailureArgs,
+    #[command(flatten)]
+    seed: SeedArgs,
+    #[command(flatten)]
+    dashboard: DashboardOptions,
+}
+
+pub async fn run(options: DaemonOptions) -> Result<(), CarbonError> {
+    if options.backfill > MAX_INTENSITY_RANGE {
+        return Err(usage_error(format!(
+            "--backfill can be at most {hours}h, the API maximum",
+            hours = MAX_INTENSITY_RANGE.num_hours()
+        )));
+    }
+
+    let mut store = Store::open(&options.db).map_err(|source| CarbonError::OpenArchive {
+        path: options.db.clone().into(),
+        source,
+    })?;
+    options.seed.seed(&mut store)?;
+
+    web::run_daemon(options.dashboard, store, options.backfill, options.failures).await
+}
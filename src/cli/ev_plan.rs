@@ -0,0 +1,225 @@
+//! `ev-plan`: the half-hour slots to charge an electric car in for the
+//! least CO₂ before it's needed. Unlike `best-window` the slots needn't be
+//! contiguous, since a car can pause charging; the plan is printed, or
+//! written as JSON or CSV for a smart charger integration to follow.
+
+use crate::CarbonError;
+use crate::cli::footprint::{describe_grams, parse_positive};
+use crate::cli::timezone::{local, next_at};
+use crate::cli::{ClientArgs, RegionArgs, usage_error};
+use crate::model::{FORECAST_HORIZON, IntensityIndex, parse_datetime_arg};
+use crate::output::{OutputFormat, Record, render_all};
+use crate::schedule::{ChargeSlot, describe_duration, greenest_charge, immediate_charge};
+use crate::units::{self, round_tenth};
+use chrono::{DateTime, NaiveTime, SecondsFormat, TimeDelta, Utc};
+use serde::Serialize;
+use tracing::trace;
+
+#[derive(Debug, clap::Args)]
+pub struct EvPlanOptions {
+    /// Energy the car needs, in kWh
+    #[arg(long, value_name = "KWH", value_parser = parse_positive)]
+    kwh_needed: f64,
+    /// The charger's power, in kW, e.g. 7 for a typical home wallbox
+    #[arg(long, value_name = "KW", value_parser = parse_positive)]
+    charger_kw: f64,
+    /// When the car must be charged: a time on the display zone's clock,
+    /// e.g. 07:30, meaning the next one to come, or a timestamp such as
+    /// 2025-06-27T07:30Z
+    #[arg(long, value_name = "TIME", value_parser = parse_ready_by)]
+    ready_by: ReadyBy,
+    /// Output format; JSON is the whole plan, CSV one row per slot
+    #[arg(long, visible_alias = "output", value_enum, default_value = "text")]
+    format: OutputFormat,
+    #[command(flatten)]
+    region: RegionArgs,
+    #[command(flatten)]
+    client: ClientArgs,
+}
+
+/// `--ready-by`
+#[derive(Clone, Copy, Debug)]
+enum ReadyBy {
+    Clock(NaiveTime),
+    At(DateTime<Utc>),
+}
+
+impl ReadyBy {
+    fn after(self, now: DateTime<Utc>) -> DateTime<Utc> {
+        match self {
+            ReadyBy::Clock(time) => next_at(time, now),
+            ReadyBy::At(at) => at,
+        }
+    }
+}
+
+fn parse_ready_by(value: &str) -> Result<ReadyBy, String> {
+    if let Ok(time) = NaiveTime::parse_from_str(value, "%H:%M") {
+        return Ok(ReadyBy::Clock(time));
+    }
+    parse_datetime_arg(value)
+        .map(ReadyBy::At)
+        .map_err(|_| format!("Invalid time '{value}': expected e.g. 07:30 or 2025-06-27T07:30Z"))
+}
+
+/// The schedule, with what charging straight away would emit instead
+#[derive(Debug, Serialize)]
+struct ChargePlan {
+    ready_by: DateTime<Utc>,
+    energy_kwh: f64,
+    charger_kw: f64,
+    /// Over all the slots
+    emissions_g: f64,
+    /// Charging from now without a pause
+    immediate_emissions_g: f64,
+    saving_g: f64,
+    /// In time order
+    slots: Vec<Slot>,
+}
+
+impl ChargePlan {
+    fn new(ready_by: DateTime<Utc>, energy_kwh: f64, charger_kw: f64, slots: Vec<ChargeSlot>, immediate: &[ChargeSlot]) -> Self {
+        let emissions_g = round_tenth(slots.iter().map(ChargeSlot::emissions_g).sum());
+        let immediate_emissions_g = round_tenth(immediate.iter().map(ChargeSlot::emissions_g).sum());
+        ChargePlan {
+            ready_by,
+            energy_kwh,
+            charger_kw,
+            emissions_g,
+            immediate_emissions_g,
+            saving_g: round_tenth((immediate_emissions_g - emissions_g).max(0.0)),
+            slots: slots.into_iter().map(Slot::from).collect(),
+        }
+    }
+
+    /// e.g. "40 kWh at 7 kW by Fri 27 Jun 07:30 BST: 5h42m in 12 slots,
+    /// 2.40 kgCO₂ ..."
+    fn summary(&self) -> String {
+        let charging = charging_time(self.energy_kwh, self.charger_kw);
+        let mut summary = format!(
+            "{kwh} kWh at {kw} kW by {ready_by}: {charging} in {count} slot{s}, {emissions}",
+            kwh = self.energy_kwh,
+            kw = self.charger_kw,
+            ready_by = local(self.ready_by).format("%a %d %b %H:%M %Z"),
+            charging = describe_duration(charging),
+            count = self.slots.len(),
+            s = if self.slots.len() == 1 { "" } else { "s" },
+            emissions = describe_grams(self.emissions_g)
+        );
+        if self.saving_g > 0.0 {
+            summary.push_str(&format!(
+                ", {saving} ({percent:.0}%) less than charging straight away",
+                saving = describe_grams(self.saving_g),
+                percent = self.saving_g / self.immediate_emissions_g * 100.0
+            ));
+        } else {
+            summary.push_str(", the same as charging straight away");
+        }
+        summary
+    }
+}
+
+/// A [`ChargeSlot`] as shown, with its intensity in the display unit
+#[derive(Debug, Serialize)]
+struct Slot {
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+    energy_kwh: f64,
+    #[serde(serialize_with = "units::serialize")]
+    intensity: i32,
+    index: Option<IntensityIndex>,
+    #[serde(skip)]
+    emissions_g: f64,
+}
+
+impl From<ChargeSlot> for Slot {
+    fn from(slot: ChargeSlot) -> Self {
+        Slot {
+            from: slot.from,
+            to: slot.to,
+            energy_kwh: slot.energy_kwh,
+            intensity: slot.intensity,
+            index: slot.index,
+            emissions_g: slot.emissions_g(),
+        }
+    }
+}
+
+impl Record for Slot {
+    const CSV_HEADER: &'static [&'static str] = &["from", "to", "energy_kwh", "intensity", "index", "emissions_g"];
+
+    fn csv_fields(&self) -> Vec<String> {
+        vec![
+            self.from.to_rfc3339_opts(SecondsFormat::Secs, true),
+            self.to.to_rfc3339_opts(SecondsFormat::Secs, true),
+            format!("{energy:.2}", energy = self.energy_kwh),
+            units::field(self.intensity),
+            self.index.map(|index| index.to_string()).unwrap_or_default(),
+            format!("{emissions:.1}", emissions = self.emissions_g),
+        ]
+    }
+
+    fn text(&self) -> String {
+        format!(
+            "{from}-{to}  {energy:>5.2} kWh  {intensity:>5} {unit}  {index}",
+            from = local(self.from).format("%a %H:%M"),
+            to = local(self.to).format("%H:%M"),
+            energy = self.energy_kwh,
+            intensity = units::figure(self.intensity),
+            unit = units::label(),
+            index = self.index.map_or("unknown", IntensityIndex::as_str)
+        )
+    }
+}
+
+pub async fn run(options: EvPlanOptions) -> Result<(), CarbonError> {
+    let now = Utc::now();
+    let ready_by = options.ready_by.after(now);
+    if ready_by <= now {
+        return Err(usage_error("--ready-by must be in the future"));
+    }
+    if ready_by > now + FORECAST_HORIZON {
+        return Err(usage_error(format!(
+            "--ready-by must be within the {hours}h forecast",
+            hours = FORECAST_HORIZON.num_hours()
+        )));
+    }
+    let needed = charging_time(options.kwh_needed, options.charger_kw);
+    if needed > ready_by - now {
+        return Err(usage_error(format!(
+            "{kwh} kWh at {kw} kW takes {needed}, but {ready} is only {left} away",
+            kwh = options.kwh_needed,
+            kw = options.charger_kw,
+            needed = describe_duration(needed),
+            ready = local(ready_by).format("%a %H:%M %Z"),
+            left = describe_duration(ready_by - now)
+        )));
+    }
+
+    let points = options
+        .client
+        .client()?
+        .intensity_between_for(options.region.selector().as_ref(), now, ready_by)
+        .await?;
+    trace!("Parsed response data: {:?}", points);
+
+    let missing = || CarbonError::EmptyData("forecast covering enough of the time until --ready-by".into());
+    let slots = greenest_charge(&points, now, ready_by, options.kwh_needed, options.charger_kw).ok_or_else(missing)?;
+    let immediate = immediate_charge(&points, now, ready_by, options.kwh_needed, options.charger_kw).ok_or_else(missing)?;
+    let plan = ChargePlan::new(ready_by, options.kwh_needed, options.charger_kw, slots, &immediate);
+
+    match options.format {
+        OutputFormat::Text => {
+            println!("{summary}", summary = plan.summary());
+            print!("{slots}", slots = render_all(&plan.slots, OutputFormat::Text)?);
+        }
+        OutputFormat::Json => println!("{json}", json = serde_json::to_string(&plan)?),
+        OutputFormat::Csv => print!("{csv}", csv = render_all(&plan.slots, OutputFormat::Csv)?),
+    }
+    Ok(())
+}
+
+/// How long `energy_kwh` takes at `power_kw`, to the second
+fn charging_time(energy_kwh: f64, power_kw: f64) -> TimeDelta {
+    TimeDelta::seconds((energy_kwh / power_kw * 3600.0).ceil() as i64)
+}
@@ -0,0 +1,195 @@
+//! `export`: the local archive as Parquet or Arrow files with typed columns
+//! and UTC timestamps, for pandas, polars or DuckDB
+
+use crate::CarbonError;
+use crate::model::{GenerationMixPeriod, IntensityIndex, IntensityPoint, parse_datetime_arg};
+use crate::store::{DEFAULT_DB_PATH, Store};
+use arrow_array::{ArrayRef, Float64Array, Int32Array, RecordBatch, StringArray, TimestampMillisecondArray};
+use arrow_schema::{DataType, Field, Schema, TimeUnit};
+use chrono::{DateTime, Utc};
+use parquet::arrow::ArrowWriter;
+use parquet::basic::Compression;
+use parquet::errors::ParquetError;
+use parquet::file::properties::WriterProperties;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Timezone on every timestamp column, as the IANA name readers expect
+const TIMEZONE: &str = "UTC";
+
+/// File format written by `export`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum ExportFormat {
+    /// Apache Parquet, Snappy-compressed
+    #[default]
+    Parquet,
+    /// Arrow IPC file (Feather v2)
+    Arrow,
+}
+
+impl ExportFormat {
+    fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Parquet => "parquet",
+            ExportFormat::Arrow => "arrow",
+        }
+    }
+}
+
+#[derive(Debug, clap::Args)]
+pub struct ExportOptions {
+    /// File format
+    #[arg(long, value_enum, default_value = "parquet")]
+    format: ExportFormat,
+    /// Directory to write `intensity` and `generation_mix` files into
+    #[arg(long, value_name = "DIR", default_value = ".")]
+    out_dir: PathBuf,
+    /// Start of the range, e.g. 2024-01-01 [default: the oldest stored]
+    #[arg(long, value_parser = parse_datetime_arg)]
+    from: Option<DateTime<Utc>>,
+    /// End of the range [default: the newest stored]
+    #[arg(long, value_parser = parse_datetime_arg)]
+    to: Option<DateTime<Utc>>,
+    /// Database file
+    #[arg(long, value_name = "PATH", default_value = DEFAULT_DB_PATH)]
+    db: String,
+}
+
+pub async fn run(options: ExportOptions) -> Result<(), CarbonError> {
+    let store = Store::open(&options.db).map_err(|source| CarbonError::OpenArchive {
+        path: options.db.clone().into(),
+        source,
+    })?;
+    let summary = store.summary()?;
+    let (points, periods) = match (
+        options.from.or(summary.earliest),
+        options.to.or(summary.latest),
+    ) {
+        (Some(from), Some(to)) => (
+            store.intensity_between(from, to)?,
+            store.generation_between(from, to)?,
+        ),
+        // Nothing stored
+        _ => (Vec::new(), Vec::new()),
+    };
+
+    std::fs::create_dir_all(&options.out_dir).map_err(|e| {
+        CarbonError::io(
+            format!("Failed to create '{dir}'", dir = options.out_dir.display()),
+            e,
+        )
+    })?;
+    let extension = options.format.extension();
+    let intensity_path = options.out_dir.join(format!("intensity.{extension}"));
+    let mix_path = options.out_dir.join(format!("generation_mix.{extension}"));
+    let mix = generation_mix_batch(&periods);
+    write(&intensity_path, &intensity_batch(&points), options.format)?;
+    write(&mix_path, &mix, options.format)?;
+    println!(
+        "Wrote {points} intensity periods to {intensity_path} and {rows} generation mix rows to {mix_path}",
+        points = points.len(),
+        intensity_path = intensity_path.display(),
+        rows = mix.num_rows(),
+        mix_path = mix_path.display()
+    );
+
+    Ok(())
+}
+
+/// A UTC timestamp column, to the millisecond as pandas and Parquet expect
+fn timestamp_field(name: &str) -> Field {
+    Field::new(
+        name,
+        DataType::Timestamp(TimeUnit::Millisecond, Some(TIMEZONE.into())),
+        false,
+    )
+}
+
+fn timestamps(times: impl Iterator<Item = DateTime<Utc>>) -> ArrayRef {
+    Arc::new(
+        TimestampMillisecondArray::from_iter_values(times.map(|time| time.timestamp_millis()))
+            .with_timezone(TIMEZONE),
+    )
+}
+
+/// One row per settlement period: `from`, `to`, `intensity` (the actual
+/// where measured, otherwise the forecast), `actual`, `forecast` and `index`
+pub fn intensity_batch(points: &[IntensityPoint]) -> RecordBatch {
+    let schema = Schema::new(vec![
+        timestamp_field("from"),
+        timestamp_field("to"),
+        Field::new("intensity", DataType::Int32, false),
+        Field::new("actual", DataType::Int32, true),
+        Field::new("forecast", DataType::Int32, true),
+        Field::new("index", DataType::Utf8, true),
+    ]);
+    let columns: Vec<ArrayRef> = vec![
+        timestamps(points.iter().map(|point| point.from)),
+        timestamps(points.iter().map(|point| point.to)),
+        Arc::new(Int32Array::from_iter_values(points.iter().map(|point| point.intensity))),
+        Arc::new(points.iter().map(|point| point.actual).collect::<Int32Array>()),
+        Arc::new(points.iter().map(|point| point.forecast).collect::<Int32Array>()),
+        Arc::new(
+            points
+                .iter()
+                .map(|point| point.index.map(IntensityIndex::as_str))
+                .collect::<StringArray>(),
+        ),
+    ];
+
+    RecordBatch::try_new(Arc::new(schema), columns).expect("columns match the schema")
+}
+
+/// One row per fuel per settlement period: `from`, `to`, `fuel` and `perc`
+pub fn generation_mix_batch(periods: &[GenerationMixPeriod]) -> RecordBatch {
+    let rows: Vec<_> = periods
+        .iter()
+        .flat_map(|period| period.generation_mix.iter().map(move |source| (period, source)))
+        .collect();
+    let schema = Schema::new(vec![
+        timestamp_field("from"),
+        timestamp_field("to"),
+        Field::new("fuel", DataType::Utf8, false),
+        Field::new("perc", DataType::Float64, false),
+    ]);
+    let columns: Vec<ArrayRef> = vec![
+        timestamps(rows.iter().map(|(period, _)| period.from)),
+        timestamps(rows.iter().map(|(period, _)| period.to)),
+        Arc::new(StringArray::from_iter_values(rows.iter().map(|(_, source)| &source.fuel))),
+        Arc::new(Float64Array::from_iter_values(rows.iter().map(|(_, source)| source.perc))),
+    ];
+
+    RecordBatch::try_new(Arc::new(schema), columns).expect("columns match the schema")
+}
+
+/// Write `batch` to a new file at `path`
+pub fn write(path: &Path, batch: &RecordBatch, format: ExportFormat) -> Result<(), CarbonError> {
+    let export_error = |source: ParquetError| CarbonError::Export {
+        path: path.to_path_buf(),
+        source,
+    };
+    let file = File::create(path).map_err(|e| {
+        CarbonError::io(format!("Failed to write '{path}'", path = path.display()), e)
+    })?;
+
+    match format {
+        ExportFormat::Parquet => {
+            let properties = WriterProperties::builder()
+                .set_compression(Compression::SNAPPY)
+                .build();
+            let mut writer = ArrowWriter::try_new(file, batch.schema(), Some(properties))
+                .map_err(export_error)?;
+            writer.write(batch).map_err(export_error)?;
+            writer.close().map_err(export_error)?;
+        }
+        ExportFormat::Arrow => {
+            let mut writer = arrow_ipc::writer::FileWriter::try_new(file, &batch.schema())
+                .map_err(|e| export_error(e.into()))?;
+            writer.write(batch).map_err(|e| export_error(e.into()))?;
+            writer.finish().map_err(|e| export_error(e.into()))?;
+        }
+    }
+
+    Ok(())
+}
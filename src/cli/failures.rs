@@ -0,0 +1,79 @@
+//! What the pollers that record into the archive, `archive record` and
+//! `daemon`, do while upstream stays down. Each run of failed passes is
+//! marked in the archive as a [`Gap`], so later analysis knows periods may be
+//! missing. By default they log each failure and keep polling; with
+//! `--max-failures` they give up after that many in a row, so a service
+//! manager can restart them or raise the alarm.
+
+use crate::CarbonError;
+use crate::store::{Gap, Store};
+use chrono::{DateTime, Utc};
+use tracing::{info, warn};
+
+/// `--max-failures`, for the pollers
+#[derive(Clone, Copy, Debug, clap::Args)]
+pub struct FailureArgs {
+    /// Exit after this many recording passes in a row fail, with the last
+    /// one's status. Without it, failures are logged and polling carries on.
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u32).range(1..))]
+    max_failures: Option<u32>,
+}
+
+impl FailureArgs {
+    pub fn count(self) -> FailureCount {
+        FailureCount::new(self.max_failures)
+    }
+}
+
+/// Recording passes that have failed in a row
+#[derive(Debug)]
+pub struct FailureCount {
+    max_failures: Option<u32>,
+    /// The run of failures so far, if the latest pass failed
+    gap: Option<Gap>,
+}
+
+impl FailureCount {
+    /// Give up after `max_failures` in a row, or never with `None`
+    pub fn new(max_failures: Option<u32>) -> Self {
+        FailureCount { max_failures, gap: None }
+    }
+
+    /// Count the `result` of a pass that ran at `at`. A failure is logged
+    /// and marked in `store` as part of the current gap; once
+    /// `--max-failures` passes in a row have failed, it's returned to end
+    /// the poller.
+    pub fn record(&mut self, store: &mut Store, at: DateTime<Utc>, result: Result<(), CarbonError>) -> Result<(), CarbonError> {
+        let error = match result {
+            Ok(()) => {
+                if let Some(gap) = self.gap.take() {
+                    info!(failures = gap.failures, since = %gap.from, "Recording recovered");
+                }
+                return Ok(());
+            }
+            Err(error) => error,
+        };
+
+        let gap = self.gap.get_or_insert_with(|| Gap {
+            from: at,
+            to: at,
+            failures: 0,
+            error: String::new(),
+        });
+        gap.to = at;
+        gap.failures += 1;
+        gap.error = error.to_string();
+        warn!(failures = gap.failures, "Recording pass failed: {error}");
+        if let Err(e) = store.record_gap(gap) {
+            warn!(error = %e, "Failed to mark the gap in the archive");
+        }
+
+        match self.max_failures {
+            Some(max_failures) if gap.failures >= max_failures => Err(CarbonError::TooManyFailures {
+                failures: gap.failures,
+                last: Box::new(error),
+            }),
+            _ => Ok(()),
+        }
+    }
+}
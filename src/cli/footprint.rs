@@ -0,0 +1,294 @@
+//! `footprint`: turn an appliance's energy use into grams of CO₂ at the
+//! current or forecast intensity, and what waiting for the greenest time
+//! would save
+
+use crate::CarbonError;
+use crate::cli::timezone::local;
+use crate::cli::{ClientArgs, RegionArgs, usage_error};
+use crate::model::{FORECAST_HORIZON, IntensityIndex, IntensityPoint, parse_datetime_arg};
+use crate::output::{OutputFormat, Record, render_one};
+use crate::schedule::{Window, describe_duration, greenest_window};
+use crate::settlement::SETTLEMENT_PERIOD;
+use crate::units::{self, round_tenth};
+use chrono::{DateTime, SecondsFormat, TimeDelta, Utc};
+use serde::Serialize;
+use tracing::trace;
+use utoipa::ToSchema;
+
+// How far ahead `--compare` looks for a greener time
+pub(crate) const COMPARE_WITHIN: TimeDelta = TimeDelta::hours(24);
+
+// A rough everyday yardstick: an average petrol car emits about 160 gCO₂e
+// per km (UK government GHG conversion factors)
+const PETROL_CAR_G_PER_KM: f64 = 160.0;
+
+#[derive(Debug, clap::Args)]
+pub struct FootprintOptions {
+    /// Energy used, in kWh
+    #[arg(long, value_name = "KWH", value_parser = parse_positive, conflicts_with = "watts", required_unless_present = "watts")]
+    kwh: Option<f64>,
+    /// Power drawn, in watts; needs --hours
+    #[arg(long, value_parser = parse_positive, requires = "hours")]
+    watts: Option<f64>,
+    /// How long it runs, in hours, e.g. 1.5; the intensity is averaged over
+    /// this long, and --compare looks for a window of this length [default:
+    /// half an hour]
+    #[arg(long, value_parser = parse_positive)]
+    hours: Option<f64>,
+    /// Start time, to use the forecast instead of the current intensity,
+    /// e.g. 2025-06-26T18:00Z
+    #[arg(long, value_parser = parse_datetime_arg)]
+    at: Option<DateTime<Utc>>,
+    /// Also show the greenest time to run it in the next 24 hours, and the
+    /// saving
+    #[arg(long)]
+    compare: bool,
+    /// Output format
+    #[arg(long, visible_alias = "output", value_enum, default_value = "text")]
+    format: OutputFormat,
+    #[command(flatten)]
+    region: RegionArgs,
+    #[command(flatten)]
+    client: ClientArgs,
+}
+
+/// Emissions for running the job at one time
+#[derive(Debug, Serialize, ToSchema)]
+pub(crate) struct Run {
+    pub(crate) from: DateTime<Utc>,
+    pub(crate) to: DateTime<Utc>,
+    /// Mean intensity over the run in gCO₂/kWh
+    #[serde(serialize_with = "units::serialize_f64")]
+    pub(crate) intensity: f64,
+    /// Band at the start of the run
+    pub(crate) index: Option<IntensityIndex>,
+    pub(crate) emissions_g: f64,
+}
+
+/// The greenest time in the next 24 hours, against the requested one
+#[derive(Debug, Serialize, ToSchema)]
+pub(crate) struct Comparison {
+    #[serde(flatten)]
+    pub(crate) best: Run,
+    pub(crate) saving_g: f64,
+    pub(crate) saving_percent: f64,
+}
+
+#[derive(Debug, Serialize)]
+struct Footprint {
+    energy_kwh: f64,
+    #[serde(flatten)]
+    run: Run,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    greenest: Option<Comparison>,
+}
+
+impl Record for Footprint {
+    const CSV_HEADER: &'static [&'static str] = &[
+        "energy_kwh",
+        "from",
+        "to",
+        "intensity",
+        "index",
+        "emissions_g",
+        "greenest_from",
+        "greenest_to",
+        "greenest_intensity",
+        "greenest_emissions_g",
+        "saving_g",
+    ];
+
+    fn csv_fields(&self) -> Vec<String> {
+        let time = |t: DateTime<Utc>| t.to_rfc3339_opts(SecondsFormat::Secs, true);
+        let mut fields = vec![
+            format!("{kwh}", kwh = self.energy_kwh),
+            time(self.run.from),
+            time(self.run.to),
+            format!("{intensity:.1}", intensity = units::convert(self.run.intensity)),
+            self.run
+                .index
+                .map(|index| index.to_string())
+                .unwrap_or_default(),
+            format!("{emissions:.1}", emissions = self.run.emissions_g),
+        ];
+        match &self.greenest {
+            Some(greenest) => fields.extend([
+                time(greenest.best.from),
+                time(greenest.best.to),
+                format!("{intensity:.1}", intensity = units::convert(greenest.best.intensity)),
+                format!("{emissions:.1}", emissions = greenest.best.emissions_g),
+                format!("{saving:.1}", saving = greenest.saving_g),
+            ]),
+            None => fields.extend(std::iter::repeat_n(String::new(), 5)),
+        }
+        fields
+    }
+
+    fn text(&self) -> String {
+        let band = self
+            .run
+            .index
+            .map(|index| format!(" ({index})"))
+            .unwrap_or_default();
+        let mut text = format!(
+            "{kwh} kWh from {from} at {intensity} {unit}{band}: {emissions}, \
+             about the same as driving {km:.1} km in an average petrol car",
+            kwh = self.energy_kwh,
+            from = local(self.run.from).format("%a %d %b %H:%M %Z"),
+            intensity = units::figure(self.run.intensity),
+            unit = units::label(),
+            emissions = describe_grams(self.run.emissions_g),
+            km = self.run.emissions_g / PETROL_CAR_G_PER_KM
+        );
+
+        match &self.greenest {
+            Some(greenest) if greenest.saving_g > 0.0 => text.push_str(&format!(
+                "\nGreenest time in the next 24h: {from} to {to} at {intensity} {unit}: \
+                 {emissions}, saving {saving} ({percent:.0}%)",
+                from = local(greenest.best.from).format("%a %d %b %H:%M"),
+                to = local(greenest.best.to).format("%H:%M %Z"),
+                intensity = units::figure(greenest.best.intensity),
+                unit = units::label(),
+                emissions = describe_grams(greenest.best.emissions_g),
+                saving = describe_grams(greenest.saving_g),
+                percent = greenest.saving_percent
+            )),
+            Some(_) => text.push_str("\nThis is already the greenest time in the next 24h"),
+            None => {}
+        }
+        text
+    }
+}
+
+pub async fn run(options: FootprintOptions) -> Result<(), CarbonError> {
+    let energy_kwh = match (options.kwh, options.watts, options.hours) {
+        (Some(kwh), _, _) => kwh,
+        (None, Some(watts), Some(hours)) => watts * hours / 1000.0,
+        _ => return Err(usage_error("Give --kwh, or --watts with --hours")),
+    };
+    // A run shorter than a settlement period still falls in one
+    let duration = options
+        .hours
+        .map(|hours| TimeDelta::seconds((hours * 3600.0).round() as i64))
+        .unwrap_or(SETTLEMENT_PERIOD);
+
+    let now = Utc::now();
+    let start = options.at.unwrap_or(now);
+    if start > now + FORECAST_HORIZON - duration {
+        return Err(usage_error(format!(
+            "--at must leave the run inside the {hours}h forecast",
+            hours = FORECAST_HORIZON.num_hours()
+        )));
+    }
+
+    let region = options.region.selector();
+    let end = if options.compare {
+        (start + duration).max(now + COMPARE_WITHIN)
+    } else {
+        start + duration
+    };
+    let points = options
+        .client
+        .client()?
+        .intensity_between_for(region.as_ref(), start.min(now), end)
+        .await?;
+    trace!("Parsed response data: {:?}", points);
+
+    let run = run_at(&points, start, duration, energy_kwh)
+        .ok_or_else(|| CarbonError::EmptyData("intensity for the requested time".into()))?;
+    let greenest = if options.compare {
+        let candidates: Vec<_> = points
+            .iter()
+            .filter(|p| p.to > now && p.to <= now + COMPARE_WITHIN)
+            .cloned()
+            .collect();
+        let window = greenest_window(&candidates, duration).ok_or_else(|| {
+            CarbonError::EmptyData(format!(
+                "forecast long enough to fit {duration} in the next 24h",
+                duration = describe_duration(duration)
+            ))
+        })?;
+        Some(compare(&run, &window, &candidates, energy_kwh))
+    } else {
+        None
+    };
+
+    let footprint = Footprint {
+        energy_kwh,
+        run,
+        greenest,
+    };
+    print!("{output}", output = render_one(&footprint, options.format)?);
+
+    Ok(())
+}
+
+/// Emissions for a run of `duration` from `start`, averaging the intensity
+/// of every settlement period it touches
+pub(crate) fn run_at(
+    points: &[IntensityPoint],
+    start: DateTime<Utc>,
+    duration: TimeDelta,
+    energy_kwh: f64,
+) -> Option<Run> {
+    let end = start + duration;
+    let covered: Vec<_> = points
+        .iter()
+        .filter(|p| p.to > start && p.from < end)
+        .collect();
+    let first = covered.first()?;
+    let intensity = round_tenth(
+        covered.iter().map(|p| p.intensity as f64).sum::<f64>() / covered.len() as f64,
+    );
+
+    Some(Run {
+        from: start,
+        to: end,
+        intensity,
+        index: first.index,
+        emissions_g: round_tenth(energy_kwh * intensity),
+    })
+}
+
+/// The greenest window as a run, with what it saves over `run`
+pub(crate) fn compare(run: &Run, window: &Window, points: &[IntensityPoint], energy_kwh: f64) -> Comparison {
+    let index = points
+        .iter()
+        .find(|p| p.from == window.from)
+        .and_then(|p| p.index);
+    let emissions_g = round_tenth(energy_kwh * window.average);
+    let saving_g = round_tenth((run.emissions_g - emissions_g).max(0.0));
+
+    Comparison {
+        best: Run {
+            from: window.from,
+            to: window.to,
+            intensity: round_tenth(window.average),
+            index,
+            emissions_g,
+        },
+        saving_g,
+        saving_percent: if run.emissions_g > 0.0 {
+            round_tenth(saving_g / run.emissions_g * 100.0)
+        } else {
+            0.0
+        },
+    }
+}
+
+/// "598 gCO₂", or "1.25 kgCO₂" from a kilogram up
+pub(crate) fn describe_grams(grams: f64) -> String {
+    if grams >= 1000.0 {
+        format!("{kg:.2} kgCO₂", kg = grams / 1000.0)
+    } else {
+        format!("{grams:.0} gCO₂")
+    }
+}
+
+pub(crate) fn parse_positive(value: &str) -> Result<f64, String> {
+    value
+        .parse()
+        .ok()
+        .filter(|&number: &f64| number > 0.0 && number.is_finite())
+        .ok_or_else(|| format!("Invalid value '{value}': expected a positive number"))
+}
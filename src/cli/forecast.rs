@@ -0,0 +1,98 @@
+use crate::{CarbonClient, CarbonError};
+use crate::cli::ClientArgs;
+use crate::cli::timezone::local;
+use crate::model::{IntensityIndex, IntensityPoint};
+use crate::output::{OutputFormat, Record, render_all};
+use crate::units;
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::Serialize;
+use tracing::{instrument, trace};
+
+/// One forecast half-hour slot
+#[derive(Debug, Serialize)]
+struct ForecastSlot {
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+    #[serde(serialize_with = "units::serialize")]
+    forecast: i32,
+    index: Option<IntensityIndex>,
+}
+
+impl Record for ForecastSlot {
+    const CSV_HEADER: &'static [&'static str] = &["from", "to", "forecast", "index"];
+
+    fn csv_fields(&self) -> Vec<String> {
+        vec![
+            self.from.to_rfc3339_opts(SecondsFormat::Secs, true),
+            self.to.to_rfc3339_opts(SecondsFormat::Secs, true),
+            units::field(self.forecast),
+            self.index.map(|index| index.to_string()).unwrap_or_default(),
+        ]
+    }
+
+    fn text(&self) -> String {
+        format!(
+            "{from}-{to}   {forecast:>8}  {index}",
+            from = local(self.from).format("%H:%M"),
+            to = local(self.to).format("%H:%M"),
+            forecast = units::figure(self.forecast),
+            index = self.index.map_or("unknown", IntensityIndex::as_str)
+        )
+    }
+}
+
+#[derive(Debug, clap::Args)]
+pub struct ForecastOptions {
+    /// Output format
+    #[arg(long, visible_alias = "output", value_enum, default_value = "text")]
+    format: OutputFormat,
+    #[command(flatten)]
+    client: ClientArgs,
+}
+
+pub async fn run(options: ForecastOptions) -> Result<(), CarbonError> {
+    let format = options.format;
+    let slots = fetch_forecast(&options.client.client()?).await?;
+
+    if format != OutputFormat::Text {
+        print!("{output}", output = render_all(&slots, format)?);
+        return Ok(());
+    }
+
+    if slots.is_empty() {
+        println!("No forecast available");
+        return Ok(());
+    }
+
+    // 48 hours spans three calendar days, so head each day's slots
+    println!("Period ({zone})  {unit:>8}  Index", zone = local(slots[0].from).format("%Z"), unit = units::label());
+    let mut current_day = None;
+    for slot in &slots {
+        let day = local(slot.from).date_naive();
+        if current_day != Some(day) {
+            println!("{day}", day = day.format("%a %d %b"));
+            current_day = Some(day);
+        }
+        println!("{line}", line = slot.text());
+    }
+
+    Ok(())
+}
+
+#[instrument(skip(client))]
+async fn fetch_forecast(client: &CarbonClient) -> Result<Vec<ForecastSlot>, CarbonError> {
+    let points = client.forecast_48h(Utc::now()).await?;
+    trace!("Parsed response data: {:?}", points);
+
+    Ok(points.into_iter().filter_map(forecast_slot).collect())
+}
+
+/// Slots without a forecast value are dropped
+fn forecast_slot(point: IntensityPoint) -> Option<ForecastSlot> {
+    Some(ForecastSlot {
+        from: point.from,
+        to: point.to,
+        forecast: point.forecast?,
+        index: point.index,
+    })
+}
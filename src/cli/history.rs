@@ -0,0 +1,327 @@
+use crate::{CarbonClient, CarbonError};
+use crate::cli::timezone::{self, local};
+use crate::cli::{ClientArgs, RegionArgs, regional_client, usage_error};
+use crate::model::{IntensityIndex, IntensityPoint, MAX_INTENSITY_RANGE, RegionSelector, parse_datetime_arg};
+use crate::output::{OutputFormat, Record, render_all};
+use crate::provider::{GridClient, GridDataProvider};
+use crate::settlement::{self, SETTLEMENT_PERIOD};
+use crate::units;
+use chrono::{DateTime, SecondsFormat, TimeDelta, Utc};
+use clap::Subcommand;
+use serde::Serialize;
+use std::path::PathBuf;
+use tracing::{trace, instrument};
+
+// Window used when neither --hours nor --from/--to is given
+const DEFAULT_HOURS: i64 = 12;
+
+/// Average intensity over one bucket of settlement periods. `hour` is the
+/// bucket's start in the display zone, e.g. `2025-06-26 14:00` (or `14:30`
+/// with 30-minute buckets).
+#[derive(Debug, Serialize)]
+struct HourlyAverage {
+    hour: String,
+    #[serde(serialize_with = "units::serialize")]
+    avg_intensity: i32,
+    /// Span of the settlement periods averaged
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+    /// Averages over the periods that have each value; `None` if none do
+    #[serde(serialize_with = "units::serialize_option")]
+    avg_actual: Option<i32>,
+    #[serde(serialize_with = "units::serialize_option")]
+    avg_forecast: Option<i32>,
+    /// The API's band for the latest period in the bucket
+    index: Option<IntensityIndex>,
+}
+
+impl Record for HourlyAverage {
+    const CSV_HEADER: &'static [&'static str] = &[
+        "hour",
+        "avg_intensity",
+        "from",
+        "to",
+        "avg_actual",
+        "avg_forecast",
+        "index",
+    ];
+
+    fn csv_fields(&self) -> Vec<String> {
+        vec![
+            self.hour.clone(),
+            units::field(self.avg_intensity),
+            self.from.to_rfc3339_opts(SecondsFormat::Secs, true),
+            self.to.to_rfc3339_opts(SecondsFormat::Secs, true),
+            self.avg_actual.map(units::field).unwrap_or_default(),
+            self.avg_forecast.map(units::field).unwrap_or_default(),
+            self.index.map(|index| index.to_string()).unwrap_or_default(),
+        ]
+    }
+
+    fn text(&self) -> String {
+        format!("{hour}: {intensity}", hour = self.hour, intensity = units::figure(self.avg_intensity))
+    }
+}
+
+/// One settlement period as written by `history export`
+#[derive(Debug, Serialize)]
+struct ExportRow {
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+    #[serde(serialize_with = "units::serialize_option")]
+    actual: Option<i32>,
+    #[serde(serialize_with = "units::serialize_option")]
+    forecast: Option<i32>,
+    index: Option<IntensityIndex>,
+}
+
+impl Record for ExportRow {
+    const CSV_HEADER: &'static [&'static str] = &["from", "to", "actual", "forecast", "index"];
+
+    fn csv_fields(&self) -> Vec<String> {
+        vec![
+            self.from.to_rfc3339_opts(SecondsFormat::Secs, true),
+            self.to.to_rfc3339_opts(SecondsFormat::Secs, true),
+            self.actual.map(units::field).unwrap_or_default(),
+            self.forecast.map(units::field).unwrap_or_default(),
+            self.index.map(|index| index.to_string()).unwrap_or_default(),
+        ]
+    }
+
+    fn text(&self) -> String {
+        format!("{from}", from = self.from.format("%Y-%m-%d %H:%M"))
+    }
+}
+
+/// Width of the averaging buckets
+#[derive(Clone, Copy, Debug, PartialEq, clap::ValueEnum)]
+enum Bucket {
+    /// One settlement period, the API's native resolution
+    #[value(name = "30min")]
+    HalfHour,
+    /// Two settlement periods
+    Hour,
+}
+
+impl Bucket {
+    fn width(self) -> TimeDelta {
+        match self {
+            Bucket::HalfHour => SETTLEMENT_PERIOD,
+            Bucket::Hour => TimeDelta::hours(1),
+        }
+    }
+}
+
+/// Per-bucket averages of past intensity
+#[derive(Debug, clap::Args)]
+#[command(args_conflicts_with_subcommands = true)]
+pub struct HistoryOptions {
+    #[command(subcommand)]
+    command: Option<HistoryCommand>,
+    /// Cover the last N hours [default: 12]
+    #[arg(long, value_parser = parse_hours, conflicts_with_all = ["from", "to"])]
+    hours: Option<i64>,
+    /// Start of the range, e.g. 2025-06-26T14:00Z
+    #[arg(long, value_parser = parse_datetime_arg, requires = "to")]
+    from: Option<DateTime<Utc>>,
+    /// End of the range
+    #[arg(long, value_parser = parse_datetime_arg, requires = "from")]
+    to: Option<DateTime<Utc>>,
+    /// Width of the averaging buckets
+    #[arg(long, value_enum, default_value = "hour")]
+    bucket: Bucket,
+    /// Output format
+    #[arg(long, visible_alias = "output", value_enum, default_value = "text")]
+    format: OutputFormat,
+    #[command(flatten)]
+    region: RegionArgs,
+    #[command(flatten)]
+    client: ClientArgs,
+}
+
+#[derive(Debug, Subcommand)]
+enum HistoryCommand {
+    /// Write every settlement period in a range of any length as CSV,
+    /// fetched 14 days at a time
+    Export(ExportArgs),
+}
+
+#[derive(Debug, clap::Args)]
+struct ExportArgs {
+    /// Start of the range, e.g. 2024-01-01 or 2024-01-01T06:00Z
+    #[arg(long, value_parser = parse_datetime_arg)]
+    from: DateTime<Utc>,
+    /// End of the range [default: now]
+    #[arg(long, value_parser = parse_datetime_arg)]
+    to: Option<DateTime<Utc>>,
+    /// File to write; standard output if not given
+    #[arg(long, value_name = "PATH")]
+    out: Option<PathBuf>,
+    #[command(flatten)]
+    region: RegionArgs,
+}
+
+impl HistoryOptions {
+    /// The time range asked for, checked against the API's limits
+    fn range(&self) -> Result<(DateTime<Utc>, DateTime<Utc>), CarbonError> {
+        let (from, to) = match (self.from, self.to) {
+            (Some(from), Some(to)) => (from, to),
+            // The last N hours of completed buckets, so the first and last
+            // aren't part-filled
+            _ => {
+                let to = timezone::floor(Utc::now(), self.bucket.width());
+                (to - TimeDelta::hours(self.hours.unwrap_or(DEFAULT_HOURS)), to)
+            }
+        };
+
+        if from >= to {
+            return Err(usage_error(format!(
+                "--from ({from}) must be before --to ({to})",
+                from = from.format("%Y-%m-%d %H:%M"),
+                to = to.format("%Y-%m-%d %H:%M")
+            )));
+        }
+        if to - from > MAX_INTENSITY_RANGE {
+            return Err(usage_error(format!(
+                "Requested range of {hours} hours exceeds the API maximum of {max_days} days",
+                hours = (to - from).num_hours(),
+                max_days = MAX_INTENSITY_RANGE.num_days()
+            )));
+        }
+
+        Ok((from, to))
+    }
+}
+
+/// `--hours`: a positive number within the API's range limit
+fn parse_hours(value: &str) -> Result<i64, String> {
+    let hours: i64 = value
+        .parse()
+        .map_err(|_| format!("Invalid --hours '{value}': expected a whole number of hours"))?;
+    if hours <= 0 {
+        return Err("--hours must be greater than 0".to_string());
+    }
+    if hours > MAX_INTENSITY_RANGE.num_hours() {
+        return Err(format!(
+            "Requested range of {hours} hours exceeds the API maximum of {max_days} days",
+            max_days = MAX_INTENSITY_RANGE.num_days()
+        ));
+    }
+    Ok(hours)
+}
+
+pub async fn run(options: HistoryOptions) -> Result<(), CarbonError> {
+    if let Some(HistoryCommand::Export(args)) = options.command {
+        return export(&options.client.client()?, args).await;
+    }
+
+    let (from, to) = options.range()?;
+    let region = options.region.selector();
+
+    let hourly_averages =
+        fetch_carbon_intensity_history(&options.client.provider()?, region.as_ref(), from, to, options.bucket).await?;
+    print!("{output}", output = render_all(&hourly_averages, options.format)?);
+    
+    Ok(())
+}
+
+/// `history export`: every settlement period from `--from` to `--to`, in
+/// chunks the API will serve, as CSV
+async fn export(client: &CarbonClient, args: ExportArgs) -> Result<(), CarbonError> {
+    let to = args.to.unwrap_or_else(Utc::now);
+    if args.from >= to {
+        return Err(usage_error(format!(
+            "--from ({from}) must be before --to ({to})",
+            from = args.from.format("%Y-%m-%d %H:%M"),
+            to = to.format("%Y-%m-%d %H:%M")
+        )));
+    }
+    let region = args.region.selector();
+
+    let points = client
+        .intensity_range_for(region.as_ref(), args.from, to)
+        .await?;
+
+    let rows: Vec<ExportRow> = points
+        .into_iter()
+        .map(|point| ExportRow {
+            from: point.from,
+            to: point.to,
+            actual: point.actual,
+            forecast: point.forecast,
+            index: point.index,
+        })
+        .collect();
+    let csv = render_all(&rows, OutputFormat::Csv)?;
+
+    match args.out {
+        Some(path) => {
+            std::fs::write(&path, csv)
+                .map_err(|e| CarbonError::io(format!("Failed to write '{path}'", path = path.display()), e))?;
+            println!(
+                "Wrote {count} settlement periods to {path}",
+                count = rows.len(),
+                path = path.display()
+            );
+        }
+        None => print!("{csv}"),
+    }
+
+    Ok(())
+}
+
+#[instrument(skip(client))]
+async fn fetch_carbon_intensity_history(
+    client: &GridClient,
+    region: Option<&RegionSelector>,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+    bucket: Bucket,
+) -> Result<Vec<HourlyAverage>, CarbonError> {
+    let points = match region {
+        Some(region) => {
+            regional_client(client)?
+                .intensity_between_for(Some(region), from, to)
+                .await?
+        }
+        None => client.intensity_between(from, to).await?,
+    };
+    trace!("Parsed response data: {:?}", points);
+
+    // The API also returns the period ending at `from`; keep only those
+    // starting in the range so it doesn't open an extra, part-filled bucket
+    let first_period = settlement::floor(from, SETTLEMENT_PERIOD);
+    
+    // Group by bucket start and calculate average intensity
+    let mut hourly_data: std::collections::BTreeMap<DateTime<Utc>, Vec<IntensityPoint>> = std::collections::BTreeMap::new();
+    
+    for point in points.into_iter().filter(|p| p.from >= first_period && p.from < to) {
+        let start = timezone::floor(point.from, bucket.width());
+        hourly_data.entry(start).or_default().push(point);
+    }
+    
+    // Average each bucket; points arrive oldest first, so each bucket is too
+    let hourly_averages = hourly_data
+        .into_iter()
+        .filter_map(|(start, points)| {
+            let (first, last) = (points.first()?, points.last()?);
+            Some(HourlyAverage {
+                avg_intensity: average(points.iter().map(|p| p.intensity))?,
+                from: first.from,
+                to: last.to,
+                avg_actual: average(points.iter().filter_map(|p| p.actual)),
+                avg_forecast: average(points.iter().filter_map(|p| p.forecast)),
+                index: last.index,
+                hour: local(start).format("%Y-%m-%d %H:%M").to_string(),
+            })
+        })
+        .collect();
+    
+    Ok(hourly_averages)
+}
+
+/// Integer mean, or `None` for no values
+fn average(values: impl Iterator<Item = i32>) -> Option<i32> {
+    let (sum, count) = values.fold((0, 0), |(sum, count), v| (sum + v, count + 1));
+    (count > 0).then(|| sum / count)
+}
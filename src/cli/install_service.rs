@@ -0,0 +1,170 @@
+//! `install-service`: write a systemd unit that runs `carbon daemon` or
+//! `carbon web` with readiness and watchdog notifications, and optionally a
+//! socket unit so systemd holds the port and starts the server on the first
+//! connection
+
+use crate::CarbonError;
+use crate::cli::{parse_seconds, usage_error};
+use std::fmt::Write as _;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Where system-wide units go
+const SYSTEM_UNIT_DIR: &str = "/etc/systemd/system";
+
+#[derive(Debug, clap::Args)]
+pub struct InstallServiceOptions {
+    /// Command the service runs
+    #[arg(long, value_enum, default_value = "daemon")]
+    command: ServiceCommand,
+    /// Unit name, without `.service`; also names the state directory the
+    /// command runs in, which holds the daemon's archive
+    #[arg(long, default_value = "carbon-vibe")]
+    name: String,
+    /// Install for the current user, in ~/.config/systemd/user, instead of
+    /// system-wide in /etc/systemd/system
+    #[arg(long)]
+    user: bool,
+    /// Write the units into this directory instead
+    #[arg(long, value_name = "DIR")]
+    out_dir: Option<PathBuf>,
+    /// Also write a socket unit listening here, e.g. 0.0.0.0:3000, so
+    /// systemd holds the port across restarts and starts the server on the
+    /// first connection; the server then ignores --bind
+    #[arg(long, value_name = "ADDR")]
+    socket: Option<SocketAddr>,
+    /// Restart the service if it stops answering systemd's watchdog for this
+    /// many seconds; 0 turns the watchdog off
+    #[arg(long, value_name = "SECONDS", value_parser = parse_seconds, default_value = "60")]
+    watchdog: Duration,
+    /// Options for the command, after `--`, e.g. `-- --bind 0.0.0.0:3000`
+    #[arg(last = true, value_name = "ARGS")]
+    args: Vec<String>,
+}
+
+#[derive(Clone, Copy, Debug, clap::ValueEnum)]
+enum ServiceCommand {
+    /// Record into the local archive and serve the dashboard from it
+    Daemon,
+    /// Serve the dashboard straight from upstream
+    Web,
+}
+
+impl ServiceCommand {
+    fn as_str(self) -> &'static str {
+        match self {
+            ServiceCommand::Daemon => "daemon",
+            ServiceCommand::Web => "web",
+        }
+    }
+}
+
+pub fn run(options: InstallServiceOptions) -> Result<(), CarbonError> {
+    let exe = std::env::current_exe().map_err(|e| CarbonError::io("Failed to find the carbon executable", e))?;
+    let dir = match &options.out_dir {
+        Some(dir) => dir.clone(),
+        None if options.user => user_unit_dir()?,
+        None => PathBuf::from(SYSTEM_UNIT_DIR),
+    };
+    std::fs::create_dir_all(&dir)
+        .map_err(|e| CarbonError::io(format!("Failed to create '{dir}'", dir = dir.display()), e))?;
+
+    let service = format!("{name}.service", name = options.name);
+    write_unit(&dir.join(&service), &service_unit(&options, &exe))?;
+    let start = match options.socket {
+        Some(addr) => {
+            let socket = format!("{name}.socket", name = options.name);
+            write_unit(&dir.join(&socket), &socket_unit(&options.name, addr))?;
+            socket
+        }
+        None => service,
+    };
+
+    let systemctl = if options.user { "systemctl --user" } else { "systemctl" };
+    println!("Start it now and at boot with:\n  {systemctl} daemon-reload\n  {systemctl} enable --now {start}");
+    Ok(())
+}
+
+fn write_unit(path: &Path, contents: &str) -> Result<(), CarbonError> {
+    std::fs::write(path, contents)
+        .map_err(|e| CarbonError::io(format!("Failed to write '{path}'", path = path.display()), e))?;
+    println!("Wrote {path}", path = path.display());
+    Ok(())
+}
+
+/// `$XDG_CONFIG_HOME/systemd/user`, by default under ~/.config
+fn user_unit_dir() -> Result<PathBuf, CarbonError> {
+    let config = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+        .ok_or_else(|| usage_error("Neither XDG_CONFIG_HOME nor HOME is set; give --out-dir"))?;
+    Ok(config.join("systemd").join("user"))
+}
+
+/// The service unit: `Type=notify` so dependants wait until it's listening,
+/// restarted on failure or a missed watchdog, and run in its own state
+/// directory so a relative `--db` lands there
+fn service_unit(options: &InstallServiceOptions, exe: &Path) -> String {
+    let mut exec_start = quote(&exe.to_string_lossy());
+    for arg in [options.command.as_str()].into_iter().chain(options.args.iter().map(String::as_str)) {
+        exec_start.push(' ');
+        exec_start.push_str(&quote(arg));
+    }
+
+    let mut unit = String::new();
+    let _ = writeln!(unit, "[Unit]");
+    let _ = writeln!(unit, "Description=carbon-vibe grid carbon intensity {command}", command = options.command.as_str());
+    if !options.user {
+        let _ = writeln!(unit, "Wants=network-online.target");
+        let _ = writeln!(unit, "After=network-online.target");
+    }
+    if options.socket.is_some() {
+        let _ = writeln!(unit, "Requires={name}.socket", name = options.name);
+        let _ = writeln!(unit, "After={name}.socket", name = options.name);
+    }
+    let _ = writeln!(unit);
+    let _ = writeln!(unit, "[Service]");
+    let _ = writeln!(unit, "Type=notify");
+    let _ = writeln!(unit, "ExecStart={exec_start}");
+    let _ = writeln!(unit, "Restart=on-failure");
+    let _ = writeln!(unit, "RestartSec=5");
+    if !options.watchdog.is_zero() {
+        let _ = writeln!(unit, "WatchdogSec={secs}", secs = options.watchdog.as_secs());
+    }
+    let _ = writeln!(unit, "StateDirectory={name}", name = options.name);
+    let _ = writeln!(unit, "WorkingDirectory=%S/{name}", name = options.name);
+    if !options.user {
+        // Its own unprivileged user, which can only write the state directory
+        let _ = writeln!(unit, "DynamicUser=yes");
+    }
+    let _ = writeln!(unit);
+    let _ = writeln!(unit, "[Install]");
+    let _ = writeln!(unit, "WantedBy={target}", target = if options.user { "default.target" } else { "multi-user.target" });
+    unit
+}
+
+/// The socket unit, which starts the service on the first connection
+fn socket_unit(name: &str, addr: SocketAddr) -> String {
+    let mut unit = String::new();
+    let _ = writeln!(unit, "[Unit]");
+    let _ = writeln!(unit, "Description=carbon-vibe dashboard socket");
+    let _ = writeln!(unit);
+    let _ = writeln!(unit, "[Socket]");
+    let _ = writeln!(unit, "ListenStream={addr}");
+    let _ = writeln!(unit, "Service={name}.service");
+    let _ = writeln!(unit);
+    let _ = writeln!(unit, "[Install]");
+    let _ = writeln!(unit, "WantedBy=sockets.target");
+    unit
+}
+
+/// One `ExecStart=` word: double-quoted if it has spaces or quotes, with
+/// `%` and `$` doubled so systemd doesn't expand them
+fn quote(word: &str) -> String {
+    let escaped = word.replace('%', "%%").replace('$', "$$");
+    if !escaped.is_empty() && !escaped.contains(|c: char| c.is_whitespace() || matches!(c, '"' | '\'' | '\\')) {
+        return escaped;
+    }
+    format!("\"{quoted}\"", quoted = escaped.replace('\\', "\\\\").replace('"', "\\\""))
+}
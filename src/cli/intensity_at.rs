@@ -0,0 +1,69 @@
+use crate::{CarbonClient, CarbonError};
+use crate::cli::ClientArgs;
+use crate::cli::timezone::local;
+use crate::lookup::{containing_period, interpolated_intensity};
+use crate::model::{IntensityPoint, parse_datetime_arg};
+use crate::units;
+use chrono::{DateTime, Utc};
+use tracing::{instrument, trace};
+
+/// Look up the intensity at one moment
+#[derive(Debug, clap::Args)]
+pub struct IntensityAtOptions {
+    /// Time to look up, e.g. 2025-06-26T14:17Z
+    #[arg(value_parser = parse_datetime_arg)]
+    at: DateTime<Utc>,
+    /// Estimate between neighbouring period midpoints instead of using the
+    /// settlement period's value
+    #[arg(long)]
+    interpolate: bool,
+    #[command(flatten)]
+    client: ClientArgs,
+}
+
+pub async fn run(options: IntensityAtOptions) -> Result<(), CarbonError> {
+    let IntensityAtOptions { at, interpolate, client } = options;
+    let periods = fetch_periods_around(&client.client()?, at).await?;
+
+    if interpolate {
+        if let Some((value, before, after)) = interpolated_intensity(&periods, at) {
+            println!(
+                "{time}: {value} {unit} (interpolated between {before} and {after})",
+                time = local(at).format("%Y-%m-%d %H:%M %Z"),
+                value = units::figure(value),
+                unit = units::label(),
+                before = local(before).format("%H:%M"),
+                after = local(after).format("%H:%M")
+            );
+            return Ok(());
+        }
+        trace!("Not enough surrounding periods to interpolate, using step value");
+    }
+
+    let period = containing_period(&periods, at)
+        .ok_or_else(|| CarbonError::EmptyData("data for the requested time".into()))?;
+    println!(
+        "{time}: {intensity} {unit} (settlement period {from}-{to})",
+        time = local(at).format("%Y-%m-%d %H:%M %Z"),
+        intensity = units::figure(period.intensity),
+        unit = units::label(),
+        from = local(period.from).format("%H:%M"),
+        to = local(period.to).format("%H:%M")
+    );
+
+    Ok(())
+}
+
+#[instrument(skip(client))]
+async fn fetch_periods_around(
+    client: &CarbonClient,
+    at: DateTime<Utc>,
+) -> Result<Vec<IntensityPoint>, CarbonError> {
+    // One hour either side gives the neighbouring periods needed to interpolate
+    let periods = client
+        .intensity_between(at - chrono::Duration::hours(1), at + chrono::Duration::hours(1))
+        .await?;
+    trace!("Parsed response data: {:?}", periods);
+
+    Ok(periods)
+}
@@ -0,0 +1,38 @@
+//! `man`: roff man pages generated from the argument definitions, one for
+//! `carbon` and one per command (`carbon-history.1` and so on)
+
+use crate::CarbonError;
+use crate::cli::Cli;
+use clap::CommandFactory;
+use std::path::PathBuf;
+
+#[derive(Debug, clap::Args)]
+pub struct ManOptions {
+    /// Write `carbon.1` and a page for each command into this directory
+    /// instead of printing `carbon.1`
+    #[arg(long, value_name = "DIR")]
+    out_dir: Option<PathBuf>,
+}
+
+pub fn run(options: ManOptions) -> Result<(), CarbonError> {
+    let command = Cli::command();
+    match options.out_dir {
+        Some(dir) => {
+            std::fs::create_dir_all(&dir).map_err(|e| {
+                CarbonError::io(format!("Failed to create '{dir}'", dir = dir.display()), e)
+            })?;
+            clap_mangen::generate_to(command, &dir).map_err(|e| {
+                CarbonError::io(
+                    format!("Failed to write man pages to '{dir}'", dir = dir.display()),
+                    e,
+                )
+            })?;
+            println!("Wrote man pages to {dir}", dir = dir.display());
+        }
+        None => clap_mangen::Man::new(command)
+            .render(&mut std::io::stdout())
+            .map_err(|e| CarbonError::io("Failed to write the man page", e))?,
+    }
+
+    Ok(())
+}
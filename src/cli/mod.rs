@@ -0,0 +1,509 @@
+//! The command-line tools. Each command lives in its own module with a clap
+//! options struct and a `run` function; the `carbon` binary dispatches to
+//! them as subcommands and the single-purpose binaries (`current`, `history`,
+//! ...) run one directly.
+
+pub mod accuracy;
+pub mod advice;
+pub mod alert;
+pub mod archive;
+pub mod backfill;
+pub mod best_window;
+pub mod calendar;
+pub mod compare;
+pub mod completions;
+pub mod config;
+pub mod current;
+pub mod daemon;
+pub mod ev_plan;
+pub mod export;
+pub mod failures;
+pub mod footprint;
+pub mod forecast;
+pub mod history;
+pub mod install_service;
+pub mod intensity_at;
+pub mod man;
+pub mod mqtt;
+pub mod report;
+pub mod run;
+pub mod seed;
+pub mod single_flight;
+pub mod stats;
+pub mod systemd;
+pub mod timezone;
+pub mod today;
+pub mod tui;
+pub mod watch;
+pub mod web;
+
+use crate::{CarbonClient, CarbonError};
+use crate::client::{DEFAULT_BASE_URL, DEFAULT_MAX_ATTEMPTS, DEFAULT_TIMEOUT};
+use crate::electricitymaps::{DEFAULT_ELECTRICITYMAPS_URL, ElectricityMapsClient};
+use crate::entsoe::{Area, DEFAULT_ENTSOE_URL, EntsoeClient};
+use crate::model::{REGION_NAMES, RegionSelector};
+use crate::octopus::{DEFAULT_AGILE_PRODUCT, DEFAULT_OCTOPUS_URL, OctopusClient, parse_agile_region};
+use crate::provider::GridClient;
+use crate::quality::{self, StrictArgs};
+use crate::settlement::next_poll;
+use crate::units::UnitsArgs;
+use chrono::{TimeDelta, Utc};
+use clap::builder::{PossibleValue, PossibleValuesParser, TypedValueParser};
+use clap::error::ErrorKind;
+use clap::{FromArgMatches, Parser, Subcommand};
+use reqwest::header::HeaderValue;
+use std::fmt;
+use std::time::Duration;
+
+/// UK grid carbon intensity from the command line
+#[derive(Debug, Parser)]
+#[command(name = "carbon", version)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Command,
+    #[command(flatten)]
+    pub timezone: timezone::TimezoneArgs,
+    #[command(flatten)]
+    pub units: UnitsArgs,
+    #[command(flatten)]
+    pub strict: StrictArgs,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Current intensity, national or for one region
+    Current(current::CurrentOptions),
+    /// Current intensity in every region, greenest first
+    Compare(compare::CompareOptions),
+    /// Averages over a past time range
+    History(history::HistoryOptions),
+    /// Statistics over the last N days: percentiles, greenest hours, weekdays
+    /// against weekends
+    Stats(stats::StatsOptions),
+    /// How close the forecast came to the actual intensity over the last N
+    /// days
+    Accuracy(accuracy::AccuracyOptions),
+    /// Weekly or monthly digest: average, best and worst days, mix trends
+    /// and what shifting load would have saved, printed or emailed
+    Report(report::ReportOptions),
+    /// Intensity at a specific time
+    IntensityAt(intensity_at::IntensityAtOptions),
+    /// Forecast for the rest of today with the cleanest period
+    Today(today::TodayOptions),
+    /// 48-hour forward forecast
+    Forecast(forecast::ForecastOptions),
+    /// Greenest time to run something in the coming hours
+    BestWindow(best_window::BestWindowOptions),
+    /// iCalendar file of the forecast windows below a threshold
+    Calendar(calendar::CalendarOptions),
+    /// Estimated CO₂ for an appliance's energy use, now or at a later time
+    Footprint(footprint::FootprintOptions),
+    /// Whether to start an appliance from config.toml now or wait for a
+    /// greener time, and the CO₂ difference
+    Advice(advice::AdviceOptions),
+    /// Plan EV charging in the greenest half-hours before a deadline,
+    /// pausing between them
+    EvPlan(ev_plan::EvPlanOptions),
+    /// Record history into a local database and query it
+    Archive(archive::ArchiveOptions),
+    /// Fill the local archive with history back to a date, resuming if
+    /// interrupted
+    Backfill(backfill::BackfillOptions),
+    /// Write the local archive as Parquet or Arrow files for data analysis
+    Export(export::ExportOptions),
+    /// Notify when intensity drops below or rises above a threshold
+    Watch(watch::WatchOptions),
+    /// Send alerts to stdout, webhooks, Slack, Discord, email or MQTT when
+    /// rules from a file start to hold
+    Alert(alert::AlertOptions),
+    /// Publish to an MQTT broker, with Home Assistant discovery
+    Mqtt(mqtt::MqttOptions),
+    /// Run a command once intensity is low enough, or at a deadline
+    Run(run::RunOptions),
+    /// Serve the web dashboard
+    Web(web::DashboardOptions),
+    /// Live dashboard in the terminal
+    Tui(tui::TuiOptions),
+    /// Record into the local archive and serve the dashboard from it
+    Daemon(daemon::DaemonOptions),
+    /// Print a shell completion script for `carbon`
+    Completions(completions::CompletionsOptions),
+    /// Write man pages for `carbon` and its commands
+    Man(man::ManOptions),
+    /// Write a systemd unit running `daemon` or `web`, with watchdog and
+    /// optional socket activation
+    InstallService(install_service::InstallServiceOptions),
+}
+
+impl Command {
+    pub async fn run(self) -> Result<(), CarbonError> {
+        match self {
+            Command::Current(options) => current::run(options).await,
+            Command::Compare(options) => compare::run(options).await,
+            Command::History(options) => history::run(options).await,
+            Command::Stats(options) => stats::run(options).await,
+            Command::Accuracy(options) => accuracy::run(options).await,
+            Command::Report(options) => report::run(options).await,
+            Command::IntensityAt(options) => intensity_at::run(options).await,
+            Command::Today(options) => today::run(options).await,
+            Command::Forecast(options) => forecast::run(options).await,
+            Command::BestWindow(options) => best_window::run(options).await,
+            Command::Calendar(options) => calendar::run(options).await,
+            Command::Footprint(options) => footprint::run(options).await,
+            Command::Advice(options) => advice::run(options).await,
+            Command::EvPlan(options) => ev_plan::run(options).await,
+            Command::Archive(options) => archive::run(options).await,
+            Command::Backfill(options) => backfill::run(options).await,
+            Command::Export(options) => export::run(options).await,
+            Command::Watch(options) => watch::run(options).await,
+            Command::Alert(options) => alert::run(options).await,
+            Command::Mqtt(options) => mqtt::run(options).await,
+            Command::Run(options) => run::run(options).await,
+            Command::Web(options) => web::run(options).await,
+            Command::Tui(options) => tui::run(options).await,
+            Command::Daemon(options) => daemon::run(options).await,
+            Command::Completions(options) => completions::run(options),
+            Command::Man(options) => man::run(options),
+            Command::InstallService(options) => install_service::run(options),
+        }
+    }
+}
+
+/// `--postcode` or `--region`, for commands that can show one region instead
+/// of the national figures
+#[derive(Clone, Debug, Default, clap::Args)]
+pub struct RegionArgs {
+    /// GB postcode to show figures for (only the outward code is used)
+    #[arg(long, value_parser = RegionSelector::postcode, conflicts_with = "region")]
+    postcode: Option<RegionSelector>,
+    /// Region id from the API, e.g. 13 for London
+    #[arg(long, value_name = "1-17", value_parser = region_id_parser())]
+    region: Option<u32>,
+}
+
+/// `--region` and `--regions`: one of the API's region ids, which shell completion offers
+/// with each region's name
+pub(crate) fn region_id_parser() -> impl TypedValueParser<Value = u32> {
+    PossibleValuesParser::new(
+        REGION_NAMES
+            .iter()
+            .enumerate()
+            .map(|(index, name)| PossibleValue::new((index + 1).to_string()).help(*name)),
+    )
+    .try_map(|id| id.parse::<u32>())
+}
+
+impl RegionArgs {
+    /// The region asked for, or `None` for national figures
+    pub fn selector(&self) -> Option<RegionSelector> {
+        match (&self.postcode, self.region) {
+            (Some(postcode), _) => Some(postcode.clone()),
+            (None, Some(region_id)) => Some(RegionSelector::RegionId(region_id)),
+            (None, None) => None,
+        }
+    }
+}
+
+/// Where grid data comes from
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum Provider {
+    /// The UK Carbon Intensity API, for Great Britain
+    #[default]
+    CarbonIntensity,
+    /// The ENTSO-E Transparency Platform, for the rest of Europe; needs an
+    /// API token
+    Entsoe,
+    /// Electricity Maps, for zones around the world; needs an API key
+    #[value(name = "electricitymaps")]
+    ElectricityMaps,
+}
+
+impl fmt::Display for Provider {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let value = clap::ValueEnum::to_possible_value(self).expect("no provider is skipped");
+        f.write_str(value.get_name())
+    }
+}
+
+/// Upstream request settings, accepted by every command
+#[derive(Clone, Debug, clap::Args)]
+#[command(next_help_heading = "Upstream requests")]
+pub struct ClientArgs {
+    /// Source of grid data. Only current, history and web can use providers
+    /// other than carbon-intensity.
+    #[arg(long, global = true, value_enum, default_value_t = Provider::CarbonIntensity)]
+    provider: Provider,
+    /// Base URL of the provider's API, e.g. a local mock or mirror [default:
+    /// the provider's public API]
+    #[arg(long, global = true, value_name = "URL", env = "CARBON_API_URL")]
+    api_url: Option<String>,
+    /// ENTSO-E Transparency Platform API token
+    #[arg(long, global = true, value_name = "TOKEN", env = "ENTSOE_TOKEN", hide_env_values = true)]
+    entsoe_token: Option<String>,
+    /// ENTSO-E area to show, by country code (e.g. DE, FR, DK1) or EIC code
+    #[arg(long, global = true, value_name = "AREA", value_parser = Area::lookup)]
+    entsoe_area: Option<Area>,
+    /// Electricity Maps API key
+    #[arg(
+        long,
+        global = true,
+        value_name = "KEY",
+        env = "ELECTRICITYMAPS_TOKEN",
+        hide_env_values = true,
+        value_parser = parse_header_value
+    )]
+    electricitymaps_token: Option<HeaderValue>,
+    /// Electricity Maps zone to show, e.g. DE, FR or US-CAL-CISO
+    #[arg(long, global = true, value_name = "ZONE")]
+    zone: Option<String>,
+    /// Attempts per request to the provider's API, including the first;
+    /// timeouts, connection failures and 5xx responses are retried
+    #[arg(
+        long,
+        global = true,
+        default_value_t = DEFAULT_MAX_ATTEMPTS,
+        value_parser = clap::value_parser!(u32).range(1..)
+    )]
+    max_attempts: u32,
+    /// Give up on a single attempt after this many seconds
+    #[arg(long, global = true, value_name = "SECONDS", default_value_t = DEFAULT_TIMEOUT.as_secs())]
+    request_timeout: u64,
+}
+
+impl ClientArgs {
+    /// Carbon Intensity API client, for commands that only work with GB data
+    pub fn client(&self) -> Result<CarbonClient, CarbonError> {
+        if self.provider != Provider::CarbonIntensity {
+            return Err(usage_error(format!(
+                "--provider {provider} only works with current, history and web",
+                provider = self.provider
+            )));
+        }
+        Ok(self.http(DEFAULT_BASE_URL))
+    }
+
+    /// Client for the chosen provider
+    pub fn provider(&self) -> Result<GridClient, CarbonError> {
+        match self.provider {
+            Provider::CarbonIntensity => Ok(GridClient::CarbonIntensity(self.http(DEFAULT_BASE_URL))),
+            Provider::Entsoe => {
+                let token = self.entsoe_token.as_deref().ok_or_else(|| {
+                    usage_error("--provider entsoe needs --entsoe-token (or ENTSOE_TOKEN)")
+                })?;
+                let area = self
+                    .entsoe_area
+                    .ok_or_else(|| usage_error("--provider entsoe needs --entsoe-area"))?;
+                Ok(GridClient::Entsoe(EntsoeClient::new(
+                    self.http(DEFAULT_ENTSOE_URL),
+                    token,
+                    area,
+                )))
+            }
+            Provider::ElectricityMaps => {
+                let token = self.electricitymaps_token.clone().ok_or_else(|| {
+                    usage_error(
+                        "--provider electricitymaps needs --electricitymaps-token (or ELECTRICITYMAPS_TOKEN)",
+                    )
+                })?;
+                let zone = self
+                    .zone
+                    .as_deref()
+                    .ok_or_else(|| usage_error("--provider electricitymaps needs --zone"))?;
+                Ok(GridClient::ElectricityMaps(ElectricityMapsClient::new(
+                    self.http(DEFAULT_ELECTRICITYMAPS_URL),
+                    token,
+                    zone,
+                )))
+            }
+        }
+    }
+
+    /// Client for the provider's API at `default_url`, or `--api-url`
+    fn http(&self, default_url: &str) -> CarbonClient {
+        self.http_at(self.api_url.as_deref().unwrap_or(default_url))
+    }
+
+    /// Client for another API at `base_url`, with the same retry settings
+    fn http_at(&self, base_url: &str) -> CarbonClient {
+        CarbonClient::with_base_url(base_url)
+            .with_max_attempts(self.max_attempts)
+            .with_timeout(Duration::from_secs(self.request_timeout))
+    }
+}
+
+/// Octopus Agile prices, for commands that can weigh price against carbon
+#[derive(Clone, Debug, clap::Args)]
+#[command(next_help_heading = "Agile prices")]
+pub struct PriceArgs {
+    /// Octopus Agile region letter, e.g. C for London; weighs Agile prices
+    /// against carbon when given
+    #[arg(long, value_name = "A-P", value_parser = parse_agile_region)]
+    agile_region: Option<char>,
+    /// Agile product code
+    #[arg(long, value_name = "CODE", default_value = DEFAULT_AGILE_PRODUCT)]
+    agile_product: String,
+    /// How much price counts against carbon, from 0 (carbon only) to 1
+    /// (price only)
+    #[arg(long, value_name = "0-1", value_parser = parse_weight, default_value_t = 0.5)]
+    price_weight: f64,
+    /// Base URL of the Octopus Energy API, e.g. a local mock
+    #[arg(long, value_name = "URL", env = "OCTOPUS_API_URL", default_value = DEFAULT_OCTOPUS_URL)]
+    octopus_url: String,
+}
+
+impl PriceArgs {
+    /// Client for the Agile tariff asked for, or `None` without
+    /// `--agile-region`
+    pub fn octopus(&self, client: &ClientArgs) -> Option<OctopusClient> {
+        self.agile_region.map(|region| {
+            OctopusClient::new(client.http_at(&self.octopus_url), &self.agile_product, region)
+        })
+    }
+
+    pub fn price_weight(&self) -> f64 {
+        self.price_weight
+    }
+}
+
+/// Parse the `carbon` command line, with defaults from the config file
+pub fn parse_cli() -> Cli {
+    let command = config::with_config(<Cli as clap::CommandFactory>::command(), None);
+    let cli = Cli::from_arg_matches(&command.get_matches()).unwrap_or_else(|e| e.exit());
+    cli.timezone.apply();
+    cli.units.apply();
+    cli.strict.apply();
+    cli
+}
+
+/// Parse the command line of a single-purpose binary, e.g. `current`, which
+/// takes the same options (and config file settings) as its `carbon`
+/// subcommand
+pub fn parse_args<A: clap::Args>(name: &'static str) -> A {
+    let command = A::augment_args(clap::Command::new(name).version(env!("CARGO_PKG_VERSION")));
+    let command = <timezone::TimezoneArgs as clap::Args>::augment_args(command);
+    let command = <UnitsArgs as clap::Args>::augment_args(command);
+    let command = <StrictArgs as clap::Args>::augment_args(command);
+    let matches = config::with_config(command, Some(name)).get_matches();
+    timezone::TimezoneArgs::from_arg_matches(&matches)
+        .unwrap_or_else(|e| e.exit())
+        .apply();
+    UnitsArgs::from_arg_matches(&matches).unwrap_or_else(|e| e.exit()).apply();
+    StrictArgs::from_arg_matches(&matches).unwrap_or_else(|e| e.exit()).apply();
+    A::from_arg_matches(&matches).unwrap_or_else(|e| e.exit())
+}
+
+pub fn init_tracing() {
+    let filter = match std::env::var("RUST_LOG") {
+        Ok(level) if level == "trace" => "carbon_vibe=trace,warn".to_string(),
+        Ok(level) => level,
+        Err(_) => "info".to_string(),
+    };
+
+    // One JSON object per line, with the fields of the enclosing spans, for
+    // log collectors
+    let json = match std::env::var("CARBON_LOG_FORMAT") {
+        Ok(format) if format == "json" => true,
+        Ok(format) if format != "text" => {
+            eprintln!("Warning: unknown CARBON_LOG_FORMAT '{format}', expected text or json");
+            false
+        }
+        _ => false,
+    };
+
+    // Logs go to stderr so they never mix into JSON/CSV on stdout
+    let subscriber = tracing_subscriber::fmt()
+        .with_env_filter(tracing_subscriber::EnvFilter::new(filter))
+        .with_writer(std::io::stderr);
+    if json {
+        subscriber.json().init();
+    } else {
+        subscriber.init();
+    }
+}
+
+/// Exit with a message if a command failed, or raised data-quality warnings
+/// under `--strict`, with the status for the kind of failure (see
+/// [`CarbonError::exit_code`]); bad arguments exit 2, as clap uses
+pub fn exit_on_error(result: Result<(), CarbonError>) {
+    if let Err(e) = result.and_then(|()| quality::check_deferred()) {
+        if let CarbonError::Usage(usage) = &e {
+            usage.exit();
+        }
+        // Report failures by message rather than as a debug dump
+        eprintln!("Error: {error}", error = e);
+        std::process::exit(e.exit_code());
+    }
+}
+
+/// A combination of arguments that is invalid in a way clap can't check,
+/// reported like clap's own errors
+pub fn usage_error(message: impl fmt::Display) -> CarbonError {
+    CarbonError::Usage(clap::Error::raw(ErrorKind::ArgumentConflict, format!("{message}\n")))
+}
+
+/// The Carbon Intensity API client behind `provider`, for `--postcode` and
+/// `--region`, which only it has data for
+pub fn regional_client(provider: &GridClient) -> Result<&CarbonClient, CarbonError> {
+    provider
+        .carbon_intensity()
+        .ok_or_else(|| usage_error("--postcode and --region only work with --provider carbon-intensity"))
+}
+
+/// An `--interval` as a timer period. `parse_duration_arg` only accepts
+/// positive durations, so this only fails for one built some other way.
+pub fn interval_duration(interval: TimeDelta) -> Result<Duration, CarbonError> {
+    interval
+        .to_std()
+        .map_err(|_| usage_error("--interval must be positive"))
+}
+
+/// Wakes an `--interval` polling loop: straight away, then in step with
+/// settlement periods (see [`next_poll`]), so a half-hourly poller picks up
+/// each new period just after it's published rather than up to half an hour
+/// late
+pub struct PollTicker {
+    interval: TimeDelta,
+    started: bool,
+}
+
+impl PollTicker {
+    /// Fails as [`interval_duration`] does
+    pub fn new(interval: TimeDelta) -> Result<Self, CarbonError> {
+        interval_duration(interval)?;
+        Ok(PollTicker {
+            interval,
+            started: false,
+        })
+    }
+
+    pub async fn tick(&mut self) {
+        if !std::mem::replace(&mut self.started, true) {
+            return;
+        }
+        let now = Utc::now();
+        let wake = next_poll(now, self.interval);
+        tokio::time::sleep((wake - now).to_std().unwrap_or_default()).await;
+    }
+}
+
+/// A weighting from 0 to 1
+fn parse_weight(value: &str) -> Result<f64, String> {
+    value
+        .parse()
+        .ok()
+        .filter(|weight| (0.0..=1.0).contains(weight))
+        .ok_or_else(|| format!("Invalid weight '{value}': expected a number from 0 to 1"))
+}
+
+/// An API key, which has to be sendable as a header
+fn parse_header_value(value: &str) -> Result<HeaderValue, String> {
+    HeaderValue::from_str(value).map_err(|_| "API keys can only contain printable ASCII".to_string())
+}
+
+/// Parse a whole number of seconds
+pub fn parse_seconds(value: &str) -> Result<Duration, String> {
+    value
+        .parse()
+        .map(Duration::from_secs)
+        .map_err(|_| format!("Invalid duration '{value}': expected seconds"))
+}
@@ -0,0 +1,290 @@
+//! `mqtt`: publish intensity, index and generation mix to an MQTT broker on a
+//! schedule, with Home Assistant discovery so they appear as sensors
+
+use crate::{CarbonClient, CarbonError};
+use crate::cli::{ClientArgs, PollTicker, RegionArgs};
+use crate::model::{FuelSource, IntensityIndex, RegionSelector};
+use crate::schedule::parse_duration_arg;
+use chrono::TimeDelta;
+use rumqttc::{AsyncClient, Event, LastWill, Packet, QoS};
+use serde::Serialize;
+use std::collections::HashSet;
+use std::time::Duration;
+use tracing::{info, instrument, warn};
+
+// Pause before reconnecting after the connection to the broker fails
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+#[derive(Debug, clap::Args)]
+pub struct MqttOptions {
+    /// Broker host name or address
+    #[arg(long, default_value = "localhost")]
+    broker: String,
+    /// Broker port
+    #[arg(long, default_value_t = 1883)]
+    port: u16,
+    #[arg(long, requires = "password")]
+    username: Option<String>,
+    /// Broker password; prefer the environment variable, which isn't visible
+    /// in the process list
+    #[arg(
+        long,
+        env = "CARBON_MQTT_PASSWORD",
+        hide_env_values = true,
+        requires = "username"
+    )]
+    password: Option<String>,
+    /// MQTT client id, also identifying the device in Home Assistant
+    #[arg(long, default_value = "carbon-vibe")]
+    client_id: String,
+    /// Values are published under this prefix, e.g. carbon-vibe/intensity
+    #[arg(long, value_name = "PREFIX", default_value = "carbon-vibe")]
+    topic_prefix: String,
+    /// Prefix Home Assistant watches for discovery messages
+    #[arg(long, value_name = "PREFIX", default_value = "homeassistant")]
+    discovery_prefix: String,
+    /// Don't publish Home Assistant discovery messages
+    #[arg(long)]
+    no_discovery: bool,
+    /// How often to publish
+    #[arg(long, value_parser = parse_duration_arg, default_value = "30m")]
+    interval: TimeDelta,
+    #[command(flatten)]
+    region: RegionArgs,
+    #[command(flatten)]
+    client: ClientArgs,
+}
+
+/// What each publishing pass sends
+#[derive(Debug)]
+struct Snapshot {
+    intensity: i32,
+    index: Option<IntensityIndex>,
+    generation_mix: Vec<FuelSource>,
+}
+
+/// A Home Assistant MQTT discovery config for one sensor
+#[derive(Debug, Serialize)]
+struct SensorConfig<'a> {
+    name: String,
+    unique_id: String,
+    state_topic: String,
+    availability_topic: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    unit_of_measurement: Option<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    state_class: Option<&'static str>,
+    icon: &'static str,
+    device: Device<'a>,
+}
+
+#[derive(Debug, Serialize)]
+struct Device<'a> {
+    identifiers: [&'a str; 1],
+    name: &'static str,
+    model: &'static str,
+}
+
+/// Topics and discovery for one connection
+struct Publisher {
+    mqtt: AsyncClient,
+    topic_prefix: String,
+    discovery_prefix: Option<String>,
+    node_id: String,
+    /// Sensors already announced to Home Assistant; fuels are announced as
+    /// they first appear in the mix
+    announced: HashSet<String>,
+}
+
+impl Publisher {
+    fn topic(&self, name: &str) -> String {
+        format!("{prefix}/{name}", prefix = self.topic_prefix)
+    }
+
+    async fn publish(&self, topic: String, payload: String) -> Result<(), rumqttc::ClientError> {
+        // Retained, so new subscribers (and Home Assistant after a restart)
+        // see the latest values straight away
+        self.mqtt
+            .publish(topic, QoS::AtLeastOnce, true, payload)
+            .await
+    }
+
+    async fn publish_snapshot(&mut self, snapshot: &Snapshot) -> Result<(), rumqttc::ClientError> {
+        self.announce(
+            "intensity",
+            "Carbon intensity",
+            Some("gCO₂/kWh"),
+            "mdi:molecule-co2",
+        )
+        .await?;
+        self.announce("index", "Carbon intensity index", None, "mdi:leaf")
+            .await?;
+        self.publish(self.topic("intensity"), snapshot.intensity.to_string())
+            .await?;
+        // An empty retained message would clear the last known band
+        if let Some(index) = snapshot.index {
+            self.publish(self.topic("index"), index.to_string()).await?;
+        }
+
+        for source in &snapshot.generation_mix {
+            let name = format!("generation/{fuel}", fuel = source.fuel);
+            let label = format!("Generation {fuel}", fuel = source.fuel);
+            self.announce(&name, &label, Some("%"), "mdi:transmission-tower")
+                .await?;
+            self.publish(self.topic(&name), format!("{perc:.1}", perc = source.perc))
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Send the discovery config for a sensor the first time it is published
+    async fn announce(
+        &mut self,
+        name: &str,
+        label: &str,
+        unit: Option<&'static str>,
+        icon: &'static str,
+    ) -> Result<(), rumqttc::ClientError> {
+        let Some(discovery_prefix) = &self.discovery_prefix else {
+            return Ok(());
+        };
+        if self.announced.contains(name) {
+            return Ok(());
+        }
+
+        // Object ids only allow [a-zA-Z0-9_-]
+        let object_id = name.replace('/', "_");
+        let availability_topic = self.topic("status");
+        let config = SensorConfig {
+            name: label.to_string(),
+            unique_id: format!("{node}_{object_id}", node = self.node_id),
+            state_topic: self.topic(name),
+            availability_topic: &availability_topic,
+            unit_of_measurement: unit,
+            // Numeric sensors get history graphs and statistics
+            state_class: unit.map(|_| "measurement"),
+            icon,
+            device: Device {
+                identifiers: [&self.node_id],
+                name: "Carbon Vibe",
+                model: "GB grid carbon intensity",
+            },
+        };
+        let topic = format!(
+            "{discovery_prefix}/sensor/{node}/{object_id}/config",
+            node = self.node_id
+        );
+        let payload = serde_json::to_string(&config).unwrap_or_default();
+        self.publish(topic, payload).await?;
+        self.announced.insert(name.to_string());
+        Ok(())
+    }
+}
+
+pub async fn run(options: MqttOptions) -> Result<(), CarbonError> {
+    let client = options.client.client()?;
+    let region = options.region.selector();
+    let status_topic = format!("{prefix}/status", prefix = options.topic_prefix);
+
+    let mut mqtt_options =
+        rumqttc::MqttOptions::new(&options.client_id, &options.broker, options.port);
+    mqtt_options.set_keep_alive(Duration::from_secs(60));
+    // The broker marks the sensors unavailable if we drop off
+    mqtt_options.set_last_will(LastWill::new(
+        &status_topic,
+        "offline",
+        QoS::AtLeastOnce,
+        true,
+    ));
+    if let (Some(username), Some(password)) = (options.username, options.password) {
+        mqtt_options.set_credentials(username, password);
+    }
+
+    let (mqtt, mut event_loop) = AsyncClient::new(mqtt_options, 64);
+
+    // The event loop does the network I/O and reconnects; it has to be polled
+    // for anything to be sent
+    let status_client = mqtt.clone();
+    let broker = format!("{host}:{port}", host = options.broker, port = options.port);
+    tokio::spawn(async move {
+        loop {
+            match event_loop.poll().await {
+                Ok(Event::Incoming(Packet::ConnAck(_))) => {
+                    info!("Connected to MQTT broker {broker}");
+                    // Undo the last will after a reconnect
+                    if let Err(e) =
+                        status_client.try_publish(&status_topic, QoS::AtLeastOnce, true, "online")
+                    {
+                        warn!("Failed to publish status: {e}");
+                    }
+                }
+                Ok(_) => {}
+                Err(e) => {
+                    warn!("MQTT connection to {broker} failed: {e}");
+                    tokio::time::sleep(RECONNECT_DELAY).await;
+                }
+            }
+        }
+    });
+
+    let mut publisher = Publisher {
+        mqtt,
+        discovery_prefix: (!options.no_discovery).then_some(options.discovery_prefix),
+        topic_prefix: options.topic_prefix,
+        node_id: options.client_id,
+        announced: HashSet::new(),
+    };
+
+    let mut ticker = PollTicker::new(options.interval)?;
+    loop {
+        ticker.tick().await;
+        let snapshot = match fetch_snapshot(&client, region.as_ref()).await {
+            Ok(snapshot) => snapshot,
+            Err(e) => {
+                warn!("Failed to fetch intensity: {e}");
+                continue;
+            }
+        };
+        match publisher.publish_snapshot(&snapshot).await {
+            Ok(()) => info!(
+                "Published intensity {intensity} and {fuels} fuels",
+                intensity = snapshot.intensity,
+                fuels = snapshot.generation_mix.len()
+            ),
+            Err(e) => warn!("Failed to publish: {e}"),
+        }
+    }
+}
+
+/// Intensity, index and generation mix, national or for the selected region
+#[instrument(skip(client))]
+async fn fetch_snapshot(
+    client: &CarbonClient,
+    region: Option<&RegionSelector>,
+) -> Result<Snapshot, CarbonError> {
+    match region {
+        Some(region) => {
+            let region = client.regional_reading(region).await?.region;
+            let intensity = region.intensity.best().ok_or_else(|| {
+                CarbonError::EmptyData(format!(
+                    "intensity for {region}",
+                    region = region.short_name
+                ))
+            })?;
+            Ok(Snapshot {
+                intensity,
+                index: region.intensity.index,
+                generation_mix: region.generation_mix,
+            })
+        }
+        None => {
+            let period = client.current_period().await?;
+            let generation_mix = client.generation_mix().await?;
+            Ok(Snapshot {
+                intensity: period.intensity,
+                index: period.index,
+                generation_mix,
+            })
+        }
+    }
+}
@@ -0,0 +1,519 @@
+//! `report`: a weekly or monthly digest of the national grid, for
+//! sustainability reporting. It covers the average intensity and how it
+//! moved since the period before, the best and worst days, which fuels rose
+//! and fell, and what moving a daily load into each day's greenest window
+//! would have saved. It is emailed as HTML with a plain text part, or
+//! printed.
+
+use crate::CarbonError;
+use crate::cli::alert::smtp_transport;
+use crate::cli::footprint::{describe_grams, parse_positive};
+use crate::cli::{ClientArgs, usage_error};
+use crate::model::{GenerationMixPeriod, IntensityIndex, IntensityPoint};
+use crate::schedule::{describe_duration, greenest_window, parse_duration_arg};
+use crate::settlement::{SettlementPeriod, uk_midnight};
+use crate::store::Store;
+use crate::theme::LIGHT;
+use crate::units;
+use askama::Template;
+use chrono::{DateTime, Datelike, Days, Months, NaiveDate, TimeDelta, Utc};
+use lettre::message::{Mailbox, MultiPart};
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncTransport, Message};
+use std::collections::BTreeMap;
+use tracing::info;
+
+/// Which whole period to report on, in UK time
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum ReportPeriod {
+    /// The last whole week, Monday to Sunday
+    Weekly,
+    /// The last whole calendar month
+    Monthly,
+}
+
+impl ReportPeriod {
+    /// Start of the period before the last whole one before `today`, the
+    /// start of that last one, and the day after it ends
+    fn days(self, today: NaiveDate) -> (NaiveDate, NaiveDate, NaiveDate) {
+        match self {
+            ReportPeriod::Weekly => {
+                let end = today - Days::new(today.weekday().num_days_from_monday().into());
+                (end - Days::new(14), end - Days::new(7), end)
+            }
+            ReportPeriod::Monthly => {
+                let end = today.with_day(1).expect("every month has a first day");
+                let start = end - Months::new(1);
+                (start - Months::new(1), start, end)
+            }
+        }
+    }
+
+    /// What the comparisons are against, e.g. "the week before"
+    fn previous(self) -> &'static str {
+        match self {
+            ReportPeriod::Weekly => "the week before",
+            ReportPeriod::Monthly => "the month before",
+        }
+    }
+}
+
+/// SMTP settings for `--email`, usually kept in the config file's `[report]`
+/// table
+#[derive(Debug, clap::Args)]
+#[command(next_help_heading = "Email")]
+struct SmtpArgs {
+    /// SMTP server to send through, e.g. smtp.example.com
+    #[arg(long, value_name = "HOST")]
+    smtp_server: Option<String>,
+    /// SMTP port [default: 587, or 25 with --smtp-no-tls]
+    #[arg(long, value_name = "PORT")]
+    smtp_port: Option<u16>,
+    /// Send without STARTTLS, e.g. to a relay on the local network
+    #[arg(long)]
+    smtp_no_tls: bool,
+    /// SMTP user name, if the server needs one
+    #[arg(long, value_name = "USER")]
+    smtp_username: Option<String>,
+    /// Environment variable holding the SMTP password, so it stays out of the
+    /// config file
+    #[arg(long, value_name = "VAR")]
+    smtp_password_env: Option<String>,
+    /// Sender, e.g. "Carbon Vibe <carbon@example.com>"
+    #[arg(long, value_name = "ADDRESS")]
+    email_from: Option<String>,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct ReportOptions {
+    #[arg(value_enum)]
+    period: ReportPeriod,
+    /// Email the report to this address instead of printing it; repeat for
+    /// more recipients
+    #[arg(long, value_name = "ADDRESS")]
+    email: Vec<String>,
+    /// Print the email's HTML instead, e.g. to preview it; nothing is sent
+    #[arg(long)]
+    html: bool,
+    /// Energy a day that could run at any time, such as EV charging, for
+    /// the savings estimate
+    #[arg(long, value_name = "KWH", value_parser = parse_positive, default_value = "10")]
+    shift_kwh: f64,
+    /// How long that load takes to run each day, e.g. 3h or 90m
+    #[arg(long, value_name = "DURATION", value_parser = parse_duration_arg, default_value = "3h")]
+    shift_duration: TimeDelta,
+    /// Read history from this archive database (see `archive record`)
+    /// instead of the API
+    #[arg(long, value_name = "PATH")]
+    db: Option<String>,
+    #[command(flatten)]
+    smtp: SmtpArgs,
+    #[command(flatten)]
+    client: ClientArgs,
+}
+
+/// A load that could run at any time of day, for the savings estimate
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ShiftableLoad {
+    pub kwh_per_day: f64,
+    pub duration: TimeDelta,
+}
+
+/// Mean intensity over one UK calendar day
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DayMean {
+    pub date: NaiveDate,
+    pub mean: f64,
+}
+
+/// A fuel's mean share of generation over the period, and over the period
+/// before if there's a figure for it
+#[derive(Clone, Debug, PartialEq)]
+pub struct FuelTrend {
+    pub fuel: String,
+    pub share: f64,
+    pub previous: Option<f64>,
+}
+
+/// The shiftable load's emissions run at each day's mean intensity, against
+/// run in each day's greenest window
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Saving {
+    pub load: ShiftableLoad,
+    pub days: usize,
+    pub typical_g: f64,
+    pub shifted_g: f64,
+}
+
+impl Saving {
+    pub fn saved_g(&self) -> f64 {
+        self.typical_g - self.shifted_g
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Report {
+    pub period: ReportPeriod,
+    /// E.g. "Week of 16 June 2025" or "June 2025"
+    pub title: String,
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    pub mean: f64,
+    /// `None` without figures for the period before
+    pub previous_mean: Option<f64>,
+    /// Oldest first, and never empty
+    pub days: Vec<DayMean>,
+    /// Largest share first
+    pub mix: Vec<FuelTrend>,
+    /// `None` if no day had a window as long as the load
+    pub saving: Option<Saving>,
+}
+
+pub async fn run(options: ReportOptions) -> Result<(), CarbonError> {
+    let now = Utc::now();
+    let (previous, start, end) = options.period.days(SettlementPeriod::containing(now).date());
+    let (previous, to) = (uk_midnight(previous), uk_midnight(end));
+
+    let (points, periods) = match &options.db {
+        Some(db) => {
+            let store = Store::open(db).map_err(|source| CarbonError::OpenArchive {
+                path: db.into(),
+                source,
+            })?;
+            (store.intensity_between(previous, to)?, store.generation_between(previous, to)?)
+        }
+        None => {
+            let client = options.client.client()?;
+            (
+                client.intensity_range_for(None, previous, to).await?,
+                client.generation_range(previous, to).await?,
+            )
+        }
+    };
+
+    let load = ShiftableLoad {
+        kwh_per_day: options.shift_kwh,
+        duration: options.shift_duration,
+    };
+    let report = compile(options.period, now, &points, &periods, load).ok_or_else(|| {
+        CarbonError::EmptyData(format!("intensity from {start} to {last}", last = end - Days::new(1)))
+    })?;
+
+    if options.html {
+        println!("{html}", html = report.html());
+        return Ok(());
+    }
+    if options.email.is_empty() {
+        println!("{text}", text = report.text());
+        return Ok(());
+    }
+    send(&report, &options).await
+}
+
+/// The report on the last whole `period` before `now`, from `points` and
+/// `periods` covering it and the one before it, oldest first. `None` if
+/// there's no intensity for the period itself.
+pub fn compile(
+    period: ReportPeriod,
+    now: DateTime<Utc>,
+    points: &[IntensityPoint],
+    periods: &[GenerationMixPeriod],
+    load: ShiftableLoad,
+) -> Option<Report> {
+    let (previous, start, end) = period.days(SettlementPeriod::containing(now).date());
+    let (from, to) = (uk_midnight(start), uk_midnight(end));
+    let is_current = |time: DateTime<Utc>| time >= from && time < to;
+    let is_previous = |time: DateTime<Utc>| time >= uk_midnight(previous) && time < from;
+
+    let mut days: BTreeMap<NaiveDate, Vec<IntensityPoint>> = BTreeMap::new();
+    for point in points.iter().filter(|point| is_current(point.from)) {
+        days.entry(SettlementPeriod::containing(point.from).date())
+            .or_default()
+            .push(point.clone());
+    }
+    let mean = mean_intensity(days.values().flatten())?;
+    let previous_mean = mean_intensity(points.iter().filter(|point| is_previous(point.from)));
+
+    let mut saving = Saving {
+        load,
+        days: 0,
+        typical_g: 0.0,
+        shifted_g: 0.0,
+    };
+    for day in days.values() {
+        if let (Some(window), Some(day_mean)) = (greenest_window(day, load.duration), mean_intensity(day.iter())) {
+            saving.days += 1;
+            saving.typical_g += load.kwh_per_day * day_mean;
+            saving.shifted_g += load.kwh_per_day * window.average;
+        }
+    }
+
+    let previous_shares = mean_shares(periods.iter().filter(|period| is_previous(period.from)));
+    let mut mix: Vec<FuelTrend> = mean_shares(periods.iter().filter(|period| is_current(period.from)))
+        .into_iter()
+        .map(|(fuel, share)| FuelTrend {
+            previous: previous_shares.get(&fuel).copied(),
+            fuel,
+            share,
+        })
+        .collect();
+    mix.sort_by(|a, b| b.share.total_cmp(&a.share));
+
+    Some(Report {
+        period,
+        title: match period {
+            ReportPeriod::Weekly => format!("Week of {start}", start = start.format("%-d %B %Y")),
+            ReportPeriod::Monthly => start.format("%B %Y").to_string(),
+        },
+        from,
+        to,
+        mean,
+        previous_mean,
+        days: days
+            .iter()
+            .filter_map(|(&date, points)| Some(DayMean { date, mean: mean_intensity(points.iter())? }))
+            .collect(),
+        mix,
+        saving: (saving.days > 0).then_some(saving),
+    })
+}
+
+fn mean_intensity<'a>(points: impl Iterator<Item = &'a IntensityPoint>) -> Option<f64> {
+    let (count, sum) = points.fold((0usize, 0.0), |(count, sum), point| (count + 1, sum + point.intensity as f64));
+    (count > 0).then(|| sum / count as f64)
+}
+
+/// Each fuel's mean percentage across `periods`
+fn mean_shares<'a>(periods: impl Iterator<Item = &'a GenerationMixPeriod>) -> BTreeMap<String, f64> {
+    let mut totals: BTreeMap<String, f64> = BTreeMap::new();
+    let mut count = 0;
+    for period in periods {
+        count += 1;
+        for source in &period.generation_mix {
+            *totals.entry(source.fuel.clone()).or_default() += source.perc;
+        }
+    }
+    totals.values_mut().for_each(|total| *total /= count as f64);
+    totals
+}
+
+impl Report {
+    pub fn best_day(&self) -> DayMean {
+        *self
+            .days
+            .iter()
+            .min_by(|a, b| a.mean.total_cmp(&b.mean))
+            .expect("a report has at least one day")
+    }
+
+    pub fn worst_day(&self) -> DayMean {
+        *self
+            .days
+            .iter()
+            .max_by(|a, b| a.mean.total_cmp(&b.mean))
+            .expect("a report has at least one day")
+    }
+
+    /// The average and how it compares, e.g. "Average 142 gCO₂/kWh, 8% lower
+    /// than the week before"
+    fn summary(&self) -> String {
+        let average = format!("Average {mean} {unit}", mean = units::figure(self.mean), unit = units::label());
+        let previous = self.period.previous();
+        match self.previous_mean {
+            Some(before) if before > 0.0 => {
+                let change = ((self.mean - before) / before * 100.0).round();
+                match change {
+                    0.0 => format!("{average}, the same as {previous}"),
+                    change if change < 0.0 => format!("{average}, {percent}% lower than {previous}", percent = -change),
+                    change => format!("{average}, {change}% higher than {previous}"),
+                }
+            }
+            _ => format!("{average}, with no figures for {previous}"),
+        }
+    }
+
+    fn describe_day(day: DayMean) -> String {
+        format!(
+            "{date} at {mean} {unit}",
+            date = day.date.format("%a %-d %b"),
+            mean = units::figure(day.mean),
+            unit = units::label()
+        )
+    }
+
+    /// E.g. "Shifting 10 kWh a day into each day's greenest 3h would have
+    /// saved 4.21 kgCO₂ (28%) over 7 days"
+    fn describe_saving(saving: &Saving) -> String {
+        let percent = if saving.typical_g > 0.0 {
+            saving.saved_g() / saving.typical_g * 100.0
+        } else {
+            0.0
+        };
+        format!(
+            "Shifting {kwh} kWh a day into each day's greenest {duration} would have saved {saved} ({percent:.0}%) \
+             over {days} day{s}",
+            kwh = saving.load.kwh_per_day,
+            duration = describe_duration(saving.load.duration),
+            saved = describe_grams(saving.saved_g()),
+            days = saving.days,
+            s = if saving.days == 1 { "" } else { "s" }
+        )
+    }
+
+    fn describe_change(trend: &FuelTrend) -> String {
+        match trend.previous {
+            Some(previous) => format!("{change:+.1} points", change = trend.share - previous),
+            None => "new".to_string(),
+        }
+    }
+
+    fn subject(&self) -> String {
+        format!("Carbon intensity report: {title}", title = self.title)
+    }
+
+    /// The report as plain text, also the email's text part
+    pub fn text(&self) -> String {
+        let mut lines = vec![
+            self.subject(),
+            self.summary(),
+            format!("Greenest day: {day}", day = Report::describe_day(self.best_day())),
+            format!("Dirtiest day: {day}", day = Report::describe_day(self.worst_day())),
+        ];
+        if !self.mix.is_empty() {
+            let fuels: Vec<String> = self
+                .mix
+                .iter()
+                .map(|trend| {
+                    format!(
+                        "{fuel} {share:.1}% ({change})",
+                        fuel = trend.fuel,
+                        share = trend.share,
+                        change = Report::describe_change(trend)
+                    )
+                })
+                .collect();
+            lines.push(format!("Generation mix: {fuels}", fuels = fuels.join(", ")));
+        }
+        if let Some(saving) = &self.saving {
+            lines.push(Report::describe_saving(saving));
+        }
+        lines.join("\n")
+    }
+
+    /// The report as an HTML email, styled inline since mail clients drop
+    /// stylesheets
+    pub fn html(&self) -> String {
+        let worst = self.worst_day().mean.max(1.0);
+        let band = |intensity: f64| LIGHT.band(IntensityIndex::for_intensity(intensity.round() as i32));
+        let email = ReportEmail {
+            title: &self.title,
+            summary: self.summary(),
+            mean_colour: band(self.mean),
+            best_day: Report::describe_day(self.best_day()),
+            worst_day: Report::describe_day(self.worst_day()),
+            days: self
+                .days
+                .iter()
+                .map(|day| DayRow {
+                    date: day.date.format("%a %-d %b").to_string(),
+                    mean: units::figure(day.mean),
+                    colour: band(day.mean),
+                    width: (day.mean / worst * 100.0).round().max(1.0) as u32,
+                })
+                .collect(),
+            mix: self
+                .mix
+                .iter()
+                .map(|trend| MixRow {
+                    fuel: trend.fuel.clone(),
+                    share: format!("{share:.1}%", share = trend.share),
+                    change: Report::describe_change(trend),
+                })
+                .collect(),
+            saving: self.saving.as_ref().map(Report::describe_saving),
+            unit: units::label(),
+            heading: LIGHT.heading,
+            muted: LIGHT.muted,
+            grid: LIGHT.grid,
+        };
+        email.render().expect("the report template renders")
+    }
+}
+
+/// The HTML email, rendered from `templates/report.html`
+#[derive(Template)]
+#[template(path = "report.html")]
+struct ReportEmail<'a> {
+    title: &'a str,
+    summary: String,
+    mean_colour: &'static str,
+    best_day: String,
+    worst_day: String,
+    days: Vec<DayRow>,
+    mix: Vec<MixRow>,
+    saving: Option<String>,
+    /// The display unit, for the daily averages' heading
+    unit: &'static str,
+    heading: &'static str,
+    muted: &'static str,
+    grid: &'static str,
+}
+
+/// One day's bar, its width a percentage of the dirtiest day's
+struct DayRow {
+    date: String,
+    mean: String,
+    colour: &'static str,
+    width: u32,
+}
+
+struct MixRow {
+    fuel: String,
+    share: String,
+    change: String,
+}
+
+/// Send `report` to each `--email` address through the SMTP server
+async fn send(report: &Report, options: &ReportOptions) -> Result<(), CarbonError> {
+    let smtp = &options.smtp;
+    let (Some(server), Some(from)) = (&smtp.smtp_server, &smtp.email_from) else {
+        return Err(usage_error(
+            "--email needs --smtp-server and --email-from, e.g. in the config file's [report] table",
+        ));
+    };
+    let mailbox = |address: &String| {
+        address
+            .parse::<Mailbox>()
+            .map_err(|e| usage_error(format!("Invalid email address '{address}': {e}")))
+    };
+
+    let mut message = Message::builder().from(mailbox(from)?).subject(report.subject());
+    for recipient in &options.email {
+        message = message.to(mailbox(recipient)?);
+    }
+    let message = message
+        .multipart(MultiPart::alternative_plain_html(report.text(), report.html()))
+        .map_err(usage_error)?;
+
+    let credentials = match &smtp.smtp_username {
+        Some(username) => {
+            let password = match &smtp.smtp_password_env {
+                Some(env) => std::env::var(env)
+                    .map_err(|_| usage_error(format!("Environment variable {env} is not set")))?,
+                None => String::new(),
+            };
+            Some(Credentials::new(username.clone(), password))
+        }
+        None => None,
+    };
+    let mailer = smtp_transport(server, smtp.smtp_port, !smtp.smtp_no_tls, credentials)
+        .map_err(CarbonError::Email)?;
+    mailer.send(message).await.map_err(CarbonError::Email)?;
+    info!(
+        "Emailed the report for {title} to {recipients}",
+        title = report.title,
+        recipients = options.email.join(", ")
+    );
+
+    Ok(())
+}
@@ -0,0 +1,176 @@
+//! `run`: hold a command back until the grid is clean enough, for
+//! carbon-aware batch jobs and backups
+
+use crate::cli::timezone::local;
+use crate::cli::{ClientArgs, RegionArgs};
+use crate::model::{FORECAST_HORIZON, IntensityPoint, RegionSelector};
+use crate::schedule::{describe_duration, parse_duration_arg};
+use crate::units;
+use crate::{CarbonClient, CarbonError};
+use chrono::{DateTime, TimeDelta, Utc};
+use tracing::{info, instrument, trace, warn};
+
+#[derive(Debug, clap::Args)]
+pub struct RunOptions {
+    /// Start as soon as intensity is at or below this many gCO₂/kWh
+    #[arg(long, value_name = "G", value_parser = clap::value_parser!(i32).range(0..))]
+    max_intensity: i32,
+    /// Start anyway once this long has passed, e.g. 8h or 1h30m
+    #[arg(long, value_parser = parse_duration_arg)]
+    deadline: TimeDelta,
+    /// How often to check while waiting; forecasts are revised, so a low
+    /// period further off is checked again on arrival
+    #[arg(long, value_parser = parse_duration_arg, default_value = "30m")]
+    interval: TimeDelta,
+    /// The command to run, after `--`
+    #[arg(last = true, required = true, value_name = "COMMAND")]
+    command: Vec<String>,
+    #[command(flatten)]
+    region: RegionArgs,
+    #[command(flatten)]
+    client: ClientArgs,
+}
+
+/// What one look at the forecast decided
+#[derive(Debug)]
+enum Decision<'a> {
+    /// The period in progress is clean enough
+    RunNow(&'a IntensityPoint),
+    /// Check again at this time: when a clean period is forecast to start, or
+    /// after the check interval, whichever is sooner
+    WaitUntil(DateTime<Utc>),
+}
+
+pub async fn run(options: RunOptions) -> Result<(), CarbonError> {
+    let client = options.client.client()?;
+    let region = options.region.selector();
+    let interval = options.interval;
+    let started = Utc::now();
+    let deadline = started + options.deadline;
+    let program = options.command.join(" ");
+
+    loop {
+        let now = Utc::now();
+        if now >= deadline {
+            let current = fetch_points(&client, region.as_ref(), now, now + interval)
+                .await
+                .map_err(|e| warn!("Failed to fetch intensity: {e}"))
+                .ok()
+                .and_then(|points| points.into_iter().find(|p| p.from <= now && now < p.to));
+            info!(
+                "Deadline reached without intensity falling to {max}; running '{program}' at {intensity}",
+                max = options.max_intensity,
+                intensity = describe_reading(current.as_ref())
+            );
+            break;
+        }
+
+        let next_check = match fetch_points(&client, region.as_ref(), now, deadline).await {
+            Ok(points) => match decide(&points, now, options.max_intensity, interval) {
+                Decision::RunNow(point) => {
+                    let waited = now - started;
+                    let waited = if waited < TimeDelta::minutes(1) {
+                        String::new()
+                    } else {
+                        format!(" after waiting {waited}", waited = describe_duration(waited))
+                    };
+                    info!(
+                        "Running '{program}' at {intensity}{waited}",
+                        intensity = describe_reading(Some(point))
+                    );
+                    break;
+                }
+                Decision::WaitUntil(next_check) => next_check,
+            },
+            Err(e) => {
+                warn!("Failed to fetch intensity: {e}");
+                now + interval
+            }
+        };
+
+        let wake = next_check.min(deadline);
+        info!(
+            "Waiting until {wake} for intensity at or below {max} gCO₂/kWh (deadline {deadline})",
+            wake = local(wake).format("%H:%M"),
+            max = options.max_intensity,
+            deadline = local(deadline).format("%a %H:%M %Z")
+        );
+        tokio::time::sleep((wake - now).to_std().unwrap_or_default()).await;
+    }
+
+    let status = tokio::process::Command::new(&options.command[0])
+        .args(&options.command[1..])
+        .status()
+        .await
+        .map_err(|e| CarbonError::io(format!("Failed to run '{program}'"), e))?;
+
+    match exit_code(status) {
+        0 => Ok(()),
+        code => Err(CarbonError::CommandFailed {
+            command: program,
+            code,
+        }),
+    }
+}
+
+/// Run now if the period in progress is at or below `max`; otherwise wait
+/// for the first later period forecast to be, checking again within
+/// `interval` in case the forecast changes
+fn decide(
+    points: &[IntensityPoint],
+    now: DateTime<Utc>,
+    max: i32,
+    interval: TimeDelta,
+) -> Decision<'_> {
+    if let Some(current) = points.iter().find(|p| p.from <= now && now < p.to)
+        && current.intensity <= max
+    {
+        return Decision::RunNow(current);
+    }
+
+    let recheck = now + interval;
+    let next_clean = points
+        .iter()
+        .find(|p| p.from > now && p.intensity <= max)
+        .map(|p| p.from);
+    Decision::WaitUntil(next_clean.map_or(recheck, |from| from.min(recheck)))
+}
+
+/// Actual or forecast intensity from the period in progress up to `to`,
+/// national or for the selected region, oldest first
+#[instrument(skip(client))]
+async fn fetch_points(
+    client: &CarbonClient,
+    region: Option<&RegionSelector>,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+) -> Result<Vec<IntensityPoint>, CarbonError> {
+    // Nothing is forecast beyond the horizon
+    let to = to.min(from + FORECAST_HORIZON);
+    let points = client.intensity_between_for(region, from, to).await?;
+    trace!("Parsed response data: {:?}", points);
+    Ok(points)
+}
+
+/// e.g. "95 gCO₂/kWh (low)"
+fn describe_reading(point: Option<&IntensityPoint>) -> String {
+    match point {
+        Some(IntensityPoint {
+            intensity,
+            index: Some(index),
+            ..
+        }) => format!("{intensity} {unit} ({index})", intensity = units::figure(*intensity), unit = units::label()),
+        Some(point) => format!("{intensity} {unit}", intensity = units::figure(point.intensity), unit = units::label()),
+        None => "unknown intensity".to_string(),
+    }
+}
+
+/// The command's exit status, or 128 plus the signal number if it was killed,
+/// as shells report it
+fn exit_code(status: std::process::ExitStatus) -> i32 {
+    #[cfg(unix)]
+    if let Some(signal) = std::os::unix::process::ExitStatusExt::signal(&status) {
+        return 128 + signal;
+    }
+    status.code().unwrap_or(1)
+}
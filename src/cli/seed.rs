@@ -0,0 +1,162 @@
+//! `--seed-history`: prime the archive that `archive record` and `daemon`
+//! record into from history kept elsewhere, a CSV export or another archive.
+//! The pollers carry on from the newest period it holds, rather than fetching
+//! from scratch. Periods the archive already has are kept, so the same seed
+//! can be given on every start.
+
+use crate::CarbonError;
+use crate::model::{FuelSource, GenerationMixPeriod, IntensityPoint};
+use crate::store::Store;
+use chrono::{DateTime, Utc};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use tracing::info;
+
+/// First bytes of every SQLite database file
+const SQLITE_HEADER: &[u8] = b"SQLite format 3\0";
+
+/// `--seed-history`, for the pollers
+#[derive(Clone, Debug, clap::Args)]
+pub struct SeedArgs {
+    /// Before polling, copy the periods the archive lacks from another
+    /// archive or a CSV export: `history export`, `archive intensity` or
+    /// `archive mix`, in gCO₂/kWh
+    #[arg(long, value_name = "PATH")]
+    seed_history: Option<PathBuf>,
+}
+
+impl SeedArgs {
+    /// Seed `store`, if `--seed-history` was given
+    pub fn seed(&self, store: &mut Store) -> Result<(), CarbonError> {
+        let Some(path) = &self.seed_history else {
+            return Ok(());
+        };
+        let seeded = seed_history(store, path)?;
+        info!(
+            path = %path.display(),
+            "Seeded {intensity} intensity and {generation} generation mix periods",
+            intensity = seeded.intensity_periods,
+            generation = seeded.generation_periods
+        );
+        Ok(())
+    }
+}
+
+/// Periods a seed added to the archive
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct SeedSummary {
+    pub intensity_periods: usize,
+    pub generation_periods: usize,
+}
+
+/// Copy the periods `store` lacks from the archive or CSV export at `path`
+pub fn seed_history(store: &mut Store, path: &Path) -> Result<SeedSummary, CarbonError> {
+    let invalid = |message: String| CarbonError::SeedHistory {
+        path: path.to_path_buf(),
+        message,
+    };
+    let contents = std::fs::read(path).map_err(|e| invalid(e.to_string()))?;
+    let (points, periods) = if contents.starts_with(SQLITE_HEADER) {
+        read_archive(path).map_err(|e| invalid(e.to_string()))?
+    } else {
+        let text = String::from_utf8(contents).map_err(|_| invalid("not a CSV file or an archive".to_string()))?;
+        parse_csv(&text).map_err(invalid)?
+    };
+
+    Ok(SeedSummary {
+        intensity_periods: store.seed_intensity(&points)?,
+        generation_periods: store.seed_generation(&periods)?,
+    })
+}
+
+/// Everything in the archive at `path`, over the span its intensity covers
+fn read_archive(path: &Path) -> rusqlite::Result<(Vec<IntensityPoint>, Vec<GenerationMixPeriod>)> {
+    let source = Store::open_read_only(path)?;
+    let summary = source.summary()?;
+    let (Some(from), Some(to)) = (summary.earliest, summary.latest) else {
+        return Ok((Vec::new(), Vec::new()));
+    };
+    Ok((source.intensity_between(from, to)?, source.generation_between(from, to)?))
+}
+
+/// Intensity points or generation mix periods from a CSV export, told apart
+/// by its header
+fn parse_csv(text: &str) -> Result<(Vec<IntensityPoint>, Vec<GenerationMixPeriod>), String> {
+    let mut lines = text
+        .lines()
+        .enumerate()
+        .map(|(index, line)| (index + 1, line))
+        .filter(|(_, line)| !line.trim().is_empty());
+    let (_, header) = lines.next().ok_or("the file is empty")?;
+    let header: Vec<&str> = header.split(',').map(str::trim).collect();
+    let column = |name: &str| header.iter().position(|&column| column == name);
+    let (Some(from), Some(to)) = (column("from"), column("to")) else {
+        return Err("expected an archive, or a CSV export with 'from' and 'to' columns".to_string());
+    };
+
+    let mut points = Vec::new();
+    let mut periods: BTreeMap<DateTime<Utc>, GenerationMixPeriod> = BTreeMap::new();
+    for (number, line) in lines {
+        let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+        let field = |index: Option<usize>| index.and_then(|index| fields.get(index)).copied().unwrap_or("");
+        let at_line = |message: String| format!("line {number}: {message}");
+        let period_from = timestamp(field(Some(from))).map_err(at_line)?;
+        let period_to = timestamp(field(Some(to))).map_err(at_line)?;
+
+        if let (Some(fuel), Some(perc)) = (column("fuel"), column("perc")) {
+            let perc = field(Some(perc))
+                .parse()
+                .map_err(|_| at_line(format!("'{perc}' isn't a percentage", perc = field(Some(perc)))))?;
+            periods
+                .entry(period_from)
+                .or_insert_with(|| GenerationMixPeriod {
+                    from: period_from,
+                    to: period_to,
+                    generation_mix: Vec::new(),
+                })
+                .generation_mix
+                .push(FuelSource { fuel: field(Some(fuel)).to_string(), perc });
+            continue;
+        }
+
+        let actual = figure(field(column("actual"))).map_err(at_line)?;
+        let forecast = figure(field(column("forecast"))).map_err(at_line)?;
+        // `archive intensity` has the figure shown as well; the others have
+        // only the actual and forecast it's taken from
+        let Some(intensity) = figure(field(column("intensity"))).map_err(at_line)?.or(actual.or(forecast)) else {
+            continue;
+        };
+        let index = match field(column("index")) {
+            "" => None,
+            index => Some(index.parse().map_err(at_line)?),
+        };
+        points.push(IntensityPoint {
+            from: period_from,
+            to: period_to,
+            intensity,
+            actual,
+            forecast,
+            index,
+        });
+    }
+
+    Ok((points, periods.into_values().collect()))
+}
+
+fn timestamp(value: &str) -> Result<DateTime<Utc>, String> {
+    DateTime::parse_from_rfc3339(value)
+        .map(|at| at.with_timezone(&Utc))
+        .map_err(|_| format!("'{value}' isn't an RFC 3339 time"))
+}
+
+/// A gCO₂/kWh figure, or `None` if the field is empty. Exports in other units
+/// have fractional figures, which are rejected rather than misread.
+fn figure(value: &str) -> Result<Option<i32>, String> {
+    if value.is_empty() {
+        return Ok(None);
+    }
+    value
+        .parse()
+        .map(Some)
+        .map_err(|_| format!("'{value}' isn't a whole gCO₂/kWh figure; export with the default --units"))
+}
@@ -0,0 +1,93 @@
+//! Single-flight fetches: when several requests miss a cache at once, the
+//! first goes upstream and the rest wait for its result rather than each
+//! making the same requests.
+
+use crate::CarbonError;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use tokio::sync::watch;
+
+/// What the fetch in flight ends with, shared with everyone waiting on it
+type Outcome<T> = Option<Result<T, Arc<CarbonError>>>;
+
+/// One fetch at a time, whose result goes to every caller that asked for it
+/// while it ran
+pub struct SingleFlight<T> {
+    in_flight: Mutex<Option<watch::Receiver<Outcome<T>>>>,
+}
+
+impl<T> Default for SingleFlight<T> {
+    fn default() -> Self {
+        SingleFlight {
+            in_flight: Mutex::new(None),
+        }
+    }
+}
+
+impl<T: Clone> SingleFlight<T> {
+    /// Whether a fetch is running now
+    pub fn is_running(&self) -> bool {
+        self.slot().is_some()
+    }
+
+    /// `fetch`'s result, or if a fetch is already running, that one's
+    /// instead, leaving `fetch` unused. If the caller running the fetch
+    /// gives up on it, e.g. because its client went away, one of those
+    /// waiting starts its own.
+    pub async fn run(&self, fetch: impl Future<Output = Result<T, CarbonError>>) -> Result<T, CarbonError> {
+        loop {
+            // Decided without holding the lock across an await
+            let role = {
+                let mut slot = self.slot();
+                match slot.as_ref() {
+                    Some(receiver) => Role::Wait(receiver.clone()),
+                    None => {
+                        let (sender, receiver) = watch::channel(None);
+                        *slot = Some(receiver);
+                        Role::Lead(sender)
+                    }
+                }
+            };
+            let mut waiting = match role {
+                Role::Wait(receiver) => receiver,
+                Role::Lead(sender) => return self.lead(sender, fetch).await,
+            };
+            if let Ok(outcome) = waiting.wait_for(Option::is_some).await {
+                let result = outcome.clone().expect("waited for an outcome");
+                return result.map_err(CarbonError::Shared);
+            }
+        }
+    }
+
+    async fn lead(
+        &self,
+        sender: watch::Sender<Outcome<T>>,
+        fetch: impl Future<Output = Result<T, CarbonError>>,
+    ) -> Result<T, CarbonError> {
+        // Frees the slot however this ends, so a cancelled fetch doesn't
+        // leave the next caller waiting on nothing
+        let _clear = ClearOnDrop(&self.in_flight);
+        let result = fetch.await.map_err(Arc::new);
+        sender.send_replace(Some(result.clone()));
+        result.map_err(CarbonError::Shared)
+    }
+
+    fn slot(&self) -> std::sync::MutexGuard<'_, Option<watch::Receiver<Outcome<T>>>> {
+        self.in_flight.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+enum Role<T> {
+    /// Run the fetch and send everyone its outcome
+    Lead(watch::Sender<Outcome<T>>),
+    /// Wait for the outcome of the fetch already running
+    Wait(watch::Receiver<Outcome<T>>),
+}
+
+struct ClearOnDrop<'a, T>(&'a Mutex<Option<T>>);
+
+impl<T> Drop for ClearOnDrop<'_, T> {
+    fn drop(&mut self) {
+        *self.0.lock().unwrap_or_else(|poisoned| poisoned.into_inner()) = None;
+    }
+}
@@ -0,0 +1,229 @@
+//! `stats`: summary statistics over the last N days of intensity, from the
+//! API or a local archive: spread, percentiles, the greenest and dirtiest
+//! hours of the day, and weekdays against weekends
+
+use crate::CarbonError;
+use crate::cli::timezone::{display_zone, local};
+use crate::cli::{ClientArgs, RegionArgs};
+use crate::model::IntensityPoint;
+use crate::output::{OutputFormat, Record, render_one};
+use crate::store::Store;
+use crate::units;
+use chrono::{DateTime, Datelike, SecondsFormat, TimeDelta, Timelike, Utc, Weekday};
+use serde::Serialize;
+use tracing::trace;
+
+#[derive(Debug, clap::Args)]
+pub struct StatsOptions {
+    /// Cover the last N days
+    #[arg(long, value_parser = clap::value_parser!(i64).range(1..=365), default_value = "30")]
+    days: i64,
+    /// Read history from this archive database (see `archive record`)
+    /// instead of the API; national figures only
+    #[arg(long, value_name = "PATH", conflicts_with_all = ["postcode", "region"])]
+    db: Option<String>,
+    /// Output format
+    #[arg(long, visible_alias = "output", value_enum, default_value = "text")]
+    format: OutputFormat,
+    #[command(flatten)]
+    region: RegionArgs,
+    #[command(flatten)]
+    client: ClientArgs,
+}
+
+/// Mean intensity for one hour of the day, over every day in the range
+#[derive(Clone, Copy, Debug, Serialize)]
+struct HourOfDay {
+    /// Hour of the day in the display zone (`--timezone`), 0-23
+    hour: u32,
+    #[serde(serialize_with = "units::serialize_f64")]
+    mean: f64,
+}
+
+#[derive(Debug, Serialize)]
+struct Stats {
+    /// Span of the settlement periods summarised
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+    periods: usize,
+    #[serde(serialize_with = "units::serialize")]
+    min: i32,
+    #[serde(serialize_with = "units::serialize")]
+    max: i32,
+    #[serde(serialize_with = "units::serialize_f64")]
+    mean: f64,
+    #[serde(serialize_with = "units::serialize_f64")]
+    median: f64,
+    #[serde(serialize_with = "units::serialize")]
+    p90: i32,
+    greenest_hour: HourOfDay,
+    dirtiest_hour: HourOfDay,
+    /// `None` if the range has no periods on those days
+    #[serde(serialize_with = "units::serialize_option_f64")]
+    weekday_mean: Option<f64>,
+    #[serde(serialize_with = "units::serialize_option_f64")]
+    weekend_mean: Option<f64>,
+    by_hour: Vec<HourOfDay>,
+}
+
+impl Record for Stats {
+    const CSV_HEADER: &'static [&'static str] = &[
+        "from",
+        "to",
+        "periods",
+        "min",
+        "max",
+        "mean",
+        "median",
+        "p90",
+        "greenest_hour",
+        "greenest_hour_mean",
+        "dirtiest_hour",
+        "dirtiest_hour_mean",
+        "weekday_mean",
+        "weekend_mean",
+    ];
+
+    fn csv_fields(&self) -> Vec<String> {
+        let mean = |value: Option<f64>| value.map(|v| format!("{v:.1}", v = units::convert(v))).unwrap_or_default();
+        vec![
+            self.from.to_rfc3339_opts(SecondsFormat::Secs, true),
+            self.to.to_rfc3339_opts(SecondsFormat::Secs, true),
+            self.periods.to_string(),
+            units::field(self.min),
+            units::field(self.max),
+            format!("{mean:.1}", mean = units::convert(self.mean)),
+            format!("{median:.1}", median = units::convert(self.median)),
+            units::field(self.p90),
+            self.greenest_hour.hour.to_string(),
+            format!("{mean:.1}", mean = units::convert(self.greenest_hour.mean)),
+            self.dirtiest_hour.hour.to_string(),
+            format!("{mean:.1}", mean = units::convert(self.dirtiest_hour.mean)),
+            mean(self.weekday_mean),
+            mean(self.weekend_mean),
+        ]
+    }
+
+    fn text(&self) -> String {
+        let hour = |hour: HourOfDay| {
+            format!(
+                "{from:02}:00-{to:02}:00 {zone} at {mean} {unit} on average",
+                from = hour.hour,
+                zone = display_zone(),
+                to = (hour.hour + 1) % 24,
+                mean = units::figure(hour.mean),
+                unit = units::label()
+            )
+        };
+        let mean = |value: Option<f64>| {
+            value
+                .map(|v| format!("{v} {unit}", v = units::figure(v), unit = units::label()))
+                .unwrap_or_else(|| "no data".to_string())
+        };
+        format!(
+            "{periods} settlement periods from {from} to {to}\n\
+             Min {min}, max {max}, mean {mean}, median {median}, 90th percentile {p90} {unit}\n\
+             Greenest hour of the day: {greenest}\n\
+             Dirtiest hour of the day: {dirtiest}\n\
+             Weekdays: {weekday}; weekends: {weekend}",
+            periods = self.periods,
+            from = local(self.from).format("%Y-%m-%d %H:%M %Z"),
+            to = local(self.to).format("%Y-%m-%d %H:%M %Z"),
+            min = units::figure(self.min),
+            max = units::figure(self.max),
+            mean = units::figure(self.mean),
+            median = units::figure(self.median),
+            p90 = units::figure(self.p90),
+            unit = units::label(),
+            greenest = hour(self.greenest_hour),
+            dirtiest = hour(self.dirtiest_hour),
+            weekday = mean(self.weekday_mean),
+            weekend = mean(self.weekend_mean)
+        )
+    }
+}
+
+pub async fn run(options: StatsOptions) -> Result<(), CarbonError> {
+    let to = Utc::now();
+    let from = to - TimeDelta::days(options.days);
+
+    let points = match &options.db {
+        Some(db) => {
+            let store = Store::open(db).map_err(|source| CarbonError::OpenArchive {
+                path: db.into(),
+                source,
+            })?;
+            store.intensity_between(from, to)?
+        }
+        None => {
+            let region = options.region.selector();
+            options
+                .client
+                .client()?
+                .intensity_range_for(region.as_ref(), from, to)
+                .await?
+        }
+    };
+    trace!("Parsed response data: {:?}", points);
+
+    let stats = summarise(&points).ok_or_else(|| {
+        CarbonError::EmptyData(format!(
+            "intensity for the last {days} days",
+            days = options.days
+        ))
+    })?;
+    print!("{output}", output = render_one(&stats, options.format)?);
+
+    Ok(())
+}
+
+/// Statistics over `points`, or `None` if there are none
+fn summarise(points: &[IntensityPoint]) -> Option<Stats> {
+    let (first, last) = (points.first()?, points.last()?);
+
+    let mut values: Vec<i32> = points.iter().map(|p| p.intensity).collect();
+    values.sort_unstable();
+    let middle = values.len() / 2;
+    let median = if values.len().is_multiple_of(2) {
+        (values[middle - 1] + values[middle]) as f64 / 2.0
+    } else {
+        values[middle] as f64
+    };
+    // Nearest rank: the smallest value at least 90% of periods are at or below
+    let p90 = values[(values.len() * 9).div_ceil(10) - 1];
+
+    let by_hour: Vec<HourOfDay> = (0..24)
+        .filter_map(|hour| {
+            let mean = mean(points.iter().filter(|p| local(p.from).hour() == hour))?;
+            Some(HourOfDay { hour, mean })
+        })
+        .collect();
+    let greenest_hour = *by_hour.iter().min_by(|a, b| a.mean.total_cmp(&b.mean))?;
+    let dirtiest_hour = *by_hour.iter().max_by(|a, b| a.mean.total_cmp(&b.mean))?;
+
+    let is_weekend = |p: &&IntensityPoint| matches!(local(p.from).weekday(), Weekday::Sat | Weekday::Sun);
+
+    Some(Stats {
+        from: first.from,
+        to: last.to,
+        periods: points.len(),
+        min: *values.first()?,
+        max: *values.last()?,
+        mean: mean(points.iter())?,
+        median,
+        p90,
+        greenest_hour,
+        dirtiest_hour,
+        weekday_mean: mean(points.iter().filter(|p| !is_weekend(p))),
+        weekend_mean: mean(points.iter().filter(is_weekend)),
+        by_hour,
+    })
+}
+
+/// Mean intensity to a tenth, or `None` for no points
+fn mean<'a>(points: impl Iterator<Item = &'a IntensityPoint>) -> Option<f64> {
+    let (sum, count) = points.fold((0i64, 0i64), |(sum, count), p| {
+        (sum + p.intensity as i64, count + 1)
+    });
+    (count > 0).then(|| (sum as f64 / count as f64 * 10.0).round() / 10.0)
+}
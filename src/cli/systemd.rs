@@ -0,0 +1,72 @@
+//! Running under systemd: readiness and watchdog notifications, and the
+//! listening socket when the service is socket activated. Each is a no-op
+//! when the process wasn't started by systemd, or on systems without it.
+
+use crate::CarbonError;
+#[cfg(unix)]
+use std::time::Duration;
+#[cfg(unix)]
+use tracing::{info, warn};
+
+/// The listening socket systemd passed in, when the service was started by
+/// a socket unit, or `None` to bind one as usual
+pub fn activated_listener() -> Result<Option<std::net::TcpListener>, CarbonError> {
+    #[cfg(unix)]
+    {
+        use std::os::fd::FromRawFd;
+
+        let mut fds = sd_notify::listen_fds().map_err(|e| CarbonError::io("Failed to read the sockets passed by systemd", e))?;
+        let Some(fd) = fds.next() else {
+            return Ok(None);
+        };
+        if fds.next().is_some() {
+            warn!("systemd passed in more than one socket; listening on the first");
+        }
+        // SAFETY: systemd hands over descriptors from 3 up as open sockets
+        // owned by this process, and `listen_fds` only yields them once
+        let listener = unsafe { std::net::TcpListener::from_raw_fd(fd) };
+        listener
+            .set_nonblocking(true)
+            .map_err(|e| CarbonError::io("Failed to use the socket passed by systemd", e))?;
+        info!("Using the socket passed by systemd");
+        Ok(Some(listener))
+    }
+    #[cfg(not(unix))]
+    Ok(None)
+}
+
+/// Tell systemd the service has started (`READY=1`), and keep its watchdog
+/// fed (`WATCHDOG=1`) at half the unit's `WatchdogSec=` for as long as the
+/// runtime is responsive
+pub fn notify_ready() {
+    #[cfg(unix)]
+    {
+        notify(sd_notify::NotifyState::Ready);
+
+        let mut usec = 0;
+        if sd_notify::watchdog_enabled(false, &mut usec) {
+            let period = Duration::from_micros(usec) / 2;
+            info!(period_ms = period.as_millis() as u64, "Feeding the systemd watchdog");
+            tokio::spawn(async move {
+                let mut interval = tokio::time::interval(period);
+                loop {
+                    interval.tick().await;
+                    notify(sd_notify::NotifyState::Watchdog);
+                }
+            });
+        }
+    }
+}
+
+/// Tell systemd the service is shutting down (`STOPPING=1`)
+pub fn notify_stopping() {
+    #[cfg(unix)]
+    notify(sd_notify::NotifyState::Stopping);
+}
+
+#[cfg(unix)]
+fn notify(state: sd_notify::NotifyState) {
+    if let Err(e) = sd_notify::notify(false, &[state]) {
+        warn!(error = %e, "Failed to notify systemd");
+    }
+}
@@ -0,0 +1,97 @@
+//! The time zone times are shown in: `--timezone`, or the system's own.
+//! Times are still fetched, stored and written to JSON and CSV in UTC; this
+//! changes how they read in text output and charts, and where hours and days
+//! begin when grouping by them.
+
+use chrono::{DateTime, NaiveDate, NaiveTime, Offset, TimeDelta, TimeZone, Utc};
+use chrono_tz::Tz;
+use std::sync::OnceLock;
+use tracing::warn;
+
+/// Set once the command line is parsed
+static DISPLAY_ZONE: OnceLock<Tz> = OnceLock::new();
+
+/// `--timezone`, accepted by every command
+#[derive(Clone, Debug, clap::Args)]
+pub struct TimezoneArgs {
+    /// Time zone to show times in, e.g. Europe/London or UTC [default: the
+    /// system's]
+    #[arg(long, global = true, value_name = "ZONE", value_parser = parse_timezone)]
+    timezone: Option<Tz>,
+}
+
+impl TimezoneArgs {
+    /// Show times in the zone asked for from now on. Without one, the
+    /// system's is looked up on first use, once logging is set up to report
+    /// a failure.
+    pub fn apply(&self) {
+        if let Some(zone) = self.timezone {
+            let _ = DISPLAY_ZONE.set(zone);
+        }
+    }
+}
+
+fn parse_timezone(value: &str) -> Result<Tz, String> {
+    value
+        .parse()
+        .map_err(|_| format!("Unknown time zone '{value}': expected a name such as Europe/London or UTC"))
+}
+
+/// The system's zone by its IANA name, from `TZ` or the OS settings, or UTC
+/// if it can't be told
+fn system_zone() -> Tz {
+    match iana_time_zone::get_timezone() {
+        Ok(name) => name.parse().unwrap_or_else(|_| {
+            warn!("Unknown system time zone '{name}'; showing times in UTC");
+            Tz::UTC
+        }),
+        Err(e) => {
+            warn!(error = %e, "Couldn't tell the system time zone; showing times in UTC");
+            Tz::UTC
+        }
+    }
+}
+
+/// The zone times are shown in: `--timezone`, or the system's
+pub fn display_zone() -> Tz {
+    *DISPLAY_ZONE.get_or_init(system_zone)
+}
+
+/// `time` on the display zone's clock. Formatting with `%Z` gives the zone's
+/// abbreviation, e.g. BST.
+pub fn local(time: DateTime<Utc>) -> DateTime<Tz> {
+    time.with_timezone(&display_zone())
+}
+
+/// The start of the `width` bucket `time` falls in, counted on the display
+/// zone's clock, so hours begin on the hour there even in zones a half hour
+/// off UTC
+pub fn floor(time: DateTime<Utc>, width: TimeDelta) -> DateTime<Utc> {
+    let offset = TimeDelta::seconds(local(time).offset().fix().local_minus_utc().into());
+    crate::settlement::floor(time + offset, width) - offset
+}
+
+/// The first time after `after` that the display zone's clock reads `time`,
+/// e.g. tomorrow's 07:30 once today's has gone. A time skipped by a clock
+/// change falls back to UTC.
+pub fn next_at(time: NaiveTime, after: DateTime<Utc>) -> DateTime<Utc> {
+    let on = |date: NaiveDate| {
+        let naive = date.and_time(time);
+        display_zone()
+            .from_local_datetime(&naive)
+            .earliest()
+            .map_or_else(|| naive.and_utc(), |at| at.with_timezone(&Utc))
+    };
+    let today = local(after).date_naive();
+    let at = on(today);
+    if at > after { at } else { on(today + TimeDelta::days(1)) }
+}
+
+/// When `date` begins in the display zone. A midnight skipped by a clock
+/// change falls back to UTC midnight.
+pub fn start_of_day(date: NaiveDate) -> DateTime<Utc> {
+    display_zone()
+        .from_local_datetime(&date.and_time(NaiveTime::MIN))
+        .earliest()
+        .map_or_else(|| date.and_time(NaiveTime::MIN).and_utc(), |midnight| midnight.with_timezone(&Utc))
+}
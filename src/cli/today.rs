@@ -0,0 +1,98 @@
+use crate::{CarbonClient, CarbonError};
+use crate::cli::ClientArgs;
+use crate::cli::timezone::{local, start_of_day};
+use crate::model::IntensityPoint;
+use crate::units;
+use chrono::{DateTime, Utc};
+use tracing::{instrument, trace};
+
+/// A forecast settlement period for the rest of today
+#[derive(Debug)]
+pub struct ForecastPeriod {
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    pub forecast: i32,
+    pub index: String,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct TodayOptions {
+    #[command(flatten)]
+    client: ClientArgs,
+}
+
+pub async fn run(options: TodayOptions) -> Result<(), CarbonError> {
+    let periods = fetch_rest_of_day_forecast(&options.client.client()?).await?;
+
+    if periods.is_empty() {
+        println!("No forecast periods left today");
+        return Ok(());
+    }
+
+    let cleanest = cleanest_period(&periods);
+
+    println!("Period ({zone})  {unit:>8}  Index", zone = local(periods[0].from).format("%Z"), unit = units::label());
+    for period in &periods {
+        let marker = if cleanest.is_some_and(|c| std::ptr::eq(c, period)) {
+            "  <- cleanest"
+        } else {
+            ""
+        };
+        println!(
+            "{from}-{to}   {forecast:>8}  {index}{marker}",
+            from = local(period.from).format("%H:%M"),
+            to = local(period.to).format("%H:%M"),
+            forecast = units::figure(period.forecast),
+            index = period.index,
+            marker = marker
+        );
+    }
+
+    if let Some(cleanest) = cleanest {
+        println!();
+        println!(
+            "Cleanest period today: {from}-{to} at {forecast} {unit} ({index})",
+            from = local(cleanest.from).format("%H:%M"),
+            to = local(cleanest.to).format("%H:%M"),
+            forecast = units::figure(cleanest.forecast),
+            unit = units::label(),
+            index = cleanest.index
+        );
+    }
+
+    Ok(())
+}
+
+#[instrument(skip(client))]
+async fn fetch_rest_of_day_forecast(
+    client: &CarbonClient,
+) -> Result<Vec<ForecastPeriod>, CarbonError> {
+    // From now until midnight in the display zone
+    let now = Utc::now();
+    let end_of_day = start_of_day(local(now).date_naive() + chrono::Duration::days(1));
+
+    let points = client.intensity_between(now, end_of_day).await?;
+    trace!("Parsed response data: {:?}", points);
+
+    Ok(forecast_periods(points))
+}
+
+/// The periods with a forecast, in the order given
+pub fn forecast_periods(points: Vec<IntensityPoint>) -> Vec<ForecastPeriod> {
+    points
+        .into_iter()
+        .filter_map(|point| {
+            Some(ForecastPeriod {
+                from: point.from,
+                to: point.to,
+                forecast: point.forecast?,
+                index: point.index.map_or_else(|| "unknown".to_string(), |index| index.to_string()),
+            })
+        })
+        .collect()
+}
+
+/// The period with the lowest forecast; the earliest wins a tie
+pub fn cleanest_period(periods: &[ForecastPeriod]) -> Option<&ForecastPeriod> {
+    periods.iter().min_by_key(|p| p.forecast)
+}
@@ -0,0 +1,327 @@
+//! `tui`: a terminal dashboard with the current intensity coloured by band,
+//! the last 24 hours as a sparkline and the generation mix as a bar chart,
+//! refreshed on an interval
+
+use crate::cli::timezone::local;
+use crate::cli::{ClientArgs, RegionArgs, interval_duration};
+use crate::model::{FuelSource, IntensityIndex, IntensityPoint, RegionSelector};
+use crate::schedule::parse_duration_arg;
+use crate::units;
+use crate::{CarbonClient, CarbonError};
+use chrono::{DateTime, TimeDelta, Utc};
+use ratatui::backend::{Backend, ClearType};
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Bar, BarChart, Block, Paragraph, Sparkline, SparklineBar};
+use ratatui::{DefaultTerminal, Frame};
+use tokio::sync::mpsc;
+use tokio::time::MissedTickBehavior;
+use tracing::{instrument, trace};
+
+// How much history the sparkline shows
+const HISTORY: TimeDelta = TimeDelta::hours(24);
+
+#[derive(Debug, clap::Args)]
+pub struct TuiOptions {
+    /// How often to refresh, e.g. 30m or 1h
+    #[arg(long, value_parser = parse_duration_arg, default_value = "30m")]
+    interval: TimeDelta,
+    #[command(flatten)]
+    region: RegionArgs,
+    #[command(flatten)]
+    client: ClientArgs,
+}
+
+/// Everything on screen from one refresh
+#[derive(Debug)]
+struct Snapshot {
+    /// Region name, or `None` for national figures
+    region: Option<String>,
+    intensity: i32,
+    index: Option<IntensityIndex>,
+    history: Vec<IntensityPoint>,
+    generation_mix: Vec<FuelSource>,
+    fetched_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Default)]
+struct App {
+    snapshot: Option<Snapshot>,
+    /// Why the latest refresh failed; the last good data stays on screen
+    error: Option<String>,
+    refreshing: bool,
+    next_refresh: Option<DateTime<Utc>>,
+}
+
+pub async fn run(options: TuiOptions) -> Result<(), CarbonError> {
+    let client = options.client.client()?;
+    let region = options.region.selector();
+    let interval = interval_duration(options.interval)?;
+
+    // crossterm's reads block, so they get a thread of their own
+    let (events_tx, events) = mpsc::unbounded_channel();
+    std::thread::spawn(move || {
+        while let Ok(event) = event::read() {
+            if events_tx.send(event).is_err() {
+                break;
+            }
+        }
+    });
+
+    let mut terminal = ratatui::init();
+    let result = run_app(
+        &mut terminal,
+        events,
+        &client,
+        region.as_ref(),
+        interval,
+        options.interval,
+    )
+    .await;
+    ratatui::restore();
+    result
+}
+
+async fn run_app(
+    terminal: &mut DefaultTerminal,
+    mut events: mpsc::UnboundedReceiver<Event>,
+    client: &CarbonClient,
+    region: Option<&RegionSelector>,
+    interval: std::time::Duration,
+    every: TimeDelta,
+) -> Result<(), CarbonError> {
+    let draw_error = |e| CarbonError::io("Failed to draw the dashboard", e);
+    let mut app = App::default();
+    let mut ticker = tokio::time::interval(interval);
+    ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+    loop {
+        terminal
+            .draw(|frame| draw(frame, &app))
+            .map_err(draw_error)?;
+
+        tokio::select! {
+            _ = ticker.tick() => {
+                app.refreshing = true;
+                terminal.draw(|frame| draw(frame, &app)).map_err(draw_error)?;
+
+                match fetch_snapshot(client, region).await {
+                    Ok(snapshot) => {
+                        app.snapshot = Some(snapshot);
+                        app.error = None;
+                    }
+                    Err(e) => app.error = Some(e.to_string()),
+                }
+                app.refreshing = false;
+                app.next_refresh = Some(Utc::now() + every);
+                // Retry warnings go to stderr, over the dashboard; wipe the
+                // screen and redraw all of it. `Terminal::clear` would ask
+                // for the cursor position, and the reply would be lost to
+                // the event thread.
+                terminal
+                    .backend_mut()
+                    .clear_region(ClearType::All)
+                    .map_err(draw_error)?;
+                terminal.swap_buffers();
+            }
+            event = events.recv() => match event {
+                Some(Event::Key(key)) if key.kind == KeyEventKind::Press => match key.code {
+                    KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                    KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                        return Ok(());
+                    }
+                    KeyCode::Char('r') => ticker.reset_immediately(),
+                    _ => {}
+                },
+                // Resizes and the like just need a redraw
+                Some(_) => {}
+                None => return Ok(()),
+            },
+        }
+    }
+}
+
+/// The current reading, the last 24 hours and the generation mix, national or
+/// for the selected region
+#[instrument(skip(client))]
+async fn fetch_snapshot(
+    client: &CarbonClient,
+    region: Option<&RegionSelector>,
+) -> Result<Snapshot, CarbonError> {
+    let now = Utc::now();
+    let history = client
+        .intensity_between_for(region, now - HISTORY, now)
+        .await?;
+    trace!("Parsed response data: {:?}", history);
+
+    let snapshot = match region {
+        Some(selector) => {
+            let region = client.regional_reading(selector).await?.region;
+            let intensity = region.intensity.best().ok_or_else(|| {
+                CarbonError::EmptyData(format!(
+                    "intensity for {region}",
+                    region = region.short_name
+                ))
+            })?;
+            Snapshot {
+                intensity,
+                index: region.intensity.index,
+                region: Some(region.short_name),
+                history,
+                generation_mix: region.generation_mix,
+                fetched_at: now,
+            }
+        }
+        None => {
+            let period = client.current_period().await?;
+            Snapshot {
+                region: None,
+                intensity: period.intensity,
+                index: period.index,
+                history,
+                generation_mix: client.generation_mix().await?,
+                fetched_at: now,
+            }
+        }
+    };
+    Ok(snapshot)
+}
+
+fn draw(frame: &mut Frame, app: &App) {
+    let fuels = app
+        .snapshot
+        .as_ref()
+        .map_or(0, |snapshot| snapshot.generation_mix.len() as u16);
+    let [header, history, mix, status] = Layout::vertical([
+        Constraint::Length(3),
+        Constraint::Min(5),
+        Constraint::Length(fuels + 2),
+        Constraint::Length(1),
+    ])
+    .areas(frame.area());
+
+    frame.render_widget(status_line(app), status);
+    let Some(snapshot) = &app.snapshot else {
+        let message = if app.refreshing {
+            "Fetching intensity…"
+        } else {
+            "No data yet"
+        };
+        frame.render_widget(Paragraph::new(message).block(Block::bordered()), header);
+        return;
+    };
+
+    let title = format!(
+        " Carbon intensity: {region} ",
+        region = snapshot.region.as_deref().unwrap_or("National")
+    );
+    let band = Style::new()
+        .fg(band_colour(snapshot.index))
+        .add_modifier(Modifier::BOLD);
+    let mut current = vec![
+        Span::styled(units::figure(snapshot.intensity), band),
+        Span::raw(format!(" {unit}", unit = units::label())),
+    ];
+    if let Some(index) = snapshot.index {
+        current.extend([Span::raw("  "), Span::styled(index.to_string(), band)]);
+    }
+    frame.render_widget(
+        Paragraph::new(Line::from(current)).block(Block::bordered().title(title)),
+        header,
+    );
+
+    frame.render_widget(sparkline(&snapshot.history), history);
+    frame.render_widget(mix_chart(&snapshot.generation_mix), mix);
+}
+
+/// The last 24 hours, one bar per settlement period coloured by its band
+fn sparkline(points: &[IntensityPoint]) -> Sparkline<'_> {
+    let values = points.iter().map(|p| p.intensity.max(0) as u64);
+    let title = match (values.clone().min(), values.clone().max()) {
+        (Some(min), Some(max)) => format!(
+            " Last 24 hours: {min}–{max} {unit} ",
+            min = units::figure(min as f64),
+            max = units::figure(max as f64),
+            unit = units::label()
+        ),
+        _ => " Last 24 hours ".to_string(),
+    };
+    let bars: Vec<SparklineBar> = points
+        .iter()
+        .map(|p| {
+            SparklineBar::from(p.intensity.max(0) as u64)
+                .style(Some(Style::new().fg(band_colour(p.index))))
+        })
+        .collect();
+
+    Sparkline::default()
+        .block(Block::bordered().title(title))
+        .data(bars)
+}
+
+/// Share of generation by fuel, largest first
+fn mix_chart(generation_mix: &[FuelSource]) -> BarChart<'_> {
+    let mut fuels: Vec<_> = generation_mix.iter().collect();
+    fuels.sort_by(|a, b| b.perc.total_cmp(&a.perc));
+    let bars: Vec<Bar> = fuels
+        .into_iter()
+        .map(|fuel| {
+            // Tenths of a percent, so small shares still show
+            Bar::with_label(fuel.fuel.as_str(), (fuel.perc * 10.0).round() as u64)
+                .text_value(format!("{perc:.1}%", perc = fuel.perc))
+        })
+        .collect();
+
+    BarChart::horizontal(bars)
+        .block(Block::bordered().title(" Generation mix "))
+        .bar_width(1)
+        .bar_gap(0)
+        .max(1000)
+}
+
+/// When the data is from and when it's next refreshed, or why the last
+/// refresh failed, with the keys
+fn status_line(app: &App) -> Line<'_> {
+    let mut spans = Vec::new();
+    if app.refreshing {
+        spans.push(Span::raw("Refreshing… "));
+    } else if let Some(error) = &app.error {
+        spans.push(Span::styled(
+            format!("{error} "),
+            Style::new().fg(Color::Red),
+        ));
+    }
+    if let Some(snapshot) = &app.snapshot {
+        spans.push(Span::raw(format!(
+            "Updated {updated} ",
+            updated = local(snapshot.fetched_at).format("%H:%M %Z")
+        )));
+    }
+    if let Some(next) = app.next_refresh
+        && !app.refreshing
+    {
+        spans.push(Span::raw(format!(
+            "· next at {next} ",
+            next = local(next).format("%H:%M")
+        )));
+    }
+    spans.push(Span::styled(
+        "· r refresh · q quit",
+        Style::new().add_modifier(Modifier::DIM),
+    ));
+    Line::from(spans)
+}
+
+/// Greens for the cleanest bands through to red for the dirtiest
+fn band_colour(index: Option<IntensityIndex>) -> Color {
+    match index {
+        Some(IntensityIndex::VeryLow) => Color::Green,
+        Some(IntensityIndex::Low) => Color::LightGreen,
+        Some(IntensityIndex::Moderate) => Color::Yellow,
+        Some(IntensityIndex::High) => Color::LightRed,
+        Some(IntensityIndex::VeryHigh) => Color::Red,
+        None => Color::Reset,
+    }
+}
@@ -0,0 +1,257 @@
+//! `watch`: poll the current intensity and raise a desktop notification when
+//! it crosses a threshold, or call webhooks when its index changes band
+
+use crate::{CarbonClient, CarbonError};
+use crate::cli::{ClientArgs, PollTicker, RegionArgs, usage_error};
+use crate::model::{IntensityIndex, RegionSelector};
+use crate::schedule::parse_duration_arg;
+use crate::units;
+use crate::webhook::{BandChange, BandReading, WebhookPublisher};
+use chrono::{DateTime, TimeDelta, Utc};
+use notify_rust::Notification;
+use tracing::{info, instrument, warn};
+
+/// Which side of the thresholds the grid was last seen on
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Zone {
+    Normal,
+    Below,
+    Above,
+}
+
+/// One poll's worth of intensity, national or regional
+#[derive(Clone, Debug)]
+struct Reading {
+    intensity: i32,
+    index: Option<IntensityIndex>,
+    /// Start of the settlement period
+    from: DateTime<Utc>,
+    /// Region short name, `None` for national
+    region: Option<String>,
+}
+
+/// The `--below`/`--above` thresholds with their hysteresis margin
+#[derive(Clone, Copy, Debug)]
+struct Thresholds {
+    below: Option<i32>,
+    above: Option<i32>,
+    hysteresis: i32,
+}
+
+impl Thresholds {
+    /// The zone for a new reading. A zone is entered as soon as its threshold
+    /// is crossed but only left once the reading is `hysteresis` back on the
+    /// other side, so a value hovering around a threshold notifies once.
+    fn next_zone(&self, current: Zone, intensity: i32) -> Zone {
+        let below = self.below.is_some_and(|below| intensity < below);
+        let above = self.above.is_some_and(|above| intensity > above);
+        let still_below = self
+            .below
+            .is_some_and(|below| intensity < below + self.hysteresis);
+        let still_above = self
+            .above
+            .is_some_and(|above| intensity > above - self.hysteresis);
+
+        match current {
+            _ if below => Zone::Below,
+            _ if above => Zone::Above,
+            Zone::Below if still_below => Zone::Below,
+            Zone::Above if still_above => Zone::Above,
+            _ => Zone::Normal,
+        }
+    }
+}
+
+#[derive(Debug, clap::Args)]
+#[command(group = clap::ArgGroup::new("trigger").required(true).multiple(true))]
+pub struct WatchOptions {
+    /// Notify when intensity drops below this many gCO₂/kWh
+    #[arg(long, value_name = "G", group = "trigger")]
+    below: Option<i32>,
+    /// Notify when intensity rises above this many gCO₂/kWh
+    #[arg(long, value_name = "G", group = "trigger")]
+    above: Option<i32>,
+    /// How far back across a threshold (gCO₂/kWh) intensity must move
+    /// before that threshold can notify again
+    #[arg(long, value_name = "G", default_value_t = 10, value_parser = clap::value_parser!(i32).range(0..))]
+    hysteresis: i32,
+    /// POST to this URL when the intensity index changes band; repeat for
+    /// several URLs
+    #[arg(long = "webhook", value_name = "URL", group = "trigger")]
+    webhooks: Vec<String>,
+    /// Webhook body with {{previous_index}}, {{previous_intensity}},
+    /// {{index}}, {{intensity}}, {{timestamp}} and {{region}} placeholders,
+    /// or @FILE to read it from a file; defaults to a JSON description of the
+    /// change
+    #[arg(long, value_name = "TEMPLATE", requires = "webhooks", value_parser = parse_template)]
+    webhook_template: Option<String>,
+    /// How often to check
+    #[arg(long, value_parser = parse_duration_arg, default_value = "30m")]
+    interval: TimeDelta,
+    #[command(flatten)]
+    region: RegionArgs,
+    #[command(flatten)]
+    client: ClientArgs,
+}
+
+pub async fn run(options: WatchOptions) -> Result<(), CarbonError> {
+    if let (Some(below), Some(above)) = (options.below, options.above)
+        && below > above
+    {
+        return Err(usage_error(format!(
+            "--below ({below}) must not be higher than --above ({above})"
+        )));
+    }
+
+    let thresholds = Thresholds {
+        below: options.below,
+        above: options.above,
+        hysteresis: options.hysteresis,
+    };
+    let client = options.client.client()?;
+    let region = options.region.selector();
+    let webhooks = WebhookPublisher::new(options.webhooks, options.webhook_template);
+
+    let mut zone = Zone::Normal;
+    let mut last: Option<Reading> = None;
+    let mut ticker = PollTicker::new(options.interval)?;
+    loop {
+        ticker.tick().await;
+        let reading = match fetch_reading(&client, region.as_ref()).await {
+            Ok(reading) => reading,
+            Err(e) => {
+                warn!("Failed to fetch intensity: {e}");
+                continue;
+            }
+        };
+
+        info!(
+            "Intensity {intensity} {unit} ({index:?})",
+            intensity = units::figure(reading.intensity),
+            unit = units::label(),
+            index = reading.index
+        );
+        if thresholds.below.is_some() || thresholds.above.is_some() {
+            let next = thresholds.next_zone(zone, reading.intensity);
+            if next != zone
+                && let Some(message) = alert_message(next, reading.intensity, reading.index)
+            {
+                notify(message).await;
+            }
+            zone = next;
+        }
+
+        if !webhooks.is_empty()
+            && let Some(change) = last.as_ref().and_then(|last| band_change(last, &reading))
+        {
+            webhooks.publish(&change).await;
+        }
+        last = Some(reading);
+    }
+}
+
+/// The band change between two readings, if both have a band and it moved.
+/// The first reading only sets the starting band.
+fn band_change(previous: &Reading, current: &Reading) -> Option<BandChange> {
+    let (previous_index, current_index) = (previous.index?, current.index?);
+    if previous_index == current_index {
+        return None;
+    }
+    Some(BandChange {
+        previous: BandReading {
+            index: previous_index,
+            intensity: previous.intensity,
+        },
+        current: BandReading {
+            index: current_index,
+            intensity: current.intensity,
+        },
+        timestamp: current.from,
+        region: current.region.clone(),
+    })
+}
+
+/// A webhook body given inline, or read from a file with `@path`
+fn parse_template(value: &str) -> Result<String, String> {
+    match value.strip_prefix('@') {
+        Some(path) => std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read template '{path}': {e}")),
+        None => Ok(value.to_string()),
+    }
+}
+
+/// Intensity and its band, national or for the selected region
+#[instrument(skip(client))]
+async fn fetch_reading(
+    client: &CarbonClient,
+    region: Option<&RegionSelector>,
+) -> Result<Reading, CarbonError> {
+    match region {
+        Some(region) => {
+            let reading = client.regional_reading(region).await?;
+            let region = reading.region;
+            let intensity = region.intensity.best().ok_or_else(|| {
+                CarbonError::EmptyData(format!(
+                    "intensity for {region}",
+                    region = region.short_name
+                ))
+            })?;
+            Ok(Reading {
+                intensity,
+                index: region.intensity.index,
+                from: reading.from,
+                region: Some(region.short_name),
+            })
+        }
+        None => {
+            let period = client.current_period().await?;
+            Ok(Reading {
+                intensity: period.intensity,
+                index: period.index,
+                from: period.from,
+                region: None,
+            })
+        }
+    }
+}
+
+/// What to say on entering a zone; nothing on returning to normal
+fn alert_message(zone: Zone, intensity: i32, index: Option<IntensityIndex>) -> Option<String> {
+    // Name the band where the API gives one, e.g. "very low carbon"
+    let (band, advice) = match zone {
+        Zone::Below => (
+            index.map_or("low", IntensityIndex::as_str),
+            "good time to charge",
+        ),
+        Zone::Above => (
+            index.map_or("high", IntensityIndex::as_str),
+            "consider waiting",
+        ),
+        Zone::Normal => return None,
+    };
+    Some(format!(
+        "Grid is {band} carbon ({intensity} {unit}) — {advice}.",
+        intensity = units::figure(intensity),
+        unit = units::label()
+    ))
+}
+
+/// Show a desktop notification, and print it too for headless sessions.
+/// A missing notification service is logged rather than stopping the watch.
+async fn notify(message: String) {
+    println!("{message}");
+    let shown = tokio::task::spawn_blocking(move || {
+        Notification::new()
+            .appname("carbon-vibe")
+            .summary("Carbon intensity")
+            .body(&message)
+            .show()
+            .map(|_| ())
+    })
+    .await;
+    match shown {
+        Ok(Ok(())) => {}
+        Ok(Err(e)) => warn!("Failed to show notification: {e}"),
+        Err(e) => warn!("Notification task failed: {e}"),
+    }
+}
@@ -0,0 +1,3716 @@
+use axum::{
+    Router,
+    extract::{ConnectInfo, Path, Query, State},
+    extract::ws::{Message, WebSocket, WebSocketUpgrade},
+    Json,
+    http::{
+        HeaderMap, HeaderName, HeaderValue, StatusCode,
+        header::{ACCEPT_ENCODING, AUTHORIZATION, CACHE_CONTROL, CONTENT_ENCODING, CONTENT_TYPE, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED, RETRY_AFTER, VARY, WWW_AUTHENTICATE},
+    },
+    middleware::{self, Next},
+    response::{
+        AppendHeaders, Html, IntoResponse, Response,
+        sse::{Event, KeepAlive, Sse},
+    },
+    routing::get,
+};
+use askama::Template;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use hyper_util::rt::{TokioExecutor, TokioIo, TokioTimer};
+use hyper_util::server::conn::auto::Builder;
+use hyper_util::server::graceful::GracefulShutdown;
+use hyper_util::service::TowerToHyperService;
+use crate::{CarbonClient, CarbonError};
+use crate::alert::{AlertEvent, History, Reading, Rule};
+use crate::badge::{BADGE_UNAVAILABLE, render_badge};
+use crate::cli::advice::{Advice, Appliances, advise, load_appliances};
+use crate::calendar::{DEFAULT_BELOW, low_carbon_windows, to_ics};
+use crate::chart::{
+    CHART_SIZE, SPARKLINE_HEIGHT, SPARKLINE_HISTORY, SPARKLINE_WIDTH, TREEMAP_SIZE, escape_xml, fuel_series, mix_color,
+    moving_average, render_intensity_chart, render_mix_history_chart, render_mix_treemap, render_pie_chart, sparkline_path,
+    trailing_periods,
+};
+use crate::cli::alert::load_rules;
+use crate::cli::archive::record_from;
+use crate::cli::failures::{FailureArgs, FailureCount};
+use crate::cli::single_flight::SingleFlight;
+use crate::cli::systemd;
+use crate::cli::timezone::local;
+use crate::cli::{ClientArgs, PriceArgs, RegionArgs, parse_seconds, regional_client, usage_error};
+use crate::context::{CONTEXT_WINDOW, IntensityContext};
+use crate::eink::{EINK_SIZE, EinkScreen, describe_green_window, render_eink};
+use crate::mix::{FactorOverrides, FuelSourceWithIntensity, UNKNOWN_FUEL, enrich_generation_mix, weighted_intensity};
+use crate::map::{REGION_OUTLINES, map_size, outline_path};
+use crate::model::{CarbonFactors, FuelSource, GenerationMixPeriod, IntensityIndex, IntensityPoint, REGION_NAMES, Region, RegionSelector};
+use crate::octopus::{OctopusClient, UnitRate};
+use crate::provider::{FUEL_FACTORS, GridClient, GridDataProvider};
+use crate::quality::{DataWarning, Warnings};
+use crate::raster::{data_uri, encode_jpeg, encode_monochrome, rasterise, system_fonts};
+use crate::schedule::{cheap_green_window, describe_duration, parse_duration_arg};
+use crate::settlement::{SETTLEMENT_PERIOD, SettlementPeriod, next_poll, until_next_period};
+use crate::store::Store;
+use crate::theme::{DEFAULT_MIN_CONTRAST, LIGHT, THEMES, ThemeChoice, palette_contrast_warnings};
+use crate::units;
+use chrono::{DateTime, TimeDelta, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::fmt;
+use std::net::{IpAddr, SocketAddr};
+use std::path::PathBuf;
+use std::io::Write;
+use std::str::FromStr;
+use std::sync::{Arc, OnceLock};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+use futures_util::{Stream, stream};
+use tokio::sync::{RwLock, Semaphore, broadcast, watch};
+use tower::ServiceBuilder;
+use tower_http::LatencyUnit;
+use tower_http::add_extension::AddExtension;
+use tower_http::compression::CompressionLayer;
+use tower_http::compression::predicate::{DefaultPredicate, NotForContentType, Predicate};
+use tower_http::request_id::{MakeRequestUuid, PropagateRequestIdLayer, SetRequestIdLayer};
+use tower_http::trace::{DefaultOnResponse, TraceLayer};
+use utoipa::{IntoParams, OpenApi, ToSchema};
+use tracing::{Instrument, Level, Span, debug, error, info, info_span, instrument, warn};
+
+// Number of timeline points averaged for the trend overlay (0 disables it)
+const DEFAULT_MOVING_AVERAGE_WINDOW: usize = 5;
+
+// Past its TTL, cached data is still served while a background refresh runs,
+// but only up to this age; older data is refetched before responding
+const CACHE_MAX_STALE: Duration = Duration::from_secs(30 * 60);
+
+// The published carbon factors almost never change, so they're fetched at
+// most this often rather than with every refresh
+const FACTORS_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+// Messages held for a `/ws` client that's slow to read before the oldest are
+// dropped; a few hours of updates and alerts
+const PUSH_CAPACITY: usize = 16;
+
+// How far the timeline reaches either side of the current settlement period
+const TIMELINE_SPAN: TimeDelta = TimeDelta::hours(12);
+
+/// Reference the headline intensity is compared against
+#[derive(Clone, Debug, PartialEq)]
+pub enum Baseline {
+    /// Average of today's actual readings in the timeline
+    Daily,
+    /// Fixed reference value in gCO₂/kWh
+    Fixed(i32),
+}
+
+impl FromStr for Baseline {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "daily" => Ok(Baseline::Daily),
+            _ => value.parse().map(Baseline::Fixed).map_err(|_| {
+                format!("Invalid baseline '{value}': expected 'daily' or a gCO₂/kWh value")
+            }),
+        }
+    }
+}
+
+// Pixels per degree of latitude on the /regions map
+const REGION_MAP_SCALE: f64 = 60.0;
+
+// Bounds on `/chart.png`'s and `/treemap.svg`'s size, so one request can't
+// ask for a gigapixel
+const MIN_CHART_SIZE: (u32, u32) = (200, 100);
+const MAX_CHART_SIZE: (u32, u32) = (2400, 1600);
+
+// `/chart.jpg` quality, 1-100
+const CHART_JPEG_QUALITY: u8 = 90;
+
+// How far back the generation mix history goes: the stacked chart shows all
+// of it and the legend sparklines the latest `SPARKLINE_HISTORY`
+const MIX_HISTORY: chrono::TimeDelta = chrono::TimeDelta::hours(24);
+
+// Be a good citizen towards the free API when many pages load at once
+const DEFAULT_MAX_UPSTREAM_REQUESTS: usize = 4;
+
+// A page load plus its live updates is a handful of requests, so these only
+// bite on scripted or runaway clients
+const DEFAULT_RATE_LIMIT: u32 = 120;
+const DEFAULT_RATE_LIMIT_BURST: u32 = 60;
+
+// Well under the common 1024 open file limit
+const DEFAULT_MAX_CONCURRENT_REQUESTS: usize = 256;
+
+// The API's figure comes from its own model, which also estimates embedded
+// solar and wind, so the mix worked through the factors is typically within
+// a couple of dozen gCO₂/kWh of it
+const DEFAULT_MIX_CHECK_THRESHOLD: u32 = 30;
+
+// Clients tracked for rate limiting before idle ones are forgotten
+const RATE_LIMIT_PRUNE_AT: usize = 4096;
+
+/// Where the generation mix legend sits relative to the pie chart
+#[derive(Clone, Copy, Debug, PartialEq, clap::ValueEnum)]
+enum LegendPosition {
+    Right,
+    Bottom,
+}
+
+/// Server-wide options taken from the command line
+#[derive(Clone, Debug, clap::Args)]
+pub struct DashboardOptions {
+    /// Address and port to listen on
+    #[arg(long, value_name = "ADDR", default_value = "127.0.0.1:3000")]
+    bind: SocketAddr,
+    /// Compare the headline against 'daily' (today's average of actual
+    /// readings) or a fixed gCO₂/kWh value
+    #[arg(long)]
+    baseline: Option<Baseline>,
+    /// Set TCP_NODELAY on accepted sockets
+    #[arg(long)]
+    tcp_nodelay: bool,
+    /// How long a client has to send a request's headers, in seconds. On a
+    /// kept-alive connection the time counts from the previous response, so
+    /// it also closes connections left idle that long; 0 waits indefinitely
+    // hyper's default
+    #[arg(long, value_name = "SECONDS", value_parser = parse_seconds, default_value = "30")]
+    header_read_timeout: Duration,
+    /// Close each connection after its response instead of keeping it open
+    /// for the next request
+    #[arg(long)]
+    no_keep_alive: bool,
+    /// On SIGTERM or Ctrl-C, how long in-flight requests get to finish before
+    /// the server exits anyway, in seconds
+    // Inside Kubernetes' default 30-second termination grace period
+    #[arg(long, value_name = "SECONDS", value_parser = parse_seconds, default_value = "20")]
+    shutdown_timeout: Duration,
+    /// Minimum palette contrast ratio checked at startup; 0 skips the check,
+    /// and a failure stops the server starting with `--strict`
+    #[arg(long, default_value_t = DEFAULT_MIN_CONTRAST)]
+    min_contrast: f64,
+    /// Show imports per interconnector (France, Netherlands, Belgium, Norway,
+    /// Ireland) instead of one slice, apportioned by capacity
+    #[arg(long)]
+    split_imports: bool,
+    /// Where the generation mix legend sits relative to the pie chart
+    #[arg(long, value_enum, default_value = "bottom")]
+    legend_position: LegendPosition,
+    /// Colour theme; 'auto' follows the browser's light or dark preference.
+    /// A page can pick its own with `?theme=`
+    #[arg(long, value_enum, default_value = "auto")]
+    theme: ThemeChoice,
+    /// Show the pie chart without a legend
+    #[arg(long, conflicts_with = "legend_position")]
+    no_legend: bool,
+    /// Flag the intensity worked out from the mix and factors when it's more
+    /// than this many gCO₂/kWh from the reported figure
+    #[arg(long, value_name = "GCO2_PER_KWH", default_value_t = DEFAULT_MIX_CHECK_THRESHOLD)]
+    mix_check_threshold: u32,
+    /// Fix the intensity chart's y-axis minimum instead of following the data
+    #[arg(long, value_name = "GCO2_PER_KWH")]
+    y_min: Option<f64>,
+    /// Fix the intensity chart's y-axis maximum instead of following the data
+    #[arg(long, value_name = "GCO2_PER_KWH")]
+    y_max: Option<f64>,
+    /// Cap on simultaneous requests to the Carbon Intensity API
+    #[arg(long, value_parser = parse_request_limit, default_value_t = DEFAULT_MAX_UPSTREAM_REQUESTS)]
+    max_upstream_requests: usize,
+    /// Requests per minute allowed from each client IP address, on average;
+    /// 0 disables rate limiting
+    #[arg(long, value_name = "REQUESTS", default_value_t = DEFAULT_RATE_LIMIT)]
+    rate_limit: u32,
+    /// Requests a client may make in quick succession before `--rate-limit`
+    /// applies
+    #[arg(long, value_name = "REQUESTS", value_parser = clap::value_parser!(u32).range(1..), default_value_t = DEFAULT_RATE_LIMIT_BURST)]
+    rate_limit_burst: u32,
+    /// Cap on requests being handled at once across all clients; more are
+    /// turned away with 503. 0 disables the cap
+    #[arg(long, default_value_t = DEFAULT_MAX_CONCURRENT_REQUESTS)]
+    max_concurrent_requests: usize,
+    /// Require this token on every page and API route, as `Authorization:
+    /// Bearer TOKEN` or as the password for HTTP basic auth; prefer the
+    /// environment variable, which isn't visible in the process list
+    #[arg(long, value_name = "TOKEN", env = "CARBON_WEB_TOKEN", hide_env_values = true)]
+    auth_token: Option<String>,
+    /// Require HTTP basic auth with this user name and `--auth-password`
+    #[arg(long, value_name = "USER", requires = "auth_password")]
+    auth_user: Option<String>,
+    #[arg(
+        long,
+        env = "CARBON_WEB_PASSWORD",
+        hide_env_values = true,
+        requires = "auth_user"
+    )]
+    auth_password: Option<String>,
+    /// Length of the cheap-and-green window shown with --agile-region, e.g.
+    /// 2h or 90m
+    #[arg(long, value_name = "DURATION", value_parser = parse_duration_arg, default_value = "2h")]
+    price_window: TimeDelta,
+    #[command(flatten)]
+    prices: PriceArgs,
+    // Show one region's figures instead of the national ones
+    #[command(flatten)]
+    region: RegionArgs,
+    /// How long upstream data is reused between requests, in seconds; 0
+    /// disables the cache
+    #[arg(long, value_name = "SECONDS", value_parser = parse_seconds, default_value = "300")]
+    cache_ttl: Duration,
+    /// How often to check upstream for new data to push to `/events`,
+    /// `/api/stream` and `/ws` clients, in seconds
+    // Settlement periods are 30 minutes, so the default catches each one
+    // within a few minutes of it landing
+    #[arg(long, value_name = "SECONDS", value_parser = parse_refresh_interval, default_value = "300")]
+    refresh_interval: Duration,
+    /// TOML file of emission factors in gCO₂/kWh to use instead of the
+    /// published ones, e.g. `gas = 490` or `norwegian-imports = 20`
+    #[arg(long, value_name = "PATH")]
+    factors: Option<PathBuf>,
+    /// Alert rules file, as for `alert`, whose alerts are pushed to `/ws`
+    /// clients; the rules' channels are left to `alert`
+    #[arg(long, value_name = "PATH")]
+    alerts: Option<PathBuf>,
+    #[command(flatten)]
+    client: ClientArgs,
+    /// `/api/advice` profiles, from the config file's `[appliances]` rather
+    /// than the command line
+    #[arg(skip)]
+    appliances: Appliances,
+}
+
+impl DashboardOptions {
+    /// Serve `/api/advice` for these appliances
+    pub fn with_appliances(self, appliances: Appliances) -> Self {
+        DashboardOptions { appliances, ..self }
+    }
+
+    /// Legend placement, or `None` to show the pie chart alone
+    fn legend(&self) -> Option<LegendPosition> {
+        (!self.no_legend).then_some(self.legend_position)
+    }
+
+    /// Log the settings the server runs with, after the command line, the
+    /// environment and the config file have had their say. Secrets are only
+    /// reported as set or not.
+    fn log_effective(&self) {
+        let auth = match (&self.auth_token, &self.auth_user) {
+            (_, Some(_)) => "basic",
+            (Some(_), None) => "token",
+            (None, None) => "none",
+        };
+        let client = &self.client;
+        info!(
+            bind = %self.bind,
+            provider = %client.provider,
+            api_url = client.api_url.as_deref().unwrap_or("default"),
+            entsoe_token = client.entsoe_token.is_some(),
+            electricitymaps_token = client.electricitymaps_token.is_some(),
+            region = %self.region.selector().map_or_else(|| "national".to_string(), |region| region.to_string()),
+            cache_ttl_secs = self.cache_ttl.as_secs(),
+            refresh_interval_secs = self.refresh_interval.as_secs(),
+            max_upstream_requests = self.max_upstream_requests,
+            rate_limit = self.rate_limit,
+            rate_limit_burst = self.rate_limit_burst,
+            max_concurrent_requests = self.max_concurrent_requests,
+            header_read_timeout_secs = self.header_read_timeout.as_secs(),
+            keep_alive = !self.no_keep_alive,
+            shutdown_timeout_secs = self.shutdown_timeout.as_secs(),
+            auth,
+            baseline = ?self.baseline,
+            mix_check_threshold = self.mix_check_threshold,
+            agile_region = ?self.prices.agile_region,
+            factors = ?self.factors,
+            alerts = ?self.alerts,
+            appliances = ?self.appliances.keys().collect::<Vec<_>>(),
+            theme = ?self.theme,
+            "Effective configuration"
+        );
+    }
+}
+
+fn parse_request_limit(value: &str) -> Result<usize, String> {
+    value
+        .parse()
+        .ok()
+        .filter(|&limit| limit > 0)
+        .ok_or_else(|| format!("Invalid request limit '{value}': expected a positive number"))
+}
+
+fn parse_refresh_interval(value: &str) -> Result<Duration, String> {
+    parse_seconds(value)
+        .ok()
+        .filter(|interval| !interval.is_zero())
+        .ok_or_else(|| format!("Invalid refresh interval '{value}': expected a positive number of seconds"))
+}
+
+struct AppState {
+    client: GridClient,
+    /// Agile prices, with --agile-region
+    octopus: Option<OctopusClient>,
+    options: DashboardOptions,
+    cache: DashboardCache,
+    factors: FactorsCache,
+    metrics: UpstreamMetrics,
+    /// Latest data fetched by `publish_dashboard_updates`, watched by each
+    /// `/events` connection
+    updates: watch::Sender<Option<Arc<DashboardData>>>,
+    /// JSON messages for `/ws` clients: new figures from the same task, and
+    /// alerts from `--alerts`
+    pushes: broadcast::Sender<String>,
+    /// With `--alerts`: the rules, checked each time the data is refreshed
+    alerts: Option<DashboardAlerts>,
+    /// Set once the server has been asked to stop; ends `/events` streams and
+    /// `/ws` connections so they can close, and fails `/readyz`
+    shutting_down: watch::Sender<bool>,
+    limits: RequestLimits,
+    /// With `daemon`: the local archive pages are served from, kept up to
+    /// date by `poll_archive` rather than fetched per request
+    archive: Option<Arc<std::sync::Mutex<Store>>>,
+    /// System fonts for `/chart.png` and `/eink`, loaded on first use
+    fonts: OnceLock<Arc<resvg::usvg::fontdb::Database>>,
+}
+
+impl AppState {
+    fn new(options: DashboardOptions) -> Result<Self, CarbonError> {
+        check_options(&options)?;
+        let client = options
+            .client
+            .provider()?
+            .with_max_in_flight(options.max_upstream_requests);
+        // Fail at startup rather than on every page load
+        if options.region.selector().is_some() {
+            regional_client(&client)?;
+        }
+        let octopus = options.prices.octopus(&options.client);
+        if octopus.is_some() && client.carbon_intensity().is_none() {
+            return Err(usage_error("--agile-region only works with --provider carbon-intensity"));
+        }
+
+        let overrides = match &options.factors {
+            Some(path) => load_factor_overrides(path)?,
+            None => FactorOverrides::default(),
+        };
+        let alerts = match &options.alerts {
+            Some(path) => Some(DashboardAlerts::new(load_rules(path)?.1)),
+            None => None,
+        };
+
+        Ok(AppState {
+            client,
+            octopus,
+            limits: RequestLimits::new(&options),
+            options,
+            cache: DashboardCache::new(),
+            factors: FactorsCache::new(overrides),
+            metrics: UpstreamMetrics::default(),
+            updates: watch::Sender::new(None),
+            pushes: broadcast::Sender::new(PUSH_CAPACITY),
+            alerts,
+            shutting_down: watch::Sender::new(false),
+            archive: None,
+            fonts: OnceLock::new(),
+        })
+    }
+}
+
+/// Upstream data shared between requests, since it only changes every
+/// half hour. Fresh data is served as-is. Stale data is served at once while
+/// a single background task refreshes it (stale-while-revalidate), and a
+/// missing or very stale entry is fetched by one request while concurrent
+/// ones wait for its result, as are uncached fetches with `--cache-ttl 0`.
+struct DashboardCache {
+    entry: RwLock<Option<CachedData>>,
+    /// The fetch from upstream in progress, shared by everyone who needs it
+    refresh: SingleFlight<Arc<DashboardData>>,
+}
+
+/// Counters for upstream fetches, exported at `/metrics`, and the latest
+/// outcomes, reported by the health endpoints
+#[derive(Default)]
+struct UpstreamMetrics {
+    fetches: AtomicU64,
+    errors: AtomicU64,
+    /// Total time spent in fetches, in microseconds
+    duration_micros: AtomicU64,
+    last_success: std::sync::Mutex<Option<DateTime<Utc>>>,
+    last_error: std::sync::Mutex<Option<UpstreamError>>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+struct UpstreamError {
+    at: DateTime<Utc>,
+    error: String,
+}
+
+impl UpstreamMetrics {
+    fn record(&self, duration: Duration, error: Option<String>) {
+        self.fetches.fetch_add(1, Ordering::Relaxed);
+        self.duration_micros
+            .fetch_add(duration.as_micros() as u64, Ordering::Relaxed);
+
+        let now = Utc::now();
+        match error {
+            None => *lock(&self.last_success) = Some(now),
+            Some(error) => {
+                self.errors.fetch_add(1, Ordering::Relaxed);
+                *lock(&self.last_error) = Some(UpstreamError { at: now, error });
+            }
+        }
+    }
+
+    /// Whether the most recent fetch succeeded; `None` before the first
+    fn reachable(&self) -> Option<bool> {
+        let last_success = *lock(&self.last_success);
+        match (last_success, lock(&self.last_error).as_ref()) {
+            (None, None) => None,
+            (Some(success), Some(error)) => Some(success > error.at),
+            (success, _) => Some(success.is_some()),
+        }
+    }
+}
+
+/// Per-client rate limits and the global cap on requests in progress,
+/// enforced by `limit_requests` on every route but the health probes
+struct RequestLimits {
+    /// `None` when rate limiting is off
+    rate_limiter: Option<RateLimiter>,
+    /// `None` when there's no cap
+    in_flight: Option<Arc<Semaphore>>,
+    rate_limited: AtomicU64,
+    over_capacity: AtomicU64,
+    /// Requests `require_auth` turned away
+    unauthorised: AtomicU64,
+}
+
+impl RequestLimits {
+    fn new(options: &DashboardOptions) -> Self {
+        RequestLimits {
+            rate_limiter: (options.rate_limit > 0)
+                .then(|| RateLimiter::new(options.rate_limit, options.rate_limit_burst)),
+            in_flight: (options.max_concurrent_requests > 0)
+                .then(|| Arc::new(Semaphore::new(options.max_concurrent_requests))),
+            rate_limited: AtomicU64::new(0),
+            over_capacity: AtomicU64::new(0),
+            unauthorised: AtomicU64::new(0),
+        }
+    }
+}
+
+/// Token bucket per client IP address: each holds up to `burst` requests and
+/// refills at the sustained rate
+struct RateLimiter {
+    per_second: f64,
+    burst: f64,
+    buckets: std::sync::Mutex<HashMap<IpAddr, TokenBucket>>,
+}
+
+#[derive(Clone, Copy)]
+struct TokenBucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+impl RateLimiter {
+    fn new(per_minute: u32, burst: u32) -> Self {
+        RateLimiter {
+            per_second: per_minute as f64 / 60.0,
+            burst: burst as f64,
+            buckets: std::sync::Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Take a request from `ip`'s bucket, or say how long until one is
+    /// available
+    fn check(&self, ip: IpAddr, now: Instant) -> Result<(), Duration> {
+        let mut buckets = lock(&self.buckets);
+        if buckets.len() >= RATE_LIMIT_PRUNE_AT {
+            // A full bucket is the same as no bucket
+            buckets.retain(|_, bucket| self.refill(*bucket, now).tokens < self.burst);
+        }
+
+        let bucket = buckets.entry(ip).or_insert(TokenBucket {
+            tokens: self.burst,
+            updated: now,
+        });
+        *bucket = self.refill(*bucket, now);
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64((1.0 - bucket.tokens) / self.per_second))
+        }
+    }
+
+    fn refill(&self, bucket: TokenBucket, now: Instant) -> TokenBucket {
+        let elapsed = now.saturating_duration_since(bucket.updated).as_secs_f64();
+        TokenBucket {
+            tokens: (bucket.tokens + elapsed * self.per_second).min(self.burst),
+            updated: now,
+        }
+    }
+}
+
+/// Turn a request away with 429 if its client is over the rate limit, or 503
+/// if the server is already handling as many requests as it's allowed. Long
+/// `/events`, `/api/stream` and `/ws` connections only count while their
+/// response is being started.
+async fn limit_requests(
+    State(state): State<Arc<AppState>>,
+    connect_info: Option<ConnectInfo<SocketAddr>>,
+    request: axum::extract::Request,
+    next: Next,
+) -> Response {
+    let limits = &state.limits;
+    if let (Some(limiter), Some(ConnectInfo(addr))) = (&limits.rate_limiter, connect_info)
+        && let Err(retry_after) = limiter.check(addr.ip(), Instant::now())
+    {
+        limits.rate_limited.fetch_add(1, Ordering::Relaxed);
+        debug!(client = %addr.ip(), "Rate limited");
+        return too_busy(StatusCode::TOO_MANY_REQUESTS, retry_after, "Too many requests");
+    }
+
+    let _permit = match &limits.in_flight {
+        Some(permits) => match permits.clone().try_acquire_owned() {
+            Ok(permit) => Some(permit),
+            Err(_) => {
+                limits.over_capacity.fetch_add(1, Ordering::Relaxed);
+                warn!("Turned away a request: too many in progress");
+                return too_busy(
+                    StatusCode::SERVICE_UNAVAILABLE,
+                    Duration::from_secs(1),
+                    "Server busy",
+                );
+            }
+        },
+        None => None,
+    };
+
+    next.run(request).await
+}
+
+/// Turn a request away with 401 unless it carries the `--auth-token` or the
+/// `--auth-user` credentials, when either is set. The challenge offers basic
+/// auth so a browser prompts for them; with only a token, any user name and
+/// the token as the password will do.
+async fn require_auth(
+    State(state): State<Arc<AppState>>,
+    request: axum::extract::Request,
+    next: Next,
+) -> Response {
+    let options = &state.options;
+    if (options.auth_token.is_none() && options.auth_user.is_none())
+        || authorised(options, request.headers())
+    {
+        return next.run(request).await;
+    }
+
+    state.limits.unauthorised.fetch_add(1, Ordering::Relaxed);
+    debug!(path = request.uri().path(), "Unauthorised");
+    let mut response = (
+        StatusCode::UNAUTHORIZED,
+        [(CACHE_CONTROL, "no-store")],
+        "Authentication required",
+    )
+        .into_response();
+    let challenges = response.headers_mut();
+    challenges.append(
+        WWW_AUTHENTICATE,
+        HeaderValue::from_static(r#"Basic realm="carbon-vibe", charset="UTF-8""#),
+    );
+    if options.auth_token.is_some() {
+        challenges.append(WWW_AUTHENTICATE, HeaderValue::from_static(r#"Bearer realm="carbon-vibe""#));
+    }
+    response
+}
+
+fn authorised(options: &DashboardOptions, headers: &HeaderMap) -> bool {
+    let Some(authorization) = headers.get(AUTHORIZATION).and_then(|value| value.to_str().ok()) else {
+        return false;
+    };
+    let (scheme, credentials) = authorization.split_once(' ').unwrap_or((authorization, ""));
+    let credentials = credentials.trim();
+    let is_token = |secret: &str| {
+        options
+            .auth_token
+            .as_deref()
+            .is_some_and(|token| same_secret(secret, token))
+    };
+
+    if scheme.eq_ignore_ascii_case("bearer") {
+        return is_token(credentials);
+    }
+    if !scheme.eq_ignore_ascii_case("basic") {
+        return false;
+    }
+    let Some(decoded) = BASE64
+        .decode(credentials)
+        .ok()
+        .and_then(|bytes| String::from_utf8(bytes).ok())
+    else {
+        return false;
+    };
+    let Some((user, password)) = decoded.split_once(':') else {
+        return false;
+    };
+    let is_user = match (&options.auth_user, &options.auth_password) {
+        // Both compared, so a right user name doesn't answer sooner
+        (Some(expected_user), Some(expected_password)) => {
+            same_secret(user, expected_user) & same_secret(password, expected_password)
+        }
+        _ => false,
+    };
+    is_user || is_token(password)
+}
+
+/// Compare without stopping at the first difference, so the response time
+/// doesn't tell a guesser how much of a secret they have right
+fn same_secret(given: &str, expected: &str) -> bool {
+    given.len() == expected.len()
+        && given
+            .bytes()
+            .zip(expected.bytes())
+            .fold(0, |difference, (a, b)| difference | (a ^ b))
+            == 0
+}
+
+fn too_busy(status: StatusCode, retry_after: Duration, message: &'static str) -> Response {
+    // Retry-After is whole seconds; round up so a retry isn't early
+    let retry_after = retry_after.as_secs() + u64::from(retry_after.subsec_nanos() > 0);
+    (
+        status,
+        [
+            (RETRY_AFTER, retry_after.to_string()),
+            (CACHE_CONTROL, "no-store".to_string()),
+        ],
+        message,
+    )
+        .into_response()
+}
+
+/// A poisoned lock only means another request panicked mid-update of a plain
+/// value, which is still usable
+fn lock<T>(mutex: &std::sync::Mutex<T>) -> std::sync::MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+struct CachedData {
+    data: Arc<DashboardData>,
+    fetched_at: Instant,
+    /// Settlement period the data was fetched in; a new period means a new
+    /// headline figure, whatever the TTL
+    period: SettlementPeriod,
+}
+
+impl CachedData {
+    /// Within `ttl` and still in the period it was fetched in
+    fn is_fresh(&self, ttl: Duration) -> bool {
+        self.fetched_at.elapsed() < ttl && self.period == SettlementPeriod::containing(Utc::now())
+    }
+}
+
+impl DashboardCache {
+    fn new() -> Self {
+        DashboardCache {
+            entry: RwLock::new(None),
+            refresh: SingleFlight::default(),
+        }
+    }
+}
+
+/// The provider's carbon factors with the `--factors` overrides applied,
+/// kept for `FACTORS_TTL` whatever `--cache-ttl` is
+struct FactorsCache {
+    overrides: FactorOverrides,
+    entry: RwLock<Option<(Instant, CarbonFactors)>>,
+    refresh: SingleFlight<CarbonFactors>,
+}
+
+impl FactorsCache {
+    fn new(overrides: FactorOverrides) -> Self {
+        FactorsCache {
+            overrides,
+            entry: RwLock::new(None),
+            refresh: SingleFlight::default(),
+        }
+    }
+
+    /// The cached factors, refetched once they're a day old. If that fails,
+    /// the old ones are still good enough.
+    async fn get(&self, client: &impl GridDataProvider) -> Result<CarbonFactors, CarbonError> {
+        let cached = self.entry.read().await.clone();
+        if let Some((fetched_at, factors)) = &cached
+            && fetched_at.elapsed() < FACTORS_TTL
+        {
+            return Ok(factors.clone());
+        }
+
+        let refreshed = self
+            .refresh
+            .run(async {
+                let factors = self.overrides.apply(client.factors().await?);
+                *self.entry.write().await = Some((Instant::now(), factors.clone()));
+                Ok(factors)
+            })
+            .await;
+        match refreshed {
+            Ok(factors) => Ok(factors),
+            Err(e) => match cached {
+                Some((_, factors)) => {
+                    warn!(error = %e, "Error refreshing carbon factors; keeping the old ones");
+                    Ok(factors)
+                }
+                None => Err(e),
+            },
+        }
+    }
+}
+
+/// Read and check the `--factors` file
+fn load_factor_overrides(path: &std::path::Path) -> Result<FactorOverrides, CarbonError> {
+    let invalid = |message: String| CarbonError::FactorOverrides {
+        path: path.to_path_buf(),
+        message,
+    };
+    let contents = std::fs::read_to_string(path).map_err(|e| invalid(e.to_string()))?;
+    let overrides = toml::from_str(&contents).map_err(|e| invalid(e.to_string()))?;
+    info!(path = %path.display(), "Using carbon factor overrides");
+    Ok(overrides)
+}
+
+#[derive(Debug, Deserialize)]
+struct DashboardQuery {
+    // Moving average window in points, e.g. `/?ma=9`
+    ma: Option<usize>,
+    // Overrides `--theme` for the page, e.g. `/?theme=dark`
+    theme: Option<ThemeChoice>,
+}
+
+/// Everything the dashboard shows, as fetched from upstream
+#[derive(Debug, Default, PartialEq, Serialize, ToSchema)]
+struct DashboardData {
+    /// Region shown, e.g. "London"; `None` for national figures
+    region: Option<String>,
+    /// `None` if it couldn't be fetched and there was nothing older to show
+    #[serde(serialize_with = "units::serialize_option")]
+    #[schema(value_type = Option<f64>)]
+    intensity: Option<i32>,
+    /// The API's band for the headline figure
+    index: Option<IntensityIndex>,
+    /// With `daemon`: how the headline ranks among the archive's past year
+    #[serde(skip_serializing_if = "Option::is_none")]
+    context: Option<IntensityContext>,
+    #[serde(serialize_with = "serialize_fuels")]
+    #[schema(value_type = Vec<ApiFuel>)]
+    generation_mix: Vec<FuelSourceWithIntensity>,
+    #[serde(serialize_with = "serialize_periods")]
+    #[schema(value_type = Vec<ApiPeriod>)]
+    timeline_points: Vec<IntensityPoint>,
+    // Only feeds the stacked mix chart and legend sparklines, which are
+    // rendered into `sections`
+    #[serde(skip)]
+    generation_history: Vec<GenerationMixPeriod>,
+    /// Agile prices over the timeline, with --agile-region
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    prices: Vec<UnitRate>,
+    /// Panels that couldn't be fetched on the latest refresh
+    #[serde(skip_serializing_if = "PanelErrors::is_empty")]
+    errors: PanelErrors,
+    /// Raised while fetching this data, for `check_data_quality`
+    #[serde(skip)]
+    warnings: Warnings,
+}
+
+impl DashboardData {
+    /// The intensity the generation mix works out at with each fuel's
+    /// factor, to compare with the reported figure
+    fn mix_intensity(&self) -> Option<i32> {
+        weighted_intensity(&self.generation_mix).map(|intensity| intensity.round() as i32)
+    }
+
+    /// Nothing to show: every panel failed with `error`
+    fn unavailable(error: &CarbonError) -> Self {
+        let error = || {
+            Some(PanelError {
+                error: error.to_string(),
+                stale_since: None,
+            })
+        };
+        DashboardData {
+            errors: PanelErrors {
+                current: error(),
+                mix: error(),
+                timeline: error(),
+            },
+            ..DashboardData::default()
+        }
+    }
+}
+
+/// Why a panel couldn't be fetched
+#[derive(Clone, Debug, PartialEq, Serialize, ToSchema)]
+struct PanelError {
+    error: String,
+    /// When the older data shown in its place was fetched; `None` if there
+    /// was none
+    stale_since: Option<DateTime<Utc>>,
+}
+
+/// The headline figure, the generation mix and the timeline are fetched
+/// separately, so one failing upstream call only affects its own panel,
+/// which shows the last data cached for it or an error message
+#[derive(Clone, Debug, Default, PartialEq, Serialize, ToSchema)]
+struct PanelErrors {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    current: Option<PanelError>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    mix: Option<PanelError>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    timeline: Option<PanelError>,
+}
+
+impl PanelErrors {
+    fn is_empty(&self) -> bool {
+        self.current.is_none() && self.mix.is_none() && self.timeline.is_none()
+    }
+
+    /// One line for the logs and health report, e.g. "mix: HTTP 500 ..."
+    fn summary(&self) -> Option<String> {
+        let failed: Vec<String> = [("current", &self.current), ("mix", &self.mix), ("timeline", &self.timeline)]
+            .into_iter()
+            .filter_map(|(panel, error)| Some(format!("{panel}: {error}", error = error.as_ref()?.error)))
+            .collect();
+        (!failed.is_empty()).then(|| failed.join("; "))
+    }
+}
+
+/// A panel's data if its fetch succeeded; otherwise `None`, with the error
+/// logged and kept in `slot`
+fn panel<T, E: fmt::Display>(name: &str, result: Result<T, E>, slot: &mut Option<PanelError>) -> Option<T> {
+    match result {
+        Ok(value) => Some(value),
+        Err(e) => {
+            warn!(panel = name, error = %e, "Error fetching dashboard panel");
+            *slot = Some(PanelError {
+                error: e.to_string(),
+                stale_since: None,
+            });
+            None
+        }
+    }
+}
+
+/// Fill the panels that failed in `data` from the previous cache entry,
+/// where it had them, marking them stale as of when they were fetched
+fn fill_stale_panels(data: &mut DashboardData, previous: &CachedData) {
+    let fetched = Utc::now() - TimeDelta::from_std(previous.fetched_at.elapsed()).unwrap_or_default();
+    let old = &previous.data;
+    if use_stale(&mut data.errors.current, old.errors.current.as_ref(), fetched) {
+        data.intensity = old.intensity;
+        data.index = old.index;
+    }
+    if use_stale(&mut data.errors.mix, old.errors.mix.as_ref(), fetched) {
+        data.generation_mix = old.generation_mix.clone();
+    }
+    if use_stale(&mut data.errors.timeline, old.errors.timeline.as_ref(), fetched) {
+        data.timeline_points = old.timeline_points.clone();
+    }
+}
+
+/// Whether a failed panel can show the previous entry's data for it, which
+/// it can unless that had failed too with nothing older. Marks the error
+/// with the age of what's shown.
+fn use_stale(error: &mut Option<PanelError>, previous: Option<&PanelError>, previous_fetched: DateTime<Utc>) -> bool {
+    let Some(error) = error else {
+        return false;
+    };
+    let since = match previous {
+        None => previous_fetched,
+        Some(PanelError {
+            stale_since: Some(since),
+            ..
+        }) => *since,
+        Some(_) => return false,
+    };
+    error.stale_since = Some(since);
+    true
+}
+
+/// Server-rendered HTML for each part of the page that changes with the data,
+/// so the auto-refresh can swap them in without a second copy of the chart
+/// code in JavaScript
+#[derive(Debug, Serialize, ToSchema)]
+struct DashboardSections {
+    /// Why the headline is missing or out of date, if it is
+    current_status: String,
+    /// With `daemon`, e.g. "greener than 71% of the past year"
+    intensity_context: String,
+    baseline_delta: String,
+    /// The mix × factors figure beside the reported one, flagged if they
+    /// diverge
+    mix_check: String,
+    price_window: String,
+    intensity_chart: String,
+    generation_mix: String,
+    mix_history_chart: String,
+}
+
+/// The dashboard page, rendered from `templates/dashboard.html`. Askama
+/// checks the template's variables against these fields at compile time.
+#[derive(Template)]
+#[template(path = "dashboard.html")]
+struct DashboardPage<'a> {
+    /// The theme's colours, which `?theme=` can change per request
+    theme_css: String,
+    region: Option<&'a str>,
+    /// In the display unit
+    intensity: Option<String>,
+    unit: &'static str,
+    index: &'a str,
+    index_class: String,
+    sections: &'a DashboardSections,
+}
+
+/// `/regions`, rendered from `templates/regions.html`
+#[derive(Template)]
+#[template(path = "regions.html")]
+struct RegionsPage {
+    theme_css: String,
+    /// The settlement period the figures are for
+    period: String,
+    map: String,
+    chart: String,
+    rows: Vec<RegionRow>,
+    unit: &'static str,
+    /// Why there are no figures, if there aren't
+    error: Option<String>,
+}
+
+/// `/api/docs`, rendered from `templates/api_docs.html`
+#[derive(Template)]
+#[template(path = "api_docs.html")]
+struct ApiDocsPage {
+    spec_url: &'static str,
+}
+
+/// One region in the `/regions` table
+struct RegionRow {
+    region_id: u32,
+    region: String,
+    /// In the display unit
+    intensity: Option<String>,
+    index: &'static str,
+    index_class: String,
+}
+
+/// A file under `static/`, compiled in so the binary serves it wherever it
+/// runs
+struct StaticAsset {
+    name: &'static str,
+    content_type: &'static str,
+    body: &'static str,
+    /// The body compressed with each [`Encoding`], on first request
+    brotli: OnceLock<Vec<u8>>,
+    gzip: OnceLock<Vec<u8>>,
+}
+
+impl StaticAsset {
+    const fn new(name: &'static str, content_type: &'static str, body: &'static str) -> Self {
+        StaticAsset {
+            name,
+            content_type,
+            body,
+            brotli: OnceLock::new(),
+            gzip: OnceLock::new(),
+        }
+    }
+
+    /// Strong entity tag from a hash of the body, so browsers revalidate and
+    /// get a 304 until a new build changes the file. Each encoding is a
+    /// different representation, so gets its own tag.
+    fn etag(&self, encoding: Option<Encoding>) -> String {
+        let hash = fnv1a(self.body.as_bytes());
+        match encoding {
+            Some(encoding) => format!("\"{hash:016x}-{token}\"", token = encoding.token()),
+            None => format!("\"{hash:016x}\""),
+        }
+    }
+
+    /// The body in `encoding`, compressed once and kept, as the assets
+    /// never change while the server runs
+    fn encoded(&self, encoding: Encoding) -> &[u8] {
+        let slot = match encoding {
+            Encoding::Brotli => &self.brotli,
+            Encoding::Gzip => &self.gzip,
+        };
+        slot.get_or_init(|| encoding.compress(self.body.as_bytes()))
+    }
+}
+
+static STATIC_ASSETS: [StaticAsset; 2] = [
+    StaticAsset::new(
+        "dashboard.css",
+        "text/css; charset=utf-8",
+        include_str!("../../static/dashboard.css"),
+    ),
+    StaticAsset::new(
+        "dashboard.js",
+        "text/javascript; charset=utf-8",
+        include_str!("../../static/dashboard.js"),
+    ),
+];
+
+/// Content codings the static assets are served in, best first
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Encoding {
+    Brotli,
+    Gzip,
+}
+
+impl Encoding {
+    const PREFERENCE: [Encoding; 2] = [Encoding::Brotli, Encoding::Gzip];
+
+    /// As in `Accept-Encoding` and `Content-Encoding`
+    fn token(self) -> &'static str {
+        match self {
+            Encoding::Brotli => "br",
+            Encoding::Gzip => "gzip",
+        }
+    }
+
+    /// The best coding an `Accept-Encoding` header allows, if any. Codings
+    /// with `q=0` are refused; other weights aren't ranked, since both
+    /// compress text well.
+    fn negotiate(accept_encoding: &str) -> Option<Encoding> {
+        let accepted: Vec<&str> = accept_encoding
+            .split(',')
+            .filter_map(|coding| {
+                let mut parts = coding.split(';').map(str::trim);
+                let name = parts.next()?;
+                let refused = parts.any(|param| {
+                    param
+                        .strip_prefix("q=")
+                        .and_then(|q| q.parse::<f64>().ok())
+                        .is_some_and(|q| q == 0.0)
+                });
+                (!refused).then_some(name)
+            })
+            .collect();
+        Encoding::PREFERENCE
+            .into_iter()
+            .find(|encoding| accepted.iter().any(|name| name.eq_ignore_ascii_case(encoding.token())))
+    }
+
+    /// At the highest quality, since it's done once per asset
+    fn compress(self, body: &[u8]) -> Vec<u8> {
+        let mut compressed = Vec::new();
+        match self {
+            Encoding::Brotli => {
+                let mut writer = brotli::CompressorWriter::new(&mut compressed, 4096, 11, 22);
+                // Writing to a Vec can't fail
+                let _ = writer.write_all(body);
+            }
+            Encoding::Gzip => {
+                let mut encoder = flate2::write::GzEncoder::new(&mut compressed, flate2::Compression::best());
+                let _ = encoder.write_all(body);
+                let _ = encoder.finish();
+            }
+        }
+        compressed
+    }
+}
+
+/// 64-bit FNV-1a, which unlike `DefaultHasher` is the same across Rust
+/// releases, so a rebuild without changes keeps the same tags
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// The JSON API's description, served at `/api/openapi.json` and browsable
+/// at `/api/docs`, for integrators to generate clients from
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "carbon-vibe",
+        description = "GB grid carbon intensity, generation mix and forecast, as shown on the dashboard. \
+            Responses are cacheable until the next settlement period is due."
+    ),
+    paths(
+        serve_api_data,
+        serve_api_intensity,
+        serve_api_generation,
+        serve_api_timeline,
+        serve_api_stream,
+        serve_api_advice
+    )
+)]
+struct ApiDoc;
+
+/// `GET /api/openapi.json`: the OpenAPI 3.1 description of the `/api/*`
+/// routes. It's fixed at build time, so it's cacheable for as long as the
+/// static assets.
+async fn serve_openapi() -> Response {
+    let mut doc = ApiDoc::openapi();
+    // The crate has no license field, which utoipa would publish as a
+    // license with an empty name, which the spec doesn't allow
+    doc.info.license = None;
+    ([(CACHE_CONTROL, "no-cache")], Json(doc)).into_response()
+}
+
+/// `GET /api/docs`: Swagger UI over `/api/openapi.json`, to browse and try
+/// the API. The UI itself loads from unpkg.
+async fn serve_api_docs() -> Response {
+    let page = ApiDocsPage {
+        spec_url: "/api/openapi.json",
+    };
+    match page.render() {
+        Ok(html) => Html(html).into_response(),
+        Err(e) => {
+            error!(error = %e, "Error rendering the API docs page");
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+/// Query for the API routes that return the moving average; unlike
+/// [`DashboardQuery`], without the page's `?theme=`
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct MovingAverageParam {
+    /// Moving average window in points; 0 or 1 for none [default: 5]
+    ma: Option<usize>,
+}
+
+/// Body of `GET /api/data`
+#[derive(Debug, Serialize, ToSchema)]
+struct ApiData<'a> {
+    #[serde(flatten)]
+    data: &'a DashboardData,
+    /// Each fuel's share times its factor, as a check on `intensity`
+    #[serde(serialize_with = "units::serialize_option")]
+    #[schema(value_type = Option<f64>)]
+    mix_intensity: Option<i32>,
+    sections: DashboardSections,
+}
+
+/// Body of `GET /api/intensity`
+#[derive(Debug, Serialize, ToSchema)]
+struct ApiIntensity<'a> {
+    region: Option<&'a str>,
+    /// Actual value where measured, otherwise the forecast; `None` if it
+    /// couldn't be fetched
+    #[serde(serialize_with = "units::serialize_option")]
+    #[schema(value_type = Option<f64>)]
+    intensity: Option<i32>,
+    index: Option<IntensityIndex>,
+    /// Each fuel's share times its factor, as a check on `intensity`
+    #[serde(serialize_with = "units::serialize_option")]
+    #[schema(value_type = Option<f64>)]
+    mix_intensity: Option<i32>,
+    /// The settlement period in progress, with its actual and forecast values
+    /// and index
+    period: Option<ApiPeriod>,
+}
+
+/// One line of `GET /api/stream`, and the body of `update` messages on
+/// `/ws`: a settlement period as it begins
+#[derive(Debug, Serialize, ToSchema)]
+struct ApiStreamLine<'a> {
+    region: Option<&'a str>,
+    /// The period now in progress, with its actual and forecast values and
+    /// index
+    period: ApiPeriod,
+    /// Each fuel's share times its factor, as a check on the period's
+    /// intensity
+    #[serde(serialize_with = "units::serialize_option")]
+    #[schema(value_type = Option<f64>)]
+    mix_intensity: Option<i32>,
+    #[serde(serialize_with = "serialize_fuels")]
+    #[schema(value_type = Vec<ApiFuel>)]
+    generation_mix: &'a [FuelSourceWithIntensity],
+}
+
+/// Body of `GET /api/generation`
+#[derive(Debug, Serialize, ToSchema)]
+struct ApiGeneration<'a> {
+    region: Option<&'a str>,
+    #[serde(serialize_with = "serialize_fuels")]
+    #[schema(value_type = Vec<ApiFuel>)]
+    generation_mix: &'a [FuelSourceWithIntensity],
+    /// Mix for each settlement period over the last 24 hours, oldest first
+    history: &'a [GenerationMixPeriod],
+}
+
+/// Body of `GET /api/timeline`
+#[derive(Debug, Serialize, ToSchema)]
+struct ApiTimeline<'a> {
+    region: Option<&'a str>,
+    #[serde(serialize_with = "serialize_periods")]
+    #[schema(value_type = Vec<ApiPeriod>)]
+    points: &'a [IntensityPoint],
+    /// Trend value for each point, as drawn on the chart; empty when `?ma=0`
+    #[serde(serialize_with = "units::serialize_all_f64")]
+    moving_average: Vec<f64>,
+}
+
+/// A settlement period as the API shows it: an [`IntensityPoint`] with its
+/// figures in the display unit
+#[derive(Clone, Copy, Debug, Serialize, ToSchema)]
+struct ApiPeriod {
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+    /// The actual value where measured, otherwise the forecast
+    #[serde(serialize_with = "units::serialize")]
+    #[schema(value_type = f64)]
+    intensity: i32,
+    #[serde(serialize_with = "units::serialize_option")]
+    #[schema(value_type = Option<f64>)]
+    actual: Option<i32>,
+    #[serde(serialize_with = "units::serialize_option")]
+    #[schema(value_type = Option<f64>)]
+    forecast: Option<i32>,
+    /// The API's band, e.g. "low" or "very high"
+    index: Option<IntensityIndex>,
+}
+
+impl From<&IntensityPoint> for ApiPeriod {
+    fn from(point: &IntensityPoint) -> Self {
+        ApiPeriod {
+            from: point.from,
+            to: point.to,
+            intensity: point.intensity,
+            actual: point.actual,
+            forecast: point.forecast,
+            index: point.index,
+        }
+    }
+}
+
+/// A generation mix entry as the API shows it, with the fuel's factor in the
+/// display unit
+#[derive(Debug, Serialize, ToSchema)]
+struct ApiFuel<'a> {
+    fuel: &'a str,
+    perc: f64,
+    /// `None` for the unknown share and fuels such as `battery` with no
+    /// factor given
+    #[serde(serialize_with = "units::serialize_option")]
+    #[schema(value_type = Option<f64>)]
+    carbon_intensity: Option<i32>,
+}
+
+impl<'a> From<&'a FuelSourceWithIntensity> for ApiFuel<'a> {
+    fn from(source: &'a FuelSourceWithIntensity) -> Self {
+        ApiFuel {
+            fuel: &source.fuel,
+            perc: source.perc,
+            carbon_intensity: source.carbon_intensity,
+        }
+    }
+}
+
+fn serialize_periods<S: serde::Serializer>(points: &[IntensityPoint], serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_seq(points.iter().map(ApiPeriod::from))
+}
+
+fn serialize_fuels<S: serde::Serializer>(mix: &[FuelSourceWithIntensity], serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_seq(mix.iter().map(ApiFuel::from))
+}
+
+/// Generation mix for each settlement period over the last 24 hours, used
+/// for the stacked mix chart and the legend sparklines
+async fn fetch_generation_history(
+    client: &impl GridDataProvider,
+) -> Result<Vec<GenerationMixPeriod>, CarbonError> {
+    let now = chrono::Utc::now();
+
+    client.generation_between(now - MIX_HISTORY, now).await
+}
+
+async fn fetch_carbon_data(
+    client: &GridClient,
+    options: &DashboardOptions,
+    factors: &FactorsCache,
+) -> Result<DashboardData, CarbonError> {
+    if let Some(region) = &options.region.selector() {
+        return fetch_regional_data(regional_client(client)?, region, options.split_imports, factors).await;
+    }
+    let split_imports = options.split_imports;
+
+    // Fetch 24-hour timeline data (12 hours past + 12 hours future)
+    let (timeline_from, timeline_to) = timeline_range(Utc::now());
+
+    // Each panel is fetched on its own; only if all of them fail is there
+    // nothing to show
+    let (current, mix, timeline) = tokio::join!(
+        client.current_period(),
+        async {
+            // Combine generation mix with carbon intensity factors
+            let generation_mix = client.generation_mix().await?;
+            let factors = factors.get(client).await?;
+            let mut warnings = Warnings::default();
+            let enriched = enrich_generation_mix(generation_mix, &factors, split_imports, &mut warnings);
+            Ok::<_, CarbonError>((enriched, warnings))
+        },
+        client.intensity_between(timeline_from, timeline_to),
+    );
+    let (current, mix, timeline) = match (current, mix, timeline) {
+        (Err(e), Err(_), Err(_)) => return Err(e),
+        results => results,
+    };
+    let mut errors = PanelErrors::default();
+    let current = panel("current", current, &mut errors.current);
+    let (enriched_mix, warnings) = panel("mix", mix, &mut errors.mix).unwrap_or_default();
+    let timeline_points = panel("timeline", timeline, &mut errors.timeline).unwrap_or_default();
+
+    // The mix history is optional, so a failure here only drops the stacked
+    // chart and sparklines
+    let generation_history = match fetch_generation_history(client).await {
+        Ok(history) => history,
+        Err(e) => {
+            warn!(error = %e, "Error fetching generation history");
+            vec![]
+        }
+    };
+
+    debug!(
+        intensity = current.as_ref().map(|current| current.intensity),
+        mix_items = enriched_mix.len(),
+        timeline_points = timeline_points.len(),
+        "Fetched national data"
+    );
+
+    Ok(DashboardData {
+        region: client.area_name().map(str::to_string),
+        intensity: current.as_ref().map(|current| current.intensity),
+        index: current.and_then(|current| current.index),
+        context: None,
+        generation_mix: enriched_mix,
+        timeline_points,
+        generation_history,
+        // Filled in by `fetch_and_record`
+        prices: vec![],
+        errors,
+        warnings,
+    })
+}
+
+/// Regional equivalent of `fetch_carbon_data`. The regional range endpoint
+/// includes a generation mix per period, so one request covers both the
+/// timeline and the mix history.
+async fn fetch_regional_data(
+    client: &CarbonClient,
+    region: &RegionSelector,
+    split_imports: bool,
+    factors: &FactorsCache,
+) -> Result<DashboardData, CarbonError> {
+    // Fetch 24-hour timeline data (12 hours past + 12 hours future)
+    let now = Utc::now();
+    let (timeline_from, timeline_to) = timeline_range(now);
+
+    // The reading has the current intensity and generation mix for the
+    // region, and the range reaches back far enough for the mix history.
+    // Factors are only published nationally.
+    let (reading, range, factors) = tokio::join!(
+        client.regional_reading(region),
+        client.regional_between(region, now - MIX_HISTORY, timeline_to),
+        factors.get(client),
+    );
+    let (reading, range) = match (reading, range) {
+        (Err(e), Err(_)) => return Err(e),
+        results => results,
+    };
+    let region_name = match (&reading, &range) {
+        (Ok(reading), _) => reading.region.short_name.clone(),
+        (_, Ok(entry)) => entry.short_name.clone(),
+        _ => unreachable!("one of the fetches succeeded"),
+    };
+
+    let mut errors = PanelErrors::default();
+    let (current, mix) = match reading {
+        Ok(reading) => (
+            reading
+                .region
+                .intensity
+                .best()
+                .map(|intensity| (intensity, reading.region.intensity.index))
+                .ok_or_else(|| CarbonError::EmptyData("regional intensity".into()).to_string()),
+            factors
+                .map(|factors| {
+                    let mut warnings = Warnings::default();
+                    let enriched = enrich_generation_mix(reading.region.generation_mix, &factors, split_imports, &mut warnings);
+                    (enriched, warnings)
+                })
+                .map_err(|e| e.to_string()),
+        ),
+        // Both panels come from the reading
+        Err(e) => (Err(e.to_string()), Err(e.to_string())),
+    };
+    let current = panel("current", current, &mut errors.current);
+    let (enriched_mix, warnings) = panel("mix", mix, &mut errors.mix).unwrap_or_default();
+    let periods = panel("timeline", range.map(|entry| entry.data), &mut errors.timeline).unwrap_or_default();
+
+    let timeline_points = periods
+        .iter()
+        .filter(|period| period.from >= timeline_from)
+        .filter_map(|period| period.to_intensity_point())
+        .collect();
+    let generation_history = periods
+        .iter()
+        .filter(|period| period.from <= now)
+        .map(|period| period.to_generation_period())
+        .collect();
+
+    debug!(
+        region = region_name,
+        intensity = current.map(|(intensity, _)| intensity),
+        mix_items = enriched_mix.len(),
+        "Fetched regional data"
+    );
+
+    Ok(DashboardData {
+        index: current.and_then(|(_, index)| index),
+        context: None,
+        region: Some(region_name),
+        intensity: current.map(|(intensity, _)| intensity),
+        generation_mix: enriched_mix,
+        timeline_points,
+        generation_history,
+        // Filled in by `fetch_and_record`
+        prices: vec![],
+        errors,
+        warnings,
+    })
+}
+
+/// Dashboard data from the local archive: the same 24 hours as
+/// `fetch_carbon_data`, with the newest stored period at or before now as the
+/// headline. If polling has been failing that's an older period, but the page
+/// still loads.
+fn archived_data(
+    store: &Store,
+    split_imports: bool,
+    overrides: &FactorOverrides,
+) -> Result<DashboardData, CarbonError> {
+    let now = Utc::now();
+    let (timeline_from, timeline_to) = timeline_range(now);
+
+    let timeline_points = store.intensity_between(timeline_from, timeline_to)?;
+    let current = timeline_points
+        .iter()
+        .rfind(|p| p.from <= now)
+        .ok_or_else(|| CarbonError::EmptyData("archived intensity for the last 12 hours".into()))?;
+    let history = store.intensity_between(current.from - CONTEXT_WINDOW, current.from)?;
+    let generation_history = store.generation_between(now - MIX_HISTORY, now)?;
+    let generation_mix = generation_history
+        .last()
+        .map(|period| period.generation_mix.clone())
+        .unwrap_or_default();
+
+    let mut warnings = Warnings::default();
+    Ok(DashboardData {
+        region: None,
+        intensity: Some(current.intensity),
+        index: current.index,
+        context: IntensityContext::of(current.intensity, &history),
+        // The API's factors don't change, so the archive doesn't keep them
+        generation_mix: enrich_generation_mix(generation_mix, &overrides.apply(FUEL_FACTORS), split_imports, &mut warnings),
+        timeline_points,
+        generation_history,
+        prices: vec![],
+        errors: PanelErrors::default(),
+        warnings,
+    })
+}
+
+/// Dashboard data through the cache (see `DashboardCache`)
+async fn dashboard_data(state: &Arc<AppState>) -> Result<Arc<DashboardData>, CarbonError> {
+    let cache = &state.cache;
+    let ttl = state.options.cache_ttl;
+    if ttl.is_zero() {
+        // Nothing is kept, but page loads at the same moment share a fetch
+        return cache
+            .refresh
+            .run(async {
+                let mut data = fetch_and_record(state).await?;
+                check_data_quality(&mut data, state.options.mix_check_threshold)?;
+                Ok(Arc::new(data))
+            })
+            .await;
+    }
+
+    if let Some(cached) = cache.entry.read().await.as_ref() {
+        if cached.is_fresh(ttl) {
+            return Ok(cached.data.clone());
+        }
+        if cached.fetched_at.elapsed() < ttl + CACHE_MAX_STALE {
+            // Only one refresh at a time; if one is running, it'll land soon
+            if !cache.refresh.is_running() {
+                let state = state.clone();
+                // Logged under the request that triggered the refresh
+                tokio::spawn(
+                    async move {
+                        if let Err(e) = state.cache.refresh.run(refresh_dashboard_data(&state)).await {
+                            warn!(error = %e, "Error refreshing cached data");
+                        }
+                    }
+                    .in_current_span(),
+                );
+            }
+            return Ok(cached.data.clone());
+        }
+    }
+
+    // Nothing usable cached: fetch once and let concurrent requests share it
+    cache
+        .refresh
+        .run(async {
+            // A fetch may have landed since the cache was checked
+            if let Some(cached) = cache.entry.read().await.as_ref()
+                && cached.is_fresh(ttl)
+            {
+                return Ok(cached.data.clone());
+            }
+            refresh_dashboard_data(state).await
+        })
+        .await
+}
+
+/// `fetch_carbon_data` with its latency and outcome counted in the metrics.
+/// Each API request made is logged in this span, with its own timing. With
+/// `daemon` the archive is read instead, and its polls are what's counted.
+#[instrument(name = "upstream_fetch", skip_all)]
+async fn fetch_and_record(state: &AppState) -> Result<DashboardData, CarbonError> {
+    if let Some(archive) = &state.archive {
+        return archived_data(&lock(archive), state.options.split_imports, &state.factors.overrides);
+    }
+
+    let started = Instant::now();
+    let mut result = fetch_carbon_data(&state.client, &state.options, &state.factors).await;
+    if let (Ok(data), Some(octopus)) = (&mut result, &state.octopus) {
+        data.prices = fetch_prices(octopus).await;
+    }
+    let elapsed = started.elapsed();
+    let elapsed_ms = elapsed.as_millis() as u64;
+    // A fetch with some panels missing counts as failed, though the rest of
+    // the page is still served
+    let error = match &result {
+        Ok(data) => data.errors.summary(),
+        Err(e) => Some(e.to_string()),
+    };
+    match (&result, &error) {
+        (Ok(_), None) => info!(elapsed_ms, "Upstream fetch finished"),
+        (Ok(_), Some(error)) => warn!(elapsed_ms, error = %error, "Upstream fetch partly failed"),
+        (Err(e), _) => warn!(elapsed_ms, error = %e, "Upstream fetch failed"),
+    }
+    state.metrics.record(elapsed, error);
+
+    result
+}
+
+/// Agile prices over the timeline. Like the mix history they're optional, so
+/// a failure only drops the price line.
+async fn fetch_prices(octopus: &OctopusClient) -> Vec<UnitRate> {
+    // The same 24 hours as the timeline
+    let (from, to) = timeline_range(Utc::now());
+    match octopus.unit_rates(from, to).await {
+        Ok(rates) => rates,
+        Err(e) => {
+            warn!(error = %e, "Error fetching Agile prices");
+            vec![]
+        }
+    }
+}
+
+/// Fetch from upstream and store the result in the cache, keeping the
+/// previous data for any panels that failed
+async fn refresh_dashboard_data(state: &AppState) -> Result<Arc<DashboardData>, CarbonError> {
+    let mut data = fetch_and_record(state).await?;
+    if let Some(previous) = state.cache.entry.read().await.as_ref() {
+        fill_stale_panels(&mut data, previous);
+    }
+    // Under --strict, degraded data fails the refresh rather than being cached
+    check_data_quality(&mut data, state.options.mix_check_threshold)?;
+    let data = Arc::new(data);
+    *state.cache.entry.write().await = Some(CachedData {
+        data: data.clone(),
+        fetched_at: Instant::now(),
+        period: SettlementPeriod::containing(Utc::now()),
+    });
+
+    Ok(data)
+}
+
+/// Flag panels showing older data in place of a failed fetch, and a mix ×
+/// factors figure more than `threshold` from the reported one. Under
+/// `--strict` these and the warnings raised while fetching `data` are an
+/// error.
+fn check_data_quality(data: &mut DashboardData, threshold: u32) -> Result<(), CarbonError> {
+    let mut warnings = std::mem::take(&mut data.warnings);
+    let panels = [
+        ("Current intensity", &data.errors.current),
+        ("Generation mix", &data.errors.mix),
+        ("Timeline", &data.errors.timeline),
+    ];
+    for (panel, error) in panels {
+        if let Some(since) = error.as_ref().and_then(|error| error.stale_since) {
+            warnings.raise(DataWarning::Stale { panel, since });
+        }
+    }
+    if let (Some(reported), Some(computed)) = (data.intensity, data.mix_intensity())
+        && (computed - reported).unsigned_abs() > threshold
+    {
+        warnings.raise(DataWarning::MixDivergence { reported, computed, threshold });
+    }
+    warnings.check()
+}
+
+/// CSS class colouring the headline by band, green for very low through red
+/// for very high; matches the class the page script derives from `index`
+fn index_class(index: Option<IntensityIndex>) -> String {
+    index
+        .map(|index| format!("index-{band}", band = index.as_str().replace(' ', "-")))
+        .unwrap_or_default()
+}
+
+fn render_sections(
+    data: &DashboardData,
+    options: &DashboardOptions,
+    moving_average_window: usize,
+) -> DashboardSections {
+    // A failed panel shows its error above the older data kept for it, or
+    // on its own if there's none
+    let mix_error = render_panel_error("Generation mix", data.errors.mix.as_ref());
+    let generation_mix = if data.generation_mix.is_empty() && data.errors.mix.is_some() {
+        mix_error
+    } else {
+        mix_error + &render_generation_mix(&data.generation_mix, &data.generation_history, options.legend())
+    };
+
+    DashboardSections {
+        current_status: render_panel_error("Current intensity", data.errors.current.as_ref()),
+        intensity_context: data
+            .context
+            .map(|context| {
+                format!(
+                    r#"<div class="intensity-context">{text}</div>"#,
+                    text = context.describe(Utc::now())
+                )
+            })
+            .unwrap_or_default(),
+        baseline_delta: data
+            .intensity
+            .map(|intensity| render_baseline_delta(intensity, options.baseline.as_ref(), &data.timeline_points))
+            .unwrap_or_default(),
+        mix_check: render_mix_check(data.intensity, data.mix_intensity(), options.mix_check_threshold),
+        price_window: render_price_window(data, options),
+        intensity_chart: render_panel_error("Timeline", data.errors.timeline.as_ref())
+            + &render_intensity_chart(
+                &data.timeline_points,
+                moving_average_window,
+                options.y_min,
+                options.y_max,
+                CHART_SIZE,
+            ),
+        generation_mix,
+        mix_history_chart: render_mix_history_chart(&data.generation_mix, &data.generation_history),
+    }
+}
+
+/// Notice for a panel that couldn't be fetched, e.g. "Generation mix
+/// couldn't be refreshed; showing data from 14:05", with the error itself
+/// as its tooltip. Empty if the panel is current.
+fn render_panel_error(panel: &str, error: Option<&PanelError>) -> String {
+    let Some(error) = error else {
+        return String::new();
+    };
+    let text = match error.stale_since {
+        Some(since) => format!(
+            "{panel} couldn't be refreshed; showing data from {since}",
+            since = local(since).format("%H:%M")
+        ),
+        None => format!("{panel} unavailable"),
+    };
+    format!(
+        r#"<div class="panel-error" title="{error}">{text}</div>"#,
+        error = escape_xml(&error.error)
+    )
+}
+
+async fn serve_app(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<DashboardQuery>,
+    headers: HeaderMap,
+) -> Response {
+    let options = &state.options;
+    let moving_average_window = query.ma.unwrap_or(DEFAULT_MOVING_AVERAGE_WINDOW);
+
+    // Fetch data server-side
+    let fetch_started = Instant::now();
+    let (data, cache_headers) = match dashboard_data(&state).await {
+        Ok(data) => {
+            let validators = Validators::of(&data, Utc::now());
+            if validators.matches(&headers) {
+                return validators.not_modified(options.cache_ttl);
+            }
+            let cache_headers = validators.headers(options.cache_ttl);
+            (data, cache_headers)
+        }
+        Err(e) => {
+            warn!(error = %e, "Error fetching data");
+            (Arc::new(DashboardData::unavailable(&e)), vec![(CACHE_CONTROL, "no-store".to_string())])
+        }
+    };
+    let fetch_duration = fetch_started.elapsed();
+
+    let render_started = Instant::now();
+    let sections = render_sections(&data, options, moving_average_window);
+    let page = DashboardPage {
+        theme_css: query.theme.unwrap_or(options.theme).stylesheet(),
+        region: data.region.as_deref(),
+        intensity: data.intensity.map(units::figure),
+        unit: units::label(),
+        index: data.index.map(IntensityIndex::as_str).unwrap_or_default(),
+        index_class: index_class(data.index),
+        sections: &sections,
+    };
+    let html = match page.render() {
+        Ok(html) => html,
+        Err(e) => {
+            error!(error = %e, "Error rendering the dashboard");
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+    let render_duration = render_started.elapsed();
+
+    (
+        [(
+            HeaderName::from_static("server-timing"),
+            server_timing(fetch_duration, render_duration),
+        )],
+        AppendHeaders(cache_headers),
+        Html(html),
+    )
+        .into_response()
+}
+
+/// `GET /api/data`: the dashboard data as JSON, plus the rendered sections
+/// the page uses to refresh itself. Fetch failures return 502 rather than
+/// zeros so the page keeps its last good data.
+#[utoipa::path(
+    get,
+    path = "/api/data",
+    summary = "Everything the dashboard shows",
+    params(MovingAverageParam),
+    responses(
+        (status = 200, description = "The dashboard data, with the page's rendered sections", body = ApiData),
+        (status = 304, description = "Unchanged since the client's `If-None-Match` or `If-Modified-Since`"),
+        (status = 502, description = "Every panel failed to fetch from upstream", body = ApiErrorBody),
+    )
+)]
+async fn serve_api_data(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<MovingAverageParam>,
+    headers: HeaderMap,
+) -> Response {
+    let options = &state.options;
+    let moving_average_window = query.ma.unwrap_or(DEFAULT_MOVING_AVERAGE_WINDOW);
+
+    let fetch_started = Instant::now();
+    let data = match dashboard_data(&state).await {
+        Ok(data) => data,
+        Err(e) => return api_error(e),
+    };
+    let fetch_duration = fetch_started.elapsed();
+    let validators = Validators::of(&data, Utc::now());
+    if validators.matches(&headers) {
+        return validators.not_modified(options.cache_ttl);
+    }
+
+    let render_started = Instant::now();
+    let body = ApiData {
+        sections: render_sections(&data, options, moving_average_window),
+        mix_intensity: data.mix_intensity(),
+        data: &data,
+    };
+    let render_duration = render_started.elapsed();
+
+    (
+        [(
+            HeaderName::from_static("server-timing"),
+            server_timing(fetch_duration, render_duration),
+        )],
+        AppendHeaders(validators.headers(options.cache_ttl)),
+        Json(body),
+    )
+        .into_response()
+}
+
+/// `GET /static/{file}`: the page's stylesheet and script. Browsers must
+/// revalidate, and get a 304 without a body while their copy's tag matches.
+async fn serve_static(Path(file): Path<String>, headers: HeaderMap) -> Response {
+    let Some(asset) = STATIC_ASSETS.iter().find(|asset| asset.name == file) else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+
+    let encoding = headers
+        .get(ACCEPT_ENCODING)
+        .and_then(|value| value.to_str().ok())
+        .and_then(Encoding::negotiate);
+    let etag = asset.etag(encoding);
+    let unchanged = headers
+        .get(IF_NONE_MATCH)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|tags| tags.split(',').map(str::trim).any(|tag| tag == "*" || tag == etag));
+    let cache_headers = [
+        (ETAG, etag),
+        (CACHE_CONTROL, "no-cache".to_string()),
+        (VARY, ACCEPT_ENCODING.to_string()),
+    ];
+    if unchanged {
+        return (StatusCode::NOT_MODIFIED, cache_headers).into_response();
+    }
+
+    // Precompressed, so the compression layer passes it through as it is
+    match encoding {
+        Some(encoding) => (
+            cache_headers,
+            [(CONTENT_TYPE, asset.content_type), (CONTENT_ENCODING, encoding.token())],
+            asset.encoded(encoding).to_vec(),
+        )
+            .into_response(),
+        None => (cache_headers, [(CONTENT_TYPE, asset.content_type)], asset.body).into_response(),
+    }
+}
+
+/// `GET /api/intensity`: the headline figure and the period in progress
+#[utoipa::path(
+    get,
+    path = "/api/intensity",
+    summary = "The current intensity",
+    responses(
+        (status = 200, description = "The headline figure, its band and the period in progress", body = ApiIntensity),
+        (status = 304, description = "Unchanged since the client's `If-None-Match` or `If-Modified-Since`"),
+        (status = 502, description = "Every panel failed to fetch from upstream", body = ApiErrorBody),
+    )
+)]
+async fn serve_api_intensity(State(state): State<Arc<AppState>>, headers: HeaderMap) -> Response {
+    serve_api_view(&state, &headers, |data| {
+        let now = Utc::now();
+        Json(ApiIntensity {
+            region: data.region.as_deref(),
+            intensity: data.intensity,
+            index: data.index,
+            mix_intensity: data.mix_intensity(),
+            period: data.timeline_points.iter().find(|p| p.from <= now && now < p.to).map(ApiPeriod::from),
+        })
+        .into_response()
+    })
+    .await
+}
+
+/// `GET /api/generation`: the current mix with each fuel's carbon factor,
+/// plus recent history
+#[utoipa::path(
+    get,
+    path = "/api/generation",
+    summary = "The generation mix",
+    responses(
+        (status = 200, description = "Each fuel's share and carbon factor, and the last 24 hours of the mix", body = ApiGeneration),
+        (status = 304, description = "Unchanged since the client's `If-None-Match` or `If-Modified-Since`"),
+        (status = 502, description = "Every panel failed to fetch from upstream", body = ApiErrorBody),
+    )
+)]
+async fn serve_api_generation(State(state): State<Arc<AppState>>, headers: HeaderMap) -> Response {
+    serve_api_view(&state, &headers, |data| {
+        Json(ApiGeneration {
+            region: data.region.as_deref(),
+            generation_mix: &data.generation_mix,
+            history: &data.generation_history,
+        })
+        .into_response()
+    })
+    .await
+}
+
+/// `GET /api/timeline`: the 24 hours of actual and forecast intensity the
+/// chart shows, with its moving average (`?ma=` as for the page)
+#[utoipa::path(
+    get,
+    path = "/api/timeline",
+    summary = "The intensity timeline",
+    params(MovingAverageParam),
+    responses(
+        (status = 200, description = "12 hours either side of now, actual then forecast", body = ApiTimeline),
+        (status = 304, description = "Unchanged since the client's `If-None-Match` or `If-Modified-Since`"),
+        (status = 502, description = "Every panel failed to fetch from upstream", body = ApiErrorBody),
+    )
+)]
+async fn serve_api_timeline(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<MovingAverageParam>,
+    headers: HeaderMap,
+) -> Response {
+    let moving_average_window = query.ma.unwrap_or(DEFAULT_MOVING_AVERAGE_WINDOW);
+    serve_api_view(&state, &headers, |data| {
+        let values: Vec<i32> = data.timeline_points.iter().map(|p| p.intensity).collect();
+        Json(ApiTimeline {
+            region: data.region.as_deref(),
+            points: &data.timeline_points,
+            moving_average: moving_average(&values, moving_average_window),
+        })
+        .into_response()
+    })
+    .await
+}
+
+/// `GET /api/advice/{appliance}`: whether to start an appliance from the
+/// config file's `[appliances]` now or wait for a greener time. Fetched from
+/// upstream each time, like `/calendar.ics`, as an appliance may wait longer
+/// than the dashboard's data looks ahead.
+#[utoipa::path(
+    get,
+    path = "/api/advice/{appliance}",
+    summary = "Run an appliance now or wait",
+    params(("appliance" = String, Path, description = "Name of an `[appliances.<name>]` table in the config file, e.g. washing-machine")),
+    responses(
+        (status = 200, description = "Emissions starting now and at the greenest time in the appliance's window, and which to do", body = Advice),
+        (status = 404, description = "No such appliance is configured", body = ApiErrorBody),
+        (status = 502, description = "The forecast couldn't be fetched from upstream", body = ApiErrorBody),
+    )
+)]
+async fn serve_api_advice(State(state): State<Arc<AppState>>, Path(name): Path<String>) -> Response {
+    let Some(appliance) = state.options.appliances.get(&name) else {
+        return (
+            StatusCode::NOT_FOUND,
+            [(CACHE_CONTROL, "no-store".to_string())],
+            Json(ApiErrorBody {
+                error: format!("No appliance '{name}' is configured"),
+            }),
+        )
+            .into_response();
+    };
+    let now = Utc::now();
+    let advice = match fetch_forecast(&state, now, now + appliance.within + appliance.duration).await {
+        Ok((_, points)) => advise(&name, appliance, &points, now),
+        Err(e) => Err(e),
+    };
+    match advice {
+        Ok(advice) => (
+            [(CACHE_CONTROL, cache_control(state.options.cache_ttl, now))],
+            Json(advice),
+        )
+            .into_response(),
+        Err(e) => api_error(e),
+    }
+}
+
+/// Respond with one JSON view of the (cached) dashboard data
+async fn serve_api_view(
+    state: &Arc<AppState>,
+    headers: &HeaderMap,
+    view: impl FnOnce(&DashboardData) -> Response,
+) -> Response {
+    match dashboard_data(state).await {
+        Ok(data) => {
+            let validators = Validators::of(&data, Utc::now());
+            if validators.matches(headers) {
+                return validators.not_modified(state.options.cache_ttl);
+            }
+            (AppendHeaders(validators.headers(state.options.cache_ttl)), view(&data)).into_response()
+        }
+        Err(e) => api_error(e),
+    }
+}
+
+/// Validators for a response drawn from the dashboard data. The entity tag
+/// hashes the data with the settlement period it's served in, since the
+/// charts mark the period in progress; it's weak, as renders of the same
+/// data may differ in ways that don't matter. The period's start is its
+/// last modification. A client polling with either gets a 304 until a new
+/// period lands or a refresh changes the data, instead of a full render.
+struct Validators {
+    etag: String,
+    last_modified: DateTime<Utc>,
+}
+
+impl Validators {
+    fn of(data: &DashboardData, now: DateTime<Utc>) -> Self {
+        let period = SettlementPeriod::containing(now).from();
+        // The mix history isn't in the JSON, but the page charts it
+        let content = format!(
+            "{version} {period} {data} {history}",
+            version = env!("CARGO_PKG_VERSION"),
+            data = serde_json::to_string(data).unwrap_or_default(),
+            history = serde_json::to_string(&data.generation_history).unwrap_or_default()
+        );
+        Validators {
+            etag: format!("W/\"{hash:016x}\"", hash = fnv1a(content.as_bytes())),
+            last_modified: period,
+        }
+    }
+
+    /// Whether the client's copy is current: by `If-None-Match` if it sent
+    /// one, comparing weakly, otherwise by `If-Modified-Since`
+    fn matches(&self, headers: &HeaderMap) -> bool {
+        let header = |name| headers.get(name).and_then(|value| value.to_str().ok());
+        let opaque = |tag: &str| tag.trim().trim_start_matches("W/").to_string();
+        if let Some(tags) = header(IF_NONE_MATCH) {
+            return tags
+                .split(',')
+                .any(|tag| tag.trim() == "*" || opaque(tag) == opaque(&self.etag));
+        }
+        header(IF_MODIFIED_SINCE)
+            .and_then(|since| DateTime::parse_from_rfc2822(since).ok())
+            .is_some_and(|since| since >= self.last_modified)
+    }
+
+    /// `ETag`, `Last-Modified` and `Cache-Control` until the next period
+    fn headers(&self, ttl: Duration) -> Vec<(HeaderName, String)> {
+        vec![
+            (ETAG, self.etag.clone()),
+            (LAST_MODIFIED, self.last_modified.format("%a, %d %b %Y %H:%M:%S GMT").to_string()),
+            (CACHE_CONTROL, cache_control(ttl, Utc::now())),
+        ]
+    }
+
+    fn not_modified(&self, ttl: Duration) -> Response {
+        (StatusCode::NOT_MODIFIED, AppendHeaders(self.headers(ttl))).into_response()
+    }
+}
+
+/// Body of an API error response
+#[derive(Debug, Serialize, ToSchema)]
+struct ApiErrorBody {
+    /// e.g. "API returned 500 Internal Server Error"
+    error: String,
+}
+
+/// 502 with the upstream error as JSON; never cached
+fn api_error(error: CarbonError) -> Response {
+    warn!(error = %error, "Error fetching data");
+    (
+        StatusCode::BAD_GATEWAY,
+        [(CACHE_CONTROL, "no-store".to_string())],
+        Json(ApiErrorBody {
+            error: error.to_string(),
+        }),
+    )
+        .into_response()
+}
+
+/// `Cache-Control` for API responses: cacheable until the next settlement
+/// period is due, or until the server's own cache expires if that is sooner
+fn cache_control(ttl: Duration, now: DateTime<Utc>) -> String {
+    if ttl.is_zero() {
+        return "no-cache".to_string();
+    }
+
+    format!(
+        "public, max-age={max_age}",
+        max_age = seconds_until_next_period(now).min(ttl.as_secs())
+    )
+}
+
+/// Seconds until the next settlement period starts, on the hour or half
+/// hour, for `max-age`; at least 1
+fn seconds_until_next_period(now: DateTime<Utc>) -> u64 {
+    until_next_period(now).num_seconds().max(1) as u64
+}
+
+/// The timeline's range: 12 hours either side of the start of the current
+/// settlement period, so it holds whole periods and the same number of them
+/// whenever in the period it's fetched
+fn timeline_range(now: DateTime<Utc>) -> (DateTime<Utc>, DateTime<Utc>) {
+    let current = SettlementPeriod::containing(now).from();
+    (current - TIMELINE_SPAN, current + TIMELINE_SPAN)
+}
+
+/// `GET /badge.svg`: a shields.io-style badge with the headline intensity
+/// on its band's colour, for embedding live grid status in READMEs and
+/// wikis. Cacheable until the next settlement period starts, when the figure
+/// can next change. If the headline figure can't be fetched the badge says so
+/// and isn't cached.
+async fn serve_badge(State(state): State<Arc<AppState>>) -> Response {
+    let data = dashboard_data(&state).await;
+    let headline = match &data {
+        Ok(data) => data.intensity.map(|intensity| (data, intensity)),
+        Err(e) => {
+            warn!(error = %e, "Error fetching data for badge");
+            None
+        }
+    };
+    let (label, message, color, cache_control) = match headline {
+        Some((data, intensity)) => (
+            data.region
+                .as_ref()
+                .map_or_else(|| "grid carbon".to_string(), |region| format!("{region} carbon")),
+            format!("{intensity} {unit}", intensity = units::figure(intensity), unit = units::label()),
+            data.index.map_or(BADGE_UNAVAILABLE, |index| LIGHT.band(index)),
+            format!(
+                "public, max-age={max_age}",
+                max_age = seconds_until_next_period(Utc::now())
+            ),
+        ),
+        None => (
+            "grid carbon".to_string(),
+            "unavailable".to_string(),
+            BADGE_UNAVAILABLE,
+            "no-store".to_string(),
+        ),
+    };
+
+    (
+        [(CONTENT_TYPE, "image/svg+xml".to_string()), (CACHE_CONTROL, cache_control)],
+        render_badge(&label, &message, color),
+    )
+        .into_response()
+}
+
+/// Query for `GET /chart.png` and `/chart.jpg`, e.g.
+/// `/chart.png?hours=12&width=800&height=480&theme=dark`
+#[derive(Debug, Deserialize)]
+struct ChartQuery {
+    /// Hours of the timeline shown, centred on now, 1 to 24 [default: 24]
+    hours: Option<i64>,
+    /// Image size in pixels [default: 500 by 180, as on the dashboard]
+    width: Option<u32>,
+    height: Option<u32>,
+    /// [default: `--theme`, with `auto` drawn light]
+    theme: Option<ThemeChoice>,
+    /// Moving average window in points, as `/?ma=`
+    ma: Option<usize>,
+    /// Answer with the image as a `data:` URI in plain text, for an HTML
+    /// email's `<img src>` [default: false]
+    #[serde(default)]
+    data_uri: bool,
+}
+
+/// Raster formats the timeline chart is served in
+#[derive(Clone, Copy, Debug)]
+enum ChartFormat {
+    Png,
+    Jpeg,
+}
+
+/// `GET /chart.png`: the dashboard's timeline chart rasterised, for places
+/// that can't render SVG or run the page's CSS: emails, chat unfurls, e-ink
+/// displays. Cacheable like the API, since it's drawn from the same data.
+async fn serve_chart_png(State(state): State<Arc<AppState>>, Query(query): Query<ChartQuery>) -> Response {
+    serve_chart(&state, query, ChartFormat::Png).await
+}
+
+/// `GET /chart.jpg`: as `/chart.png`, for displays that only take JPEG
+async fn serve_chart_jpeg(State(state): State<Arc<AppState>>, Query(query): Query<ChartQuery>) -> Response {
+    serve_chart(&state, query, ChartFormat::Jpeg).await
+}
+
+async fn serve_chart(state: &Arc<AppState>, query: ChartQuery, format: ChartFormat) -> Response {
+    let data = match dashboard_data(state).await {
+        Ok(data) => data,
+        Err(e) => return api_error(e),
+    };
+    // The timeline is 12 hours either side of the current period, so its
+    // middle is now
+    let span = TimeDelta::hours(query.hours.unwrap_or(24).clamp(1, 24)) / 2;
+    let points: Vec<IntensityPoint> = match (data.timeline_points.first(), data.timeline_points.last()) {
+        (Some(first), Some(last)) => {
+            let middle = first.from + (last.to - first.from) / 2;
+            data.timeline_points
+                .iter()
+                .filter(|point| point.from >= middle - span && point.to <= middle + span)
+                .cloned()
+                .collect()
+        }
+        _ => vec![],
+    };
+
+    let width = query.width.unwrap_or(CHART_SIZE.0 as u32).clamp(MIN_CHART_SIZE.0, MAX_CHART_SIZE.0);
+    let height = query.height.unwrap_or(CHART_SIZE.1 as u32).clamp(MIN_CHART_SIZE.1, MAX_CHART_SIZE.1);
+    // Bigger images are the dashboard's chart scaled up, rather than the
+    // same text and lines on a wider plot
+    let scale = (width as f64 / CHART_SIZE.0).min(height as f64 / CHART_SIZE.1).max(1.0);
+    let svg = render_intensity_chart(
+        &points,
+        query.ma.unwrap_or(DEFAULT_MOVING_AVERAGE_WINDOW),
+        state.options.y_min,
+        state.options.y_max,
+        (width as f64 / scale, height as f64 / scale),
+    );
+    if svg.is_empty() {
+        return api_error(CarbonError::EmptyData("timeline to chart".into()));
+    }
+
+    let theme = query.theme.unwrap_or(state.options.theme).fixed();
+    let transform = resvg::tiny_skia::Transform::from_scale(scale as f32, scale as f32);
+    let image = rasterise(&theme.resolve(&svg), theme.page_background, transform, (width, height), state.fonts())
+        .and_then(|pixmap| match format {
+            ChartFormat::Png => pixmap.encode_png().map_err(|e| e.to_string()),
+            ChartFormat::Jpeg => encode_jpeg(&pixmap, CHART_JPEG_QUALITY),
+        });
+    let content_type = match format {
+        ChartFormat::Png => "image/png",
+        ChartFormat::Jpeg => "image/jpeg",
+    };
+    let (image, content_type) = if query.data_uri {
+        let uri = image.map(|image| data_uri(content_type, &image).into_bytes());
+        (uri, "text/plain; charset=utf-8")
+    } else {
+        (image, content_type)
+    };
+    image_response(image, content_type, cache_control(state.options.cache_ttl, Utc::now()))
+}
+
+/// Query for `GET /treemap.svg`, e.g. `/treemap.svg?width=800&height=480&theme=dark`
+#[derive(Debug, Deserialize)]
+struct TreemapQuery {
+    /// Image size in pixels [default: 500 by 300]
+    width: Option<u32>,
+    height: Option<u32>,
+    /// [default: `--theme`, with `auto` drawn light]
+    theme: Option<ThemeChoice>,
+}
+
+/// `GET /treemap.svg`: the generation mix as a squarified treemap, each
+/// fuel's area its share, in the pie's colours. A standalone SVG with the
+/// theme's colours filled in, cacheable like the API.
+async fn serve_treemap(State(state): State<Arc<AppState>>, Query(query): Query<TreemapQuery>) -> Response {
+    let data = match dashboard_data(&state).await {
+        Ok(data) => data,
+        Err(e) => return api_error(e),
+    };
+    let width = query.width.unwrap_or(TREEMAP_SIZE.0 as u32).clamp(MIN_CHART_SIZE.0, MAX_CHART_SIZE.0);
+    let height = query.height.unwrap_or(TREEMAP_SIZE.1 as u32).clamp(MIN_CHART_SIZE.1, MAX_CHART_SIZE.1);
+    let svg = render_mix_treemap(&data.generation_mix, (width as f64, height as f64));
+    if svg.is_empty() {
+        return api_error(CarbonError::EmptyData("generation mix to chart".into()));
+    }
+
+    let theme = query.theme.unwrap_or(state.options.theme).fixed();
+    (
+        [
+            (CONTENT_TYPE, "image/svg+xml".to_string()),
+            (CACHE_CONTROL, cache_control(state.options.cache_ttl, Utc::now())),
+        ],
+        theme.resolve(&svg),
+    )
+        .into_response()
+}
+
+/// Query for `GET /eink`, e.g. `/eink?width=800&height=480&rotate=90&large=true`
+#[derive(Debug, Deserialize)]
+struct EinkQuery {
+    /// The panel's size in pixels, as its driver expects the image
+    /// [default: 800 by 480]
+    width: Option<u32>,
+    height: Option<u32>,
+    /// Degrees clockwise to turn the content, 0, 90, 180 or 270, for a panel
+    /// mounted on its side or upside down [default: 0]
+    rotate: Option<u32>,
+    /// Bigger text, for reading across a room [default: false]
+    large: Option<bool>,
+    /// Green window threshold in gCO₂/kWh, as `/calendar.ics` [default: 100]
+    below: Option<i32>,
+}
+
+/// `GET /eink`: the headline intensity, its band and the next green window
+/// as a 1-bit PNG for e-paper displays, which only show black and white and
+/// are slow enough to refresh that they're best polled every half hour.
+/// Like `/badge.svg`, an image is always returned, saying what couldn't be
+/// fetched, and is cacheable until the next settlement period.
+async fn serve_eink(State(state): State<Arc<AppState>>, Query(query): Query<EinkQuery>) -> Response {
+    let now = Utc::now();
+    let below = query.below.unwrap_or(DEFAULT_BELOW);
+    let (data, forecast) = tokio::join!(
+        dashboard_data(&state),
+        fetch_forecast(&state, now, now + TimeDelta::days(2))
+    );
+    let data = data.inspect_err(|e| warn!(error = %e, "Error fetching data for the e-ink screen"));
+    let green_window = match forecast {
+        Ok((_, points)) => describe_green_window(&low_carbon_windows(&points, below), now),
+        Err(e) => {
+            warn!(error = %e, "Error fetching the forecast for the e-ink screen");
+            "Next green window unavailable".to_string()
+        }
+    };
+    let fetched = data.as_ref().is_ok_and(|data| data.intensity.is_some());
+    let screen = EinkScreen {
+        area: data
+            .as_ref()
+            .ok()
+            .and_then(|data| data.region.clone())
+            .unwrap_or_else(|| "GB grid".to_string()),
+        intensity: data.as_ref().ok().and_then(|data| data.intensity),
+        index: data.as_ref().ok().and_then(|data| data.index),
+        green_window,
+        updated: now,
+    };
+
+    let (width, height) = (
+        query.width.unwrap_or(EINK_SIZE.0).clamp(MIN_CHART_SIZE.0, MAX_CHART_SIZE.0),
+        query.height.unwrap_or(EINK_SIZE.1).clamp(MIN_CHART_SIZE.1, MAX_CHART_SIZE.1),
+    );
+    // Lay the content out the way up it's read, then turn it to fit the panel
+    let rotate = query.rotate.unwrap_or(0) % 360 / 90 * 90;
+    let (content_width, content_height) = if rotate % 180 == 0 { (width, height) } else { (height, width) };
+    let transform = resvg::tiny_skia::Transform::from_rotate(rotate as f32).post_translate(
+        match rotate {
+            90 | 180 => width as f32,
+            _ => 0.0,
+        },
+        match rotate {
+            180 | 270 => height as f32,
+            _ => 0.0,
+        },
+    );
+    let svg = render_eink(&screen, (content_width, content_height), query.large.unwrap_or(false));
+    let image = rasterise(&svg, "#FFFFFF", transform, (width, height), state.fonts()).and_then(|pixmap| encode_monochrome(&pixmap));
+
+    let cache_control = if fetched {
+        format!("public, max-age={max_age}", max_age = seconds_until_next_period(now))
+    } else {
+        "no-store".to_string()
+    };
+    image_response(image, "image/png", cache_control)
+}
+
+impl AppState {
+    /// System fonts for the labels of rasterised images, loaded on first use
+    fn fonts(&self) -> &Arc<resvg::usvg::fontdb::Database> {
+        self.fonts.get_or_init(|| Arc::new(system_fonts()))
+    }
+}
+
+/// An encoded image, or a 500 if it couldn't be drawn
+fn image_response(image: Result<Vec<u8>, String>, content_type: &str, cache_control: String) -> Response {
+    match image {
+        Ok(image) => (
+            [(CONTENT_TYPE, content_type.to_string()), (CACHE_CONTROL, cache_control)],
+            image,
+        )
+            .into_response(),
+        Err(e) => {
+            error!(error = %e, "Error rasterising an image");
+            (StatusCode::INTERNAL_SERVER_ERROR, [(CACHE_CONTROL, "no-store")], e).into_response()
+        }
+    }
+}
+
+/// Query for `GET /calendar.ics`, e.g. `/calendar.ics?below=80&days=1`
+#[derive(Debug, Deserialize)]
+struct CalendarQuery {
+    /// Threshold in gCO₂/kWh [default: 100]
+    below: Option<i32>,
+    /// Days ahead, 1 or 2 [default: 2]
+    days: Option<i64>,
+}
+
+/// The forecast's low-carbon windows as an iCalendar feed, for calendar apps
+/// to subscribe to. Fetched from upstream each time, as the dashboard's own
+/// data only looks 12 hours ahead.
+async fn serve_calendar(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<CalendarQuery>,
+) -> Response {
+    let below = query.below.unwrap_or(DEFAULT_BELOW);
+    let days = query.days.unwrap_or(2).clamp(1, 2);
+    let now = Utc::now();
+    match fetch_forecast(&state, now, now + TimeDelta::days(days)).await {
+        Ok((area, points)) => (
+            [
+                (CONTENT_TYPE, "text/calendar; charset=utf-8".to_string()),
+                (CACHE_CONTROL, cache_control(state.options.cache_ttl, now)),
+            ],
+            to_ics(&low_carbon_windows(&points, below), below, area.as_deref(), now),
+        )
+            .into_response(),
+        Err(e) => api_error(e),
+    }
+}
+
+/// `GET /regions`: every region's current intensity as a map, a bar chart
+/// and a table, greenest first. Fetched from upstream each time, like
+/// `/calendar.ics`, as the dashboard's own data covers one area.
+async fn serve_regions(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<DashboardQuery>,
+) -> Response {
+    let theme_css = query.theme.unwrap_or(state.options.theme).stylesheet();
+    let snapshot = match state.client.carbon_intensity() {
+        Some(client) => client.regional_intensities().await,
+        None => Err(usage_error("The region comparison only works with --provider carbon-intensity")),
+    };
+    let (status, page) = match snapshot {
+        Ok(snapshot) => {
+            let regions = snapshot.by_intensity();
+            let page = RegionsPage {
+                theme_css,
+                period: SettlementPeriod::containing(snapshot.from).to_string(),
+                map: render_region_map(&regions),
+                chart: render_region_chart(&regions),
+                rows: regions
+                    .iter()
+                    .map(|region| RegionRow {
+                        region_id: region.region_id,
+                        region: region.short_name.clone(),
+                        intensity: region.intensity.best().map(units::figure),
+                        index: region.intensity.index.map(IntensityIndex::as_str).unwrap_or_default(),
+                        index_class: index_class(region.intensity.index),
+                    })
+                    .collect(),
+                unit: units::label(),
+                error: None,
+            };
+            (StatusCode::OK, page)
+        }
+        Err(e) => {
+            warn!(error = %e, "Error fetching regional data");
+            let page = RegionsPage {
+                theme_css,
+                period: String::new(),
+                map: String::new(),
+                chart: String::new(),
+                rows: Vec::new(),
+                unit: units::label(),
+                error: Some(e.to_string()),
+            };
+            (StatusCode::BAD_GATEWAY, page)
+        }
+    };
+    match page.render() {
+        Ok(html) => (
+            status,
+            [(CACHE_CONTROL, cache_control(state.options.cache_ttl, Utc::now()))],
+            Html(html),
+        )
+            .into_response(),
+        Err(e) => {
+            error!(error = %e, "Error rendering the regions page");
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+/// Choropleth of the DNO regions (see [`crate::map`]) in their bands'
+/// colours, with a key in the North Sea. Regions without a figure are left
+/// grey.
+fn render_region_map(regions: &[&Region]) -> String {
+    let (width, height) = map_size(REGION_MAP_SCALE);
+
+    let mut outlines = String::new();
+    for outline in &REGION_OUTLINES {
+        let region = regions.iter().find(|region| region.region_id == outline.region_id);
+        let name = escape_xml(region.map_or(REGION_NAMES[outline.region_id as usize - 1], |region| {
+            region.short_name.as_str()
+        }));
+        let (fill, title) = match region.and_then(|region| region.intensity.best()) {
+            Some(intensity) => {
+                let band = region
+                    .and_then(|region| region.intensity.index)
+                    .unwrap_or_else(|| IntensityIndex::for_intensity(intensity));
+                (
+                    format!("var(--band-{band})", band = band.as_str().replace(' ', "-")),
+                    format!("{name}: {intensity} {unit} ({band})", intensity = units::figure(intensity), unit = units::label()),
+                )
+            }
+            None => ("var(--grid)".to_string(), format!("{name}: no figure")),
+        };
+        outlines.push_str(&format!(
+            "<path class=\"region\" data-region=\"{region_id}\" d=\"{path}\" style=\"fill: {fill}; stroke: var(--slice-border)\" stroke-width=\"1\" stroke-linejoin=\"round\"><title>{title}</title></path>",
+            region_id = outline.region_id,
+            path = outline_path(outline, REGION_MAP_SCALE)
+        ));
+    }
+
+    let mut key = String::new();
+    for (i, band) in IntensityIndex::ALL.into_iter().enumerate() {
+        let y = 12.0 + 18.0 * i as f64;
+        key.push_str(&format!(
+            "<rect x=\"{x}\" y=\"{y}\" width=\"12\" height=\"12\" style=\"fill: var(--band-{band_class})\" rx=\"2\"/>
+            <text x=\"{text_x}\" y=\"{text_y}\" font-family=\"Arial, sans-serif\" font-size=\"11\" style=\"fill: var(--axis-label)\">{band}</text>",
+            x = width - 90.0,
+            band_class = band.as_str().replace(' ', "-"),
+            text_x = width - 72.0,
+            text_y = y + 10.0
+        ));
+    }
+
+    format!(
+        "<svg width=\"{width:.0}\" height=\"{height:.0}\" viewBox=\"0 0 {width:.0} {height:.0}\" role=\"img\" aria-label=\"Map of carbon intensity by region\">
+            {outlines}
+            {key}
+        </svg>"
+    )
+}
+
+/// Horizontal bars, one per region in the order given, coloured by band and
+/// scaled to the highest figure
+fn render_region_chart(regions: &[&Region]) -> String {
+    let (width, row_height, label_width, value_width) = (700.0, 26.0, 160.0, 50.0);
+    let bar_space = width - label_width - value_width;
+    let height = row_height * regions.len() as f64 + 10.0;
+    let max = regions
+        .iter()
+        .filter_map(|region| region.intensity.best())
+        .max()
+        .unwrap_or(0)
+        .max(1) as f64;
+
+    let mut rows = String::new();
+    for (i, region) in regions.iter().enumerate() {
+        let y = 5.0 + row_height * i as f64;
+        let name = escape_xml(&region.short_name);
+        rows.push_str(&format!(
+            "<text x=\"{x}\" y=\"{text_y}\" font-family=\"Arial, sans-serif\" font-size=\"12\" style=\"fill: var(--axis-title)\" text-anchor=\"end\">{name}</text>",
+            x = label_width - 8.0,
+            text_y = y + row_height / 2.0 + 4.0
+        ));
+        let Some(intensity) = region.intensity.best() else {
+            continue;
+        };
+        let band = region
+            .intensity
+            .index
+            .unwrap_or_else(|| IntensityIndex::for_intensity(intensity));
+        let bar_width = bar_space * intensity.max(0) as f64 / max;
+        rows.push_str(&format!(
+            "<rect x=\"{x}\" y=\"{bar_y}\" width=\"{bar_width}\" height=\"{bar_height}\" style=\"fill: var(--band-{band_class})\" rx=\"2\"><title>{name}: {intensity} {unit} ({band})</title></rect>
+            <text x=\"{value_x}\" y=\"{text_y}\" font-family=\"Arial, sans-serif\" font-size=\"11\" font-weight=\"bold\" style=\"fill: var(--axis-title)\">{intensity}</text>",
+            x = label_width,
+            bar_y = y + 3.0,
+            bar_height = row_height - 6.0,
+            band_class = band.as_str().replace(' ', "-"),
+            value_x = label_width + bar_width + 6.0,
+            text_y = y + row_height / 2.0 + 4.0,
+            intensity = units::figure(intensity),
+            unit = units::label()
+        ));
+    }
+
+    format!(
+        "<svg width=\"{width}\" height=\"{height}\" viewBox=\"0 0 {width} {height}\" role=\"img\" aria-label=\"Carbon intensity by region\">
+            <rect x=\"0\" y=\"0\" width=\"{width}\" height=\"{height}\" style=\"fill: var(--chart-background)\" rx=\"5\"/>
+            {rows}
+        </svg>"
+    )
+}
+
+/// Intensity for the dashboard's area from `from` to `to`, with the area's
+/// name where it has one
+async fn fetch_forecast(
+    state: &AppState,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+) -> Result<(Option<String>, Vec<IntensityPoint>), CarbonError> {
+    match state.options.region.selector() {
+        Some(region) => {
+            let entry = regional_client(&state.client)?
+                .regional_between(&region, from, to)
+                .await?;
+            let points = entry
+                .data
+                .iter()
+                .filter_map(|period| period.to_intensity_point())
+                .collect();
+            Ok((Some(entry.short_name), points))
+        }
+        None => Ok((
+            state.client.area_name().map(str::to_string),
+            state.client.intensity_between(from, to).await?,
+        )),
+    }
+}
+
+/// `GET /events`: a Server-Sent Events stream with one `dashboard` event,
+/// shaped like `/api/data`, each time `publish_dashboard_updates` sees new
+/// data. The page was rendered fresh, so nothing is sent on connect.
+async fn serve_events(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<DashboardQuery>,
+) -> Sse<impl Stream<Item = Result<Event, axum::Error>>> {
+    let moving_average_window = query.ma.unwrap_or(DEFAULT_MOVING_AVERAGE_WINDOW);
+    let updates = state.updates.subscribe();
+
+    let stream = stream::unfold((updates, state), move |(mut updates, state)| async move {
+        let mut shutting_down = state.shutting_down.subscribe();
+        loop {
+            // Ends the stream if the publisher has stopped, or the server is
+            // shutting down and waiting for connections to close
+            tokio::select! {
+                changed = updates.changed() => changed.ok()?,
+                _ = shutting_down.wait_for(|&stopping| stopping) => return None,
+            }
+            let Some(data) = updates.borrow_and_update().clone() else {
+                continue;
+            };
+
+            let event = Event::default().event("dashboard").json_data(ApiData {
+                sections: render_sections(&data, &state.options, moving_average_window),
+                mix_intensity: data.mix_intensity(),
+                data: &data,
+            });
+            return Some((event, (updates, state)));
+        }
+    });
+
+    Sse::new(stream).keep_alive(KeepAlive::default())
+}
+
+/// `GET /api/stream`: newline-delimited JSON, one line per settlement period
+/// as it begins, for tailing into a pipeline with `curl -N | jq`. The period
+/// in progress comes first, from the cache; later ones as
+/// `publish_dashboard_updates` brings them in. The response never ends
+/// unless the publisher stops or the server shuts down.
+#[utoipa::path(
+    get,
+    path = "/api/stream",
+    summary = "Settlement periods as they begin",
+    responses(
+        (status = 200, description = "A long-lived stream with one line per settlement period, the one in progress first", body = ApiStreamLine, content_type = "application/x-ndjson"),
+    )
+)]
+async fn serve_api_stream(State(state): State<Arc<AppState>>) -> Response {
+    // Subscribed before reading the cache, so nothing published in between
+    // is missed
+    let updates = state.updates.subscribe();
+    let first = match dashboard_data(&state).await {
+        Ok(data) => Some(data),
+        Err(e) => {
+            warn!(error = %e, "Error fetching data to start a stream");
+            None
+        }
+    };
+
+    let stream = stream::unfold(
+        (updates, state, first, None),
+        |(mut updates, state, mut next, mut last)| async move {
+            let mut shutting_down = state.shutting_down.subscribe();
+            loop {
+                let data = match next.take() {
+                    Some(data) => data,
+                    None => {
+                        tokio::select! {
+                            changed = updates.changed() => changed.ok()?,
+                            _ = shutting_down.wait_for(|&stopping| stopping) => return None,
+                        }
+                        let Some(data) = updates.borrow_and_update().clone() else {
+                            continue;
+                        };
+                        data
+                    }
+                };
+
+                // Only a period later than the last one sent makes a line;
+                // refreshes within a period are left out
+                let Some((from, line)) = stream_line(&data, Utc::now(), last) else {
+                    continue;
+                };
+                last = Some(from);
+                return Some((Ok::<_, Infallible>(line), (updates, state, next, last)));
+            }
+        },
+    );
+
+    (
+        [(CONTENT_TYPE, "application/x-ndjson"), (CACHE_CONTROL, "no-store")],
+        axum::body::Body::from_stream(stream),
+    )
+        .into_response()
+}
+
+impl<'a> ApiStreamLine<'a> {
+    /// The latest period to have begun by `now`: the one in progress, unless
+    /// the data is behind. `None` if there's none.
+    fn of(data: &'a DashboardData, now: DateTime<Utc>) -> Option<Self> {
+        Some(ApiStreamLine {
+            region: data.region.as_deref(),
+            period: data.timeline_points.iter().rev().find(|p| p.from <= now)?.into(),
+            mix_intensity: data.mix_intensity(),
+            generation_mix: &data.generation_mix,
+        })
+    }
+}
+
+/// The start of the latest period to have begun by `now` and its
+/// `/api/stream` line, or `None` if there's none or it began no later than
+/// `after`
+fn stream_line(data: &DashboardData, now: DateTime<Utc>, after: Option<DateTime<Utc>>) -> Option<(DateTime<Utc>, String)> {
+    let line = ApiStreamLine::of(data, now)?;
+    if after.is_some_and(|after| line.period.from <= after) {
+        return None;
+    }
+
+    let mut json = serde_json::to_string(&line).ok()?;
+    json.push('\n');
+    Some((line.period.from, json))
+}
+
+/// A message to `/ws` clients, sent as JSON text with its `type`
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+enum PushMessage<'a> {
+    /// The latest figures: on connecting, then whenever they change
+    Update(ApiStreamLine<'a>),
+    /// A rule from `--alerts` has started to hold
+    Alert(&'a AlertEvent),
+}
+
+impl PushMessage<'_> {
+    fn to_json(&self) -> Option<String> {
+        serde_json::to_string(self)
+            .inspect_err(|e| warn!(error = %e, "Couldn't encode a WebSocket message"))
+            .ok()
+    }
+}
+
+/// `--alerts` rules with the recent readings they're checked against
+struct DashboardAlerts {
+    rules: std::sync::Mutex<(History, Vec<Rule>)>,
+}
+
+impl DashboardAlerts {
+    fn new(rules: Vec<Rule>) -> DashboardAlerts {
+        info!(rules = rules.len(), "Pushing alerts to WebSocket clients");
+        DashboardAlerts {
+            rules: std::sync::Mutex::new((History::for_rules(&rules), rules)),
+        }
+    }
+
+    /// Record the latest period in `data` and return the alerts that have
+    /// just started to hold. A refresh within the same period replaces its
+    /// reading, as for `alert`.
+    fn check(&self, data: &DashboardData, now: DateTime<Utc>) -> Vec<AlertEvent> {
+        let (Some(period), Some(intensity)) = (data.timeline_points.iter().rev().find(|p| p.from <= now), data.intensity)
+        else {
+            return Vec::new();
+        };
+        let reading = Reading {
+            from: period.from,
+            intensity,
+            index: data.index,
+            generation_mix: data
+                .generation_mix
+                .iter()
+                .map(|source| FuelSource { fuel: source.fuel.clone(), perc: source.perc })
+                .collect(),
+            region: data.region.clone(),
+        };
+
+        let mut rules = lock(&self.rules);
+        let (history, rules) = &mut *rules;
+        history.push(reading);
+        rules.iter_mut().filter_map(|rule| rule.update(history)).collect()
+    }
+}
+
+/// `GET /ws`: a WebSocket that pushes an `update` message with the current
+/// period, its mix and mix × factors intensity on connecting and whenever
+/// they change, and an `alert` message for each rule from `--alerts` that
+/// starts to hold. Messages from the client are ignored, but pings are
+/// answered.
+async fn serve_ws(State(state): State<Arc<AppState>>, upgrade: WebSocketUpgrade) -> Response {
+    upgrade.on_upgrade(move |socket| push_to_socket(socket, state))
+}
+
+async fn push_to_socket(mut socket: WebSocket, state: Arc<AppState>) {
+    // Subscribed before reading the cache, so nothing published in between
+    // is missed
+    let mut pushes = state.pushes.subscribe();
+    let mut shutting_down = state.shutting_down.subscribe();
+
+    match dashboard_data(&state).await {
+        Ok(data) => {
+            if let Some(message) = ApiStreamLine::of(&data, Utc::now()).and_then(|line| PushMessage::Update(line).to_json())
+                && socket.send(Message::Text(message)).await.is_err()
+            {
+                return;
+            }
+        }
+        Err(e) => warn!(error = %e, "Error fetching data for a new WebSocket client"),
+    }
+
+    loop {
+        let message = tokio::select! {
+            pushed = pushes.recv() => match pushed {
+                Ok(message) => message,
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    warn!(skipped, "A WebSocket client fell behind; dropped its oldest messages");
+                    continue;
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            },
+            // Reading is also what answers pings
+            received = socket.recv() => match received {
+                Some(Ok(Message::Close(_)) | Err(_)) | None => return,
+                Some(Ok(_)) => continue,
+            },
+            _ = shutting_down.wait_for(|&stopping| stopping) => break,
+        };
+        if socket.send(Message::Text(message)).await.is_err() {
+            return;
+        }
+    }
+    let _ = socket.send(Message::Close(None)).await;
+}
+
+/// Background task behind `/events`, `/api/stream` and `/ws`: one upstream
+/// fetch per interval shared by every subscriber, skipped while nobody is
+/// listening and there are no `--alerts` rules to check. Subscribers are
+/// only woken when the data has actually changed, and a failed fetch keeps
+/// the last good data.
+async fn publish_dashboard_updates(state: Arc<AppState>) {
+    let mut interval = tokio::time::interval(state.options.refresh_interval);
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+    loop {
+        interval.tick().await;
+        if state.updates.receiver_count() == 0 && state.pushes.receiver_count() == 0 && state.alerts.is_none() {
+            continue;
+        }
+
+        refresh_and_publish(&state).await;
+    }
+}
+
+/// Refresh the page cache and send the result to `/events`, `/api/stream`
+/// and `/ws` clients if it changed, along with any alerts it sets off.
+/// Always goes upstream (or to the archive with `daemon`), sharing a fetch
+/// a page load already started.
+async fn refresh_and_publish(state: &AppState) {
+    let data = match state.cache.refresh.run(refresh_dashboard_data(state)).await {
+        Ok(data) => data,
+        Err(e) => {
+            warn!(error = %e, "Error fetching data for live updates");
+            return;
+        }
+    };
+
+    let now = Utc::now();
+    let changed = state.updates.send_if_modified(|current| {
+        if current.as_ref() == Some(&data) {
+            return false;
+        }
+        *current = Some(data.clone());
+        true
+    });
+    // Sending fails only when no `/ws` client is connected
+    if changed
+        && let Some(message) = ApiStreamLine::of(&data, now).and_then(|line| PushMessage::Update(line).to_json())
+    {
+        let _ = state.pushes.send(message);
+    }
+    if let Some(alerts) = &state.alerts {
+        for event in alerts.check(&data, now) {
+            info!(rule = %event.rule, "Alert rule fired");
+            if let Some(message) = PushMessage::Alert(&event).to_json() {
+                let _ = state.pushes.send(message);
+            }
+        }
+    }
+}
+
+/// Body of `GET /healthz` and `GET /readyz`
+#[derive(Debug, Serialize)]
+struct HealthReport {
+    /// "ok", "unavailable" or "shutting down"
+    status: &'static str,
+    upstream: UpstreamHealth,
+    /// `None` while nothing is cached, or with the cache disabled
+    cache: Option<CacheHealth>,
+}
+
+#[derive(Debug, Serialize)]
+struct UpstreamHealth {
+    /// Whether the latest fetch succeeded; `None` before the first
+    reachable: Option<bool>,
+    last_success: Option<DateTime<Utc>>,
+    last_error: Option<UpstreamError>,
+}
+
+#[derive(Debug, Serialize)]
+struct CacheHealth {
+    age_seconds: u64,
+    /// Within `--cache-ttl`; stale data is still served for a while
+    fresh: bool,
+}
+
+async fn health_report(state: &AppState, status: &'static str) -> HealthReport {
+    let cache = state.cache.entry.read().await.as_ref().map(|cached| {
+        CacheHealth {
+            age_seconds: cached.fetched_at.elapsed().as_secs(),
+            fresh: cached.is_fresh(state.options.cache_ttl),
+        }
+    });
+
+    HealthReport {
+        status,
+        upstream: UpstreamHealth {
+            reachable: state.metrics.reachable(),
+            last_success: *lock(&state.metrics.last_success),
+            last_error: lock(&state.metrics.last_error).clone(),
+        },
+        cache,
+    }
+}
+
+/// `GET /healthz`: liveness. Always 200 while the process is serving, since
+/// restarting can't fix an unreachable upstream; the report is informational.
+async fn serve_healthz(State(state): State<Arc<AppState>>) -> Response {
+    (
+        [(CACHE_CONTROL, "no-store")],
+        Json(health_report(&state, "ok").await),
+    )
+        .into_response()
+}
+
+/// `GET /readyz`: readiness. 200 when the dashboard can be served, from the
+/// cache or a fresh upstream fetch; 503 when it can't or once shutdown has
+/// begun. Reads through the cache, so probes only reach upstream when the
+/// cached data has expired.
+async fn serve_readyz(State(state): State<Arc<AppState>>) -> Response {
+    let (code, status) = if *state.shutting_down.borrow() {
+        (StatusCode::SERVICE_UNAVAILABLE, "shutting down")
+    } else {
+        match dashboard_data(&state).await {
+            Ok(_) => (StatusCode::OK, "ok"),
+            Err(e) => {
+                warn!(error = %e, "Readiness check failed");
+                (StatusCode::SERVICE_UNAVAILABLE, "unavailable")
+            }
+        }
+    };
+
+    (
+        code,
+        [(CACHE_CONTROL, "no-store")],
+        Json(health_report(&state, status).await),
+    )
+        .into_response()
+}
+
+/// `GET /metrics`: Prometheus text exposition of the latest data and the
+/// upstream fetch counters. Reads through the cache, so scraping doesn't add
+/// upstream load; if no data can be fetched only the counters are reported.
+async fn serve_metrics(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    let data = match dashboard_data(&state).await {
+        Ok(data) => Some(data),
+        Err(e) => {
+            warn!(error = %e, "Error fetching data for metrics");
+            None
+        }
+    };
+
+    (
+        [(CONTENT_TYPE, "text/plain; version=0.0.4")],
+        render_metrics(data.as_deref(), &state.metrics, &state.limits),
+    )
+}
+
+fn render_metrics(
+    data: Option<&DashboardData>,
+    metrics: &UpstreamMetrics,
+    limits: &RequestLimits,
+) -> String {
+    let mut out = String::new();
+    let mut metric = |name: &str, kind: &str, help: &str, samples: Vec<(String, String)>| {
+        out.push_str(&format!("# HELP {name} {help}\n# TYPE {name} {kind}\n"));
+        for (labels, value) in samples {
+            out.push_str(&format!("{name}{labels} {value}\n"));
+        }
+    };
+
+    if let Some(data) = data {
+        if let Some(intensity) = data.intensity {
+            metric(
+                "carbon_intensity_gco2_per_kwh",
+                "gauge",
+                "Current carbon intensity (actual where measured, otherwise forecast)",
+                vec![(String::new(), intensity.to_string())],
+            );
+        }
+
+        let now = chrono::Utc::now();
+        if let Some(forecast) = data
+            .timeline_points
+            .iter()
+            .find(|p| p.from <= now && now < p.to)
+            .and_then(|p| p.forecast)
+        {
+            metric(
+                "carbon_intensity_forecast_gco2_per_kwh",
+                "gauge",
+                "Forecast carbon intensity for the current settlement period",
+                vec![(String::new(), forecast.to_string())],
+            );
+        }
+
+        metric(
+            "carbon_generation_mix_percent",
+            "gauge",
+            "Share of current generation by fuel",
+            data.generation_mix
+                .iter()
+                .map(|fuel| {
+                    (
+                        format!(r#"{{fuel="{fuel}"}}"#, fuel = escape_label_value(&fuel.fuel)),
+                        fuel.perc.to_string(),
+                    )
+                })
+                .collect(),
+        );
+    }
+
+    metric(
+        "carbon_upstream_fetches_total",
+        "counter",
+        "Dashboard data fetches from the Carbon Intensity API",
+        vec![(String::new(), metrics.fetches.load(Ordering::Relaxed).to_string())],
+    );
+    metric(
+        "carbon_upstream_fetch_errors_total",
+        "counter",
+        "Dashboard data fetches that failed",
+        vec![(String::new(), metrics.errors.load(Ordering::Relaxed).to_string())],
+    );
+    metric(
+        "carbon_upstream_fetch_duration_seconds_total",
+        "counter",
+        "Total time spent fetching dashboard data",
+        vec![(
+            String::new(),
+            (metrics.duration_micros.load(Ordering::Relaxed) as f64 / 1_000_000.0).to_string(),
+        )],
+    );
+    metric(
+        "carbon_requests_rejected_total",
+        "counter",
+        "Requests turned away by the rate limit, the concurrency cap or authentication",
+        vec![
+            (
+                r#"{reason="rate_limit"}"#.to_string(),
+                limits.rate_limited.load(Ordering::Relaxed).to_string(),
+            ),
+            (
+                r#"{reason="concurrency"}"#.to_string(),
+                limits.over_capacity.load(Ordering::Relaxed).to_string(),
+            ),
+            (
+                r#"{reason="unauthorised"}"#.to_string(),
+                limits.unauthorised.load(Ordering::Relaxed).to_string(),
+            ),
+        ],
+    );
+
+    out
+}
+
+/// Escape a Prometheus label value
+fn escape_label_value(value: &str) -> String {
+    value
+        .replace('\\', r"\\")
+        .replace('"', r#"\""#)
+        .replace('\n', r"\n")
+}
+
+/// `Server-Timing` header value splitting request time into the upstream
+/// fetch and the page render, shown in the browser devtools' timing panel
+fn server_timing(fetch: Duration, render: Duration) -> String {
+    format!(
+        r#"fetch;desc="Upstream API fetch";dur={fetch:.1}, render;desc="Page render";dur={render:.1}"#,
+        fetch = fetch.as_secs_f64() * 1000.0,
+        render = render.as_secs_f64() * 1000.0
+    )
+}
+
+/// Resolve the baseline to a value and a description for the headline
+pub fn baseline_reference(
+    baseline: &Baseline,
+    timeline_points: &[IntensityPoint],
+    today: chrono::NaiveDate,
+) -> Option<(f64, String)> {
+    match baseline {
+        Baseline::Daily => {
+            let actuals: Vec<i32> = timeline_points
+                .iter()
+                .filter(|p| !p.is_forecast() && local(p.from).date_naive() == today)
+                .map(|p| p.intensity)
+                .collect();
+
+            if actuals.is_empty() {
+                return None;
+            }
+
+            let average = actuals.iter().sum::<i32>() as f64 / actuals.len() as f64;
+            Some((average, "today's average".to_string()))
+        }
+        Baseline::Fixed(value) => Some((
+            *value as f64,
+            format!("the {value} {unit} baseline", value = units::figure(*value), unit = units::label()),
+        )),
+    }
+}
+
+/// Describe how far the current intensity is from the baseline, e.g.
+/// "12% below today's average"
+pub fn describe_baseline_delta(intensity: i32, baseline_value: f64, description: &str) -> Option<String> {
+    if baseline_value <= 0.0 {
+        return None;
+    }
+
+    let delta_percent = ((intensity as f64 - baseline_value) / baseline_value * 100.0).round();
+    let text = if delta_percent == 0.0 {
+        format!("level with {description}", description = description)
+    } else {
+        format!(
+            "{percent}% {direction} {description}",
+            percent = delta_percent.abs(),
+            direction = if delta_percent < 0.0 { "below" } else { "above" },
+            description = description
+        )
+    };
+
+    Some(text)
+}
+
+/// The intensity worked out from the mix, e.g. "Mix × factors: 182
+/// gCO₂/kWh", and how far it is from the reported figure when that's more
+/// than `threshold`. Empty without a mix.
+fn render_mix_check(intensity: Option<i32>, mix_intensity: Option<i32>, threshold: u32) -> String {
+    let Some(mix_intensity) = mix_intensity else {
+        return String::new();
+    };
+    let difference = intensity.map_or(0, |intensity| mix_intensity - intensity);
+    let (class, note) = if difference.unsigned_abs() > threshold {
+        (
+            "mix-check diverges",
+            format!(
+                ", {amount} {direction} the reported figure",
+                amount = units::figure(difference.abs()),
+                direction = if difference < 0 { "below" } else { "above" }
+            ),
+        )
+    } else {
+        ("mix-check", String::new())
+    };
+    format!(
+        r#"<div class="{class}" title="Each fuel's share of the mix times its carbon factor. The reported figure comes from the API's own model, which also counts embedded solar and wind, so small differences are normal.">Mix × factors: {mix_intensity} {unit}{note}</div>"#,
+        mix_intensity = units::figure(mix_intensity),
+        unit = units::label()
+    )
+}
+
+fn render_baseline_delta(
+    intensity: i32,
+    baseline: Option<&Baseline>,
+    timeline_points: &[IntensityPoint],
+) -> String {
+    let today = local(chrono::Utc::now()).date_naive();
+
+    baseline
+        .and_then(|baseline| baseline_reference(baseline, timeline_points, today))
+        .and_then(|(value, description)| describe_baseline_delta(intensity, value, &description))
+        .map(|text| format!(r#"<div class="baseline-delta">{text}</div>"#, text = text))
+        .unwrap_or_default()
+}
+
+/// The Agile price now and the best window for carbon and price together
+/// over the forecast, e.g. "Agile price now: 23.4p/kWh. Cheap and green 2h:
+/// 02:00–04:00 at 45 gCO₂/kWh and 12.3p/kWh". Empty without prices.
+fn render_price_window(data: &DashboardData, options: &DashboardOptions) -> String {
+    let now = chrono::Utc::now();
+    let Some(current) = data.prices.iter().find(|rate| rate.from <= now && now < rate.to) else {
+        return String::new();
+    };
+
+    let upcoming: Vec<IntensityPoint> = data
+        .timeline_points
+        .iter()
+        .filter(|point| point.to > now)
+        .cloned()
+        .collect();
+    let window = cheap_green_window(&upcoming, &data.prices, options.price_window, options.prices.price_weight())
+        .map(|window| {
+            format!(
+                " Cheap and green {duration}: <strong>{from}–{to}</strong> at {average} {unit} and {price:.1}p/kWh.",
+                duration = describe_duration(options.price_window),
+                from = local(window.window.from).format("%H:%M"),
+                to = local(window.window.to).format("%H:%M"),
+                average = units::figure(window.window.average),
+                unit = units::label(),
+                price = window.average_price
+            )
+        })
+        .unwrap_or_default();
+
+    format!(
+        r#"<div class="price-window">Agile price now: <strong>{price:.1}p/kWh</strong>.{window}</div>"#,
+        price = current.price
+    )
+}
+
+/// Pie chart plus legend for the generation mix card, laid out according to
+/// the legend position (or without a legend at all)
+fn render_generation_mix(
+    generation_mix: &[FuelSourceWithIntensity],
+    generation_history: &[GenerationMixPeriod],
+    legend: Option<LegendPosition>,
+) -> String {
+    let chart = format!(
+        r#"<div class="chart-container">
+                    <svg width="450" height="450" viewBox="0 0 500 500">
+                        {pie_chart}
+                    </svg>
+                </div>"#,
+        pie_chart = render_pie_chart(generation_mix)
+    );
+
+    let Some(position) = legend else {
+        return chart;
+    };
+
+    let legend = format!(
+        r#"<div class="legend">
+                    <div class="legend-items">
+                        {legend_items}
+                    </div>
+                </div>"#,
+        legend_items = render_legend(generation_mix, generation_history)
+    );
+
+    match position {
+        LegendPosition::Bottom => format!(
+            "{chart}
+                {legend}",
+            chart = chart,
+            legend = legend
+        ),
+        LegendPosition::Right => format!(
+            r#"<div class="legend-right">
+                {chart}
+                {legend}
+                </div>"#,
+            chart = chart,
+            legend = legend
+        ),
+    }
+}
+
+fn render_legend(
+    generation_mix: &[FuelSourceWithIntensity],
+    generation_history: &[GenerationMixPeriod],
+) -> String {
+    generation_mix
+        .iter()
+        .enumerate()
+        .map(|(i, fuel)| {
+            let color = mix_color(generation_mix, i);
+            let intensity_text = match fuel.carbon_intensity {
+                Some(carbon_intensity) => format!("{intensity} {unit}", intensity = units::figure(carbon_intensity), unit = units::label()),
+                None => "intensity unknown".to_string(),
+            };
+
+            // The history has the unknown fuels under their own names
+            let path = if fuel.fuel == UNKNOWN_FUEL {
+                String::new()
+            } else {
+                sparkline_path(
+                    &fuel_series(trailing_periods(generation_history, SPARKLINE_HISTORY), &fuel.fuel),
+                    SPARKLINE_WIDTH,
+                    SPARKLINE_HEIGHT,
+                )
+            };
+            let sparkline = if path.is_empty() {
+                String::new()
+            } else {
+                format!(
+                    r#"<svg class="sparkline" width="{width}" height="{height}" viewBox="-1 -1 {view_width} {view_height}"><title>{fuel_name} share, last {hours} hours</title><path d="{path}" style="stroke: {color}" stroke-width="1.5" fill="none"/></svg>"#,
+                    width = SPARKLINE_WIDTH,
+                    height = SPARKLINE_HEIGHT,
+                    view_width = SPARKLINE_WIDTH + 2.0,
+                    view_height = SPARKLINE_HEIGHT + 2.0,
+                    fuel_name = fuel.fuel,
+                    hours = SPARKLINE_HISTORY.num_hours(),
+                    path = path,
+                    color = color
+                )
+            };
+
+            format!(
+                r#"<div class="legend-item">
+                <div class="legend-color" style="background-color: {color}"></div>
+                <div class="legend-info">
+                    <span class="legend-label">{fuel_name}</span>
+                    <span class="legend-details">{percentage:.1}% • {intensity_text}</span>
+                </div>
+                {sparkline}
+            </div>"#,
+                color = color,
+                fuel_name = fuel.fuel,
+                percentage = fuel.perc,
+                intensity_text = intensity_text,
+                sparkline = sparkline
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("")
+}
+
+/// The dashboard's routes over a fresh cache, without the listener, the
+/// `/events` publisher or shutdown handling, e.g. for driving requests in
+/// tests
+pub fn router(options: DashboardOptions) -> Result<Router, CarbonError> {
+    Ok(routes(Arc::new(AppState::new(options)?)))
+}
+
+fn routes(state: Arc<AppState>) -> Router {
+    Router::new()
+        .route("/", get(serve_app))
+        .route("/api/data", get(serve_api_data))
+        .route("/api/intensity", get(serve_api_intensity))
+        .route("/api/generation", get(serve_api_generation))
+        .route("/api/timeline", get(serve_api_timeline))
+        .route("/api/stream", get(serve_api_stream))
+        .route("/api/advice/:appliance", get(serve_api_advice))
+        .route("/events", get(serve_events))
+        .route("/ws", get(serve_ws))
+        .route("/metrics", get(serve_metrics))
+        .route("/badge.svg", get(serve_badge))
+        .route("/chart.png", get(serve_chart_png))
+        .route("/chart.jpg", get(serve_chart_jpeg))
+        .route("/treemap.svg", get(serve_treemap))
+        .route("/eink", get(serve_eink))
+        .route("/calendar.ics", get(serve_calendar))
+        .route("/regions", get(serve_regions))
+        // Probes stay answerable however busy the server is, and the page's
+        // own assets and the API description don't count against its limits
+        // or need credentials. Checking credentials inside the limits means
+        // guesses are rate limited too
+        .route_layer(middleware::from_fn_with_state(state.clone(), require_auth))
+        .route_layer(middleware::from_fn_with_state(state.clone(), limit_requests))
+        .route("/static/:file", get(serve_static))
+        .route("/api/openapi.json", get(serve_openapi))
+        .route("/api/docs", get(serve_api_docs))
+        .route("/healthz", get(serve_healthz))
+        .route("/readyz", get(serve_readyz))
+        .layer(
+            ServiceBuilder::new()
+                .layer(SetRequestIdLayer::x_request_id(MakeRequestUuid))
+                .layer(
+                    TraceLayer::new_for_http()
+                        .make_span_with(request_span)
+                        .on_response(
+                            DefaultOnResponse::new()
+                                .level(Level::INFO)
+                                .latency_unit(LatencyUnit::Millis),
+                        ),
+                )
+                .layer(PropagateRequestIdLayer::x_request_id())
+                // Pages are mostly inline SVG, which compresses to a fraction;
+                // images and `/events` are left alone, and so is
+                // `/api/stream`, whose lines would wait in the compressor
+                .layer(
+                    CompressionLayer::new()
+                        .br(true)
+                        .gzip(true)
+                        .compress_when(DefaultPredicate::new().and(NotForContentType::const_new("application/x-ndjson"))),
+                ),
+        )
+        .with_state(state)
+}
+
+/// Span for each request, tagged with its `x-request-id` (the caller's, or a
+/// fresh UUID, and echoed in the response) so everything logged while
+/// serving it, upstream fetches included, can be found together
+fn request_span(request: &axum::http::Request<axum::body::Body>) -> Span {
+    let request_id = request
+        .headers()
+        .get("x-request-id")
+        .and_then(|id| id.to_str().ok())
+        .unwrap_or_default();
+    info_span!(
+        "request",
+        method = %request.method(),
+        uri = %request.uri(),
+        request_id
+    )
+}
+
+pub async fn run(options: DashboardOptions) -> Result<(), CarbonError> {
+    let options = options.with_appliances(load_appliances()?);
+    serve_dashboard(Arc::new(AppState::new(options)?)).await
+}
+
+/// The dashboard served from the local archive at `store`, which is brought
+/// up to date at startup and shortly after each settlement period begins:
+/// the `daemon` command. Page loads never wait on upstream.
+pub(crate) async fn run_daemon(
+    options: DashboardOptions,
+    store: Store,
+    backfill: TimeDelta,
+    failures: FailureArgs,
+) -> Result<(), CarbonError> {
+    let mut state = AppState::new(options.with_appliances(load_appliances()?))?;
+    // The archive holds national GB figures only
+    let Some(client) = state.client.carbon_intensity().cloned() else {
+        return Err(usage_error("daemon only works with --provider carbon-intensity"));
+    };
+    if state.options.region.selector().is_some() {
+        return Err(usage_error("daemon serves national figures; use web for --postcode or --region"));
+    }
+    if state.octopus.is_some() {
+        return Err(usage_error("daemon doesn't archive prices; use web for --agile-region"));
+    }
+
+    let store = Arc::new(std::sync::Mutex::new(store));
+    state.archive = Some(store.clone());
+    let state = Arc::new(state);
+    let mut poller = tokio::spawn(poll_archive(state.clone(), client, store, backfill, failures.count()));
+    tokio::select! {
+        result = serve_dashboard(state) => {
+            poller.abort();
+            result
+        }
+        // Only ends by giving up after --max-failures
+        joined = &mut poller => Err(joined.unwrap_or_else(|e| std::panic::resume_unwind(e.into_panic()))),
+    }
+}
+
+fn check_options(options: &DashboardOptions) -> Result<(), CarbonError> {
+    if let (Some(y_min), Some(y_max)) = (options.y_min, options.y_max)
+        && y_min >= y_max
+    {
+        return Err(usage_error(format!("--y-min ({y_min}) must be below --y-max ({y_max})")));
+    }
+
+    let mut warnings = Warnings::default();
+    if options.min_contrast > 0.0 {
+        for theme in THEMES {
+            for detail in palette_contrast_warnings(&theme.palette, theme.card_background, options.min_contrast) {
+                warnings.raise(DataWarning::PaletteContrast { theme: theme.name, detail });
+            }
+        }
+    }
+
+    warnings.check()
+}
+
+/// Listen and serve until a shutdown signal, with live updates published
+async fn serve_dashboard(state: Arc<AppState>) -> Result<(), CarbonError> {
+    let options = &state.options;
+    let shutdown_timeout = options.shutdown_timeout;
+    let addr = options.bind;
+    options.log_effective();
+
+    // A socket unit's listener, in place of --bind, when systemd started us
+    let listener = match systemd::activated_listener()? {
+        Some(listener) => tokio::net::TcpListener::from_std(listener)
+            .map_err(|e| CarbonError::io("Failed to use the socket passed by systemd", e))?,
+        None => tokio::net::TcpListener::bind(addr)
+            .await
+            .map_err(|e| CarbonError::io(format!("Failed to listen on {addr}"), e))?,
+    };
+    let addr = listener.local_addr().unwrap_or(addr);
+    info!("Server running on http://{addr}");
+    systemd::notify_ready();
+
+    let shutdown = async move {
+        shutdown_signal().await;
+        info!("Shutting down, waiting up to {secs}s for open requests", secs = shutdown_timeout.as_secs());
+        systemd::notify_stopping();
+    };
+    serve_state(state, listener, shutdown).await;
+
+    Ok(())
+}
+
+/// The dashboard as `web` serves it, on a listener of the caller's choosing
+/// and until `shutdown` resolves rather than a signal: live updates are
+/// published and the connection options apply
+pub async fn serve_on(
+    listener: tokio::net::TcpListener,
+    options: DashboardOptions,
+    shutdown: impl std::future::Future<Output = ()>,
+) -> Result<(), CarbonError> {
+    serve_state(Arc::new(AppState::new(options)?), listener, shutdown).await;
+    Ok(())
+}
+
+async fn serve_state(
+    state: Arc<AppState>,
+    listener: tokio::net::TcpListener,
+    shutdown: impl std::future::Future<Output = ()>,
+) {
+    tokio::spawn(publish_dashboard_updates(state.clone()));
+    let app = routes(state.clone());
+    let options = &state.options;
+    let shutdown = async {
+        shutdown.await;
+        state.shutting_down.send_replace(true);
+    };
+    serve(listener, app, connection_builder(options), options.tcp_nodelay, shutdown, options.shutdown_timeout).await;
+}
+
+/// Bring the archive up to date now and then just after each settlement
+/// period starts, the only time upstream is contacted. Each poll is counted
+/// in the metrics and health reports as an upstream fetch; a failed one is
+/// marked as a gap and retried at the next period while pages carry on from
+/// what's stored. Returns only on giving up after `--max-failures`.
+async fn poll_archive(
+    state: Arc<AppState>,
+    client: CarbonClient,
+    store: Arc<std::sync::Mutex<Store>>,
+    backfill: TimeDelta,
+    mut failures: FailureCount,
+) -> CarbonError {
+    loop {
+        let at = Utc::now();
+        let started = Instant::now();
+        let result = archive_pass(&client, &store, backfill).await;
+        let elapsed = started.elapsed();
+        if result.is_ok() {
+            info!(elapsed_ms = elapsed.as_millis() as u64, "Archive poll finished");
+        }
+        state
+            .metrics
+            .record(elapsed, result.as_ref().err().map(|e| e.to_string()));
+        if let Err(e) = failures.record(&mut lock(&store), at, result) {
+            return e;
+        }
+        refresh_and_publish(&state).await;
+
+        let now = Utc::now();
+        let wake = next_poll(now, SETTLEMENT_PERIOD);
+        info!(%wake, "Next archive poll");
+        tokio::time::sleep((wake - now).to_std().unwrap_or_default()).await;
+    }
+}
+
+/// One recording pass as in `archive record`, plus the forecast for the
+/// timeline. Upstream is fetched without holding the lock, so pages keep
+/// loading from the store meanwhile.
+#[instrument(name = "archive_poll", skip_all)]
+async fn archive_pass(
+    client: &CarbonClient,
+    store: &std::sync::Mutex<Store>,
+    backfill: TimeDelta,
+) -> Result<(), CarbonError> {
+    let now = Utc::now();
+    let from = record_from(lock(store).latest_intensity()?, now, backfill);
+
+    // Forecasts are replaced by actuals on later passes
+    let (_, timeline_to) = timeline_range(now);
+    let points = client.intensity_between(from, timeline_to).await?;
+    let periods = client.generation_between(from, now).await?;
+    let forecasts = client.forecast_48h(now).await?;
+
+    let mut store = lock(store);
+    store.record_intensity(&points)?;
+    store.record_generation(&periods)?;
+    store.record_forecasts(now, &forecasts)?;
+
+    Ok(())
+}
+
+/// Resolves on Ctrl-C (SIGINT) or, on Unix, SIGTERM as sent by Kubernetes and
+/// most service managers
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            error!(error = %e, "Failed to listen for Ctrl-C");
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(e) => {
+                error!(error = %e, "Failed to listen for SIGTERM");
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {}
+        _ = terminate => {}
+    }
+}
+
+/// HTTP connection settings derived from the server options. hyper starts
+/// the header read timeout whenever it waits for the next request on a
+/// connection, so it also closes kept-alive connections left idle that long.
+fn connection_builder(options: &DashboardOptions) -> Builder<TokioExecutor> {
+    let mut builder = Builder::new(TokioExecutor::new());
+    let header_read_timeout = (!options.header_read_timeout.is_zero()).then_some(options.header_read_timeout);
+    builder
+        .http1()
+        .keep_alive(!options.no_keep_alive)
+        .timer(TokioTimer::new())
+        .header_read_timeout(header_read_timeout);
+
+    builder
+}
+
+/// Accept loop equivalent to `axum::serve(...).with_graceful_shutdown(...)`,
+/// but with control over socket and connection options that `axum::serve`
+/// does not expose. Once `shutdown` resolves no new connections are
+/// accepted, idle ones are closed and in-flight requests get up to
+/// `shutdown_timeout` to finish.
+async fn serve(
+    listener: tokio::net::TcpListener,
+    app: Router,
+    connection_builder: Builder<TokioExecutor>,
+    tcp_nodelay: bool,
+    shutdown: impl std::future::Future<Output = ()>,
+    shutdown_timeout: Duration,
+) {
+    let graceful = GracefulShutdown::new();
+    tokio::pin!(shutdown);
+
+    loop {
+        let accepted = tokio::select! {
+            accepted = listener.accept() => accepted,
+            _ = &mut shutdown => break,
+        };
+        let (stream, remote_addr) = match accepted {
+            Ok(connection) => connection,
+            Err(e) => {
+                warn!(error = %e, "Error accepting connection");
+                tokio::time::sleep(Duration::from_secs(1)).await;
+                continue;
+            }
+        };
+
+        if let Err(e) = stream.set_nodelay(tcp_nodelay) {
+            warn!(%remote_addr, error = %e, "Failed to set TCP_NODELAY");
+        }
+
+        // What axum::serve provides as `ConnectInfo`, for per-client limits
+        let service = TowerToHyperService::new(AddExtension::new(app.clone(), ConnectInfo(remote_addr)));
+        let connection_builder = connection_builder.clone();
+        let watcher = graceful.watcher();
+        tokio::spawn(async move {
+            let connection =
+                connection_builder.serve_connection_with_upgrades(TokioIo::new(stream), service);
+            if let Err(e) = watcher.watch(connection).await {
+                warn!(%remote_addr, error = %e, "Connection error");
+            }
+        });
+    }
+
+    drop(listener);
+    tokio::select! {
+        _ = graceful.shutdown() => info!("All connections closed"),
+        _ = tokio::time::sleep(shutdown_timeout) => {
+            warn!("Timed out waiting for connections to close");
+        }
+    }
+}
+
@@ -0,0 +1,438 @@
+use crate::error::CarbonError;
+use crate::model::{
+    CarbonFactors, CarbonFactorsData, CarbonIntensityData, FuelSource, GenerationMixData,
+    GenerationMixPeriod, GenerationMixRangeData, IntensityPoint, MAX_INTENSITY_RANGE,
+    RegionSelector, RegionalData, RegionalReading, RegionalSnapshot, SingleRegionData,
+    SingleRegionEntry, SingleRegionRangeData, format_api_datetime,
+};
+use chrono::{DateTime, Utc};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use serde::de::DeserializeOwned;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Semaphore;
+use tracing::{info, instrument, trace, warn};
+
+/// Public Carbon Intensity API
+pub const DEFAULT_BASE_URL: &str = "https://api.carbonintensity.org.uk";
+
+/// Attempts per request, including the first
+pub const DEFAULT_MAX_ATTEMPTS: u32 = 3;
+
+/// Per-request timeout, covering connect through reading the body
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Wait before the first retry; doubled for each retry after that
+pub const DEFAULT_RETRY_BACKOFF: Duration = Duration::from_millis(500);
+
+/// Longest wait between attempts, however many retries deep
+pub const MAX_RETRY_DELAY: Duration = Duration::from_secs(30);
+
+/// Typed client for the UK Carbon Intensity API.
+///
+/// Cloning is cheap and clones share the underlying connection pool and any
+/// in-flight request limit.
+#[derive(Clone, Debug)]
+pub struct CarbonClient {
+    http: reqwest::Client,
+    base_url: String,
+    /// Sent with every request, e.g. an API key
+    headers: HeaderMap,
+    in_flight: Option<Arc<Semaphore>>,
+    max_attempts: u32,
+    timeout: Duration,
+    retry_backoff: Duration,
+}
+
+impl Default for CarbonClient {
+    fn default() -> Self {
+        CarbonClient::new()
+    }
+}
+
+impl CarbonClient {
+    /// Client for the public API at [`DEFAULT_BASE_URL`]
+    pub fn new() -> Self {
+        CarbonClient::with_base_url(DEFAULT_BASE_URL)
+    }
+
+    /// Client for an API at another address, e.g. a local mock
+    pub fn with_base_url(base_url: impl Into<String>) -> Self {
+        CarbonClient {
+            http: reqwest::Client::new(),
+            base_url: base_url.into().trim_end_matches('/').to_string(),
+            headers: HeaderMap::new(),
+            in_flight: None,
+            max_attempts: DEFAULT_MAX_ATTEMPTS,
+            timeout: DEFAULT_TIMEOUT,
+            retry_backoff: DEFAULT_RETRY_BACKOFF,
+        }
+    }
+
+    /// Allow at most `limit` requests in flight at once across this client and
+    /// its clones; further requests wait for a slot.
+    pub fn with_max_in_flight(mut self, limit: usize) -> Self {
+        self.in_flight = Some(Arc::new(Semaphore::new(limit)));
+        self
+    }
+
+    /// Total attempts per request (at least 1). Only timeouts, connection
+    /// failures and 5xx responses are retried; 4xx responses fail at once.
+    pub fn with_max_attempts(mut self, max_attempts: u32) -> Self {
+        self.max_attempts = max_attempts.max(1);
+        self
+    }
+
+    /// Per-request timeout. Not applied in the browser, where `fetch` has no
+    /// timeout of its own to set.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Wait before the first retry, doubling for each one after (up to
+    /// [`MAX_RETRY_DELAY`]). Each wait is jittered to between half and all of
+    /// that.
+    pub fn with_retry_backoff(mut self, retry_backoff: Duration) -> Self {
+        self.retry_backoff = retry_backoff;
+        self
+    }
+
+    /// Send `value` as header `name` with every request. It's marked
+    /// sensitive, so it's left out of `Debug` output.
+    pub(crate) fn with_sensitive_header(mut self, name: HeaderName, mut value: HeaderValue) -> Self {
+        value.set_sensitive(true);
+        self.headers.insert(name, value);
+        self
+    }
+
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    /// Current national intensity in gCO₂/kWh (actual, or forecast if the
+    /// actual isn't published yet)
+    pub async fn current_intensity(&self) -> Result<i32, CarbonError> {
+        self.current_period().await.map(|point| point.intensity)
+    }
+
+    /// Current national settlement period, including forecast and index
+    pub async fn current_period(&self) -> Result<IntensityPoint, CarbonError> {
+        let response: CarbonIntensityData = self.get_json("/intensity").await?;
+
+        response
+            .data
+            .into_iter()
+            .find_map(IntensityPoint::from_entry)
+            .ok_or_else(|| CarbonError::EmptyData("current intensity".into()))
+    }
+
+    /// National intensity for each settlement period between `from` and `to`,
+    /// oldest first
+    pub async fn intensity_between(
+        &self,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<IntensityPoint>, CarbonError> {
+        let path = format!(
+            "/intensity/{from}/{to}",
+            from = format_api_datetime(from),
+            to = format_api_datetime(to)
+        );
+        let response: CarbonIntensityData = self.get_json(&path).await?;
+
+        let mut points: Vec<IntensityPoint> = response
+            .data
+            .into_iter()
+            .filter_map(IntensityPoint::from_entry)
+            .collect();
+        points.sort_by_key(|point| point.from);
+
+        Ok(points)
+    }
+
+    /// National forecast for each settlement period in the 48 hours from
+    /// `from`, oldest first
+    pub async fn forecast_48h(
+        &self,
+        from: DateTime<Utc>,
+    ) -> Result<Vec<IntensityPoint>, CarbonError> {
+        let path = format!("/intensity/{from}/fw48h", from = format_api_datetime(from));
+        let response: CarbonIntensityData = self.get_json(&path).await?;
+
+        let mut points: Vec<IntensityPoint> = response
+            .data
+            .into_iter()
+            .filter_map(IntensityPoint::from_entry)
+            .collect();
+        points.sort_by_key(|point| point.from);
+
+        Ok(points)
+    }
+
+    /// Current national generation mix
+    pub async fn generation_mix(&self) -> Result<Vec<FuelSource>, CarbonError> {
+        let response: GenerationMixData = self.get_json("/generation").await?;
+        Ok(response.data.generation_mix)
+    }
+
+    /// National generation mix for each settlement period between `from` and
+    /// `to`, oldest first
+    pub async fn generation_between(
+        &self,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<GenerationMixPeriod>, CarbonError> {
+        let path = format!(
+            "/generation/{from}/{to}",
+            from = format_api_datetime(from),
+            to = format_api_datetime(to)
+        );
+        let mut response: GenerationMixRangeData = self.get_json(&path).await?;
+        response.data.sort_by_key(|period| period.from);
+
+        Ok(response.data)
+    }
+
+    /// Carbon intensity factors for each generation type
+    pub async fn factors(&self) -> Result<CarbonFactors, CarbonError> {
+        let response: CarbonFactorsData = self.get_json("/intensity/factors").await?;
+
+        response
+            .data
+            .into_iter()
+            .next()
+            .ok_or_else(|| CarbonError::EmptyData("carbon factors".into()))
+    }
+
+    /// Current intensity and generation mix for every region
+    pub async fn regional_intensities(&self) -> Result<RegionalSnapshot, CarbonError> {
+        let response: RegionalData = self.get_json("/regional").await?;
+
+        response
+            .data
+            .into_iter()
+            .next()
+            .ok_or_else(|| CarbonError::EmptyData("regional data".into()))
+    }
+
+    /// Current intensity and generation mix for the region covering a GB
+    /// postcode. Only the outward code is used, so `"RG10 9AA"` and `"rg10"`
+    /// both look up `RG10`.
+    pub async fn regional_intensity(&self, postcode: &str) -> Result<RegionalReading, CarbonError> {
+        let selector = RegionSelector::postcode(postcode)
+            .map_err(|_| CarbonError::UnknownRegion(format!("postcode '{postcode}'")))?;
+        self.regional_reading(&selector).await
+    }
+
+    /// Current intensity and generation mix for a region by the API's region
+    /// id (1-17)
+    pub async fn regional_intensity_by_id(
+        &self,
+        region_id: u32,
+    ) -> Result<RegionalReading, CarbonError> {
+        self.regional_reading(&RegionSelector::RegionId(region_id))
+            .await
+    }
+
+    /// Current intensity and generation mix for a selected region. An unknown
+    /// postcode or id comes back as an empty list rather than an error
+    /// status, so that case is reported as `UnknownRegion`.
+    pub async fn regional_reading(
+        &self,
+        selector: &RegionSelector,
+    ) -> Result<RegionalReading, CarbonError> {
+        let path = format!("/regional/{region}", region = selector.path());
+        let response: SingleRegionData = self.get_json(&path).await?;
+
+        response
+            .data
+            .into_iter()
+            .next()
+            .and_then(|entry| entry.into_reading())
+            .ok_or_else(|| CarbonError::UnknownRegion(selector.to_string()))
+    }
+
+    /// Intensity and generation mix for each settlement period between
+    /// `from` and `to` in a selected region, oldest first
+    pub async fn regional_between(
+        &self,
+        selector: &RegionSelector,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<SingleRegionEntry, CarbonError> {
+        let path = format!(
+            "/regional/intensity/{from}/{to}/{region}",
+            from = format_api_datetime(from),
+            to = format_api_datetime(to),
+            region = selector.path()
+        );
+        let mut response: SingleRegionRangeData = self.get_json(&path).await?;
+        response.data.data.sort_by_key(|period| period.from);
+
+        Ok(response.data)
+    }
+
+    /// Intensity for each settlement period between `from` and `to` in a
+    /// selected region, or nationally for `None`, oldest first
+    pub async fn intensity_between_for(
+        &self,
+        region: Option<&RegionSelector>,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<IntensityPoint>, CarbonError> {
+        match region {
+            Some(region) => Ok(self
+                .regional_between(region, from, to)
+                .await?
+                .data
+                .iter()
+                .filter_map(|period| period.to_intensity_point())
+                .collect()),
+            None => self.intensity_between(from, to).await,
+        }
+    }
+
+    /// [`intensity_between_for`](Self::intensity_between_for) over a range of
+    /// any length, fetched [`MAX_INTENSITY_RANGE`] at a time. Only periods
+    /// starting in `[from, to)` are kept, once each, oldest first, preferring
+    /// a copy with an actual value.
+    pub async fn intensity_range_for(
+        &self,
+        region: Option<&RegionSelector>,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<IntensityPoint>, CarbonError> {
+        let mut points = Vec::new();
+        let mut chunk_from = from;
+        while chunk_from < to {
+            let chunk_to = (chunk_from + MAX_INTENSITY_RANGE).min(to);
+            info!("Fetching {chunk_from} to {chunk_to}");
+            points.extend(self.intensity_between_for(region, chunk_from, chunk_to).await?);
+            chunk_from = chunk_to;
+        }
+
+        // Neighbouring chunks can both return the period on their shared
+        // boundary, and the API also returns periods overlapping the range
+        // ends. Where one copy has been measured and the other is still a
+        // forecast, the measured one is kept.
+        points.retain(|point| point.from >= from && point.from < to);
+        points.sort_by_key(|point| (point.from, point.actual.is_none()));
+        points.dedup_by_key(|point| point.from);
+
+        Ok(points)
+    }
+
+    /// [`generation_between`](Self::generation_between) over a range of any
+    /// length, fetched [`MAX_INTENSITY_RANGE`] at a time. Only periods
+    /// starting in `[from, to)` are kept, once each, oldest first.
+    pub async fn generation_range(
+        &self,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<GenerationMixPeriod>, CarbonError> {
+        let mut periods = Vec::new();
+        let mut chunk_from = from;
+        while chunk_from < to {
+            let chunk_to = (chunk_from + MAX_INTENSITY_RANGE).min(to);
+            info!("Fetching the generation mix from {chunk_from} to {chunk_to}");
+            periods.extend(self.generation_between(chunk_from, chunk_to).await?);
+            chunk_from = chunk_to;
+        }
+
+        periods.retain(|period| period.from >= from && period.from < to);
+        periods.sort_by_key(|period| period.from);
+        periods.dedup_by_key(|period| period.from);
+
+        Ok(periods)
+    }
+
+    async fn get_json<T: DeserializeOwned>(&self, path: &str) -> Result<T, CarbonError> {
+        let response_text = self.get_text(path, &[]).await?;
+        let url = format!("{base_url}{path}", base_url = self.base_url, path = path);
+
+        serde_json::from_str(&response_text).map_err(|source| CarbonError::Decode { url, source })
+    }
+
+    /// Body of `path` with `query` parameters, with this client's retries,
+    /// timeout and in-flight limit. The query is left out of logs and errors,
+    /// as it may carry credentials.
+    #[instrument(skip(self, query))]
+    pub(crate) async fn get_text(&self, path: &str, query: &[(&str, &str)]) -> Result<String, CarbonError> {
+        // Held across retries so a struggling API isn't hit harder
+        let _permit = match &self.in_flight {
+            Some(permits) => permits.acquire().await.ok(),
+            None => None,
+        };
+
+        let url = format!("{base_url}{path}", base_url = self.base_url, path = path);
+
+        let mut attempt = 1;
+        let response_text = loop {
+            match self.fetch_text(&url, query).await {
+                Ok(text) => break text,
+                Err(e) if attempt < self.max_attempts && is_retryable(&e) => {
+                    let delay = retry_delay(self.retry_backoff, attempt);
+                    warn!(
+                        "Request to {} failed (attempt {}/{}), retrying in {:?}: {}",
+                        url, attempt, self.max_attempts, delay, e
+                    );
+                    sleep(delay).await;
+                    attempt += 1;
+                }
+                Err(e) => return Err(e.into()),
+            }
+        };
+        trace!("Raw response body: {}", response_text);
+
+        Ok(response_text)
+    }
+
+    async fn fetch_text(&self, url: &str, query: &[(&str, &str)]) -> Result<String, reqwest::Error> {
+        trace!("Making API request to: {}", url);
+        let request = self.http.get(url).headers(self.headers.clone()).query(query);
+        // The browser's fetch has no per-request timeout, only its own
+        #[cfg(not(target_arch = "wasm32"))]
+        let request = request.timeout(self.timeout);
+        let response = request.send().await?;
+
+        trace!("Received response with status: {}", response.status());
+        response.error_for_status()?.text().await
+    }
+}
+
+/// Wait before retry number `retry` (1 for the first): exponential backoff
+/// with "equal jitter", a random wait between half and all of the delay, so
+/// clients that failed together don't all retry in lockstep
+fn retry_delay(backoff: Duration, retry: u32) -> Duration {
+    let delay = backoff
+        .saturating_mul(2u32.saturating_pow(retry - 1))
+        .min(MAX_RETRY_DELAY);
+    let half = delay / 2;
+    half + half.mul_f64(rand::random::<f64>())
+}
+
+/// Transient failures worth another attempt: timeouts, failures to connect or
+/// send, and server errors. Client errors (4xx) won't change on retry.
+fn is_retryable(error: &reqwest::Error) -> bool {
+    // The browser's fetch doesn't tell connection failures apart from other
+    // failed requests
+    #[cfg(target_arch = "wasm32")]
+    let is_connect = false;
+    #[cfg(not(target_arch = "wasm32"))]
+    let is_connect = error.is_connect();
+
+    match error.status() {
+        Some(status) => status.is_server_error(),
+        None => error.is_timeout() || is_connect || error.is_request(),
+    }
+}
+
+/// Wait between attempts, on browser timers with `wasm` since there's no
+/// tokio runtime there
+async fn sleep(duration: Duration) {
+    #[cfg(feature = "wasm")]
+    gloo_timers::future::sleep(duration).await;
+    #[cfg(not(feature = "wasm"))]
+    tokio::time::sleep(duration).await;
+}
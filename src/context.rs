@@ -0,0 +1,65 @@
+//! Where an intensity figure sits among stored history. "143 gCO₂/kWh" means
+//! little on its own; "greener than 71% of the past year" says whether now
+//! is a good time.
+
+use crate::model::IntensityPoint;
+use chrono::{DateTime, TimeDelta, Utc};
+use serde::Serialize;
+
+/// How far back a figure is compared
+pub const CONTEXT_WINDOW: TimeDelta = TimeDelta::days(365);
+
+/// Fewest stored periods worth comparing against: one day's
+const MIN_PERIODS: usize = 48;
+
+/// The share of stored periods a figure beats, and that beat it
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[cfg_attr(feature = "native", derive(utoipa::ToSchema))]
+pub struct IntensityContext {
+    /// Percentage of periods with a higher intensity, rounded down
+    pub greener_than: u32,
+    /// Percentage of periods with a lower intensity, rounded down
+    pub dirtier_than: u32,
+    pub periods: usize,
+    /// Start of the oldest period compared against
+    pub since: DateTime<Utc>,
+}
+
+impl IntensityContext {
+    /// Rank `intensity` among `history`, oldest first, or `None` with less
+    /// than a day of it. Periods with the same figure count on neither side.
+    pub fn of(intensity: i32, history: &[IntensityPoint]) -> Option<IntensityContext> {
+        let since = history.first()?.from;
+        if history.len() < MIN_PERIODS {
+            return None;
+        }
+
+        let higher = history.iter().filter(|point| point.intensity > intensity).count();
+        let lower = history.iter().filter(|point| point.intensity < intensity).count();
+        let percent = |count: usize| (count * 100 / history.len()) as u32;
+        Some(IntensityContext {
+            greener_than: percent(higher),
+            dirtier_than: percent(lower),
+            periods: history.len(),
+            since,
+        })
+    }
+
+    /// E.g. "greener than 71% of the past year", or "dirtier than 88% of the
+    /// past 30 days" when the figure is on the high side. The span is what
+    /// history there was, up to [`CONTEXT_WINDOW`].
+    pub fn describe(&self, now: DateTime<Utc>) -> String {
+        let days = (now - self.since + TimeDelta::hours(12)).num_days();
+        let span = match days {
+            // Close enough, allowing for gaps at either end
+            360.. => "the past year".to_string(),
+            ..=1 => "the past day".to_string(),
+            days => format!("the past {days} days"),
+        };
+        if self.greener_than >= self.dirtier_than {
+            format!("greener than {percent}% of {span}", percent = self.greener_than)
+        } else {
+            format!("dirtier than {percent}% of {span}", percent = self.dirtier_than)
+        }
+    }
+}
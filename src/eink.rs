@@ -0,0 +1,104 @@
+//! The e-ink screen: the headline intensity, its band and the next green
+//! window, laid out in black and white for e-paper displays, which are slow
+//! enough to refresh that they're best polled every half hour
+
+use crate::chart::escape_xml;
+use crate::cli::timezone::local;
+use crate::model::IntensityIndex;
+use crate::schedule::Window;
+use crate::units;
+use chrono::{DateTime, Utc};
+
+// `/eink`'s default size: 7.5" Waveshare and Inky Impression panels
+pub const EINK_SIZE: (u32, u32) = (800, 480);
+
+/// What the e-ink screen shows
+pub struct EinkScreen {
+    pub area: String,
+    pub intensity: Option<i32>,
+    pub index: Option<IntensityIndex>,
+    /// e.g. "Next green window 13:00–16:30"
+    pub green_window: String,
+    pub updated: DateTime<Utc>,
+}
+
+/// e.g. "Green now until 16:30", "Next green window 13:00–16:30" or, on
+/// another day, "Next green window Tue 02:00–05:30", in the display zone
+pub fn describe_green_window(windows: &[Window], now: DateTime<Utc>) -> String {
+    let Some(window) = windows.iter().find(|window| window.to > now) else {
+        return "No green window in the next 48 hours".to_string();
+    };
+    let (from, to) = (local(window.from), local(window.to));
+    if window.from <= now {
+        format!("Green now until {to}", to = to.format("%H:%M"))
+    } else if from.date_naive() == local(now).date_naive() {
+        format!("Next green window {from}–{to}", from = from.format("%H:%M"), to = to.format("%H:%M"))
+    } else {
+        format!("Next green window {from}–{to}", from = from.format("%a %H:%M"), to = to.format("%H:%M"))
+    }
+}
+
+/// Black on white, centred lines: the area, the intensity in big figures,
+/// its band reversed out of a black box, the green window and when it was
+/// drawn. Sized for `width` by `height`, with the design's 800×480 scaled to
+/// fit and centred.
+pub fn render_eink(screen: &EinkScreen, (width, height): (u32, u32), large: bool) -> String {
+    // Font sizes in the 800×480 design: area, figure, unit, band, window,
+    // updated
+    let sizes = if large { [40.0, 200.0, 34.0, 52.0, 40.0, 28.0] } else { [30.0, 170.0, 28.0, 40.0, 30.0, 20.0] };
+    // (text, font size, bold, boxed)
+    let mut lines = vec![(format!("{area} carbon intensity", area = screen.area), sizes[0], false, false)];
+    match screen.intensity {
+        Some(intensity) => {
+            lines.push((units::figure(intensity), sizes[1], true, false));
+            lines.push((units::label().to_string(), sizes[2], false, false));
+        }
+        None => lines.push(("Intensity unavailable".to_string(), sizes[3], true, false)),
+    }
+    if let Some(index) = screen.index {
+        lines.push((index.as_str().to_uppercase(), sizes[3], true, true));
+    }
+    lines.push((screen.green_window.clone(), sizes[4], false, false));
+    lines.push((
+        format!("Updated {time}", time = local(screen.updated).format("%H:%M")),
+        sizes[5],
+        false,
+        false,
+    ));
+
+    // Each line takes its font size plus a gap; boxes have padding too
+    let line_height = |size: f64, boxed: bool| if boxed { size * 1.6 } else { size * 1.15 };
+    let design_height: f64 = lines.iter().map(|(_, size, _, boxed)| line_height(*size, *boxed)).sum();
+    let scale = (width as f64 / EINK_SIZE.0 as f64).min(height as f64 / EINK_SIZE.1 as f64);
+    let centre = width as f64 / 2.0 / scale;
+    let mut y = (height as f64 / scale - design_height) / 2.0;
+
+    let mut body = String::new();
+    for (text, size, bold, boxed) in &lines {
+        let line = line_height(*size, *boxed);
+        // Baseline a little above the bottom of the line, clear of descenders
+        let baseline = y + line - (line - size * 0.72) / 2.0;
+        if *boxed {
+            let box_width = text.chars().count() as f64 * size * 0.75 + size;
+            body.push_str(&format!(
+                r#"<rect x="{x}" y="{y}" width="{box_width}" height="{box_height}" fill="black"/>"#,
+                x = centre - box_width / 2.0,
+                y = y + size * 0.1,
+                box_height = line - size * 0.2
+            ));
+        }
+        body.push_str(&format!(
+            r#"<text x="{centre}" y="{baseline}" font-family="Arial, sans-serif" font-size="{size}" font-weight="{weight}" fill="{fill}" text-anchor="middle">{text}</text>"#,
+            weight = if *bold { "bold" } else { "normal" },
+            fill = if *boxed { "white" } else { "black" },
+            text = escape_xml(text)
+        ));
+        y += line;
+    }
+
+    format!(
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="{height}" viewBox="0 0 {view_width} {view_height}">{body}</svg>"#,
+        view_width = width as f64 / scale,
+        view_height = height as f64 / scale
+    )
+}
@@ -0,0 +1,292 @@
+//! Client for the Electricity Maps API (<https://www.electricitymaps.com/>),
+//! for zones around the world. Electricity Maps publishes hourly lifecycle
+//! carbon intensity and a power breakdown per zone, and on plans that
+//! include it, the marginal intensity: what one more kWh used now would
+//! emit.
+
+use crate::CarbonClient;
+use crate::error::CarbonError;
+use crate::model::{
+    CarbonFactors, FuelSource, GenerationMixPeriod, IntensityIndex, IntensityPoint,
+};
+use crate::provider::{FUEL_FACTORS, FuelTotals, fuel_shares};
+use chrono::{DateTime, SecondsFormat, TimeDelta, Utc};
+use reqwest::header::{HeaderName, HeaderValue};
+use serde::Deserialize;
+use serde::de::DeserializeOwned;
+use std::collections::HashMap;
+use tracing::warn;
+
+/// Public Electricity Maps API
+pub const DEFAULT_ELECTRICITYMAPS_URL: &str = "https://api.electricitymap.org";
+
+// Every value covers an hour from its `datetime`
+const GRANULARITY: TimeDelta = TimeDelta::hours(1);
+
+/// Longest range `past-range` serves at hourly granularity
+const MAX_PAST_RANGE: TimeDelta = TimeDelta::days(10);
+
+/// Typed client for one zone's data from the Electricity Maps API. Requests
+/// go through a [`CarbonClient`] pointed at Electricity Maps, for the same
+/// retries, timeout and in-flight limit.
+#[derive(Clone, Debug)]
+pub struct ElectricityMapsClient {
+    http: CarbonClient,
+    zone: String,
+}
+
+impl ElectricityMapsClient {
+    /// `http` should have Electricity Maps' base URL, e.g.
+    /// [`DEFAULT_ELECTRICITYMAPS_URL`]; `zone` is a zone key such as `DE` or
+    /// `US-CAL-CISO`
+    pub fn new(http: CarbonClient, token: HeaderValue, zone: impl Into<String>) -> Self {
+        ElectricityMapsClient {
+            http: http.with_sensitive_header(HeaderName::from_static("auth-token"), token),
+            zone: zone.into(),
+        }
+    }
+
+    /// Limit concurrent requests, as with
+    /// [`CarbonClient::with_max_in_flight`]
+    pub fn with_max_in_flight(mut self, limit: usize) -> Self {
+        self.http = self.http.with_max_in_flight(limit);
+        self
+    }
+
+    pub fn zone(&self) -> &str {
+        &self.zone
+    }
+
+    /// Latest hour's intensity. Electricity Maps fills recent hours with its
+    /// own estimates until measured data arrives; both count as actual here.
+    pub async fn current_period(&self) -> Result<IntensityPoint, CarbonError> {
+        let latest: IntensityEntry = self.get("/v3/carbon-intensity/latest", &[]).await?;
+        latest.to_point().ok_or_else(|| {
+            CarbonError::EmptyData(format!("carbon intensity for {zone}", zone = self.zone))
+        })
+    }
+
+    /// Intensity for each hour between `from` and `to`, oldest first.
+    /// Forecasts aren't included, so nothing is returned after now.
+    pub async fn intensity_between(
+        &self,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<IntensityPoint>, CarbonError> {
+        let entries: Vec<IntensityEntry> = self
+            .past_range("/v3/carbon-intensity/past-range", from, to)
+            .await?;
+        Ok(entries
+            .iter()
+            .filter_map(IntensityEntry::to_point)
+            .collect())
+    }
+
+    /// Latest hour's marginal intensity, where the plan includes it
+    pub async fn marginal_period(&self) -> Result<IntensityPoint, CarbonError> {
+        let latest: MarginalEntry = self
+            .get("/v3/marginal-carbon-intensity/latest", &[])
+            .await?;
+        let intensity = latest.marginal_carbon_intensity.ok_or_else(|| {
+            CarbonError::EmptyData(format!("marginal intensity for {zone}", zone = self.zone))
+        })?;
+
+        Ok(IntensityPoint {
+            from: latest.datetime,
+            to: latest.datetime + GRANULARITY,
+            intensity,
+            actual: Some(intensity),
+            forecast: None,
+            index: Some(IntensityIndex::for_intensity(intensity)),
+        })
+    }
+
+    /// Latest hour's generation mix
+    pub async fn generation_mix(&self) -> Result<Vec<FuelSource>, CarbonError> {
+        let latest: BreakdownEntry = self.get("/v3/power-breakdown/latest", &[]).await?;
+        latest
+            .to_period()
+            .map(|period| period.generation_mix)
+            .ok_or_else(|| {
+                CarbonError::EmptyData(format!("generation data for {zone}", zone = self.zone))
+            })
+    }
+
+    /// Generation mix for each hour between `from` and `to`, oldest first,
+    /// with Electricity Maps' production types grouped into the GB API's fuel
+    /// names
+    pub async fn generation_between(
+        &self,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<GenerationMixPeriod>, CarbonError> {
+        let entries: Vec<BreakdownEntry> = self
+            .past_range("/v3/power-breakdown/past-range", from, to)
+            .await?;
+        Ok(entries
+            .iter()
+            .filter_map(BreakdownEntry::to_period)
+            .collect())
+    }
+
+    /// Electricity Maps publishes intensity directly rather than factors, so
+    /// these only colour the dashboard legend
+    pub async fn factors(&self) -> Result<CarbonFactors, CarbonError> {
+        Ok(FUEL_FACTORS)
+    }
+
+    /// Entries of a `past-range` endpoint from `from` to `to` (clamped to
+    /// now), fetched [`MAX_PAST_RANGE`] at a time, oldest first
+    async fn past_range<T: DeserializeOwned + Timestamped>(
+        &self,
+        path: &str,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<T>, CarbonError> {
+        let to = to.min(Utc::now());
+
+        let mut entries = Vec::new();
+        let mut chunk_start = from;
+        while chunk_start < to {
+            let chunk_end = (chunk_start + MAX_PAST_RANGE).min(to);
+            let (start, end) = (format_datetime(chunk_start), format_datetime(chunk_end));
+            let range: RangeData<T> = self.get(path, &[("start", &start), ("end", &end)]).await?;
+            entries.extend(range.data);
+            chunk_start = chunk_end;
+        }
+        entries.sort_by_key(Timestamped::datetime);
+        entries.dedup_by_key(|entry| entry.datetime());
+
+        Ok(entries)
+    }
+
+    async fn get<T: DeserializeOwned>(
+        &self,
+        path: &str,
+        query: &[(&str, &str)],
+    ) -> Result<T, CarbonError> {
+        let query: Vec<(&str, &str)> = [("zone", self.zone.as_str())]
+            .into_iter()
+            .chain(query.iter().copied())
+            .collect();
+        let body = self.http.get_text(path, &query).await?;
+
+        serde_json::from_str(&body).map_err(|source| CarbonError::Decode {
+            url: format!("{base_url}{path}", base_url = self.http.base_url()),
+            source,
+        })
+    }
+}
+
+/// Electricity Maps' production types grouped into the GB API's fuel names,
+/// as for ENTSO-E. Storage discharging counts as generation; charging shows
+/// up as negative values, which are left out.
+fn fuel_for_production_type(production_type: &str) -> Option<&'static str> {
+    let fuel = match production_type {
+        "biomass" => "biomass",
+        "coal" => "coal",
+        "gas" => "gas",
+        "oil" => "oil",
+        "hydro" | "hydro discharge" => "hydro",
+        "nuclear" => "nuclear",
+        "solar" => "solar",
+        "wind" => "wind",
+        "unknown" => "other",
+        "battery discharge" => "battery",
+        "geothermal" => "other renewable",
+        _ => return None,
+    };
+    Some(fuel)
+}
+
+/// `yyyy-MM-ddTHH:mm:ssZ`, as the range endpoints take
+fn format_datetime(datetime: DateTime<Utc>) -> String {
+    datetime.to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+/// Entries ordered and de-duplicated by their hour
+trait Timestamped {
+    fn datetime(&self) -> DateTime<Utc>;
+}
+
+/// Response from the `past-range` endpoints
+#[derive(Debug, Deserialize)]
+struct RangeData<T> {
+    data: Vec<T>,
+}
+
+/// One hour from `/v3/carbon-intensity/...`
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct IntensityEntry {
+    datetime: DateTime<Utc>,
+    /// gCO₂eq/kWh, lifecycle
+    carbon_intensity: Option<i32>,
+}
+
+impl IntensityEntry {
+    fn to_point(&self) -> Option<IntensityPoint> {
+        let intensity = self.carbon_intensity?;
+        Some(IntensityPoint {
+            from: self.datetime,
+            to: self.datetime + GRANULARITY,
+            intensity,
+            actual: Some(intensity),
+            forecast: None,
+            index: Some(IntensityIndex::for_intensity(intensity)),
+        })
+    }
+}
+
+impl Timestamped for IntensityEntry {
+    fn datetime(&self) -> DateTime<Utc> {
+        self.datetime
+    }
+}
+
+/// Response from `/v3/marginal-carbon-intensity/latest`
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct MarginalEntry {
+    datetime: DateTime<Utc>,
+    marginal_carbon_intensity: Option<i32>,
+}
+
+/// One hour from `/v3/power-breakdown/...`
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BreakdownEntry {
+    datetime: DateTime<Utc>,
+    /// MW per production type; `null` where the zone doesn't report it
+    #[serde(default)]
+    power_production_breakdown: HashMap<String, Option<f64>>,
+}
+
+impl BreakdownEntry {
+    fn to_period(&self) -> Option<GenerationMixPeriod> {
+        let mut totals = FuelTotals::new();
+        for (production_type, megawatts) in &self.power_production_breakdown {
+            let Some(megawatts) = megawatts.filter(|&megawatts| megawatts > 0.0) else {
+                continue;
+            };
+            match fuel_for_production_type(production_type) {
+                Some(fuel) => *totals.entry(fuel).or_default() += megawatts,
+                None => {
+                    warn!("Skipping unknown Electricity Maps production type {production_type}")
+                }
+            }
+        }
+
+        Some(GenerationMixPeriod {
+            from: self.datetime,
+            to: self.datetime + GRANULARITY,
+            generation_mix: fuel_shares(totals)?,
+        })
+    }
+}
+
+impl Timestamped for BreakdownEntry {
+    fn datetime(&self) -> DateTime<Utc> {
+        self.datetime
+    }
+}
@@ -0,0 +1,538 @@
+//! Client for the ENTSO-E Transparency Platform
+//! (<https://transparency.entsoe.eu/>), for grid data outside Great Britain.
+//! ENTSO-E publishes actual generation per production type for each bidding
+//! zone, but no carbon intensity, so the intensity is worked out from the mix
+//! with the same per-fuel factors the dashboard uses.
+
+use crate::CarbonClient;
+use crate::error::CarbonError;
+use crate::mix::{enrich_generation_mix, weighted_intensity};
+use crate::model::{
+    CarbonFactors, FuelSource, GenerationMixPeriod, IntensityIndex, IntensityPoint, parse_api_datetime,
+};
+use crate::provider::{FUEL_FACTORS, FuelTotals, fuel_shares};
+use crate::quality::Warnings;
+use chrono::{DateTime, TimeDelta, Utc};
+use serde::Deserialize;
+use serde::de::IgnoredAny;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use tracing::{trace, warn};
+
+/// Public ENTSO-E Transparency Platform API
+pub const DEFAULT_ENTSOE_URL: &str = "https://web-api.tp.entsoe.eu";
+
+// ENTSO-E's figures lag by an hour or so; look back far enough to find the
+// latest complete interval
+const LATEST_LOOKBACK: TimeDelta = TimeDelta::hours(6);
+
+/// An ENTSO-E bidding zone or control area
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Area {
+    /// Short code accepted on the command line, e.g. "DE"
+    pub code: &'static str,
+    /// ENTSO-E's EIC area code
+    pub eic: &'static str,
+    pub name: &'static str,
+}
+
+/// Areas that can be asked for by code. Countries split into several bidding
+/// zones are listed as the whole country where ENTSO-E publishes one.
+pub const AREAS: &[Area] = &[
+    Area {
+        code: "AT",
+        eic: "10YAT-APG------L",
+        name: "Austria",
+    },
+    Area {
+        code: "BE",
+        eic: "10YBE----------2",
+        name: "Belgium",
+    },
+    Area {
+        code: "BG",
+        eic: "10YCA-BULGARIA-R",
+        name: "Bulgaria",
+    },
+    Area {
+        code: "CH",
+        eic: "10YCH-SWISSGRIDZ",
+        name: "Switzerland",
+    },
+    Area {
+        code: "CZ",
+        eic: "10YCZ-CEPS-----N",
+        name: "Czechia",
+    },
+    Area {
+        code: "DE",
+        eic: "10Y1001A1001A82H",
+        name: "Germany and Luxembourg",
+    },
+    Area {
+        code: "DK1",
+        eic: "10YDK-1--------W",
+        name: "West Denmark",
+    },
+    Area {
+        code: "DK2",
+        eic: "10YDK-2--------M",
+        name: "East Denmark",
+    },
+    Area {
+        code: "EE",
+        eic: "10Y1001A1001A39I",
+        name: "Estonia",
+    },
+    Area {
+        code: "ES",
+        eic: "10YES-REE------0",
+        name: "Spain",
+    },
+    Area {
+        code: "FI",
+        eic: "10YFI-1--------U",
+        name: "Finland",
+    },
+    Area {
+        code: "FR",
+        eic: "10YFR-RTE------C",
+        name: "France",
+    },
+    Area {
+        code: "GR",
+        eic: "10YGR-HTSO-----Y",
+        name: "Greece",
+    },
+    Area {
+        code: "HR",
+        eic: "10YHR-HEP------M",
+        name: "Croatia",
+    },
+    Area {
+        code: "HU",
+        eic: "10YHU-MAVIR----U",
+        name: "Hungary",
+    },
+    Area {
+        code: "IE",
+        eic: "10Y1001A1001A59C",
+        name: "Ireland and Northern Ireland",
+    },
+    Area {
+        code: "IT",
+        eic: "10YIT-GRTN-----B",
+        name: "Italy",
+    },
+    Area {
+        code: "LT",
+        eic: "10YLT-1001A0008Q",
+        name: "Lithuania",
+    },
+    Area {
+        code: "LV",
+        eic: "10YLV-1001A00074",
+        name: "Latvia",
+    },
+    Area {
+        code: "NL",
+        eic: "10YNL----------L",
+        name: "Netherlands",
+    },
+    Area {
+        code: "NO",
+        eic: "10YNO-0--------C",
+        name: "Norway",
+    },
+    Area {
+        code: "PL",
+        eic: "10YPL-AREA-----S",
+        name: "Poland",
+    },
+    Area {
+        code: "PT",
+        eic: "10YPT-REN------W",
+        name: "Portugal",
+    },
+    Area {
+        code: "RO",
+        eic: "10YRO-TEL------P",
+        name: "Romania",
+    },
+    Area {
+        code: "SE",
+        eic: "10YSE-1--------K",
+        name: "Sweden",
+    },
+    Area {
+        code: "SI",
+        eic: "10YSI-ELES-----O",
+        name: "Slovenia",
+    },
+    Area {
+        code: "SK",
+        eic: "10YSK-SEPS-----K",
+        name: "Slovakia",
+    },
+];
+
+impl Area {
+    /// Area by code (any case) or EIC code
+    pub fn lookup(value: &str) -> Result<Area, String> {
+        AREAS
+            .iter()
+            .find(|area| area.code.eq_ignore_ascii_case(value) || area.eic == value)
+            .copied()
+            .ok_or_else(|| {
+                let codes: Vec<&str> = AREAS.iter().map(|area| area.code).collect();
+                format!(
+                    "Unknown ENTSO-E area '{value}': expected one of {codes}",
+                    codes = codes.join(", ")
+                )
+            })
+    }
+}
+
+impl fmt::Display for Area {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name)
+    }
+}
+
+/// Typed client for one area's data from the ENTSO-E API. Requests go through
+/// a [`CarbonClient`] pointed at ENTSO-E, for the same retries, timeout and
+/// in-flight limit.
+#[derive(Clone, Debug)]
+pub struct EntsoeClient {
+    http: CarbonClient,
+    token: String,
+    area: Area,
+}
+
+impl EntsoeClient {
+    /// `http` should have ENTSO-E's base URL, e.g. [`DEFAULT_ENTSOE_URL`]
+    pub fn new(http: CarbonClient, token: impl Into<String>, area: Area) -> Self {
+        EntsoeClient {
+            http,
+            token: token.into(),
+            area,
+        }
+    }
+
+    /// Limit concurrent requests, as with
+    /// [`CarbonClient::with_max_in_flight`]
+    pub fn with_max_in_flight(mut self, limit: usize) -> Self {
+        self.http = self.http.with_max_in_flight(limit);
+        self
+    }
+
+    pub fn area(&self) -> Area {
+        self.area
+    }
+
+    /// Intensity of the latest interval with complete generation data
+    pub async fn current_period(&self) -> Result<IntensityPoint, CarbonError> {
+        let now = Utc::now();
+        self.intensity_between(now - LATEST_LOOKBACK, now)
+            .await?
+            .pop()
+            .ok_or_else(|| self.no_data())
+    }
+
+    /// Intensity for each interval between `from` and `to` with complete
+    /// generation data, oldest first. Every value is an actual one; ENTSO-E
+    /// has no per-fuel forecast to work one out from.
+    pub async fn intensity_between(
+        &self,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<IntensityPoint>, CarbonError> {
+        let periods = self.generation_between(from, to).await?;
+
+        Ok(periods
+            .into_iter()
+            .map(|period| {
+                let intensity = mix_intensity(&period.generation_mix, &FUEL_FACTORS);
+                IntensityPoint {
+                    from: period.from,
+                    to: period.to,
+                    intensity,
+                    actual: Some(intensity),
+                    forecast: None,
+                    index: Some(IntensityIndex::for_intensity(intensity)),
+                }
+            })
+            .collect())
+    }
+
+    /// Generation mix of the latest interval with complete data
+    pub async fn generation_mix(&self) -> Result<Vec<FuelSource>, CarbonError> {
+        let now = Utc::now();
+        self.generation_between(now - LATEST_LOOKBACK, now)
+            .await?
+            .pop()
+            .map(|period| period.generation_mix)
+            .ok_or_else(|| self.no_data())
+    }
+
+    /// Generation mix for each interval between `from` and `to`, oldest
+    /// first, with ENTSO-E's production types grouped into the GB API's fuel
+    /// names. Only intervals that every production type has reported are
+    /// kept, since the latest ones fill in type by type.
+    pub async fn generation_between(
+        &self,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<GenerationMixPeriod>, CarbonError> {
+        // There's nothing published for the future, and asking for it can be
+        // refused
+        let to = to.min(Utc::now());
+        if from >= to {
+            return Ok(vec![]);
+        }
+
+        let (period_start, period_end) = (format_entsoe_datetime(from), format_entsoe_datetime(to));
+        let query = [
+            ("securityToken", self.token.as_str()),
+            // Actual generation per production type
+            ("documentType", "A75"),
+            ("processType", "A16"),
+            ("in_Domain", self.area.eic),
+            ("periodStart", period_start.as_str()),
+            ("periodEnd", period_end.as_str()),
+        ];
+        let body = self.http.get_text("/api", &query).await?;
+
+        // No data is answered with an acknowledgement rather than an error
+        // status
+        if body.contains("<Acknowledgement_MarketDocument") {
+            let acknowledgement: Acknowledgement =
+                quick_xml::de::from_str(&body).map_err(|e| self.decode_error(e))?;
+            let reason = acknowledgement
+                .reasons
+                .into_iter()
+                .filter_map(|reason| reason.text)
+                .collect::<Vec<_>>()
+                .join("; ");
+            trace!("ENTSO-E acknowledgement: {reason}");
+            return Err(if reason.is_empty() {
+                self.no_data()
+            } else {
+                CarbonError::EmptyData(format!(
+                    "generation data for {area}: {reason}",
+                    area = self.area
+                ))
+            });
+        }
+
+        let document: GenerationDocument =
+            quick_xml::de::from_str(&body).map_err(|e| self.decode_error(e))?;
+        Ok(generation_periods(document))
+    }
+
+    pub async fn factors(&self) -> Result<CarbonFactors, CarbonError> {
+        Ok(FUEL_FACTORS)
+    }
+
+    fn no_data(&self) -> CarbonError {
+        CarbonError::EmptyData(format!("generation data for {area}", area = self.area))
+    }
+
+    fn decode_error(&self, error: quick_xml::DeError) -> CarbonError {
+        CarbonError::DecodeXml {
+            url: format!("{base_url}/api", base_url = self.http.base_url()),
+            source: error,
+        }
+    }
+}
+
+/// Weighted intensity of a generation mix, to the nearest gCO₂/kWh. Any
+/// warnings are left for the command to fail on under `--strict`.
+fn mix_intensity(generation_mix: &[FuelSource], factors: &CarbonFactors) -> i32 {
+    let mut warnings = Warnings::default();
+    let enriched = enrich_generation_mix(generation_mix.to_vec(), factors, false, &mut warnings);
+    warnings.defer();
+    weighted_intensity(&enriched).map_or(0, |intensity| intensity.round() as i32)
+}
+
+/// ENTSO-E's production types (psrType) grouped into the GB API's fuel
+/// names, so the mix gets the same factors and colours. Renewables without a
+/// GB equivalent share one zero-carbon entry.
+fn fuel_for_psr_type(psr_type: &str) -> Option<&'static str> {
+    let fuel = match psr_type {
+        "B01" => "biomass",
+        // Lignite, coal-derived gas, hard coal, peat
+        "B02" | "B03" | "B05" | "B08" => "coal",
+        "B04" => "gas",
+        // Oil, oil shale
+        "B06" | "B07" => "oil",
+        "B10" => "pumped storage",
+        // Run-of-river, reservoir
+        "B11" | "B12" => "hydro",
+        "B14" => "nuclear",
+        "B16" => "solar",
+        // Offshore, onshore
+        "B18" | "B19" => "wind",
+        // Waste, other
+        "B17" | "B20" => "other",
+        // Energy storage, mostly batteries
+        "B25" => "battery",
+        // Geothermal, marine, other renewable
+        "B09" | "B13" | "B15" => "other renewable",
+        _ => return None,
+    };
+    Some(fuel)
+}
+
+/// Sum each production type's output into fuels per interval, as shares of
+/// the interval's total
+fn generation_periods(document: GenerationDocument) -> Vec<GenerationMixPeriod> {
+    let mut intervals: BTreeMap<(DateTime<Utc>, DateTime<Utc>), FuelTotals> = BTreeMap::new();
+    let mut psr_types_by_interval: HashMap<DateTime<Utc>, Vec<String>> = HashMap::new();
+
+    for series in document.time_series {
+        // Series out of the zone are consumption, e.g. pumped storage
+        // pumping
+        if series.out_domain.is_some() {
+            continue;
+        }
+        let Some(psr_type) = series.psr.map(|psr| psr.psr_type) else {
+            continue;
+        };
+        let Some(fuel) = fuel_for_psr_type(&psr_type) else {
+            warn!("Skipping unknown ENTSO-E production type {psr_type}");
+            continue;
+        };
+
+        for period in series.periods {
+            let Some((start, end, resolution)) = period.span() else {
+                warn!("Skipping ENTSO-E period with unreadable times: {period:?}");
+                continue;
+            };
+            let quantities: HashMap<i64, f64> = period
+                .points
+                .iter()
+                .map(|point| (point.position, point.quantity))
+                .collect();
+
+            // Positions left out repeat the last value given (curve type A03)
+            let mut last = None;
+            let slots = (end - start).num_seconds() / resolution.num_seconds();
+            for position in 1..=slots {
+                let Some(quantity) = quantities.get(&position).copied().or(last) else {
+                    continue;
+                };
+                last = Some(quantity);
+                let from = start + resolution * (position - 1) as i32;
+                *intervals
+                    .entry((from, from + resolution))
+                    .or_default()
+                    .entry(fuel)
+                    .or_default() += quantity;
+                psr_types_by_interval
+                    .entry(from)
+                    .or_default()
+                    .push(psr_type.clone());
+            }
+        }
+    }
+
+    let complete = psr_types_by_interval
+        .values()
+        .map(Vec::len)
+        .max()
+        .unwrap_or_default();
+
+    intervals
+        .into_iter()
+        .filter(|((from, _), _)| psr_types_by_interval.get(from).map(Vec::len) == Some(complete))
+        .filter_map(|((from, to), fuels)| {
+            Some(GenerationMixPeriod {
+                from,
+                to,
+                generation_mix: fuel_shares(fuels)?,
+            })
+        })
+        .collect()
+}
+
+/// ENTSO-E's `yyyyMMddHHmm` UTC query format
+fn format_entsoe_datetime(datetime: DateTime<Utc>) -> String {
+    datetime.format("%Y%m%d%H%M").to_string()
+}
+
+/// `GL_MarketDocument`: generation per production type
+#[derive(Debug, Deserialize)]
+struct GenerationDocument {
+    #[serde(rename = "TimeSeries", default)]
+    time_series: Vec<TimeSeries>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TimeSeries {
+    #[serde(rename = "outBiddingZone_Domain.mRID")]
+    out_domain: Option<IgnoredAny>,
+    #[serde(rename = "MktPSRType")]
+    psr: Option<MktPsrType>,
+    #[serde(rename = "Period", default)]
+    periods: Vec<Period>,
+}
+
+#[derive(Debug, Deserialize)]
+struct MktPsrType {
+    #[serde(rename = "psrType")]
+    psr_type: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Period {
+    #[serde(rename = "timeInterval")]
+    interval: TimeInterval,
+    /// ISO 8601 duration, e.g. `PT15M` or `PT60M`
+    resolution: String,
+    #[serde(rename = "Point", default)]
+    points: Vec<Point>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TimeInterval {
+    start: String,
+    end: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Point {
+    position: i64,
+    /// Megawatts
+    quantity: f64,
+}
+
+impl Period {
+    /// Start, end and resolution, or `None` if any can't be read
+    fn span(&self) -> Option<(DateTime<Utc>, DateTime<Utc>, TimeDelta)> {
+        let parse = |value: &str| parse_api_datetime(value).ok();
+        let (start, end) = (parse(&self.interval.start)?, parse(&self.interval.end)?);
+
+        let duration = self.resolution.strip_prefix("PT")?;
+        let resolution = match duration.split_at(duration.len().checked_sub(1)?) {
+            (minutes, "M") => TimeDelta::minutes(minutes.parse().ok()?),
+            (hours, "H") => TimeDelta::hours(hours.parse().ok()?),
+            _ => return None,
+        };
+
+        (start < end && resolution > TimeDelta::zero()).then_some((start, end, resolution))
+    }
+}
+
+/// `Acknowledgement_MarketDocument`: sent instead of data, e.g. when none
+/// matches the query
+#[derive(Debug, Deserialize)]
+struct Acknowledgement {
+    #[serde(rename = "Reason", default)]
+    reasons: Vec<Reason>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Reason {
+    text: Option<String>,
+}
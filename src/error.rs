@@ -0,0 +1,182 @@
+use crate::quality::DataWarning;
+use std::path::PathBuf;
+
+/// Errors from [`CarbonClient`](crate::CarbonClient) and the command-line
+/// tools built on it. Each kind of failure exits with its own status (see
+/// [`CarbonError::exit_code`]), so scripts can tell them apart.
+#[derive(Debug, thiserror::Error)]
+pub enum CarbonError {
+    /// The API couldn't be reached: connection failure, timeout, or the
+    /// connection dropped mid-response
+    #[error("HTTP request failed: {0}")]
+    Network(#[source] reqwest::Error),
+    /// The API answered with a 4xx or 5xx status
+    #[error("API returned {status} for {url}")]
+    Upstream {
+        status: reqwest::StatusCode,
+        url: String,
+    },
+    /// The response body was not the JSON shape we expected
+    #[error("Failed to decode API response from {url}: {source}")]
+    Decode {
+        url: String,
+        source: serde_json::Error,
+    },
+    /// The response body was not the XML shape we expected (ENTSO-E)
+    #[error("Failed to decode API response from {url}: {source}")]
+    DecodeXml {
+        url: String,
+        source: quick_xml::DeError,
+    },
+    /// The API answered successfully but without the data asked for
+    #[error("API returned no {0}")]
+    EmptyData(String),
+    /// No region matched the postcode or region id asked for
+    #[error("No region found for {0}")]
+    UnknownRegion(String),
+    /// A date or time that couldn't be parsed
+    #[error("{0}")]
+    DateParse(String),
+    /// Invalid arguments, reported like clap's own errors
+    #[cfg(feature = "native")]
+    #[error(transparent)]
+    Usage(#[from] clap::Error),
+    /// Output couldn't be encoded as JSON
+    #[error("Failed to encode JSON: {0}")]
+    Json(#[from] serde_json::Error),
+    /// The local archive database
+    #[cfg(feature = "native")]
+    #[error("Archive database error: {0}")]
+    Database(#[from] rusqlite::Error),
+    /// Opening the archive database
+    #[cfg(feature = "native")]
+    #[error("Failed to open archive '{path}': {source}", path = path.display())]
+    OpenArchive {
+        path: PathBuf,
+        source: rusqlite::Error,
+    },
+    /// Writing a Parquet or Arrow file
+    #[cfg(feature = "native")]
+    #[error("Failed to write '{path}': {source}", path = path.display())]
+    Export {
+        path: PathBuf,
+        source: parquet::errors::ParquetError,
+    },
+    /// The mail server couldn't be reached or refused a report
+    #[cfg(feature = "native")]
+    #[error("Failed to send email: {0}")]
+    Email(#[source] lettre::transport::smtp::Error),
+    /// Reading or writing a local file or socket
+    #[error("{context}: {source}")]
+    Io {
+        context: String,
+        source: std::io::Error,
+    },
+    /// The `carbon alert` rules file couldn't be read or has a mistake
+    #[error("Invalid alert rules in '{path}': {message}", path = path.display())]
+    AlertRules { path: PathBuf, message: String },
+    /// The `web --factors` override file couldn't be read or has a mistake
+    #[error("Invalid carbon factors in '{path}': {message}", path = path.display())]
+    FactorOverrides { path: PathBuf, message: String },
+    /// The `--seed-history` file couldn't be read or isn't an export or
+    /// archive
+    #[error("Invalid seed history in '{path}': {message}", path = path.display())]
+    SeedHistory { path: PathBuf, message: String },
+    /// A command started by `carbon run` failed; its status is passed on
+    #[error("'{command}' exited with status {code}")]
+    CommandFailed { command: String, code: i32 },
+    /// One fetch's failure, reported to every request that waited on it
+    #[error(transparent)]
+    Shared(std::sync::Arc<CarbonError>),
+    /// A poller gave up after `--max-failures` passes failed in a row
+    #[error("Giving up after {failures} failed passes in a row: {last}")]
+    TooManyFailures {
+        failures: u32,
+        #[source]
+        last: Box<CarbonError>,
+    },
+    /// Data-quality warnings raised under `--strict`
+    #[error("--strict: {warnings}", warnings = describe_warnings(.0))]
+    DataQuality(Vec<DataWarning>),
+}
+
+impl CarbonError {
+    /// Process exit status for a command that failed with this error:
+    ///
+    /// | Status | Failure |
+    /// |--------|---------|
+    /// | 1 | anything local: files, the archive, encoding output |
+    /// | 2 | invalid arguments, alert rules, factor overrides or seed history |
+    /// | 3 | network: the API or mail server couldn't be reached |
+    /// | 4 | the API answered with an error status |
+    /// | 5 | the API's response couldn't be decoded |
+    /// | 6 | the API had no data for the request, or no such region |
+    /// | 7 | a date or time couldn't be parsed |
+    /// | 8 | a data-quality warning, with `--strict` |
+    ///
+    /// A command run by `carbon run` that fails exits with its own status,
+    /// and a poller that gives up after `--max-failures` with its last
+    /// failure's.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            #[cfg(feature = "native")]
+            CarbonError::Usage(e) => e.exit_code(),
+            CarbonError::AlertRules { .. }
+            | CarbonError::FactorOverrides { .. }
+            | CarbonError::SeedHistory { .. } => 2,
+            CarbonError::Network(_) => 3,
+            #[cfg(feature = "native")]
+            CarbonError::Email(_) => 3,
+            CarbonError::Upstream { .. } => 4,
+            CarbonError::Decode { .. } | CarbonError::DecodeXml { .. } => 5,
+            CarbonError::EmptyData(_) | CarbonError::UnknownRegion(_) => 6,
+            CarbonError::DateParse(_) => 7,
+            CarbonError::DataQuality(_) => 8,
+            CarbonError::CommandFailed { code, .. } => *code,
+            CarbonError::Shared(e) => e.exit_code(),
+            CarbonError::TooManyFailures { last, .. } => last.exit_code(),
+            #[cfg(feature = "native")]
+            CarbonError::Database(_) | CarbonError::OpenArchive { .. } | CarbonError::Export { .. } => 1,
+            CarbonError::Json(_) | CarbonError::Io { .. } => 1,
+        }
+    }
+
+    /// An I/O failure, described by what was being attempted
+    pub fn io(context: impl Into<String>, source: std::io::Error) -> Self {
+        CarbonError::Io {
+            context: context.into(),
+            source,
+        }
+    }
+}
+
+/// e.g. "no carbon factor for 'tidal'; showing it as unknown; generation mix
+/// adds up to 97.0%, not 100%"
+fn describe_warnings(warnings: &[DataWarning]) -> String {
+    warnings.iter().map(DataWarning::to_string).collect::<Vec<_>>().join("; ")
+}
+
+/// Error statuses become [`CarbonError::Upstream`]; anything else failed to
+/// reach the API at all
+impl From<reqwest::Error> for CarbonError {
+    fn from(e: reqwest::Error) -> Self {
+        // Query strings can carry credentials (ENTSO-E's security token), so
+        // they're kept out of error messages
+        let has_query = e.url().is_some_and(|url| url.query().is_some());
+        match e.status() {
+            Some(status) => CarbonError::Upstream {
+                status,
+                url: e
+                    .url()
+                    .map(|url| {
+                        let mut url = url.clone();
+                        url.set_query(None);
+                        url.to_string()
+                    })
+                    .unwrap_or_default(),
+            },
+            None if has_query => CarbonError::Network(e.without_url()),
+            None => CarbonError::Network(e),
+        }
+    }
+}
@@ -0,0 +1,49 @@
+//! Shared code for the carbon-vibe tools: a typed client for the UK Carbon
+//! Intensity API (<https://carbon-intensity.github.io/api-definitions/>) and
+//! the data types the binaries build on.
+//!
+//! The command-line tools, dashboard server, archive and notification
+//! channels need the `native` feature, on by default. Without it (and with
+//! `wasm` for a browser) the API clients, data types, mix enrichment and
+//! window search build for `wasm32-unknown-unknown`.
+
+pub mod accuracy;
+pub mod aggregate;
+#[cfg(feature = "native")]
+pub mod alert;
+#[cfg(feature = "native")]
+pub mod badge;
+pub mod calendar;
+#[cfg(feature = "native")]
+pub mod chart;
+#[cfg(feature = "native")]
+pub mod cli;
+pub mod client;
+pub mod context;
+#[cfg(feature = "native")]
+pub mod eink;
+pub mod electricitymaps;
+pub mod entsoe;
+pub mod error;
+pub mod lookup;
+pub mod map;
+pub mod mix;
+pub mod model;
+pub mod octopus;
+#[cfg(feature = "native")]
+pub mod output;
+pub mod provider;
+pub mod quality;
+#[cfg(feature = "native")]
+pub mod raster;
+pub mod schedule;
+pub mod settlement;
+#[cfg(feature = "native")]
+pub mod store;
+pub mod theme;
+pub mod units;
+#[cfg(feature = "native")]
+pub mod webhook;
+
+pub use client::CarbonClient;
+pub use error::CarbonError;
@@ -0,0 +1,30 @@
+//! The intensity at one moment from a run of periods: the settlement
+//! period's figure, or an estimate between neighbouring periods for a
+//! smoother answer between their midpoints.
+
+use crate::model::IntensityPoint;
+use chrono::{DateTime, Utc};
+
+/// Step-function lookup: the period whose `[from, to)` range contains `at`
+pub fn containing_period(periods: &[IntensityPoint], at: DateTime<Utc>) -> Option<&IntensityPoint> {
+    periods.iter().find(|p| p.from <= at && at < p.to)
+}
+
+/// Linear interpolation between the midpoints of the two periods either side
+/// of `at`. Returns the estimate and the two midpoints used.
+pub fn interpolated_intensity(
+    periods: &[IntensityPoint],
+    at: DateTime<Utc>,
+) -> Option<(f64, DateTime<Utc>, DateTime<Utc>)> {
+    periods.windows(2).find_map(|pair| {
+        let (before, after) = (pair[0].midpoint(), pair[1].midpoint());
+        if at < before || at > after || after == before {
+            return None;
+        }
+
+        let fraction = (at - before).num_seconds() as f64 / (after - before).num_seconds() as f64;
+        let value =
+            pair[0].intensity as f64 + (pair[1].intensity - pair[0].intensity) as f64 * fraction;
+        Some((value, before, after))
+    })
+}
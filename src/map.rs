@@ -0,0 +1,518 @@
+//! Simplified outlines of the 14 GB distribution network operator (DNO)
+//! regions the Carbon Intensity API reports on (ids 1-14), for drawing a
+//! map of regional intensity. The outlines are hand-drawn from coastline and
+//! boundary points a few kilometres apart: close enough to recognise each
+//! region, not for working out which region a place is in (use
+//! `RegionSelector::postcode` for that). Small islands, Shetland and the
+//! estuaries smaller than the Thames, Forth and Severn are left out.
+
+/// A point as (longitude, latitude) in degrees
+pub type LonLat = (f64, f64);
+
+/// Where neighbouring regions' borders meet, shared so the outlines join
+/// without gaps
+const HELENSBURGH: LonLat = (-4.7, 55.98);
+const TAY: LonLat = (-3.2, 56.38);
+const GRETNA: LonLat = (-3.06, 54.99);
+const BORDER_KIELDER: LonLat = (-2.6, 55.17);
+const BORDER_CHEVIOT: LonLat = (-2.2, 55.45);
+const BORDER_TWEED: LonLat = (-2.03, 55.8);
+const PENNINES_NORTH: LonLat = (-2.1, 54.45);
+const WHITBY: LonLat = (-0.61, 54.49);
+const PEAK_NORTH: LonLat = (-1.85, 53.45);
+const PEAK_WEST: LonLat = (-1.95, 53.32);
+const RIBBLE: LonLat = (-3.0, 53.68);
+const HUMBER: LonLat = (0.1, 53.55);
+const STOKE: LonLat = (-2.1, 53.1);
+const WASH: LonLat = (0.15, 52.8);
+const WARWICK: LonLat = (-1.5, 52.05);
+const CHILTERNS: LonLat = (-0.75, 51.95);
+const COTSWOLDS: LonLat = (-2.1, 51.6);
+const SEVERN: LonLat = (-2.5, 51.6);
+const CHEPSTOW: LonLat = (-2.67, 51.63);
+const POWYS: LonLat = (-3.1, 52.2);
+const ABERYSTWYTH: LonLat = (-4.08, 52.4);
+const LYME_BAY: LonLat = (-2.85, 50.72);
+const CHICHESTER: LonLat = (-0.9, 50.78);
+const LONDON_WEST: LonLat = (-0.5, 51.45);
+const LONDON_NORTH_WEST: LonLat = (-0.4, 51.62);
+const LONDON_NORTH: LonLat = (-0.1, 51.68);
+const LONDON_NORTH_EAST: LonLat = (0.2, 51.63);
+const THAMES_NORTH: LonLat = (0.3, 51.52);
+const THAMES_SOUTH: LonLat = (0.3, 51.46);
+const LONDON_SOUTH_EAST: LonLat = (0.15, 51.33);
+const LONDON_SOUTH: LonLat = (-0.15, 51.3);
+const LONDON_SOUTH_WEST: LonLat = (-0.4, 51.35);
+
+/// One region's outline: its API id and one or more closed polygons
+#[derive(Clone, Copy, Debug)]
+pub struct RegionOutline {
+    pub region_id: u32,
+    pub polygons: &'static [&'static [LonLat]],
+}
+
+/// Every DNO region, by API id
+pub const REGION_OUTLINES: [RegionOutline; 14] = [
+    RegionOutline {
+        region_id: 1,
+        polygons: &[
+            &[
+                HELENSBURGH,
+                (-4.9, 55.95),
+                (-5.2, 55.85),
+                (-5.45, 55.42),
+                (-5.8, 55.3),
+                (-5.7, 55.8),
+                (-5.6, 56.1),
+                (-5.47, 56.41),
+                (-5.8, 56.55),
+                (-6.22, 56.72),
+                (-5.85, 56.95),
+                (-5.83, 57.0),
+                (-5.71, 57.28),
+                (-5.85, 57.55),
+                (-5.71, 57.73),
+                (-5.3, 57.92),
+                (-5.24, 58.15),
+                (-5.0, 58.62),
+                (-4.75, 58.57),
+                (-4.42, 58.52),
+                (-3.52, 58.6),
+                (-3.37, 58.67),
+                (-3.02, 58.64),
+                (-3.09, 58.44),
+                (-3.65, 58.12),
+                (-4.0, 57.87),
+                (-4.22, 57.48),
+                (-3.87, 57.59),
+                (-3.28, 57.72),
+                (-2.0, 57.69),
+                (-1.78, 57.5),
+                (-2.08, 57.15),
+                (-2.2, 56.96),
+                (-2.47, 56.7),
+                (-2.58, 56.56),
+                (-2.87, 56.47),
+                (-2.97, 56.46),
+                TAY,
+                (-3.4, 56.35),
+                (-3.9, 56.25),
+                (-4.4, 56.2),
+            ],
+            // Skye
+            &[
+                (-6.2, 57.2),
+                (-5.7, 57.25),
+                (-6.1, 57.6),
+                (-6.4, 57.7),
+                (-6.7, 57.45),
+                (-6.3, 57.3),
+            ],
+            // Lewis and Harris
+            &[
+                (-6.9, 57.75),
+                (-6.3, 58.0),
+                (-6.2, 58.5),
+                (-6.6, 58.35),
+                (-7.1, 58.1),
+            ],
+            // Mull
+            &[(-6.35, 56.3), (-5.7, 56.45), (-5.9, 56.6), (-6.3, 56.55)],
+            // Islay
+            &[(-6.45, 55.65), (-6.05, 55.7), (-6.15, 55.9), (-6.45, 55.85)],
+            // Orkney
+            &[
+                (-3.35, 58.9),
+                (-2.9, 58.85),
+                (-2.75, 59.0),
+                (-3.2, 59.15),
+                (-3.35, 59.05),
+            ],
+        ],
+    },
+    RegionOutline {
+        region_id: 2,
+        polygons: &[
+            &[
+                GRETNA,
+                (-3.26, 54.98),
+                (-3.6, 54.87),
+                (-4.05, 54.8),
+                (-4.4, 54.68),
+                (-4.86, 54.64),
+                (-5.12, 54.84),
+                (-5.16, 55.0),
+                (-4.86, 55.24),
+                (-4.63, 55.46),
+                (-4.82, 55.64),
+                (-4.87, 55.8),
+                (-4.75, 55.95),
+                HELENSBURGH,
+                (-4.4, 56.2),
+                (-3.9, 56.25),
+                (-3.4, 56.35),
+                TAY,
+                (-2.9, 56.43),
+                (-2.8, 56.34),
+                (-2.58, 56.28),
+                (-2.82, 56.19),
+                (-3.15, 56.11),
+                (-3.6, 56.05),
+                (-3.35, 55.99),
+                (-3.18, 55.98),
+                (-2.72, 56.06),
+                (-2.52, 56.0),
+                (-2.13, 55.92),
+                BORDER_TWEED,
+                BORDER_CHEVIOT,
+                BORDER_KIELDER,
+            ],
+            // Arran
+            &[(-5.35, 55.45), (-5.1, 55.5), (-5.15, 55.7), (-5.35, 55.65)],
+        ],
+    },
+    RegionOutline {
+        region_id: 3,
+        polygons: &[&[
+            GRETNA,
+            BORDER_KIELDER,
+            (-2.5, 54.9),
+            (-2.3, 54.65),
+            PENNINES_NORTH,
+            (-2.3, 54.2),
+            (-2.1, 53.9),
+            (-2.0, 53.6),
+            PEAK_NORTH,
+            PEAK_WEST,
+            (-2.35, 53.38),
+            (-2.7, 53.52),
+            RIBBLE,
+            (-3.05, 53.82),
+            (-3.01, 53.92),
+            (-2.87, 54.07),
+            (-3.1, 54.1),
+            (-3.27, 54.21),
+            (-3.64, 54.5),
+            (-3.56, 54.64),
+            (-3.5, 54.72),
+            (-3.39, 54.87),
+            (-3.2, 54.95),
+        ]],
+    },
+    RegionOutline {
+        region_id: 4,
+        polygons: &[&[
+            BORDER_KIELDER,
+            BORDER_CHEVIOT,
+            BORDER_TWEED,
+            (-2.0, 55.77),
+            (-1.71, 55.61),
+            (-1.58, 55.33),
+            (-1.5, 55.13),
+            (-1.42, 55.02),
+            (-1.37, 54.91),
+            (-1.2, 54.69),
+            (-1.05, 54.62),
+            WHITBY,
+            (-0.8, 54.5),
+            (-1.5, 54.45),
+            PENNINES_NORTH,
+            (-2.3, 54.65),
+            (-2.5, 54.9),
+        ]],
+    },
+    RegionOutline {
+        region_id: 5,
+        polygons: &[&[
+            PENNINES_NORTH,
+            (-1.5, 54.45),
+            (-0.8, 54.5),
+            WHITBY,
+            (-0.4, 54.28),
+            (-0.08, 54.12),
+            (-0.19, 54.08),
+            (-0.16, 53.91),
+            (0.11, 53.6),
+            HUMBER,
+            (-0.6, 53.45),
+            (-1.2, 53.3),
+            (-1.6, 53.35),
+            PEAK_NORTH,
+            (-2.0, 53.6),
+            (-2.1, 53.9),
+            (-2.3, 54.2),
+        ]],
+    },
+    RegionOutline {
+        region_id: 6,
+        polygons: &[&[
+            ABERYSTWYTH,
+            (-4.05, 52.54),
+            (-4.05, 52.72),
+            (-4.13, 52.9),
+            (-4.41, 52.89),
+            (-4.77, 52.8),
+            (-4.55, 52.95),
+            (-4.3, 53.12),
+            (-4.6, 53.2),
+            (-4.65, 53.31),
+            (-4.34, 53.42),
+            (-4.08, 53.28),
+            (-3.83, 53.33),
+            (-3.49, 53.32),
+            (-3.32, 53.35),
+            (-3.13, 53.25),
+            (-3.18, 53.39),
+            (-3.04, 53.44),
+            (-3.07, 53.56),
+            RIBBLE,
+            (-2.7, 53.52),
+            (-2.35, 53.38),
+            PEAK_WEST,
+            STOKE,
+            (-2.4, 52.98),
+            (-2.75, 52.9),
+            (-3.1, 52.75),
+            (-3.15, 52.45),
+            POWYS,
+            (-3.6, 52.3),
+        ]],
+    },
+    RegionOutline {
+        region_id: 7,
+        polygons: &[&[
+            CHEPSTOW,
+            (-2.99, 51.56),
+            (-3.17, 51.46),
+            (-3.27, 51.39),
+            (-3.7, 51.48),
+            (-3.94, 51.6),
+            (-4.3, 51.56),
+            (-4.4, 51.7),
+            (-4.7, 51.67),
+            (-5.17, 51.68),
+            (-5.31, 51.88),
+            (-4.98, 52.02),
+            (-4.66, 52.12),
+            (-4.36, 52.22),
+            ABERYSTWYTH,
+            (-3.6, 52.3),
+            POWYS,
+            (-3.0, 51.95),
+            (-2.7, 51.83),
+        ]],
+    },
+    RegionOutline {
+        region_id: 8,
+        polygons: &[&[
+            STOKE,
+            (-1.8, 52.85),
+            (-1.6, 52.6),
+            (-1.45, 52.35),
+            WARWICK,
+            (-1.7, 51.75),
+            COTSWOLDS,
+            SEVERN,
+            (-2.55, 51.66),
+            CHEPSTOW,
+            (-2.7, 51.83),
+            (-3.0, 51.95),
+            POWYS,
+            (-3.15, 52.45),
+            (-3.1, 52.75),
+            (-2.75, 52.9),
+            (-2.4, 52.98),
+        ]],
+    },
+    RegionOutline {
+        region_id: 9,
+        polygons: &[&[
+            PEAK_NORTH,
+            (-1.6, 53.35),
+            (-1.2, 53.3),
+            (-0.6, 53.45),
+            HUMBER,
+            (0.26, 53.34),
+            (0.34, 53.14),
+            (0.0, 52.95),
+            WASH,
+            (-0.1, 52.6),
+            (-0.3, 52.35),
+            (-0.5, 52.1),
+            CHILTERNS,
+            WARWICK,
+            (-1.45, 52.35),
+            (-1.6, 52.6),
+            (-1.8, 52.85),
+            STOKE,
+            PEAK_WEST,
+        ]],
+    },
+    RegionOutline {
+        region_id: 10,
+        polygons: &[&[
+            CHILTERNS,
+            (-0.5, 52.1),
+            (-0.3, 52.35),
+            (-0.1, 52.6),
+            WASH,
+            (0.4, 52.75),
+            (0.49, 52.94),
+            (1.3, 52.93),
+            (1.73, 52.6),
+            (1.76, 52.48),
+            (1.68, 52.33),
+            (1.57, 52.09),
+            (1.35, 51.96),
+            (1.16, 51.79),
+            (0.95, 51.62),
+            (0.71, 51.54),
+            (0.4, 51.51),
+            THAMES_NORTH,
+            LONDON_NORTH_EAST,
+            LONDON_NORTH,
+            LONDON_NORTH_WEST,
+            (-0.55, 51.7),
+        ]],
+    },
+    RegionOutline {
+        region_id: 11,
+        polygons: &[&[
+            SEVERN,
+            (-2.72, 51.5),
+            (-2.86, 51.44),
+            (-2.98, 51.35),
+            (-3.0, 51.22),
+            (-3.47, 51.21),
+            (-4.12, 51.21),
+            (-4.53, 51.02),
+            (-4.55, 50.83),
+            (-5.08, 50.42),
+            (-5.48, 50.21),
+            (-5.71, 50.07),
+            (-5.2, 49.96),
+            (-5.05, 50.15),
+            (-4.6, 50.33),
+            (-4.14, 50.35),
+            (-3.64, 50.22),
+            (-3.5, 50.45),
+            (-3.41, 50.62),
+            (-2.94, 50.72),
+            LYME_BAY,
+            (-2.5, 50.9),
+            (-2.3, 51.2),
+            COTSWOLDS,
+        ]],
+    },
+    RegionOutline {
+        region_id: 12,
+        polygons: &[
+            &[
+                WARWICK,
+                CHILTERNS,
+                (-0.55, 51.7),
+                LONDON_NORTH_WEST,
+                LONDON_WEST,
+                LONDON_SOUTH_WEST,
+                (-0.7, 51.1),
+                CHICHESTER,
+                (-1.09, 50.79),
+                (-1.4, 50.88),
+                (-1.6, 50.73),
+                (-1.88, 50.72),
+                (-2.05, 50.58),
+                (-2.45, 50.55),
+                LYME_BAY,
+                (-2.5, 50.9),
+                (-2.3, 51.2),
+                COTSWOLDS,
+                (-1.7, 51.75),
+            ],
+            // Isle of Wight
+            &[(-1.57, 50.67), (-1.3, 50.77), (-1.08, 50.68), (-1.3, 50.58)],
+        ],
+    },
+    RegionOutline {
+        region_id: 13,
+        polygons: &[&[
+            LONDON_WEST,
+            LONDON_NORTH_WEST,
+            LONDON_NORTH,
+            LONDON_NORTH_EAST,
+            THAMES_NORTH,
+            THAMES_SOUTH,
+            LONDON_SOUTH_EAST,
+            LONDON_SOUTH,
+            LONDON_SOUTH_WEST,
+        ]],
+    },
+    RegionOutline {
+        region_id: 14,
+        polygons: &[&[
+            LONDON_SOUTH_WEST,
+            LONDON_SOUTH,
+            LONDON_SOUTH_EAST,
+            THAMES_SOUTH,
+            (0.45, 51.45),
+            (0.76, 51.44),
+            (1.02, 51.36),
+            (1.38, 51.39),
+            (1.45, 51.36),
+            (1.32, 51.12),
+            (0.97, 50.91),
+            (0.58, 50.85),
+            (0.24, 50.74),
+            (-0.14, 50.82),
+            (-0.37, 50.81),
+            (-0.68, 50.78),
+            CHICHESTER,
+            (-0.7, 51.1),
+        ]],
+    },
+];
+
+/// Westernmost and northernmost points drawn, the map's top-left corner
+const WEST: f64 = -7.2;
+const EAST: f64 = 1.9;
+const NORTH: f64 = 59.2;
+const SOUTH: f64 = 49.9;
+
+/// East-west degrees are this much shorter than north-south ones at GB's
+/// middle latitude (cos 54.5°), so the map isn't stretched sideways
+const LONGITUDE_SCALE: f64 = 0.58;
+
+/// Width and height of the map in pixels when a degree of latitude is
+/// `scale` pixels
+pub fn map_size(scale: f64) -> (f64, f64) {
+    (
+        (EAST - WEST) * LONGITUDE_SCALE * scale,
+        (NORTH - SOUTH) * scale,
+    )
+}
+
+/// A point's position on the map, from its top-left corner
+pub fn project((lon, lat): LonLat, scale: f64) -> (f64, f64) {
+    (
+        (lon - WEST) * LONGITUDE_SCALE * scale,
+        (NORTH - lat) * scale,
+    )
+}
+
+/// SVG path data for an outline, one closed subpath per polygon
+pub fn outline_path(outline: &RegionOutline, scale: f64) -> String {
+    outline
+        .polygons
+        .iter()
+        .map(|polygon| {
+            let points: Vec<String> = polygon
+                .iter()
+                .map(|&point| {
+                    let (x, y) = project(point, scale);
+                    format!("{x:.1} {y:.1}")
+                })
+                .collect();
+            format!("M {points} Z", points = points.join(" L "))
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
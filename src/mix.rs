@@ -0,0 +1,213 @@
+use crate::model::{CarbonFactors, FuelSource};
+use crate::quality::{DataWarning, Warnings};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// The mix entry fuels without a factor are gathered into, rather than
+/// being counted as zero-carbon
+pub const UNKNOWN_FUEL: &str = "unknown";
+
+/// A generation mix entry with the carbon intensity of that fuel attached
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[cfg_attr(feature = "native", derive(utoipa::ToSchema))]
+pub struct FuelSourceWithIntensity {
+    pub fuel: String,
+    pub perc: f64,
+    /// gCO₂/kWh, or `None` for [`UNKNOWN_FUEL`] and fuels such as `battery`
+    /// with no factor given
+    pub carbon_intensity: Option<i32>,
+}
+
+/// Emission factors in gCO₂/kWh to use instead of the published ones, e.g. a
+/// researcher's own life-cycle figures, read from a TOML file by `web
+/// --factors`. Any left out keep the published factor.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct FactorOverrides {
+    pub biomass: Option<i32>,
+    pub coal: Option<i32>,
+    /// The mix's single gas share, instead of the combined-cycle factor
+    pub gas: Option<i32>,
+    pub gas_combined_cycle: Option<i32>,
+    pub gas_open_cycle: Option<i32>,
+    pub hydro: Option<i32>,
+    pub nuclear: Option<i32>,
+    pub other: Option<i32>,
+    pub solar: Option<i32>,
+    pub wind: Option<i32>,
+    pub oil: Option<i32>,
+    pub pumped_storage: Option<i32>,
+    /// Battery discharge, which has no published factor
+    pub battery: Option<i32>,
+    pub dutch_imports: Option<i32>,
+    pub french_imports: Option<i32>,
+    pub irish_imports: Option<i32>,
+    pub belgian_imports: Option<i32>,
+    pub norwegian_imports: Option<i32>,
+    /// Any other fuel by its name in the mix, e.g. `"other renewable" = 20`
+    /// or a fuel a provider starts reporting
+    #[serde(default)]
+    pub fuels: BTreeMap<String, i32>,
+}
+
+impl FactorOverrides {
+    /// `factors` with these in place of the published figures
+    pub fn apply(&self, factors: CarbonFactors) -> CarbonFactors {
+        let mut fuels = factors.fuels;
+        fuels.extend(self.fuels.clone());
+        CarbonFactors {
+            biomass: self.biomass.unwrap_or(factors.biomass),
+            coal: self.coal.unwrap_or(factors.coal),
+            gas_combined_cycle: self.gas_combined_cycle.unwrap_or(factors.gas_combined_cycle),
+            gas_open_cycle: self.gas_open_cycle.unwrap_or(factors.gas_open_cycle),
+            hydro: self.hydro.unwrap_or(factors.hydro),
+            nuclear: self.nuclear.unwrap_or(factors.nuclear),
+            other: self.other.unwrap_or(factors.other),
+            solar: self.solar.unwrap_or(factors.solar),
+            wind: self.wind.unwrap_or(factors.wind),
+            oil: self.oil.unwrap_or(factors.oil),
+            dutch_imports: self.dutch_imports.unwrap_or(factors.dutch_imports),
+            french_imports: self.french_imports.unwrap_or(factors.french_imports),
+            irish_imports: self.irish_imports.unwrap_or(factors.irish_imports),
+            belgian_imports: self.belgian_imports.or(factors.belgian_imports),
+            norwegian_imports: self.norwegian_imports.or(factors.norwegian_imports),
+            pumped_storage: self.pumped_storage.or(factors.pumped_storage),
+            battery: self.battery.or(factors.battery),
+            gas: self.gas.or(factors.gas),
+            fuels,
+        }
+    }
+}
+
+/// Each interconnector's share of imports with its carbon intensity. The API
+/// only reports a total `imports` share, so it is apportioned by capacity.
+/// Belgium and Norway have no published factor yet: Norway's grid is almost
+/// all hydro, so it takes the hydro factor, and Belgium's nuclear and gas
+/// mix sits between its neighbours', so it takes the mean of the French and
+/// Dutch factors.
+fn interconnectors(factors: &CarbonFactors) -> [(&'static str, f64, i32); 5] {
+    let belgian = factors
+        .belgian_imports
+        .unwrap_or((factors.french_imports + factors.dutch_imports) / 2);
+    let norwegian = factors.norwegian_imports.unwrap_or(factors.hydro);
+
+    // Combined capacity in MW by country: IFA, IFA2 and ElecLink to France;
+    // BritNed; Nemo Link; North Sea Link; Moyle, EWIC and Greenlink to the
+    // island of Ireland
+    let capacities = [
+        ("french imports", 4000.0, factors.french_imports),
+        ("dutch imports", 1000.0, factors.dutch_imports),
+        ("belgian imports", 1000.0, belgian),
+        ("norwegian imports", 1400.0, norwegian),
+        ("irish imports", 1500.0, factors.irish_imports),
+    ];
+    let total: f64 = capacities.iter().map(|(_, capacity, _)| capacity).sum();
+
+    capacities.map(|(name, capacity, carbon_intensity)| (name, capacity / total, carbon_intensity))
+}
+
+/// Attach a carbon intensity factor to a generation mix entry. With
+/// `split_imports` the `imports` share becomes one entry per interconnector,
+/// each with its own factor; otherwise imports take the capacity-weighted
+/// mean of those factors, so both views give the same overall intensity. A
+/// fuel without a factor is raised in `warnings`.
+pub fn enrich_fuel_source(
+    fuel: FuelSource,
+    factors: &CarbonFactors,
+    split_imports: bool,
+    warnings: &mut Warnings,
+) -> Vec<FuelSourceWithIntensity> {
+    if fuel.fuel == "imports" {
+        let interconnectors = interconnectors(factors);
+        if split_imports {
+            return interconnectors
+                .into_iter()
+                .map(|(name, share, carbon_intensity)| FuelSourceWithIntensity {
+                    fuel: name.to_string(),
+                    perc: fuel.perc * share,
+                    carbon_intensity: Some(carbon_intensity),
+                })
+                .collect();
+        }
+
+        let carbon_intensity = interconnectors
+            .iter()
+            .map(|(_, share, carbon_intensity)| share * *carbon_intensity as f64)
+            .sum::<f64>()
+            .round() as i32;
+        return vec![FuelSourceWithIntensity {
+            fuel: fuel.fuel,
+            perc: fuel.perc,
+            carbon_intensity: Some(carbon_intensity),
+        }];
+    }
+
+    let carbon_intensity = match fuel.fuel.as_str() {
+        _ if factors.fuels.contains_key(&fuel.fuel) => factors.fuels.get(&fuel.fuel).copied(),
+        "biomass" => Some(factors.biomass),
+        "coal" => Some(factors.coal),
+        "gas" => Some(factors.gas.unwrap_or(factors.gas_combined_cycle)),
+        "hydro" => Some(factors.hydro),
+        "nuclear" => Some(factors.nuclear),
+        "oil" => Some(factors.oil),
+        "other" => Some(factors.other),
+        "solar" => Some(factors.solar),
+        "wind" => Some(factors.wind),
+        // Storage gives back what charged it, so there's no factor of its
+        // own unless one's published or configured
+        "storage" | "pumped storage" => factors.pumped_storage,
+        "battery" => factors.battery,
+        // Geothermal, marine and the like from ENTSO-E and Electricity Maps
+        "other renewable" => Some(0),
+        name => {
+            warnings.raise(DataWarning::UnknownFuel { fuel: name.to_string() });
+            return vec![FuelSourceWithIntensity {
+                fuel: UNKNOWN_FUEL.to_string(),
+                perc: fuel.perc,
+                carbon_intensity: None,
+            }];
+        }
+    };
+
+    vec![FuelSourceWithIntensity {
+        fuel: fuel.fuel,
+        perc: fuel.perc,
+        carbon_intensity,
+    }]
+}
+
+/// Enrich a whole generation mix with carbon intensity factors. Fuels
+/// without one share a single [`UNKNOWN_FUEL`] entry. Those fuels, and a
+/// mix that doesn't add up to 100%, are raised in `warnings`.
+pub fn enrich_generation_mix(
+    generation_mix: Vec<FuelSource>,
+    factors: &CarbonFactors,
+    split_imports: bool,
+    warnings: &mut Warnings,
+) -> Vec<FuelSourceWithIntensity> {
+    warnings.check_mix_total(generation_mix.iter().map(|fuel| fuel.perc));
+    let mut enriched: Vec<FuelSourceWithIntensity> = Vec::new();
+    for fuel in generation_mix
+        .into_iter()
+        .flat_map(|fuel| enrich_fuel_source(fuel, factors, split_imports, warnings))
+    {
+        match enriched.iter_mut().find(|entry| entry.fuel == UNKNOWN_FUEL) {
+            Some(unknown) if fuel.fuel == UNKNOWN_FUEL => unknown.perc += fuel.perc,
+            _ => enriched.push(fuel),
+        }
+    }
+    enriched
+}
+
+/// Overall intensity of an enriched mix in gCO₂/kWh, weighting each fuel's
+/// factor by its share. Shares without a factor are left out rather than
+/// counted as zero. `None` if nothing has a factor.
+pub fn weighted_intensity(generation_mix: &[FuelSourceWithIntensity]) -> Option<f64> {
+    let (weighted, total) = generation_mix
+        .iter()
+        .filter_map(|fuel| Some((fuel.perc, fuel.carbon_intensity?)))
+        .fold((0.0, 0.0), |(weighted, total), (perc, carbon_intensity)| {
+            (weighted + perc * carbon_intensity as f64, total + perc)
+        });
+    (total > 0.0).then(|| weighted / total)
+}
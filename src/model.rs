@@ -0,0 +1,534 @@
+use crate::error::CarbonError;
+use chrono::{DateTime, NaiveDate, NaiveDateTime, NaiveTime, TimeDelta, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
+/// Timestamp format used by the API in both responses and URL paths,
+/// e.g. `2025-06-26T14:30Z`
+pub const API_DATETIME_FORMAT: &str = "%Y-%m-%dT%H:%MZ";
+
+/// Longest window the API serves from a single `/intensity/{from}/{to}`
+/// request
+pub const MAX_INTENSITY_RANGE: TimeDelta = TimeDelta::days(14);
+
+/// Furthest ahead the API forecasts
+pub const FORECAST_HORIZON: TimeDelta = TimeDelta::hours(48);
+
+/// Format a time for use in an API URL path
+pub fn format_api_datetime(datetime: DateTime<Utc>) -> String {
+    datetime.format(API_DATETIME_FORMAT).to_string()
+}
+
+/// Parse a timestamp in any of the forms the API has been seen to use:
+/// minute precision (`2025-06-26T14:30Z`), with seconds or fractions
+/// (`2025-06-26T14:30:00Z`, `2025-06-26T14:30:00.000Z`), with an offset
+/// (`2025-06-26T15:30+01:00`) or with no zone at all, which is taken as UTC.
+/// A space may stand in for the `T`.
+pub fn parse_api_datetime(value: &str) -> Result<DateTime<Utc>, CarbonError> {
+    let text = value.trim().replacen(' ', "T", 1);
+    if let Ok(datetime) = DateTime::parse_from_rfc3339(&text) {
+        return Ok(datetime.with_timezone(&Utc));
+    }
+    if let Ok(datetime) = DateTime::parse_from_str(&text, "%Y-%m-%dT%H:%M%#z") {
+        return Ok(datetime.with_timezone(&Utc));
+    }
+
+    let naive = text.strip_suffix(['Z', 'z']).unwrap_or(&text);
+    ["%Y-%m-%dT%H:%M:%S%.f", "%Y-%m-%dT%H:%M"]
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(naive, format).ok())
+        .map(|naive| naive.and_utc())
+        .ok_or_else(|| {
+            CarbonError::DateParse(format!("Invalid time '{value}': expected e.g. 2025-06-26T14:30Z"))
+        })
+}
+
+/// Parse a time given on the command line. Accepts anything
+/// [`parse_api_datetime`] does, e.g. `2025-06-26T14:17Z` or RFC 3339, or a
+/// bare date for midnight (`2025-06-26`), all treated as UTC unless they
+/// carry an offset.
+pub fn parse_datetime_arg(value: &str) -> Result<DateTime<Utc>, CarbonError> {
+    if let Ok(date) = NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+        return Ok(date.and_time(NaiveTime::MIN).and_utc());
+    }
+
+    parse_api_datetime(value).map_err(|_| {
+        CarbonError::DateParse(format!(
+            "Invalid time '{value}': expected e.g. 2025-06-26T14:17Z or 2025-06-26"
+        ))
+    })
+}
+
+/// Serde adapter for the API's timestamps: written at minute precision,
+/// read with [`parse_api_datetime`]
+mod api_datetime {
+    use super::{API_DATETIME_FORMAT, parse_api_datetime};
+    use chrono::{DateTime, Utc};
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(
+        datetime: &DateTime<Utc>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&datetime.format(API_DATETIME_FORMAT).to_string())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<DateTime<Utc>, D::Error> {
+        let value = String::deserialize(deserializer)?;
+        parse_api_datetime(&value).map_err(serde::de::Error::custom)
+    }
+}
+
+/// Response from `/intensity` and `/intensity/{from}/{to}`
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CarbonIntensityData {
+    pub data: Vec<CarbonIntensityEntry>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CarbonIntensityEntry {
+    #[serde(with = "api_datetime")]
+    pub from: DateTime<Utc>,
+    #[serde(with = "api_datetime")]
+    pub to: DateTime<Utc>,
+    pub intensity: IntensityData,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct IntensityData {
+    pub actual: Option<i32>,
+    pub forecast: Option<i32>,
+    pub index: Option<IntensityIndex>,
+}
+
+/// The API's banding of an intensity value, lowest first
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "native", derive(utoipa::ToSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum IntensityIndex {
+    #[serde(rename = "very low")]
+    VeryLow,
+    Low,
+    Moderate,
+    High,
+    #[serde(rename = "very high")]
+    VeryHigh,
+}
+
+impl IntensityIndex {
+    /// Band for an intensity, for sources that don't publish one. These are
+    /// the GB API's bands for 2025.
+    pub fn for_intensity(intensity: i32) -> IntensityIndex {
+        match intensity {
+            ..40 => IntensityIndex::VeryLow,
+            40..120 => IntensityIndex::Low,
+            120..200 => IntensityIndex::Moderate,
+            200..290 => IntensityIndex::High,
+            _ => IntensityIndex::VeryHigh,
+        }
+    }
+
+    /// Every band, lowest first
+    pub const ALL: [IntensityIndex; 5] = [
+        IntensityIndex::VeryLow,
+        IntensityIndex::Low,
+        IntensityIndex::Moderate,
+        IntensityIndex::High,
+        IntensityIndex::VeryHigh,
+    ];
+
+    /// Lowest intensity in the band, as [`for_intensity`](Self::for_intensity)
+    /// bands them; each band runs up to the next one's lower bound
+    pub fn lower_bound(self) -> i32 {
+        match self {
+            IntensityIndex::VeryLow => 0,
+            IntensityIndex::Low => 40,
+            IntensityIndex::Moderate => 120,
+            IntensityIndex::High => 200,
+            IntensityIndex::VeryHigh => 290,
+        }
+    }
+
+    /// The API's name for the band, e.g. "very low"
+    pub fn as_str(self) -> &'static str {
+        match self {
+            IntensityIndex::VeryLow => "very low",
+            IntensityIndex::Low => "low",
+            IntensityIndex::Moderate => "moderate",
+            IntensityIndex::High => "high",
+            IntensityIndex::VeryHigh => "very high",
+        }
+    }
+}
+
+impl fmt::Display for IntensityIndex {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for IntensityIndex {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "very low" => Ok(IntensityIndex::VeryLow),
+            "low" => Ok(IntensityIndex::Low),
+            "moderate" => Ok(IntensityIndex::Moderate),
+            "high" => Ok(IntensityIndex::High),
+            "very high" => Ok(IntensityIndex::VeryHigh),
+            other => Err(format!("Unknown intensity index '{other}'")),
+        }
+    }
+}
+
+impl IntensityData {
+    /// The actual value where measured, otherwise the forecast
+    pub fn best(&self) -> Option<i32> {
+        self.actual.or(self.forecast)
+    }
+}
+
+/// One 30-minute settlement period of carbon intensity
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[cfg_attr(feature = "native", derive(utoipa::ToSchema))]
+pub struct IntensityPoint {
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    /// The actual value where measured, otherwise the forecast
+    pub intensity: i32,
+    pub actual: Option<i32>,
+    pub forecast: Option<i32>,
+    /// The API's band, e.g. "low" or "very high"
+    pub index: Option<IntensityIndex>,
+}
+
+impl IntensityPoint {
+    /// Build a point from an API entry, using the actual value when there is
+    /// one and the forecast otherwise. Entries with neither are skipped.
+    pub fn from_entry(entry: CarbonIntensityEntry) -> Option<Self> {
+        IntensityPoint::from_parts(entry.from, entry.to, entry.intensity)
+    }
+
+    fn from_parts(
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+        intensity: IntensityData,
+    ) -> Option<Self> {
+        Some(IntensityPoint {
+            from,
+            to,
+            intensity: intensity.best()?,
+            actual: intensity.actual,
+            forecast: intensity.forecast,
+            index: intensity.index,
+        })
+    }
+
+    /// True when no actual reading is available yet
+    pub fn is_forecast(&self) -> bool {
+        self.actual.is_none()
+    }
+
+    pub fn midpoint(&self) -> DateTime<Utc> {
+        self.from + (self.to - self.from) / 2
+    }
+}
+
+/// Response from `/generation`
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct GenerationMixData {
+    pub data: GenerationMixEntry,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct GenerationMixEntry {
+    #[serde(rename = "generationmix")]
+    pub generation_mix: Vec<FuelSource>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "native", derive(utoipa::ToSchema))]
+pub struct FuelSource {
+    pub fuel: String,
+    pub perc: f64,
+}
+
+/// Response from `/generation/{from}/{to}`
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct GenerationMixRangeData {
+    pub data: Vec<GenerationMixPeriod>,
+}
+
+/// Generation mix for one settlement period
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "native", derive(utoipa::ToSchema))]
+pub struct GenerationMixPeriod {
+    #[serde(with = "api_datetime")]
+    #[cfg_attr(feature = "native", schema(value_type = String, example = "2025-06-26T14:00Z"))]
+    pub from: DateTime<Utc>,
+    #[serde(with = "api_datetime")]
+    #[cfg_attr(feature = "native", schema(value_type = String, example = "2025-06-26T14:30Z"))]
+    pub to: DateTime<Utc>,
+    #[serde(rename = "generationmix")]
+    pub generation_mix: Vec<FuelSource>,
+}
+
+/// Response from `/intensity/factors`
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CarbonFactorsData {
+    pub data: Vec<CarbonFactors>,
+}
+
+/// Carbon intensity of each generation type in gCO₂/kWh
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CarbonFactors {
+    #[serde(rename = "Biomass")]
+    pub biomass: i32,
+    #[serde(rename = "Coal")]
+    pub coal: i32,
+    #[serde(rename = "Gas (Combined Cycle)")]
+    pub gas_combined_cycle: i32,
+    #[serde(rename = "Gas (Open Cycle)")]
+    pub gas_open_cycle: i32,
+    #[serde(rename = "Hydro")]
+    pub hydro: i32,
+    #[serde(rename = "Nuclear")]
+    pub nuclear: i32,
+    #[serde(rename = "Other")]
+    pub other: i32,
+    #[serde(rename = "Solar")]
+    pub solar: i32,
+    #[serde(rename = "Wind")]
+    pub wind: i32,
+    #[serde(rename = "Oil")]
+    pub oil: i32,
+    #[serde(rename = "Dutch Imports")]
+    pub dutch_imports: i32,
+    #[serde(rename = "French Imports")]
+    pub french_imports: i32,
+    #[serde(rename = "Irish Imports")]
+    pub irish_imports: i32,
+    /// Nemo Link and North Sea Link aren't in the published factors yet;
+    /// [`crate::mix`] estimates them when these are missing
+    #[serde(rename = "Belgian Imports", default, skip_serializing_if = "Option::is_none")]
+    pub belgian_imports: Option<i32>,
+    #[serde(rename = "Norwegian Imports", default, skip_serializing_if = "Option::is_none")]
+    pub norwegian_imports: Option<i32>,
+    #[serde(rename = "Pumped Storage", default, skip_serializing_if = "Option::is_none")]
+    pub pumped_storage: Option<i32>,
+    /// For the mix's single gas share, which doesn't say which kind; not
+    /// published, so combined cycle is assumed unless overridden
+    #[serde(skip)]
+    pub gas: Option<i32>,
+    /// Not published; battery discharge is shown as unknown without it
+    #[serde(skip)]
+    pub battery: Option<i32>,
+    /// Factors by mix fuel name for fuels not covered above, from
+    /// [`crate::mix::FactorOverrides`]
+    #[serde(skip)]
+    pub fuels: BTreeMap<String, i32>,
+}
+
+/// Response from `/regional`
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RegionalData {
+    pub data: Vec<RegionalSnapshot>,
+}
+
+/// Every region for one settlement period
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RegionalSnapshot {
+    #[serde(with = "api_datetime")]
+    pub from: DateTime<Utc>,
+    #[serde(with = "api_datetime")]
+    pub to: DateTime<Utc>,
+    pub regions: Vec<Region>,
+}
+
+impl RegionalSnapshot {
+    /// Regions from the lowest intensity to the highest, keeping the API's
+    /// order for ties, with any lacking a figure last
+    pub fn by_intensity(&self) -> Vec<&Region> {
+        let mut regions: Vec<&Region> = self.regions.iter().collect();
+        regions.sort_by_key(|region| {
+            let intensity = region.intensity.best();
+            (intensity.is_none(), intensity)
+        });
+        regions
+    }
+}
+
+/// Intensity and generation mix for one region. `region_id` is the API's own
+/// numbering (1-17, where 15-17 are England, Scotland and Wales as a whole);
+/// `dno_region` is the distribution network operator's area name.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Region {
+    #[serde(rename = "regionid")]
+    pub region_id: u32,
+    #[serde(rename = "dnoregion")]
+    pub dno_region: Option<String>,
+    #[serde(rename = "shortname")]
+    pub short_name: String,
+    pub intensity: IntensityData,
+    #[serde(rename = "generationmix")]
+    pub generation_mix: Vec<FuelSource>,
+}
+
+/// Response from `/regional/postcode/{postcode}` and
+/// `/regional/regionid/{id}`, which nest the periods inside the region
+/// rather than the other way round
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SingleRegionData {
+    pub data: Vec<SingleRegionEntry>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SingleRegionEntry {
+    #[serde(rename = "regionid")]
+    pub region_id: u32,
+    #[serde(rename = "dnoregion")]
+    pub dno_region: Option<String>,
+    #[serde(rename = "shortname")]
+    pub short_name: String,
+    pub postcode: Option<String>,
+    pub data: Vec<RegionalPeriod>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RegionalPeriod {
+    #[serde(with = "api_datetime")]
+    pub from: DateTime<Utc>,
+    #[serde(with = "api_datetime")]
+    pub to: DateTime<Utc>,
+    pub intensity: IntensityData,
+    #[serde(rename = "generationmix")]
+    pub generation_mix: Vec<FuelSource>,
+}
+
+/// Response from `/regional/intensity/{from}/{to}/postcode/{postcode}` and
+/// `/regional/intensity/{from}/{to}/regionid/{id}`. Unlike the current-period
+/// endpoints, `data` here is a single object rather than a list.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SingleRegionRangeData {
+    pub data: SingleRegionEntry,
+}
+
+impl RegionalPeriod {
+    /// This period's intensity in the same shape as the national timeline
+    pub fn to_intensity_point(&self) -> Option<IntensityPoint> {
+        IntensityPoint::from_parts(self.from, self.to, self.intensity.clone())
+    }
+
+    /// This period's generation mix in the same shape as `/generation/{from}/{to}`
+    pub fn to_generation_period(&self) -> GenerationMixPeriod {
+        GenerationMixPeriod {
+            from: self.from,
+            to: self.to,
+            generation_mix: self.generation_mix.clone(),
+        }
+    }
+}
+
+/// The API's region names by id, from 1 (North Scotland) to 17 (Wales).
+/// 15 to 17 are England, Scotland and Wales as a whole.
+pub const REGION_NAMES: [&str; 17] = [
+    "North Scotland",
+    "South Scotland",
+    "North West England",
+    "North East England",
+    "Yorkshire",
+    "North Wales & Merseyside",
+    "South Wales",
+    "West Midlands",
+    "East Midlands",
+    "East England",
+    "South West England",
+    "South England",
+    "London",
+    "South East England",
+    "England",
+    "Scotland",
+    "Wales",
+];
+
+/// Which region to ask the regional endpoints about
+#[derive(Clone, Debug, PartialEq)]
+pub enum RegionSelector {
+    /// A GB outward code, e.g. `SW1A`
+    Postcode(String),
+    /// The API's region id (1-17), e.g. 13 for London
+    RegionId(u32),
+}
+
+impl RegionSelector {
+    /// Select by postcode. Only the outward code is used, so `"RG10 9AA"` and
+    /// `"rg10"` both select `RG10`.
+    pub fn postcode(postcode: &str) -> Result<Self, String> {
+        let outward_code = postcode
+            .split_whitespace()
+            .next()
+            .unwrap_or_default()
+            .to_ascii_uppercase();
+        if outward_code.is_empty() || !outward_code.chars().all(|c| c.is_ascii_alphanumeric()) {
+            return Err(format!(
+                "Invalid postcode '{postcode}': expected e.g. SW1A or RG10 9AA"
+            ));
+        }
+
+        Ok(RegionSelector::Postcode(outward_code))
+    }
+
+    /// Trailing path segments the regional endpoints take
+    pub fn path(&self) -> String {
+        match self {
+            RegionSelector::Postcode(postcode) => format!("postcode/{postcode}"),
+            RegionSelector::RegionId(region_id) => format!("regionid/{region_id}"),
+        }
+    }
+}
+
+impl std::fmt::Display for RegionSelector {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RegionSelector::Postcode(postcode) => write!(f, "postcode '{postcode}'"),
+            RegionSelector::RegionId(region_id) => write!(f, "region id {region_id}"),
+        }
+    }
+}
+
+/// One region's figures for a single settlement period
+#[derive(Clone, Debug)]
+pub struct RegionalReading {
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    pub region: Region,
+    /// The outward code looked up, as the API gives it back; `None` when
+    /// selected by region id
+    pub postcode: Option<String>,
+}
+
+impl SingleRegionEntry {
+    /// Flatten into the same `Region` shape `/regional` uses, keeping the
+    /// first period
+    pub fn into_reading(self) -> Option<RegionalReading> {
+        let period = self.data.into_iter().next()?;
+
+        Some(RegionalReading {
+            from: period.from,
+            to: period.to,
+            region: Region {
+                region_id: self.region_id,
+                dno_region: self.dno_region,
+                short_name: self.short_name,
+                intensity: period.intensity,
+                generation_mix: period.generation_mix,
+            },
+            postcode: self.postcode,
+        })
+    }
+}
@@ -0,0 +1,141 @@
+//! Client for Octopus Energy's public tariff API
+//! (<https://developer.octopus.energy/>), for Agile's half-hourly unit
+//! prices. Agile prices follow the wholesale market, which is often cheapest
+//! when the grid is greenest, but not always, so the two are weighed together
+//! in [`cheap_green_window`](crate::schedule::cheap_green_window).
+
+use crate::CarbonClient;
+use crate::error::CarbonError;
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Public Octopus Energy API
+pub const DEFAULT_OCTOPUS_URL: &str = "https://api.octopus.energy";
+
+/// Current Agile product
+pub const DEFAULT_AGILE_PRODUCT: &str = "AGILE-24-10-01";
+
+/// Region letters (GSP groups) Agile is priced for: A is Eastern England
+/// through P, North Scotland. I and O aren't used.
+pub const AGILE_REGIONS: &str = "ABCDEFGHJKLMNP";
+
+// Enough for a fortnight of half hours; the API's maximum
+const PAGE_SIZE: &str = "1500";
+
+/// Parse an Agile region letter, e.g. `C` for London
+pub fn parse_agile_region(value: &str) -> Result<char, String> {
+    let mut chars = value.chars();
+    match (chars.next().map(|c| c.to_ascii_uppercase()), chars.next()) {
+        (Some(region), None) if AGILE_REGIONS.contains(region) => Ok(region),
+        _ => Err(format!(
+            "Invalid Agile region '{value}': expected one letter of {AGILE_REGIONS}"
+        )),
+    }
+}
+
+/// Unit price for one half hour
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "native", derive(utoipa::ToSchema))]
+pub struct UnitRate {
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    /// Pence per kWh including VAT; can be negative
+    pub price: f64,
+}
+
+/// Typed client for one Agile tariff's prices. Requests go through a
+/// [`CarbonClient`] pointed at Octopus, for the same retries and timeout.
+#[derive(Clone, Debug)]
+pub struct OctopusClient {
+    http: CarbonClient,
+    product: String,
+    region: char,
+}
+
+impl OctopusClient {
+    /// `http` should have Octopus's base URL, e.g. [`DEFAULT_OCTOPUS_URL`]
+    pub fn new(http: CarbonClient, product: impl Into<String>, region: char) -> Self {
+        OctopusClient {
+            http,
+            product: product.into(),
+            region,
+        }
+    }
+
+    /// Single-register electricity tariff code, e.g.
+    /// `E-1R-AGILE-24-10-01-C`
+    pub fn tariff_code(&self) -> String {
+        format!(
+            "E-1R-{product}-{region}",
+            product = self.product,
+            region = self.region
+        )
+    }
+
+    /// Prices for each half hour between `from` and `to` that has been
+    /// published, oldest first. The next day's prices appear around 4pm.
+    pub async fn unit_rates(
+        &self,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<UnitRate>, CarbonError> {
+        let path = format!(
+            "/v1/products/{product}/electricity-tariffs/{tariff}/standard-unit-rates/",
+            product = self.product,
+            tariff = self.tariff_code()
+        );
+        let (period_from, period_to) = (format_datetime(from), format_datetime(to));
+
+        let mut rates = Vec::new();
+        for page in 1.. {
+            let page = page.to_string();
+            let query = [
+                ("period_from", period_from.as_str()),
+                ("period_to", period_to.as_str()),
+                ("page_size", PAGE_SIZE),
+                ("page", page.as_str()),
+            ];
+            let body = self.http.get_text(&path, &query).await?;
+            let response: RatesPage =
+                serde_json::from_str(&body).map_err(|source| CarbonError::Decode {
+                    url: format!("{base_url}{path}", base_url = self.http.base_url()),
+                    source,
+                })?;
+
+            rates.extend(response.results.into_iter().filter_map(|rate| {
+                Some(UnitRate {
+                    from: rate.valid_from,
+                    to: rate.valid_to?,
+                    price: rate.value_inc_vat,
+                })
+            }));
+            if response.next.is_none() {
+                break;
+            }
+        }
+        // Newest first from the API
+        rates.sort_by_key(|rate| rate.from);
+
+        Ok(rates)
+    }
+}
+
+fn format_datetime(datetime: DateTime<Utc>) -> String {
+    datetime.to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+/// Response from `standard-unit-rates`
+#[derive(Debug, Deserialize)]
+struct RatesPage {
+    /// URL of the next page, if any
+    next: Option<String>,
+    results: Vec<RawRate>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RawRate {
+    value_inc_vat: f64,
+    valid_from: DateTime<Utc>,
+    /// `null` for a rate with no end, which Agile doesn't have
+    valid_to: Option<DateTime<Utc>>,
+}
@@ -0,0 +1,92 @@
+//! Text, JSON and CSV rendering shared by the CLI binaries. Each binary
+//! describes its records once via [`Record`] and the chosen [`OutputFormat`]
+//! picks the rendering path.
+
+use serde::Serialize;
+use std::str::FromStr;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, clap::ValueEnum)]
+pub enum OutputFormat {
+    /// Human-readable lines
+    #[default]
+    Text,
+    Json,
+    Csv,
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "text" => Ok(OutputFormat::Text),
+            "json" => Ok(OutputFormat::Json),
+            "csv" => Ok(OutputFormat::Csv),
+            other => Err(format!(
+                "Invalid format '{other}': expected text, json or csv"
+            )),
+        }
+    }
+}
+
+/// A row of CLI output that can be rendered in any [`OutputFormat`]. The JSON
+/// form comes from `Serialize`.
+pub trait Record: Serialize {
+    /// CSV column names, in the order `csv_fields` returns values
+    const CSV_HEADER: &'static [&'static str];
+
+    fn csv_fields(&self) -> Vec<String>;
+
+    fn text(&self) -> String;
+}
+
+/// Render a single record: a JSON object, or a CSV header plus one row
+pub fn render_one<R: Record>(
+    record: &R,
+    format: OutputFormat,
+) -> Result<String, serde_json::Error> {
+    match format {
+        OutputFormat::Text => Ok(format!("{text}\n", text = record.text())),
+        OutputFormat::Json => Ok(format!("{json}\n", json = serde_json::to_string(record)?)),
+        OutputFormat::Csv => Ok(render_csv(std::slice::from_ref(record))),
+    }
+}
+
+/// Render a list of records: a JSON array, or a CSV header plus one row each
+pub fn render_all<R: Record>(
+    records: &[R],
+    format: OutputFormat,
+) -> Result<String, serde_json::Error> {
+    match format {
+        OutputFormat::Text => Ok(records
+            .iter()
+            .map(|record| format!("{text}\n", text = record.text()))
+            .collect()),
+        OutputFormat::Json => Ok(format!("{json}\n", json = serde_json::to_string(records)?)),
+        OutputFormat::Csv => Ok(render_csv(records)),
+    }
+}
+
+fn render_csv<R: Record>(records: &[R]) -> String {
+    let mut csv = csv_line(R::CSV_HEADER.iter().copied());
+    for record in records {
+        csv.push_str(&csv_line(record.csv_fields().iter().map(String::as_str)));
+    }
+    csv
+}
+
+fn csv_line<'a>(fields: impl Iterator<Item = &'a str>) -> String {
+    let mut line = fields.map(csv_field).collect::<Vec<_>>().join(",");
+    line.push('\n');
+    line
+}
+
+/// Quote a CSV field (RFC 4180) when it contains a delimiter, quote or line
+/// break, doubling any embedded quotes
+pub fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{escaped}\"", escaped = value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
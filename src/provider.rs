@@ -0,0 +1,298 @@
+//! Where grid data comes from. The UK Carbon Intensity API covers Great
+//! Britain; ENTSO-E covers the rest of Europe, and Electricity Maps zones
+//! around the world. The commands that can show any of them ask for a
+//! [`GridDataProvider`] rather than a particular client.
+
+use crate::CarbonClient;
+use crate::electricitymaps::ElectricityMapsClient;
+use crate::entsoe::EntsoeClient;
+use crate::error::CarbonError;
+use crate::model::{CarbonFactors, FuelSource, GenerationMixPeriod, IntensityPoint};
+use chrono::{DateTime, Utc};
+use std::collections::BTreeMap;
+use std::future::Future;
+
+/// The GB API's published factors in gCO₂/kWh, for providers that don't
+/// publish their own. They're per fuel rather than per country, so they
+/// serve anywhere.
+pub const FUEL_FACTORS: CarbonFactors = CarbonFactors {
+    biomass: 120,
+    coal: 937,
+    gas_combined_cycle: 394,
+    gas_open_cycle: 651,
+    hydro: 0,
+    nuclear: 0,
+    other: 300,
+    solar: 0,
+    wind: 0,
+    oil: 935,
+    dutch_imports: 474,
+    french_imports: 53,
+    irish_imports: 458,
+    belgian_imports: None,
+    norwegian_imports: None,
+    pumped_storage: Some(0),
+    gas: None,
+    battery: None,
+    fuels: BTreeMap::new(),
+};
+
+/// Megawatts generated per fuel
+pub(crate) type FuelTotals = BTreeMap<&'static str, f64>;
+
+/// Each fuel's share of the total to one decimal place, in fuel name order,
+/// or `None` if nothing was generated
+pub(crate) fn fuel_shares(totals: FuelTotals) -> Option<Vec<FuelSource>> {
+    let total: f64 = totals.values().sum();
+    (total > 0.0).then(|| {
+        totals
+            .into_iter()
+            .map(|(fuel, megawatts)| FuelSource {
+                fuel: fuel.to_string(),
+                perc: (megawatts / total * 1000.0).round() / 10.0,
+            })
+            .collect()
+    })
+}
+
+/// `Send` natively, so fetches can move between tokio's worker threads. In
+/// the browser, requests go through `fetch`, whose futures are tied to the
+/// one thread there is and can't be `Send`.
+#[cfg(not(target_arch = "wasm32"))]
+pub trait MaybeSend: Send {}
+#[cfg(not(target_arch = "wasm32"))]
+impl<T: Send> MaybeSend for T {}
+#[cfg(target_arch = "wasm32")]
+pub trait MaybeSend {}
+#[cfg(target_arch = "wasm32")]
+impl<T> MaybeSend for T {}
+
+/// National (or bidding zone) intensity and generation mix from one source
+pub trait GridDataProvider {
+    /// Name of the area covered, or `None` for Great Britain
+    fn area_name(&self) -> Option<&str>;
+
+    /// Latest period, including forecast and index where the source has them
+    fn current_period(&self) -> impl Future<Output = Result<IntensityPoint, CarbonError>> + MaybeSend;
+
+    /// Intensity for each period between `from` and `to`, oldest first
+    fn intensity_between(
+        &self,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> impl Future<Output = Result<Vec<IntensityPoint>, CarbonError>> + MaybeSend;
+
+    /// Latest generation mix
+    fn generation_mix(&self) -> impl Future<Output = Result<Vec<FuelSource>, CarbonError>> + MaybeSend;
+
+    /// Generation mix for each period between `from` and `to`, oldest first
+    fn generation_between(
+        &self,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> impl Future<Output = Result<Vec<GenerationMixPeriod>, CarbonError>> + MaybeSend;
+
+    /// Carbon intensity of each generation type
+    fn factors(&self) -> impl Future<Output = Result<CarbonFactors, CarbonError>> + MaybeSend;
+}
+
+impl GridDataProvider for CarbonClient {
+    fn area_name(&self) -> Option<&str> {
+        None
+    }
+
+    fn current_period(&self) -> impl Future<Output = Result<IntensityPoint, CarbonError>> + MaybeSend {
+        CarbonClient::current_period(self)
+    }
+
+    fn intensity_between(
+        &self,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> impl Future<Output = Result<Vec<IntensityPoint>, CarbonError>> + MaybeSend {
+        CarbonClient::intensity_between(self, from, to)
+    }
+
+    fn generation_mix(&self) -> impl Future<Output = Result<Vec<FuelSource>, CarbonError>> + MaybeSend {
+        CarbonClient::generation_mix(self)
+    }
+
+    fn generation_between(
+        &self,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> impl Future<Output = Result<Vec<GenerationMixPeriod>, CarbonError>> + MaybeSend {
+        CarbonClient::generation_between(self, from, to)
+    }
+
+    fn factors(&self) -> impl Future<Output = Result<CarbonFactors, CarbonError>> + MaybeSend {
+        CarbonClient::factors(self)
+    }
+}
+
+impl GridDataProvider for EntsoeClient {
+    fn area_name(&self) -> Option<&str> {
+        Some(self.area().name)
+    }
+
+    fn current_period(&self) -> impl Future<Output = Result<IntensityPoint, CarbonError>> + MaybeSend {
+        EntsoeClient::current_period(self)
+    }
+
+    fn intensity_between(
+        &self,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> impl Future<Output = Result<Vec<IntensityPoint>, CarbonError>> + MaybeSend {
+        EntsoeClient::intensity_between(self, from, to)
+    }
+
+    fn generation_mix(&self) -> impl Future<Output = Result<Vec<FuelSource>, CarbonError>> + MaybeSend {
+        EntsoeClient::generation_mix(self)
+    }
+
+    fn generation_between(
+        &self,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> impl Future<Output = Result<Vec<GenerationMixPeriod>, CarbonError>> + MaybeSend {
+        EntsoeClient::generation_between(self, from, to)
+    }
+
+    fn factors(&self) -> impl Future<Output = Result<CarbonFactors, CarbonError>> + MaybeSend {
+        EntsoeClient::factors(self)
+    }
+}
+
+impl GridDataProvider for ElectricityMapsClient {
+    fn area_name(&self) -> Option<&str> {
+        Some(self.zone())
+    }
+
+    fn current_period(&self) -> impl Future<Output = Result<IntensityPoint, CarbonError>> + MaybeSend {
+        ElectricityMapsClient::current_period(self)
+    }
+
+    fn intensity_between(
+        &self,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> impl Future<Output = Result<Vec<IntensityPoint>, CarbonError>> + MaybeSend {
+        ElectricityMapsClient::intensity_between(self, from, to)
+    }
+
+    fn generation_mix(&self) -> impl Future<Output = Result<Vec<FuelSource>, CarbonError>> + MaybeSend {
+        ElectricityMapsClient::generation_mix(self)
+    }
+
+    fn generation_between(
+        &self,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> impl Future<Output = Result<Vec<GenerationMixPeriod>, CarbonError>> + MaybeSend {
+        ElectricityMapsClient::generation_between(self, from, to)
+    }
+
+    fn factors(&self) -> impl Future<Output = Result<CarbonFactors, CarbonError>> + MaybeSend {
+        ElectricityMapsClient::factors(self)
+    }
+}
+
+/// The provider chosen on the command line
+#[derive(Clone, Debug)]
+pub enum GridClient {
+    CarbonIntensity(CarbonClient),
+    Entsoe(EntsoeClient),
+    ElectricityMaps(ElectricityMapsClient),
+}
+
+impl GridClient {
+    /// Limit concurrent requests to the provider's API, as with
+    /// [`CarbonClient::with_max_in_flight`]
+    pub fn with_max_in_flight(self, limit: usize) -> Self {
+        match self {
+            GridClient::CarbonIntensity(client) => {
+                GridClient::CarbonIntensity(client.with_max_in_flight(limit))
+            }
+            GridClient::Entsoe(client) => GridClient::Entsoe(client.with_max_in_flight(limit)),
+            GridClient::ElectricityMaps(client) => {
+                GridClient::ElectricityMaps(client.with_max_in_flight(limit))
+            }
+        }
+    }
+
+    /// The Carbon Intensity API client, for its GB-only extras such as
+    /// regions and forecasts
+    pub fn carbon_intensity(&self) -> Option<&CarbonClient> {
+        match self {
+            GridClient::CarbonIntensity(client) => Some(client),
+            GridClient::Entsoe(_) | GridClient::ElectricityMaps(_) => None,
+        }
+    }
+
+    /// The Electricity Maps client, for its marginal intensity
+    pub fn electricity_maps(&self) -> Option<&ElectricityMapsClient> {
+        match self {
+            GridClient::ElectricityMaps(client) => Some(client),
+            GridClient::CarbonIntensity(_) | GridClient::Entsoe(_) => None,
+        }
+    }
+}
+
+impl GridDataProvider for GridClient {
+    fn area_name(&self) -> Option<&str> {
+        match self {
+            GridClient::CarbonIntensity(client) => client.area_name(),
+            GridClient::Entsoe(client) => client.area_name(),
+            GridClient::ElectricityMaps(client) => client.area_name(),
+        }
+    }
+
+    async fn current_period(&self) -> Result<IntensityPoint, CarbonError> {
+        match self {
+            GridClient::CarbonIntensity(client) => client.current_period().await,
+            GridClient::Entsoe(client) => client.current_period().await,
+            GridClient::ElectricityMaps(client) => client.current_period().await,
+        }
+    }
+
+    async fn intensity_between(
+        &self,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<IntensityPoint>, CarbonError> {
+        match self {
+            GridClient::CarbonIntensity(client) => client.intensity_between(from, to).await,
+            GridClient::Entsoe(client) => client.intensity_between(from, to).await,
+            GridClient::ElectricityMaps(client) => client.intensity_between(from, to).await,
+        }
+    }
+
+    async fn generation_mix(&self) -> Result<Vec<FuelSource>, CarbonError> {
+        match self {
+            GridClient::CarbonIntensity(client) => client.generation_mix().await,
+            GridClient::Entsoe(client) => client.generation_mix().await,
+            GridClient::ElectricityMaps(client) => client.generation_mix().await,
+        }
+    }
+
+    async fn generation_between(
+        &self,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<GenerationMixPeriod>, CarbonError> {
+        match self {
+            GridClient::CarbonIntensity(client) => client.generation_between(from, to).await,
+            GridClient::Entsoe(client) => client.generation_between(from, to).await,
+            GridClient::ElectricityMaps(client) => client.generation_between(from, to).await,
+        }
+    }
+
+    async fn factors(&self) -> Result<CarbonFactors, CarbonError> {
+        match self {
+            GridClient::CarbonIntensity(client) => client.factors().await,
+            GridClient::Entsoe(client) => client.factors().await,
+            GridClient::ElectricityMaps(client) => client.factors().await,
+        }
+    }
+}
@@ -0,0 +1,135 @@
+//! Data-quality warnings: signs that the figures shown may be degraded, such
+//! as a fuel without a carbon factor or a panel showing older data. They're
+//! logged as they're found and gathered in a [`Warnings`] for the fetch or
+//! command that raised them. With `--strict` that fetch fails, or the
+//! command once it's done, rather than carrying on with data an automated
+//! pipeline shouldn't trust.
+
+use crate::CarbonError;
+use chrono::{DateTime, Utc};
+use std::fmt;
+use std::sync::{Mutex, OnceLock};
+use tracing::warn;
+
+/// How far a generation mix may be from 100% before it's flagged; the API's
+/// shares are rounded to a tenth, so a few tenths either way are normal
+pub const MIX_TOTAL_TOLERANCE: f64 = 1.0;
+
+/// Set once the command line is parsed
+static STRICT: OnceLock<bool> = OnceLock::new();
+
+/// Warnings handed over with [`Warnings::defer`] under `--strict`, for the
+/// command to fail on once it's done
+static DEFERRED: Mutex<Vec<DataWarning>> = Mutex::new(Vec::new());
+
+/// Something that makes the data less trustworthy, without stopping it being
+/// shown
+#[derive(Clone, Debug, PartialEq)]
+pub enum DataWarning {
+    /// A fuel in the mix has no carbon factor, so it's shown as unknown and
+    /// left out of the mix's intensity
+    UnknownFuel { fuel: String },
+    /// A generation mix's shares don't add up to 100%
+    MixTotal { total: f64 },
+    /// The intensity worked out from the mix and factors is further from the
+    /// reported figure than the threshold
+    MixDivergence { reported: i32, computed: i32, threshold: u32 },
+    /// A panel couldn't be refreshed and shows data fetched at `since`
+    Stale { panel: &'static str, since: DateTime<Utc> },
+    /// A theme's palette fails the contrast check
+    PaletteContrast { theme: &'static str, detail: String },
+}
+
+impl fmt::Display for DataWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DataWarning::UnknownFuel { fuel } => write!(f, "no carbon factor for '{fuel}'; showing it as unknown"),
+            DataWarning::MixTotal { total } => write!(f, "generation mix adds up to {total:.1}%, not 100%"),
+            DataWarning::MixDivergence { reported, computed, threshold } => write!(
+                f,
+                "mix × factors gives {computed} gCO₂/kWh against {reported} reported, more than {threshold} apart"
+            ),
+            DataWarning::Stale { panel, since } => write!(
+                f,
+                "{panel} couldn't be refreshed; showing data from {since}",
+                since = since.to_rfc3339_opts(chrono::SecondsFormat::Secs, true)
+            ),
+            DataWarning::PaletteContrast { theme, detail } => write!(f, "{theme} theme: {detail}"),
+        }
+    }
+}
+
+/// `--strict`, accepted by every command
+#[cfg(feature = "native")]
+#[derive(Clone, Debug, clap::Args)]
+pub struct StrictArgs {
+    /// Fail on any data-quality warning, such as an unknown fuel, a mix that
+    /// doesn't add up to 100% or stale data, rather than logging it
+    #[arg(long, global = true)]
+    strict: bool,
+}
+
+#[cfg(feature = "native")]
+impl StrictArgs {
+    /// Treat warnings as errors from now on, with `--strict`
+    pub fn apply(&self) {
+        let _ = STRICT.set(self.strict);
+    }
+}
+
+/// Whether `--strict` was given
+pub fn strict() -> bool {
+    STRICT.get().copied().unwrap_or_default()
+}
+
+/// The warnings raised by one fetch or command, checked together. Each
+/// dashboard refresh has its own, so one fetch's warnings can't fail another.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Warnings(Vec<DataWarning>);
+
+impl Warnings {
+    /// Log `warning` and keep it for [`check`](Self::check)
+    pub fn raise(&mut self, warning: DataWarning) {
+        warn!("{warning}");
+        self.0.push(warning);
+    }
+
+    /// Flag a generation mix whose `shares` are more than
+    /// [`MIX_TOTAL_TOLERANCE`] from 100%. An empty mix is left to the caller.
+    pub fn check_mix_total(&mut self, shares: impl IntoIterator<Item = f64>) {
+        let (count, total) = shares.into_iter().fold((0, 0.0), |(count, total), perc| (count + 1, total + perc));
+        if count > 0 && (total - 100.0).abs() > MIX_TOTAL_TOLERANCE {
+            self.raise(DataWarning::MixTotal { total });
+        }
+    }
+
+    /// The warnings raised so far, oldest first
+    pub fn raised(&self) -> &[DataWarning] {
+        &self.0
+    }
+
+    /// Under `--strict`, fail with the warnings raised. They've been logged
+    /// either way.
+    pub fn check(self) -> Result<(), CarbonError> {
+        if strict() && !self.0.is_empty() {
+            Err(CarbonError::DataQuality(self.0))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Leave the warnings for the command to fail on once it's done, for
+    /// library code with no result of its own to fail
+    pub fn defer(self) {
+        if strict() {
+            DEFERRED.lock().unwrap_or_else(|e| e.into_inner()).extend(self.0);
+        }
+    }
+}
+
+/// Under `--strict`, fail with the warnings [deferred](Warnings::defer) so
+/// far, and clear them. `cli::exit_on_error` calls this once a command is
+/// done.
+pub fn check_deferred() -> Result<(), CarbonError> {
+    Warnings(std::mem::take(&mut *DEFERRED.lock().unwrap_or_else(|e| e.into_inner()))).check()
+}
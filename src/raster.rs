@@ -0,0 +1,94 @@
+//! Rasterising the dashboard's SVGs for places that can't show SVG: PNG and
+//! JPEG charts, also as `data:` URIs, and 1-bit PNGs for e-paper displays
+
+use crate::theme::rgb;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use resvg::usvg::fontdb::Database;
+use resvg::{tiny_skia, usvg};
+use std::sync::Arc;
+
+// Fonts `/chart.png` and `/eink` labels are drawn in, most Arial-like first
+const SANS_SERIF_FONTS: [&str; 5] = ["Arial", "Helvetica", "Liberation Sans", "DejaVu Sans", "Noto Sans"];
+
+/// The system's fonts, with sans-serif pointed at the most Arial-like one
+/// installed. Slow enough to load once and share.
+pub fn system_fonts() -> Database {
+    let mut fonts = Database::new();
+    fonts.load_system_fonts();
+    // Labels ask for Arial, then sans-serif, which fontdb also takes to mean
+    // Arial; point that at whichever sans-serif is installed
+    let installed = SANS_SERIF_FONTS.into_iter().find(|family| {
+        fonts
+            .faces()
+            .any(|face| face.families.iter().any(|(name, _)| name == family))
+    });
+    if let Some(family) = installed {
+        fonts.set_sans_serif_family(family);
+    }
+    fonts
+}
+
+/// Draw `svg`, whose colours are already resolved, through `transform`
+/// onto an opaque `background`, so it looks the same wherever it's shown
+/// and JPEG has nothing to lose
+pub fn rasterise(
+    svg: &str,
+    background: &str,
+    transform: tiny_skia::Transform,
+    (width, height): (u32, u32),
+    fonts: &Arc<Database>,
+) -> Result<tiny_skia::Pixmap, String> {
+    let options = usvg::Options {
+        font_family: "Arial".to_string(),
+        fontdb: Arc::clone(fonts),
+        ..usvg::Options::default()
+    };
+    let tree = usvg::Tree::from_str(svg, &options).map_err(|e| e.to_string())?;
+    let mut pixmap = tiny_skia::Pixmap::new(width, height).ok_or("Empty image size")?;
+    let [r, g, b] = rgb(background).ok_or_else(|| format!("Invalid background colour '{background}'"))?;
+    pixmap.fill(tiny_skia::Color::from_rgba8(r, g, b, 255));
+    resvg::render(&tree, transform, &mut pixmap.as_mut());
+    Ok(pixmap)
+}
+
+/// `pixmap` as a 1-bit greyscale PNG, each pixel black or white by its
+/// luma, as e-paper displays take it
+pub fn encode_monochrome(pixmap: &tiny_skia::Pixmap) -> Result<Vec<u8>, String> {
+    let (width, height) = (pixmap.width(), pixmap.height());
+    let row_bytes = (width as usize).div_ceil(8);
+    let mut bits = vec![0u8; row_bytes * height as usize];
+    for (i, pixel) in pixmap.pixels().iter().enumerate() {
+        // Drawn over an opaque background, so premultiplied is plain
+        let luma = 0.299 * pixel.red() as f64 + 0.587 * pixel.green() as f64 + 0.114 * pixel.blue() as f64;
+        if luma >= 128.0 {
+            let (x, y) = (i % width as usize, i / width as usize);
+            bits[y * row_bytes + x / 8] |= 0x80 >> (x % 8);
+        }
+    }
+
+    let mut image = Vec::new();
+    let mut encoder = png::Encoder::new(&mut image, width, height);
+    encoder.set_color(png::ColorType::Grayscale);
+    encoder.set_depth(png::BitDepth::One);
+    encoder
+        .write_header()
+        .and_then(|mut writer| writer.write_image_data(&bits))
+        .map_err(|e| e.to_string())?;
+    Ok(image)
+}
+
+/// `pixmap` as a JPEG at `quality`, 1-100
+pub fn encode_jpeg(pixmap: &tiny_skia::Pixmap, quality: u8) -> Result<Vec<u8>, String> {
+    let mut jpeg = Vec::new();
+    jpeg_encoder::Encoder::new(&mut jpeg, quality)
+        .encode(pixmap.data(), pixmap.width() as u16, pixmap.height() as u16, jpeg_encoder::ColorType::Rgba)
+        .map_err(|e| e.to_string())?;
+    Ok(jpeg)
+}
+
+/// `image` as a `data:` URI, e.g. `data:image/png;base64,iVBORw0...`, to put
+/// straight into an `<img src>` where the image can't be linked to
+pub fn data_uri(content_type: &str, image: &[u8]) -> String {
+    format!("data:{content_type};base64,{image}", image = BASE64.encode(image))
+}
@@ -0,0 +1,289 @@
+//! Finding low-carbon times to run things from the forecast
+
+use crate::model::{IntensityIndex, IntensityPoint};
+use crate::octopus::UnitRate;
+use crate::settlement::SETTLEMENT_PERIOD;
+use chrono::{DateTime, TimeDelta, Utc};
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// A run of contiguous settlement periods
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Window {
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    /// Mean intensity over the window in gCO₂/kWh
+    pub average: f64,
+}
+
+/// The contiguous window of `duration` (rounded up to whole settlement
+/// periods) with the lowest average intensity. `points` must be oldest first;
+/// windows spanning a gap in the data are skipped. The earliest window wins a
+/// tie.
+pub fn greenest_window(points: &[IntensityPoint], duration: TimeDelta) -> Option<Window> {
+    let periods = periods_in(duration);
+    if periods == 0 {
+        return None;
+    }
+
+    points
+        .windows(periods)
+        .filter(|run| run.windows(2).all(|pair| pair[0].to == pair[1].from))
+        .map(|run| Window {
+            from: run[0].from,
+            to: run[run.len() - 1].to,
+            average: run.iter().map(|p| p.intensity as f64).sum::<f64>() / run.len() as f64,
+        })
+        .fold(None, |best: Option<Window>, window| match best {
+            Some(best) if best.average <= window.average => Some(best),
+            _ => Some(window),
+        })
+}
+
+/// A [`Window`] with its mean unit price as well
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct PricedWindow {
+    #[serde(flatten)]
+    pub window: Window,
+    /// Mean price over the window in pence/kWh
+    pub average_price: f64,
+}
+
+/// The contiguous window of `duration` that's best for carbon and price
+/// together. Each period scores where its intensity and its price fall
+/// between the lowest and highest on offer, from 0 (best) to 1 (worst),
+/// weighted `price_weight` to price and the rest to carbon, so 0 gives the
+/// greenest window and 1 the cheapest. The window with the lowest mean score
+/// wins, the earliest on a tie. Periods without a price are left out, so no
+/// window spans one.
+pub fn cheap_green_window(
+    points: &[IntensityPoint],
+    rates: &[UnitRate],
+    duration: TimeDelta,
+    price_weight: f64,
+) -> Option<PricedWindow> {
+    let periods = periods_in(duration);
+    if periods == 0 {
+        return None;
+    }
+
+    let prices: HashMap<DateTime<Utc>, f64> =
+        rates.iter().map(|rate| (rate.from, rate.price)).collect();
+    let priced: Vec<(&IntensityPoint, f64)> = points
+        .iter()
+        .filter_map(|point| Some((point, *prices.get(&point.from)?)))
+        .collect();
+
+    let intensity_scale = Scale::of(priced.iter().map(|(point, _)| point.intensity as f64))?;
+    let price_scale = Scale::of(priced.iter().map(|(_, price)| *price))?;
+    let price_weight = price_weight.clamp(0.0, 1.0);
+    let score = |(point, price): &(&IntensityPoint, f64)| {
+        (1.0 - price_weight) * intensity_scale.position(point.intensity as f64)
+            + price_weight * price_scale.position(*price)
+    };
+
+    priced
+        .windows(periods)
+        .filter(|run| run.windows(2).all(|pair| pair[0].0.to == pair[1].0.from))
+        .map(|run| {
+            let count = run.len() as f64;
+            let window = PricedWindow {
+                window: Window {
+                    from: run[0].0.from,
+                    to: run[run.len() - 1].0.to,
+                    average: run.iter().map(|(p, _)| p.intensity as f64).sum::<f64>() / count,
+                },
+                average_price: run.iter().map(|(_, price)| price).sum::<f64>() / count,
+            };
+            (window, run.iter().map(score).sum::<f64>() / count)
+        })
+        .fold(
+            None,
+            |best: Option<(PricedWindow, f64)>, (window, score)| match best {
+                Some(best) if best.1 <= score => Some(best),
+                _ => Some((window, score)),
+            },
+        )
+        .map(|(window, _)| window)
+}
+
+/// Charging during one settlement period, or the part of it the plan uses
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct ChargeSlot {
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    /// Energy delivered in the slot, in kWh
+    pub energy_kwh: f64,
+    /// The period's intensity in gCO₂/kWh
+    pub intensity: i32,
+    pub index: Option<IntensityIndex>,
+}
+
+impl ChargeSlot {
+    /// Grams of CO₂ for the energy delivered
+    pub fn emissions_g(&self) -> f64 {
+        self.energy_kwh * self.intensity as f64
+    }
+}
+
+/// When to deliver `energy_kwh` at `power_kw` between `from` and `to` for
+/// the least CO₂, in time order. Charging can pause, so unlike
+/// [`greenest_window`] the periods needn't be contiguous: the greenest are
+/// filled first, the earliest on a tie, and the last one filled may only be
+/// needed from its start for part of its length. `None` if the periods in
+/// `points` can't deliver that much in time.
+pub fn greenest_charge(
+    points: &[IntensityPoint],
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+    energy_kwh: f64,
+    power_kw: f64,
+) -> Option<Vec<ChargeSlot>> {
+    let mut order: Vec<&IntensityPoint> = points.iter().collect();
+    // Stable, so a tie keeps the earlier period first
+    order.sort_by_key(|point| point.intensity);
+    fill_charge(order, from, to, energy_kwh, power_kw)
+}
+
+/// Charging from `from` without a pause, as a car does when it's plugged
+/// in, to set against [`greenest_charge`]
+pub fn immediate_charge(
+    points: &[IntensityPoint],
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+    energy_kwh: f64,
+    power_kw: f64,
+) -> Option<Vec<ChargeSlot>> {
+    fill_charge(points.iter().collect(), from, to, energy_kwh, power_kw)
+}
+
+// Rounding left over from summing slots' energy, well under a watt-hour
+const ENERGY_TOLERANCE: f64 = 1e-9;
+
+/// Fill periods in `order` at `power_kw`, each clipped to `from`..`to`,
+/// until `energy_kwh` is delivered
+fn fill_charge(
+    order: Vec<&IntensityPoint>,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+    energy_kwh: f64,
+    power_kw: f64,
+) -> Option<Vec<ChargeSlot>> {
+    let mut remaining = energy_kwh;
+    let mut slots = Vec::new();
+    for point in order {
+        if remaining <= ENERGY_TOLERANCE {
+            break;
+        }
+        let start = point.from.max(from);
+        let end = point.to.min(to);
+        if end <= start {
+            continue;
+        }
+        let capacity = power_kw * (end - start).num_seconds() as f64 / 3600.0;
+        let energy = capacity.min(remaining);
+        let end = if energy < capacity {
+            start + TimeDelta::seconds((energy / power_kw * 3600.0).ceil() as i64)
+        } else {
+            end
+        };
+        slots.push(ChargeSlot {
+            from: start,
+            to: end,
+            energy_kwh: energy,
+            intensity: point.intensity,
+            index: point.index,
+        });
+        remaining -= energy;
+    }
+    if remaining > ENERGY_TOLERANCE {
+        return None;
+    }
+    slots.sort_by_key(|slot| slot.from);
+    Some(slots)
+}
+
+/// Range of a set of values, for placing each one between 0 (lowest) and 1
+/// (highest)
+struct Scale {
+    min: f64,
+    max: f64,
+}
+
+impl Scale {
+    fn of(values: impl Iterator<Item = f64>) -> Option<Scale> {
+        values.fold(None, |scale, value| match scale {
+            None => Some(Scale {
+                min: value,
+                max: value,
+            }),
+            Some(Scale { min, max }) => Some(Scale {
+                min: min.min(value),
+                max: max.max(value),
+            }),
+        })
+    }
+
+    /// 0 for every value when they're all the same
+    fn position(&self, value: f64) -> f64 {
+        if self.max > self.min {
+            (value - self.min) / (self.max - self.min)
+        } else {
+            0.0
+        }
+    }
+}
+
+/// Number of settlement periods needed to cover `duration`
+pub fn periods_in(duration: TimeDelta) -> usize {
+    let period = SETTLEMENT_PERIOD.num_seconds();
+    let seconds = duration.num_seconds().max(0);
+    ((seconds + period - 1) / period) as usize
+}
+
+/// `3h`, `45m` or `1h30m`, the forms `parse_duration_arg` accepts
+pub fn describe_duration(duration: TimeDelta) -> String {
+    match (duration.num_hours(), duration.num_minutes() % 60) {
+        (0, minutes) => format!("{minutes}m"),
+        (hours, 0) => format!("{hours}h"),
+        (hours, minutes) => format!("{hours}h{minutes}m"),
+    }
+}
+
+/// Parse a duration given on the command line, e.g. `3h`, `90m` or `1h30m`.
+/// A bare number is taken as hours.
+pub fn parse_duration_arg(value: &str) -> Result<TimeDelta, String> {
+    let invalid = || format!("Invalid duration '{value}': expected e.g. 3h, 90m or 1h30m");
+
+    if let Ok(hours) = value.parse::<i64>() {
+        return TimeDelta::try_hours(hours)
+            .filter(|d| *d > TimeDelta::zero())
+            .ok_or_else(invalid);
+    }
+
+    let mut total = TimeDelta::zero();
+    let mut digits = String::new();
+    for c in value.chars() {
+        match c {
+            '0'..='9' => digits.push(c),
+            'h' | 'm' if !digits.is_empty() => {
+                let amount: i64 = digits.parse().map_err(|_| invalid())?;
+                let part = if c == 'h' {
+                    TimeDelta::try_hours(amount)
+                } else {
+                    TimeDelta::try_minutes(amount)
+                };
+                total = part
+                    .and_then(|part| total.checked_add(&part))
+                    .ok_or_else(invalid)?;
+                digits.clear();
+            }
+            _ => return Err(invalid()),
+        }
+    }
+
+    if !digits.is_empty() || total <= TimeDelta::zero() {
+        return Err(invalid());
+    }
+    Ok(total)
+}
@@ -0,0 +1,103 @@
+//! UK electricity settlement periods: the half hours the GB market, and so
+//! the Carbon Intensity API, reports everything in. Periods start on the
+//! hour and half hour and are numbered from 1 at midnight UK time, so a
+//! settlement day has 48 of them, or 46 and 50 when the clocks change.
+
+use chrono::{DateTime, NaiveDate, NaiveTime, TimeDelta, TimeZone, Utc};
+use chrono_tz::Europe::London;
+use std::fmt;
+
+/// Length of one settlement period
+pub const SETTLEMENT_PERIOD: TimeDelta = TimeDelta::minutes(30);
+
+/// How long after a period starts its figures are expected upstream, so
+/// pollers waking for new data don't ask a moment too soon
+pub const PUBLICATION_DELAY: TimeDelta = TimeDelta::minutes(1);
+
+/// One settlement period
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SettlementPeriod {
+    from: DateTime<Utc>,
+}
+
+impl SettlementPeriod {
+    /// The period `time` falls in
+    pub fn containing(time: DateTime<Utc>) -> SettlementPeriod {
+        SettlementPeriod {
+            from: floor(time, SETTLEMENT_PERIOD),
+        }
+    }
+
+    pub fn from(self) -> DateTime<Utc> {
+        self.from
+    }
+
+    pub fn to(self) -> DateTime<Utc> {
+        self.from + SETTLEMENT_PERIOD
+    }
+
+    pub fn next(self) -> SettlementPeriod {
+        SettlementPeriod { from: self.to() }
+    }
+
+    pub fn previous(self) -> SettlementPeriod {
+        SettlementPeriod {
+            from: self.from - SETTLEMENT_PERIOD,
+        }
+    }
+
+    /// The UK calendar day the period is settled on
+    pub fn date(self) -> NaiveDate {
+        self.from.with_timezone(&London).date_naive()
+    }
+
+    /// Number within its settlement day, from 1 at midnight UK time
+    pub fn number(self) -> u32 {
+        let midnight = uk_midnight(self.date());
+        ((self.from - midnight).num_minutes() / SETTLEMENT_PERIOD.num_minutes()) as u32 + 1
+    }
+}
+
+/// `2025-06-26 SP29`, the form the market uses
+impl fmt::Display for SettlementPeriod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{date} SP{number}",
+            date = self.date(),
+            number = self.number()
+        )
+    }
+}
+
+/// Start of the UK calendar day `date`, when its first settlement period
+/// begins
+pub fn uk_midnight(date: NaiveDate) -> DateTime<Utc> {
+    London
+        .from_local_datetime(&date.and_time(NaiveTime::MIN))
+        .earliest()
+        // The clocks never change at midnight in the UK, so this is never
+        // needed
+        .map_or_else(|| date.and_time(NaiveTime::MIN).and_utc(), |midnight| midnight.with_timezone(&Utc))
+}
+
+/// `time` rounded down to a multiple of `width` since the Unix epoch, which
+/// for divisions of a day is a boundary in UTC: the hour for an hour, the
+/// hour or half hour for a settlement period
+pub fn floor(time: DateTime<Utc>, width: TimeDelta) -> DateTime<Utc> {
+    let width = width.num_seconds().max(1);
+    let offset = time.timestamp().rem_euclid(width);
+    time - TimeDelta::seconds(offset) - TimeDelta::nanoseconds(time.timestamp_subsec_nanos() as i64)
+}
+
+/// Time from `now` until the next settlement period starts
+pub fn until_next_period(now: DateTime<Utc>) -> TimeDelta {
+    SettlementPeriod::containing(now).to() - now
+}
+
+/// When a poller running every `interval` should next wake after `now`: the
+/// next multiple of `interval` (so a half-hourly one wakes with each new
+/// period) plus [`PUBLICATION_DELAY`] for the new figures to appear
+pub fn next_poll(now: DateTime<Utc>, interval: TimeDelta) -> DateTime<Utc> {
+    floor(now - PUBLICATION_DELAY, interval) + interval + PUBLICATION_DELAY
+}
@@ -0,0 +1,418 @@
+//! Local SQLite archive of national intensity and generation mix. The API
+//! only serves a limited window of history, so `archive record` saves each
+//! settlement period as it is published and the query commands read it back.
+//! It also keeps forecasts as they stood hours ahead, which the published
+//! history overwrites, for `accuracy`, checkpoints so a long `backfill` can
+//! pick up where it stopped, and gaps where recording kept failing.
+
+use crate::accuracy::{ForecastOutcome, lead_bucket};
+use crate::model::{FuelSource, GenerationMixPeriod, IntensityIndex, IntensityPoint};
+use chrono::{DateTime, Utc};
+use rusqlite::types::{FromSql, FromSqlError, FromSqlResult, ToSql, ToSqlOutput, ValueRef};
+use rusqlite::{Connection, OpenFlags, OptionalExtension, params};
+use serde::Serialize;
+use std::path::Path;
+
+/// Database file used when none is given
+pub const DEFAULT_DB_PATH: &str = "carbon-vibe.db";
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS intensity (
+        period_from TEXT PRIMARY KEY,
+        period_to   TEXT NOT NULL,
+        intensity   INTEGER NOT NULL,
+        actual      INTEGER,
+        forecast    INTEGER,
+        band        TEXT
+    );
+    CREATE TABLE IF NOT EXISTS generation_mix (
+        period_from TEXT NOT NULL,
+        period_to   TEXT NOT NULL,
+        fuel        TEXT NOT NULL,
+        perc        REAL NOT NULL,
+        PRIMARY KEY (period_from, fuel)
+    );
+    CREATE TABLE IF NOT EXISTS forecasts (
+        period_from TEXT NOT NULL,
+        lead_hours  INTEGER NOT NULL,
+        issued      TEXT NOT NULL,
+        forecast    INTEGER NOT NULL,
+        PRIMARY KEY (period_from, lead_hours)
+    );
+    CREATE TABLE IF NOT EXISTS checkpoints (
+        name TEXT PRIMARY KEY,
+        at   TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS gaps (
+        gap_from TEXT PRIMARY KEY,
+        gap_to   TEXT NOT NULL,
+        failures INTEGER NOT NULL,
+        error    TEXT NOT NULL
+    );
+";
+
+/// What the archive holds, for `archive stats`
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct ArchiveSummary {
+    pub intensity_periods: u64,
+    pub generation_periods: u64,
+    /// Start of the oldest and end of the newest intensity period
+    pub earliest: Option<DateTime<Utc>>,
+    pub latest: Option<DateTime<Utc>>,
+}
+
+/// A run of recording passes that failed in a row. Periods published
+/// meanwhile are missing unless a later pass fetched them while the API
+/// still served them.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Gap {
+    /// When the first failed pass ran
+    pub from: DateTime<Utc>,
+    /// When the latest failed pass ran
+    pub to: DateTime<Utc>,
+    pub failures: u32,
+    /// Why the latest pass failed
+    pub error: String,
+}
+
+pub struct Store {
+    conn: Connection,
+}
+
+impl Store {
+    /// Open (creating if needed) the archive at `path`
+    pub fn open(path: impl AsRef<Path>) -> rusqlite::Result<Self> {
+        Store::init(Connection::open(path)?)
+    }
+
+    /// A throwaway archive held in memory
+    pub fn open_in_memory() -> rusqlite::Result<Self> {
+        Store::init(Connection::open_in_memory()?)
+    }
+
+    /// Open an existing archive at `path` only to read from it, creating
+    /// and changing nothing
+    pub fn open_read_only(path: impl AsRef<Path>) -> rusqlite::Result<Self> {
+        let conn = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+        Ok(Store { conn })
+    }
+
+    fn init(conn: Connection) -> rusqlite::Result<Self> {
+        conn.execute_batch(SCHEMA)?;
+        Ok(Store { conn })
+    }
+
+    /// Save intensity points, replacing any already stored for the same
+    /// period so forecasts are overwritten once actuals are published.
+    /// Returns the number of rows written.
+    pub fn record_intensity(&mut self, points: &[IntensityPoint]) -> rusqlite::Result<usize> {
+        let tx = self.conn.transaction()?;
+        {
+            let mut insert = tx.prepare_cached(
+                "INSERT OR REPLACE INTO intensity
+                     (period_from, period_to, intensity, actual, forecast, band)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            )?;
+            for point in points {
+                insert.execute(params![
+                    point.from,
+                    point.to,
+                    point.intensity,
+                    point.actual,
+                    point.forecast,
+                    point.index,
+                ])?;
+            }
+        }
+        tx.commit()?;
+        Ok(points.len())
+    }
+
+    /// Save generation mix periods, replacing any already stored for the same
+    /// period. Returns the number of periods written.
+    pub fn record_generation(
+        &mut self,
+        periods: &[GenerationMixPeriod],
+    ) -> rusqlite::Result<usize> {
+        let tx = self.conn.transaction()?;
+        {
+            let mut clear =
+                tx.prepare_cached("DELETE FROM generation_mix WHERE period_from = ?1")?;
+            let mut insert = tx.prepare_cached(
+                "INSERT INTO generation_mix (period_from, period_to, fuel, perc)
+                 VALUES (?1, ?2, ?3, ?4)",
+            )?;
+            for period in periods {
+                clear.execute(params![period.from])?;
+                for source in &period.generation_mix {
+                    insert.execute(params![period.from, period.to, source.fuel, source.perc])?;
+                }
+            }
+        }
+        tx.commit()?;
+        Ok(periods.len())
+    }
+
+    /// Save intensity points for periods not stored yet, keeping any that
+    /// are, which may have newer actuals. Returns the number of rows written.
+    pub fn seed_intensity(&mut self, points: &[IntensityPoint]) -> rusqlite::Result<usize> {
+        let tx = self.conn.transaction()?;
+        let mut written = 0;
+        {
+            let mut insert = tx.prepare_cached(
+                "INSERT OR IGNORE INTO intensity
+                     (period_from, period_to, intensity, actual, forecast, band)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            )?;
+            for point in points {
+                written += insert.execute(params![
+                    point.from,
+                    point.to,
+                    point.intensity,
+                    point.actual,
+                    point.forecast,
+                    point.index,
+                ])?;
+            }
+        }
+        tx.commit()?;
+        Ok(written)
+    }
+
+    /// Save generation mix periods not stored yet, keeping any that are.
+    /// Returns the number of periods written.
+    pub fn seed_generation(&mut self, periods: &[GenerationMixPeriod]) -> rusqlite::Result<usize> {
+        let tx = self.conn.transaction()?;
+        let mut written = 0;
+        {
+            let mut stored = tx.prepare_cached("SELECT 1 FROM generation_mix WHERE period_from = ?1 LIMIT 1")?;
+            let mut insert = tx.prepare_cached(
+                "INSERT INTO generation_mix (period_from, period_to, fuel, perc)
+                 VALUES (?1, ?2, ?3, ?4)",
+            )?;
+            for period in periods {
+                if stored.exists(params![period.from])? {
+                    continue;
+                }
+                for source in &period.generation_mix {
+                    insert.execute(params![period.from, period.to, source.fuel, source.perc])?;
+                }
+                written += 1;
+            }
+        }
+        tx.commit()?;
+        Ok(written)
+    }
+
+    /// Save forecasts fetched at `issued` under their lead time bucket,
+    /// keeping the earliest already stored in each bucket so it holds the
+    /// longest-range forecast. Periods already over and forecasts beyond 48
+    /// hours are skipped. Returns the number of rows written.
+    pub fn record_forecasts(
+        &mut self,
+        issued: DateTime<Utc>,
+        points: &[IntensityPoint],
+    ) -> rusqlite::Result<usize> {
+        let tx = self.conn.transaction()?;
+        let mut written = 0;
+        {
+            let mut insert = tx.prepare_cached(
+                "INSERT OR IGNORE INTO forecasts (period_from, lead_hours, issued, forecast)
+                 VALUES (?1, ?2, ?3, ?4)",
+            )?;
+            for point in points.iter().filter(|point| point.to > issued) {
+                let (Some(forecast), Some(lead_hours)) =
+                    (point.forecast, lead_bucket(point.from - issued))
+                else {
+                    continue;
+                };
+                written += insert.execute(params![point.from, lead_hours, issued, forecast])?;
+            }
+        }
+        tx.commit()?;
+        Ok(written)
+    }
+
+    /// Stored forecasts for periods starting in `[from, to)` whose actual
+    /// has since been published, oldest first
+    pub fn forecast_outcomes_between(
+        &self,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> rusqlite::Result<Vec<ForecastOutcome>> {
+        let mut query = self.conn.prepare_cached(
+            "SELECT forecasts.lead_hours, forecasts.forecast, intensity.actual
+             FROM forecasts JOIN intensity USING (period_from)
+             WHERE intensity.actual IS NOT NULL
+               AND period_from >= ?1 AND period_from < ?2
+             ORDER BY period_from, lead_hours",
+        )?;
+        let rows = query.query_map(params![from, to], |row| {
+            Ok(ForecastOutcome {
+                lead_hours: row.get(0)?,
+                forecast: row.get(1)?,
+                actual: row.get(2)?,
+            })
+        })?;
+        rows.collect()
+    }
+
+    /// Stored intensity for periods starting in `[from, to)`, oldest first
+    pub fn intensity_between(
+        &self,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> rusqlite::Result<Vec<IntensityPoint>> {
+        let mut query = self.conn.prepare_cached(
+            "SELECT period_from, period_to, intensity, actual, forecast, band
+             FROM intensity
+             WHERE period_from >= ?1 AND period_from < ?2
+             ORDER BY period_from",
+        )?;
+        let rows = query.query_map(params![from, to], |row| {
+            Ok(IntensityPoint {
+                from: row.get(0)?,
+                to: row.get(1)?,
+                intensity: row.get(2)?,
+                actual: row.get(3)?,
+                forecast: row.get(4)?,
+                index: row.get(5)?,
+            })
+        })?;
+        rows.collect()
+    }
+
+    /// Stored generation mix for periods starting in `[from, to)`, oldest
+    /// first
+    pub fn generation_between(
+        &self,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> rusqlite::Result<Vec<GenerationMixPeriod>> {
+        let mut query = self.conn.prepare_cached(
+            "SELECT period_from, period_to, fuel, perc
+             FROM generation_mix
+             WHERE period_from >= ?1 AND period_from < ?2
+             ORDER BY period_from, fuel",
+        )?;
+        let mut rows = query.query(params![from, to])?;
+
+        // Rows come one per fuel; fold consecutive ones into their period
+        let mut periods: Vec<GenerationMixPeriod> = Vec::new();
+        while let Some(row) = rows.next()? {
+            let from: DateTime<Utc> = row.get(0)?;
+            let source = FuelSource {
+                fuel: row.get(2)?,
+                perc: row.get(3)?,
+            };
+            match periods.last_mut() {
+                Some(period) if period.from == from => period.generation_mix.push(source),
+                _ => periods.push(GenerationMixPeriod {
+                    from,
+                    to: row.get(1)?,
+                    generation_mix: vec![source],
+                }),
+            }
+        }
+        Ok(periods)
+    }
+
+    /// Start of the newest stored intensity period
+    pub fn latest_intensity(&self) -> rusqlite::Result<Option<DateTime<Utc>>> {
+        self.conn
+            .query_row("SELECT MAX(period_from) FROM intensity", [], |row| {
+                row.get(0)
+            })
+            .optional()
+            .map(Option::flatten)
+    }
+
+    /// Time saved under a checkpoint `name`, if any
+    pub fn checkpoint(&self, name: &str) -> rusqlite::Result<Option<DateTime<Utc>>> {
+        self.conn
+            .query_row("SELECT at FROM checkpoints WHERE name = ?1", params![name], |row| {
+                row.get(0)
+            })
+            .optional()
+    }
+
+    /// Save `at` under a checkpoint `name`, replacing any earlier one
+    pub fn set_checkpoint(&mut self, name: &str, at: DateTime<Utc>) -> rusqlite::Result<()> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO checkpoints (name, at) VALUES (?1, ?2)",
+            params![name, at],
+        )?;
+        Ok(())
+    }
+
+    /// Forget a checkpoint, so the next run starts afresh
+    pub fn clear_checkpoint(&mut self, name: &str) -> rusqlite::Result<()> {
+        self.conn
+            .execute("DELETE FROM checkpoints WHERE name = ?1", params![name])?;
+        Ok(())
+    }
+
+    /// Save `gap`, replacing one that started at the same time, so a run of
+    /// failures is one gap however long it lasts
+    pub fn record_gap(&mut self, gap: &Gap) -> rusqlite::Result<()> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO gaps (gap_from, gap_to, failures, error) VALUES (?1, ?2, ?3, ?4)",
+            params![gap.from, gap.to, gap.failures, gap.error],
+        )?;
+        Ok(())
+    }
+
+    /// Gaps overlapping `[from, to)`, oldest first
+    pub fn gaps_between(&self, from: DateTime<Utc>, to: DateTime<Utc>) -> rusqlite::Result<Vec<Gap>> {
+        let mut query = self.conn.prepare_cached(
+            "SELECT gap_from, gap_to, failures, error FROM gaps
+             WHERE gap_to >= ?1 AND gap_from < ?2
+             ORDER BY gap_from",
+        )?;
+        let gaps = query.query_map(params![from, to], |row| {
+            Ok(Gap {
+                from: row.get(0)?,
+                to: row.get(1)?,
+                failures: row.get(2)?,
+                error: row.get(3)?,
+            })
+        })?;
+        gaps.collect()
+    }
+
+    pub fn summary(&self) -> rusqlite::Result<ArchiveSummary> {
+        let (intensity_periods, earliest, latest) = self.conn.query_row(
+            "SELECT COUNT(*), MIN(period_from), MAX(period_to) FROM intensity",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )?;
+        let generation_periods = self.conn.query_row(
+            "SELECT COUNT(DISTINCT period_from) FROM generation_mix",
+            [],
+            |row| row.get(0),
+        )?;
+
+        Ok(ArchiveSummary {
+            intensity_periods,
+            generation_periods,
+            earliest,
+            latest,
+        })
+    }
+}
+
+/// Stored by the API's name for the band, e.g. "very low"
+impl ToSql for IntensityIndex {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::from(self.as_str()))
+    }
+}
+
+impl FromSql for IntensityIndex {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        value
+            .as_str()?
+            .parse()
+            .map_err(|e: String| FromSqlError::Other(e.into()))
+    }
+}
@@ -0,0 +1,321 @@
+//! Colour themes for the web dashboard. Each [`Theme`] is a full set of
+//! colours; the page defines them as CSS custom properties and every chart
+//! refers to them by name (`var(--grid)`), so adding a theme only means
+//! adding one here.
+
+use crate::model::IntensityIndex;
+use serde::Deserialize;
+
+/// Number of fuel colours in every theme's palette. Even, so the colours
+/// can alternate lighter and darker all the way round the pie.
+pub const PALETTE_SIZE: usize = 16;
+
+/// WCAG 2.1 SC 1.4.11 minimum contrast for graphical objects, which every
+/// built-in palette meets
+pub const DEFAULT_MIN_CONTRAST: f64 = 3.0;
+
+/// Colour for fuels that aren't in the current mix, e.g. `imports` in the mix
+/// history when split by interconnector
+pub const UNKNOWN_FUEL_COLOR: &str = "var(--fuel-unknown)";
+
+/// Colour for the `i`th fuel of the generation mix: slices, legend swatches,
+/// sparklines and stacked areas all agree
+pub fn fuel_color(i: usize) -> String {
+    format!("var(--fuel-{slot})", slot = i % PALETTE_SIZE)
+}
+
+#[derive(Debug)]
+pub struct Theme {
+    pub name: &'static str,
+    pub page_background: &'static str,
+    /// Cards, and the background the legend swatches sit on
+    pub card_background: &'static str,
+    pub card_shadow: &'static str,
+    pub title: &'static str,
+    pub heading: &'static str,
+    pub muted: &'static str,
+    pub chart_background: &'static str,
+    pub plot_background: &'static str,
+    pub plot_border: &'static str,
+    pub grid: &'static str,
+    pub axis_label: &'static str,
+    pub axis_title: &'static str,
+    pub actual_line: &'static str,
+    pub forecast_line: &'static str,
+    pub trend_line: &'static str,
+    pub now_marker: &'static str,
+    /// Between pie slices and stacked areas
+    pub slice_border: &'static str,
+    pub slice_label: &'static str,
+    pub slice_label_secondary: &'static str,
+    /// Intensity bands, very low first
+    pub bands: [&'static str; 5],
+    /// Assigned to fuels in order, alternating lighter and darker so that
+    /// neighbouring slices stand apart
+    pub palette: [&'static str; PALETTE_SIZE],
+    pub unknown_fuel: &'static str,
+}
+
+pub const LIGHT: Theme = Theme {
+    name: "light",
+    page_background: "#f5f5f5",
+    card_background: "#FFFFFF",
+    card_shadow: "rgba(0,0,0,0.1)",
+    title: "#333333",
+    heading: "#2c3e50",
+    muted: "#7f8c8d",
+    chart_background: "#f8f9fa",
+    plot_background: "#FFFFFF",
+    plot_border: "#dee2e6",
+    grid: "#e9ecef",
+    axis_label: "#6c757d",
+    axis_title: "#495057",
+    actual_line: "#2c3e50",
+    forecast_line: "#7f8c8d",
+    trend_line: "#e67e22",
+    now_marker: "#e74c3c",
+    slice_border: "#FFFFFF",
+    slice_label: "#333333",
+    slice_label_secondary: "#666666",
+    bands: ["#1a9850", "#66bd63", "#e6a100", "#e8590c", "#c0392b"],
+    palette: [
+        "#DD6712", "#0F3D6B", "#0C998E", "#791111", "#259F0D", "#5E1384", "#1191D2", "#59330D",
+        "#F0459A", "#0A4528", "#AB840E", "#1A1ABA", "#0D9F3D", "#771133", "#F14F4F", "#0B424C",
+    ],
+    unknown_fuel: "#999999",
+};
+
+pub const DARK: Theme = Theme {
+    name: "dark",
+    page_background: "#111827",
+    card_background: "#1F2937",
+    card_shadow: "rgba(0,0,0,0.4)",
+    title: "#F3F4F6",
+    heading: "#E5E7EB",
+    muted: "#9CA3AF",
+    chart_background: "#18212F",
+    plot_background: "#1F2937",
+    plot_border: "#374151",
+    grid: "#2D3748",
+    axis_label: "#9CA3AF",
+    axis_title: "#D1D5DB",
+    actual_line: "#E5E7EB",
+    forecast_line: "#9CA3AF",
+    trend_line: "#F59E0B",
+    now_marker: "#F87171",
+    slice_border: "#1F2937",
+    slice_label: "#E5E7EB",
+    slice_label_secondary: "#9CA3AF",
+    bands: ["#4ADE80", "#A3E635", "#FACC15", "#FB923C", "#F87171"],
+    palette: [
+        "#F6DC5B", "#DD3C3C", "#4BF5D9", "#4075DD", "#72F872", "#AD49DF", "#99EAFA", "#B0671F",
+        "#FDD0E7", "#188B3E", "#96F438", "#7A64E4", "#FCD4C1", "#188687", "#E9D4FD", "#7C7D16",
+    ],
+    unknown_fuel: "#6B7280",
+};
+
+/// Every theme, for checks that should cover them all
+pub const THEMES: [&Theme; 2] = [&LIGHT, &DARK];
+
+impl Theme {
+    /// Colour for an intensity band
+    pub fn band(&self, index: IntensityIndex) -> &'static str {
+        let band = match index {
+            IntensityIndex::VeryLow => 0,
+            IntensityIndex::Low => 1,
+            IntensityIndex::Moderate => 2,
+            IntensityIndex::High => 3,
+            IntensityIndex::VeryHigh => 4,
+        };
+        self.bands[band]
+    }
+
+    /// The theme's colours by custom property name, e.g. `("grid", "#e0e0e0")`
+    fn variables(&self) -> Vec<(String, &'static str)> {
+        let named = [
+            ("page-background", self.page_background),
+            ("card-background", self.card_background),
+            ("card-shadow", self.card_shadow),
+            ("title", self.title),
+            ("heading", self.heading),
+            ("muted", self.muted),
+            ("chart-background", self.chart_background),
+            ("plot-background", self.plot_background),
+            ("plot-border", self.plot_border),
+            ("grid", self.grid),
+            ("axis-label", self.axis_label),
+            ("axis-title", self.axis_title),
+            ("actual-line", self.actual_line),
+            ("forecast-line", self.forecast_line),
+            ("trend-line", self.trend_line),
+            ("now-marker", self.now_marker),
+            ("slice-border", self.slice_border),
+            ("slice-label", self.slice_label),
+            ("slice-label-secondary", self.slice_label_secondary),
+            ("band-very-low", self.bands[0]),
+            ("band-low", self.bands[1]),
+            ("band-moderate", self.bands[2]),
+            ("band-high", self.bands[3]),
+            ("band-very-high", self.bands[4]),
+            ("fuel-unknown", self.unknown_fuel),
+        ];
+        let fuels = self
+            .palette
+            .iter()
+            .enumerate()
+            .map(|(i, &color)| (format!("fuel-{i}"), color));
+
+        named
+            .into_iter()
+            .map(|(name, color)| (name.to_string(), color))
+            .chain(fuels)
+            .collect()
+    }
+
+    /// The theme's colours as CSS custom property declarations
+    fn css_variables(&self) -> String {
+        self.variables()
+            .iter()
+            .map(|(name, color)| format!("--{name}: {color};"))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// `svg` with each `var(--name)` replaced by the theme's colour, for
+    /// renderers without CSS custom properties such as `/chart.png`'s
+    pub fn resolve(&self, svg: &str) -> String {
+        self.variables()
+            .iter()
+            .fold(svg.to_string(), |svg, (name, color)| svg.replace(&format!("var(--{name})"), color))
+    }
+}
+
+/// The red, green and blue of a `#RRGGBB` colour
+pub fn rgb(color: &str) -> Option<[u8; 3]> {
+    let hex = color.strip_prefix('#')?;
+    if hex.len() != 6 {
+        return None;
+    }
+
+    let channel = |range: std::ops::Range<usize>| u8::from_str_radix(hex.get(range)?, 16).ok();
+    Some([channel(0..2)?, channel(2..4)?, channel(4..6)?])
+}
+
+/// WCAG relative luminance of a `#RRGGBB` colour
+fn relative_luminance(color: &str) -> Option<f64> {
+    let channel = |value: u8| {
+        let value = value as f64 / 255.0;
+        if value <= 0.04045 {
+            value / 12.92
+        } else {
+            ((value + 0.055) / 1.055).powf(2.4)
+        }
+    };
+
+    let [r, g, b] = rgb(color)?;
+    Some(0.2126 * channel(r) + 0.7152 * channel(g) + 0.0722 * channel(b))
+}
+
+/// WCAG contrast ratio between two `#RRGGBB` colours, from 1.0 to 21.0
+pub fn contrast_ratio(a: &str, b: &str) -> Option<f64> {
+    let (a, b) = (relative_luminance(a)?, relative_luminance(b)?);
+    let (lighter, darker) = if a > b { (a, b) } else { (b, a) };
+    Some((lighter + 0.05) / (darker + 0.05))
+}
+
+/// Check every palette colour against the legend background, and each pair
+/// of consecutive colours (which become adjacent pie slices, wrapping around),
+/// returning one warning per failing check.
+pub fn palette_contrast_warnings(palette: &[&str], background: &str, min_contrast: f64) -> Vec<String> {
+    let mut warnings = Vec::new();
+
+    let invalid: Vec<&str> = palette
+        .iter()
+        .copied()
+        .filter(|color| relative_luminance(color).is_none())
+        .collect();
+    if !invalid.is_empty() {
+        warnings.push(format!(
+            "Palette contains invalid colours: {colors}",
+            colors = invalid.join(", ")
+        ));
+    }
+
+    let low_background: Vec<String> = palette
+        .iter()
+        .filter_map(|color| {
+            let ratio = contrast_ratio(color, background)?;
+            (ratio < min_contrast).then(|| format!("{color} ({ratio:.2}:1)", color = color, ratio = ratio))
+        })
+        .collect();
+    if !low_background.is_empty() {
+        warnings.push(format!(
+            "{count} palette colours are below {min_contrast:.1}:1 contrast against the legend background {background}: {colors}",
+            count = low_background.len(),
+            min_contrast = min_contrast,
+            background = background,
+            colors = low_background.join(", ")
+        ));
+    }
+
+    let low_adjacent: Vec<String> = (0..palette.len())
+        .filter_map(|i| {
+            let (a, b) = (palette[i], palette[(i + 1) % palette.len()]);
+            let ratio = contrast_ratio(a, b)?;
+            (ratio < min_contrast).then(|| format!("{a}/{b} ({ratio:.2}:1)", a = a, b = b, ratio = ratio))
+        })
+        .collect();
+    if palette.len() > 1 && !low_adjacent.is_empty() {
+        warnings.push(format!(
+            "{count} adjacent palette pairs are below {min_contrast:.1}:1 contrast: {pairs}",
+            count = low_adjacent.len(),
+            min_contrast = min_contrast,
+            pairs = low_adjacent.join(", ")
+        ));
+    }
+
+    warnings
+}
+
+/// Which theme the page uses: a fixed one, or whichever matches the
+/// browser's `prefers-color-scheme`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize)]
+#[cfg_attr(feature = "native", derive(clap::ValueEnum))]
+#[serde(rename_all = "lowercase")]
+pub enum ThemeChoice {
+    /// Light or dark to match the browser
+    #[default]
+    Auto,
+    Light,
+    Dark,
+}
+
+impl ThemeChoice {
+    /// The theme to draw with where there's no browser preference to
+    /// follow, e.g. a rasterised chart: light for `auto`
+    pub fn fixed(self) -> &'static Theme {
+        match self {
+            ThemeChoice::Dark => &DARK,
+            ThemeChoice::Light | ThemeChoice::Auto => &LIGHT,
+        }
+    }
+
+    /// CSS defining the theme's custom properties on `:root`
+    pub fn stylesheet(self) -> String {
+        let root = |theme: &Theme, scheme: &str| {
+            format!(
+                ":root {{ color-scheme: {scheme}; {variables} }}",
+                variables = theme.css_variables()
+            )
+        };
+        match self {
+            ThemeChoice::Light => root(&LIGHT, "light"),
+            ThemeChoice::Dark => root(&DARK, "dark"),
+            ThemeChoice::Auto => format!(
+                "{light}\n        @media (prefers-color-scheme: dark) {{ {dark} }}",
+                light = root(&LIGHT, "light dark"),
+                dark = root(&DARK, "light dark")
+            ),
+        }
+    }
+}
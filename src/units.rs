@@ -0,0 +1,155 @@
+//! Units carbon intensity is shown in. Everything is fetched, stored and
+//! computed in gCO₂/kWh, the Carbon Intensity API's unit; `--units` converts
+//! figures as they're shown or written out, for comparing against sources
+//! such as US EPA eGRID or WattTime that quote kgCO₂/MWh or lbCO₂/MWh.
+
+use serde::Serializer;
+use std::sync::OnceLock;
+
+/// Pounds in a kilogram
+const LB_PER_KG: f64 = 2.204_622_621_8;
+
+/// Set once the command line is parsed
+static DISPLAY_UNIT: OnceLock<IntensityUnit> = OnceLock::new();
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "native", derive(clap::ValueEnum))]
+pub enum IntensityUnit {
+    /// Grams of CO₂ per kilowatt-hour, as the API publishes
+    #[default]
+    #[cfg_attr(feature = "native", value(name = "g-kwh"))]
+    GramsPerKwh,
+    /// Kilograms per megawatt-hour: the same figures, as some grid
+    /// operators quote them
+    #[cfg_attr(feature = "native", value(name = "kg-mwh"))]
+    KilogramsPerMwh,
+    /// Pounds per megawatt-hour, as US EPA and WattTime figures are given
+    #[cfg_attr(feature = "native", value(name = "lb-mwh"))]
+    PoundsPerMwh,
+}
+
+impl IntensityUnit {
+    /// Multiplier from gCO₂/kWh
+    pub fn factor(self) -> f64 {
+        match self {
+            IntensityUnit::GramsPerKwh | IntensityUnit::KilogramsPerMwh => 1.0,
+            IntensityUnit::PoundsPerMwh => LB_PER_KG,
+        }
+    }
+
+    /// A gCO₂/kWh figure in this unit
+    pub fn convert(self, g_per_kwh: f64) -> f64 {
+        g_per_kwh * self.factor()
+    }
+
+    /// e.g. "lbCO₂/MWh"
+    pub fn label(self) -> &'static str {
+        match self {
+            IntensityUnit::GramsPerKwh => "gCO₂/kWh",
+            IntensityUnit::KilogramsPerMwh => "kgCO₂/MWh",
+            IntensityUnit::PoundsPerMwh => "lbCO₂/MWh",
+        }
+    }
+}
+
+/// `--units`, accepted by every command
+#[cfg(feature = "native")]
+#[derive(Clone, Debug, clap::Args)]
+pub struct UnitsArgs {
+    /// Unit to show carbon intensity in; thresholds and other options are
+    /// still given in gCO₂/kWh
+    #[arg(long, global = true, value_enum, default_value = "g-kwh")]
+    units: IntensityUnit,
+}
+
+#[cfg(feature = "native")]
+impl UnitsArgs {
+    /// Show intensity in the unit asked for from now on
+    pub fn apply(&self) {
+        let _ = DISPLAY_UNIT.set(self.units);
+    }
+}
+
+/// The unit intensity is shown in: `--units`, or gCO₂/kWh
+pub fn display_unit() -> IntensityUnit {
+    DISPLAY_UNIT.get().copied().unwrap_or_default()
+}
+
+/// A gCO₂/kWh figure in the display unit
+pub fn convert(g_per_kwh: f64) -> f64 {
+    display_unit().convert(g_per_kwh)
+}
+
+/// A gCO₂/kWh figure in the display unit, to the nearest whole number, e.g.
+/// "315" for 143 in lbCO₂/MWh
+pub fn figure(g_per_kwh: impl Into<f64>) -> String {
+    format!("{value:.0}", value = convert(g_per_kwh.into()))
+}
+
+/// A gCO₂/kWh figure in the display unit for CSV output, matching the JSON:
+/// unchanged in gCO₂/kWh or kgCO₂/MWh, and to one decimal place in lbCO₂/MWh
+pub fn field(g_per_kwh: i32) -> String {
+    match display_unit() {
+        IntensityUnit::GramsPerKwh | IntensityUnit::KilogramsPerMwh => g_per_kwh.to_string(),
+        unit => round_tenth(unit.convert(g_per_kwh as f64)).to_string(),
+    }
+}
+
+/// The display unit's label, e.g. "gCO₂/kWh"
+pub fn label() -> &'static str {
+    display_unit().label()
+}
+
+/// Serialize a gCO₂/kWh field in the display unit: unchanged in gCO₂/kWh or
+/// kgCO₂/MWh, which share their figures, and to one decimal place in
+/// lbCO₂/MWh. For `#[serde(serialize_with = "units::serialize")]`.
+pub fn serialize<S: Serializer>(g_per_kwh: &i32, serializer: S) -> Result<S::Ok, S::Error> {
+    match display_unit() {
+        IntensityUnit::GramsPerKwh | IntensityUnit::KilogramsPerMwh => serializer.serialize_i32(*g_per_kwh),
+        unit => serializer.serialize_f64(round_tenth(unit.convert(*g_per_kwh as f64))),
+    }
+}
+
+/// [`serialize`] for an optional figure
+pub fn serialize_option<S: Serializer>(g_per_kwh: &Option<i32>, serializer: S) -> Result<S::Ok, S::Error> {
+    match g_per_kwh {
+        Some(value) => serialize(value, serializer),
+        None => serializer.serialize_none(),
+    }
+}
+
+/// [`serialize`] for a fractional figure, such as an average
+pub fn serialize_f64<S: Serializer>(g_per_kwh: &f64, serializer: S) -> Result<S::Ok, S::Error> {
+    match display_unit() {
+        IntensityUnit::GramsPerKwh | IntensityUnit::KilogramsPerMwh => serializer.serialize_f64(*g_per_kwh),
+        unit => serializer.serialize_f64(round_tenth(unit.convert(*g_per_kwh))),
+    }
+}
+
+/// [`serialize_f64`] rounded to a tenth in every unit, for a mean that would
+/// otherwise carry a long tail of decimals
+pub fn serialize_tenth<S: Serializer>(g_per_kwh: &f64, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_f64(round_tenth(convert(*g_per_kwh)))
+}
+
+/// [`serialize_f64`] for an optional figure
+pub fn serialize_option_f64<S: Serializer>(g_per_kwh: &Option<f64>, serializer: S) -> Result<S::Ok, S::Error> {
+    match g_per_kwh {
+        Some(value) => serialize_f64(value, serializer),
+        None => serializer.serialize_none(),
+    }
+}
+
+/// [`serialize_f64`] for a series of figures
+pub fn serialize_all_f64<S: Serializer>(g_per_kwh: &[f64], serializer: S) -> Result<S::Ok, S::Error> {
+    match display_unit() {
+        IntensityUnit::GramsPerKwh | IntensityUnit::KilogramsPerMwh => serializer.collect_seq(g_per_kwh),
+        unit => serializer.collect_seq(g_per_kwh.iter().map(|value| round_tenth(unit.convert(*value)))),
+    }
+}
+
+/// To a tenth: plenty for converted figures and estimated grams, and keeps
+/// float noise out of the JSON
+pub fn round_tenth(value: f64) -> f64 {
+    (value * 10.0).round() / 10.0
+}
@@ -0,0 +1,131 @@
+//! Webhooks fired when the intensity index moves to a new band, for driving
+//! home automation (Home Assistant, IFTTT, ...) from `carbon watch`, and
+//! posted by `carbon alert`'s webhook channels
+
+use crate::model::IntensityIndex;
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::Serialize;
+use std::time::Duration;
+use tracing::{info, warn};
+
+/// Give up on a webhook after this long; receivers are usually local
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// One side of a band change
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct BandReading {
+    pub index: IntensityIndex,
+    pub intensity: i32,
+}
+
+/// The default webhook body, also the source of the template placeholders
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct BandChange {
+    pub previous: BandReading,
+    pub current: BandReading,
+    /// Start of the settlement period the new band was read for
+    pub timestamp: DateTime<Utc>,
+    /// Region short name, or `None` for national figures
+    pub region: Option<String>,
+}
+
+impl BandChange {
+    /// Fill a body template. Placeholders are `{{previous_index}}`,
+    /// `{{previous_intensity}}`, `{{index}}`, `{{intensity}}`,
+    /// `{{timestamp}}` (RFC 3339) and `{{region}}` ("national" when not
+    /// regional); anything else is left as written.
+    pub fn render(&self, template: &str) -> String {
+        let values = [
+            ("previous_index", self.previous.index.to_string()),
+            ("previous_intensity", self.previous.intensity.to_string()),
+            ("index", self.current.index.to_string()),
+            ("intensity", self.current.intensity.to_string()),
+            (
+                "timestamp",
+                self.timestamp.to_rfc3339_opts(SecondsFormat::Secs, true),
+            ),
+            (
+                "region",
+                self.region
+                    .clone()
+                    .unwrap_or_else(|| "national".to_string()),
+            ),
+        ];
+        fill_template(template, &values)
+    }
+}
+
+/// Replace each `{{name}}` in `template` with its value; anything else is
+/// left as written
+pub fn fill_template(template: &str, values: &[(&str, String)]) -> String {
+    values
+        .iter()
+        .fold(template.to_string(), |body, (name, value)| {
+            body.replace(&format!("{{{{{name}}}}}"), value)
+        })
+}
+
+/// Posts band changes to the configured URLs
+pub struct WebhookPublisher {
+    http: reqwest::Client,
+    urls: Vec<String>,
+    template: Option<String>,
+}
+
+impl WebhookPublisher {
+    /// Post to each of `urls`, with `template` as the body if given or the
+    /// [`BandChange`] as JSON otherwise
+    pub fn new(urls: Vec<String>, template: Option<String>) -> Self {
+        let http = reqwest::Client::builder()
+            .timeout(WEBHOOK_TIMEOUT)
+            .build()
+            .unwrap_or_default();
+        WebhookPublisher {
+            http,
+            urls,
+            template,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.urls.is_empty()
+    }
+
+    /// Post the change to every URL. A failing receiver is logged and doesn't
+    /// stop the others.
+    pub async fn publish(&self, change: &BandChange) {
+        let body = match &self.template {
+            Some(template) => change.render(template),
+            None => match serde_json::to_string(change) {
+                Ok(body) => body,
+                Err(e) => {
+                    warn!("Failed to encode webhook body: {e}");
+                    return;
+                }
+            },
+        };
+
+        self.send(&body).await;
+    }
+
+    /// Post `body` to every URL, logging failures
+    pub async fn send(&self, body: &str) {
+        for url in &self.urls {
+            let sent = self
+                .http
+                .post(url)
+                .header(reqwest::header::CONTENT_TYPE, "application/json")
+                .body(body.to_string())
+                .send()
+                .await
+                .and_then(|response| response.error_for_status());
+            match sent {
+                Ok(response) => info!(
+                    "Webhook {url} answered {status}",
+                    status = response.status()
+                ),
+                Err(e) => warn!("Webhook {url} failed: {e}"),
+            }
+        }
+    }
+}
@@ -0,0 +1,26 @@
+// The server pushes an update over Server-Sent Events whenever new
+// data lands; swap the server-rendered sections in place. While the
+// connection is down the last good data stays on screen and the
+// browser reconnects on its own.
+function applyDashboard(data) {
+    // A headline that couldn't be fetched is null, with the reason in current_status
+    document.getElementById('intensity-value').textContent = data.intensity == null ? '–' : Math.round(data.intensity);
+    // Band colours come from the index-* classes, e.g. "very low" -> index-very-low
+    const indexClass = data.index ? 'index-' + data.index.replace(' ', '-') : '';
+    document.getElementById('intensity-headline').className = 'intensity-value ' + indexClass;
+    const indexLabel = document.getElementById('intensity-index');
+    indexLabel.className = 'intensity-index ' + indexClass;
+    indexLabel.textContent = data.index || '';
+    document.getElementById('current-status').innerHTML = data.sections.current_status;
+    document.getElementById('intensity-context').innerHTML = data.sections.intensity_context;
+    document.getElementById('baseline-delta').innerHTML = data.sections.baseline_delta;
+    document.getElementById('mix-check').innerHTML = data.sections.mix_check;
+    document.getElementById('price-window').innerHTML = data.sections.price_window;
+    document.getElementById('intensity-chart').innerHTML = data.sections.intensity_chart;
+    document.getElementById('generation-mix-body').innerHTML = data.sections.generation_mix;
+    document.getElementById('mix-history-chart').innerHTML = data.sections.mix_history_chart;
+}
+
+const events = new EventSource('/events' + window.location.search);
+events.addEventListener('dashboard', (event) => applyDashboard(JSON.parse(event.data)));
+events.onerror = () => console.warn('Dashboard update stream interrupted, retrying');
@@ -0,0 +1,96 @@
+//! Forecast accuracy: forecasts archived by lead time, paired with actuals
+//! once published, and the error over them
+
+mod common;
+
+use carbon_vibe::accuracy::{ForecastError, ForecastOutcome, lead_bucket};
+use carbon_vibe::model::IntensityPoint;
+use carbon_vibe::store::Store;
+use chrono::TimeDelta;
+use common::utc;
+
+/// The period starting `hours` after noon, as forecast or once its actual is in
+fn point(hours: i64, forecast: i32, actual: Option<i32>) -> IntensityPoint {
+    let from = utc("2025-06-26T12:00:00Z") + TimeDelta::hours(hours);
+    IntensityPoint {
+        from,
+        to: from + TimeDelta::minutes(30),
+        intensity: actual.unwrap_or(forecast),
+        actual,
+        forecast: Some(forecast),
+        index: None,
+    }
+}
+
+#[test]
+fn forecasts_are_kept_by_lead_time_and_scored_against_actuals() {
+    assert_eq!(lead_bucket(TimeDelta::minutes(-20)), Some(0));
+    assert_eq!(lead_bucket(TimeDelta::hours(13)), Some(12));
+    assert_eq!(lead_bucket(TimeDelta::hours(48)), None);
+
+    let mut store = Store::open_in_memory().unwrap();
+    let noon = utc("2025-06-26T12:00:00Z");
+    // A day ahead, then 20, 14 and 2 hours ahead. The 14-hour forecast is in
+    // the same 12-24h bucket as the 20-hour one, which was there first.
+    for (hours_ahead, forecast, written) in [(24, 150, 1), (20, 140, 1), (14, 130, 0)] {
+        assert_eq!(
+            store
+                .record_forecasts(
+                    noon - TimeDelta::hours(hours_ahead),
+                    &[point(0, forecast, None)]
+                )
+                .unwrap(),
+            written
+        );
+    }
+    store
+        .record_forecasts(noon - TimeDelta::hours(2), &[point(0, 110, None)])
+        .unwrap();
+    // Periods already over aren't forecasts
+    assert_eq!(
+        store
+            .record_forecasts(noon + TimeDelta::hours(1), &[point(0, 100, None)])
+            .unwrap(),
+        0
+    );
+
+    // Nothing to score until the actual is published
+    store.record_intensity(&[point(0, 105, None)]).unwrap();
+    assert_eq!(
+        store
+            .forecast_outcomes_between(noon, noon + TimeDelta::hours(1))
+            .unwrap(),
+        []
+    );
+    store.record_intensity(&[point(0, 105, Some(100))]).unwrap();
+    let outcomes = store
+        .forecast_outcomes_between(noon, noon + TimeDelta::hours(1))
+        .unwrap();
+    assert_eq!(
+        outcomes,
+        [
+            ForecastOutcome {
+                lead_hours: 0,
+                forecast: 110,
+                actual: 100
+            },
+            ForecastOutcome {
+                lead_hours: 12,
+                forecast: 140,
+                actual: 100
+            },
+            ForecastOutcome {
+                lead_hours: 24,
+                forecast: 150,
+                actual: 100
+            },
+        ]
+    );
+
+    let error = ForecastError::of([(110, 100), (90, 120), (40, 0)]).unwrap();
+    assert_eq!(error.periods, 3);
+    assert_eq!(error.mae, 80.0 / 3.0);
+    // The zero actual is left out of the percentage
+    assert_eq!(error.mape, Some(17.5));
+    assert_eq!(error.bias, 20.0 / 3.0);
+}
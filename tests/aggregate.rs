@@ -0,0 +1,27 @@
+//! A national estimate rolled up from a recorded regional snapshot
+
+mod common;
+
+use carbon_vibe::aggregate::NationalEstimate;
+use carbon_vibe::model::RegionalData;
+use common::parse_fixture;
+
+#[test]
+fn regions_roll_up_to_a_national_estimate() {
+    let mut response: RegionalData = parse_fixture("regional.json");
+    let mut snapshot = response.data.remove(0);
+
+    // The 14 regions' forecasts add up to 2217; England, Scotland and Wales
+    // as a whole are left out. The recorded national figure is 190.
+    let estimate = NationalEstimate::of(&snapshot, 190).unwrap();
+    assert_eq!(estimate.regions, 14);
+    assert!((estimate.estimate - 2217.0 / 14.0).abs() < 1e-9, "{estimate:?}");
+    assert!((estimate.difference() - (2217.0 / 14.0 - 190.0)).abs() < 1e-9);
+    assert_eq!(estimate.difference_percent().map(|percent| percent.round()), Some(-17.0));
+
+    // Regions without a figure are skipped
+    snapshot.regions[0].intensity.forecast = None;
+    assert_eq!(NationalEstimate::of(&snapshot, 190).unwrap().regions, 13);
+    snapshot.regions.retain(|region| region.region_id > 14);
+    assert_eq!(NationalEstimate::of(&snapshot, 190), None);
+}
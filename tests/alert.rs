@@ -0,0 +1,143 @@
+//! Alert rules: parsing the rules file, conditions over several periods and
+//! firing once on the way in
+
+use carbon_vibe::alert::{AlertConfig, DEFAULT_CHAT_TEXT, History, Reading};
+use carbon_vibe::model::{FuelSource, IntensityIndex, IntensityPoint};
+use chrono::{DateTime, TimeDelta, Utc};
+
+const RULES: &str = r#"
+[channels.slack]
+type = "webhook"
+url = "http://127.0.0.1:9/hook"
+template = '{"text": "{{message}}"}'
+
+[channels.team]
+type = "discord"
+url = "http://127.0.0.1:9/discord"
+
+[[rules]]
+name = "Clean and sunny"
+when = 'index == "very low" and solar_perc > 20%'
+channels = ["slack", "team", "stdout"]
+
+[[rules]]
+name = "Sustained low"
+when = "intensity < 100 for 2 periods or wind_perc >= 60"
+channels = ["stdout"]
+message = "{{rule}} ({{intensity}} g, {{region}})"
+"#;
+
+/// A reading for the `n`th half hour from midnight
+fn reading(n: i64, intensity: i32, solar: f64) -> Reading {
+    let midnight: DateTime<Utc> = "2025-06-26T00:00:00Z".parse().unwrap();
+    Reading {
+        from: midnight + TimeDelta::minutes(30 * n),
+        intensity,
+        index: Some(IntensityIndex::for_intensity(intensity)),
+        generation_mix: vec![
+            FuelSource {
+                fuel: "solar".to_string(),
+                perc: solar,
+            },
+            FuelSource {
+                fuel: "wind".to_string(),
+                perc: 30.0,
+            },
+        ],
+        region: None,
+    }
+}
+
+#[test]
+fn rules_need_known_channels_and_valid_conditions() {
+    let (config, rules) = AlertConfig::parse(RULES).unwrap();
+    assert_eq!(config.channels.len(), 2);
+    assert_eq!(rules.len(), 2);
+
+    let unknown_channel = RULES.replace(r#"["stdout"]"#, r#"["pager"]"#);
+    assert_eq!(
+        AlertConfig::parse(&unknown_channel).unwrap_err(),
+        "rule 'Sustained low': no channel named 'pager'"
+    );
+    let bad_index = RULES.replace("very low", "very clean");
+    assert_eq!(
+        AlertConfig::parse(&bad_index).unwrap_err(),
+        "rule 'Clean and sunny': Unknown intensity index 'very clean'"
+    );
+    let bad_metric = RULES.replace("wind_perc", "windspeed");
+    assert!(
+        AlertConfig::parse(&bad_metric)
+            .unwrap_err()
+            .contains("unknown metric 'windspeed'")
+    );
+}
+
+#[test]
+fn conditions_combine_bands_and_fuel_shares() {
+    let (_, mut rules) = AlertConfig::parse(RULES).unwrap();
+    let clean_and_sunny = &mut rules[0];
+    let mut history = History::for_rules(std::slice::from_ref(clean_and_sunny));
+
+    // Very low but not sunny enough
+    history.push(reading(0, 30, 15.0));
+    assert_eq!(clean_and_sunny.update(&history), None);
+
+    history.push(reading(1, 30, 25.0));
+    let event = clean_and_sunny.update(&history).unwrap();
+    assert_eq!(
+        event.message,
+        "Clean and sunny: grid is very low at 30 gCO₂/kWh"
+    );
+}
+
+#[test]
+fn rules_fire_once_after_holding_for_enough_periods() {
+    let (_, mut rules) = AlertConfig::parse(RULES).unwrap();
+    let mut history = History::for_rules(&rules);
+    let sustained_low = &mut rules[1];
+
+    history.push(reading(0, 90, 0.0));
+    assert_eq!(sustained_low.update(&history), None);
+    // Polling again within the same period doesn't count as a second one
+    history.push(reading(0, 95, 0.0));
+    assert_eq!(sustained_low.update(&history), None);
+
+    history.push(reading(1, 80, 0.0));
+    let event = sustained_low.update(&history).unwrap();
+    assert_eq!(event.message, "Sustained low (80 g, national)");
+
+    // Still holding: no repeat until it stops and starts again
+    history.push(reading(2, 70, 0.0));
+    assert_eq!(sustained_low.update(&history), None);
+    history.push(reading(3, 150, 0.0));
+    assert_eq!(sustained_low.update(&history), None);
+    history.push(reading(4, 60, 0.0));
+    assert_eq!(sustained_low.update(&history), None);
+    history.push(reading(5, 60, 0.0));
+    assert!(sustained_low.update(&history).is_some());
+}
+
+#[test]
+fn chat_messages_show_the_band_and_an_emoji_sparkline() {
+    let (_, mut rules) = AlertConfig::parse(RULES).unwrap();
+    let mut history = History::for_rules(&rules);
+    history.push(reading(0, 30, 25.0));
+    let event = rules[0].update(&history).unwrap();
+
+    let outlook: Vec<IntensityPoint> = [30, 45, 130, 210, 300]
+        .into_iter()
+        .zip(0..)
+        .map(|(intensity, i)| IntensityPoint {
+            from: event.timestamp + TimeDelta::minutes(30 * i),
+            to: event.timestamp + TimeDelta::minutes(30 * (i + 1)),
+            intensity,
+            actual: None,
+            forecast: Some(intensity),
+            index: None,
+        })
+        .collect();
+    assert_eq!(
+        event.render_chat(DEFAULT_CHAT_TEXT, &outlook),
+        "🟩 Clean and sunny: grid is very low at 30 gCO₂/kWh\nNext 6 hours: 🟩🟨🟧🟥🟪"
+    );
+}
@@ -0,0 +1,60 @@
+//! `backfill`: windows saved newest first, with a checkpoint to resume from
+
+mod common;
+
+use carbon_vibe::cli::backfill::{BackfillSummary, backfill};
+use carbon_vibe::store::Store;
+use common::{client, mock_api, utc};
+use std::time::Duration;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+#[tokio::test]
+async fn an_interrupted_backfill_resumes_from_its_checkpoint() {
+    let server = mock_api().await;
+    // The second, older window fails once
+    Mock::given(method("GET"))
+        .and(path("/intensity/2025-01-01T00:00Z/2025-01-02T00:00Z"))
+        .respond_with(ResponseTemplate::new(400))
+        .up_to_n_times(1)
+        .with_priority(1)
+        .mount(&server)
+        .await;
+    let client = client(&server);
+    let mut store = Store::open_in_memory().unwrap();
+    let (since, until) = (utc("2025-01-01T00:00:00Z"), utc("2025-01-16T00:00:00Z"));
+
+    assert!(
+        backfill(&client, &mut store, since, until, Duration::ZERO)
+            .await
+            .is_err()
+    );
+    // The newer window was saved before the failure
+    assert_eq!(
+        store.checkpoint("backfill").unwrap(),
+        Some(utc("2025-01-02T00:00:00Z"))
+    );
+    assert_eq!(store.summary().unwrap().intensity_periods, 13);
+
+    let summary = backfill(&client, &mut store, since, until, Duration::ZERO)
+        .await
+        .unwrap();
+    assert_eq!(
+        summary,
+        BackfillSummary {
+            windows: 1,
+            intensity_periods: 0,
+            generation_periods: 0,
+        }
+    );
+    assert_eq!(store.checkpoint("backfill").unwrap(), Some(since));
+    // Only the failed window was asked for again
+    let newer_window = server
+        .received_requests()
+        .await
+        .unwrap()
+        .iter()
+        .filter(|request| request.url.path() == "/intensity/2025-01-02T00:00Z/2025-01-16T00:00Z")
+        .count();
+    assert_eq!(newer_window, 1);
+}
@@ -0,0 +1,68 @@
+//! EV charging plans: the greenest periods before a deadline, paused between
+
+use carbon_vibe::model::IntensityPoint;
+use carbon_vibe::schedule::{greenest_charge, immediate_charge};
+use carbon_vibe::settlement::SETTLEMENT_PERIOD;
+use chrono::{DateTime, TimeDelta, Utc};
+
+fn start() -> DateTime<Utc> {
+    "2025-06-26T22:00:00Z".parse().unwrap()
+}
+
+/// Half-hour periods from 22:00 with these intensities
+fn periods(intensities: &[i32]) -> Vec<IntensityPoint> {
+    intensities
+        .iter()
+        .enumerate()
+        .map(|(i, &intensity)| {
+            let from = start() + SETTLEMENT_PERIOD * i as i32;
+            IntensityPoint {
+                from,
+                to: from + SETTLEMENT_PERIOD,
+                intensity,
+                actual: None,
+                forecast: Some(intensity),
+                index: None,
+            }
+        })
+        .collect()
+}
+
+#[test]
+fn charging_fills_the_greenest_periods_and_pauses_between_them() {
+    let points = periods(&[200, 90, 150, 80, 120, 90]);
+    let end = start() + TimeDelta::hours(3);
+
+    // 8 kWh at 7 kW: the whole period at 80 and the earlier of the two at
+    // 90, then 1 kWh of the later one
+    let slots = greenest_charge(&points, start(), end, 8.0, 7.0).unwrap();
+    let froms: Vec<_> = slots.iter().map(|slot| (slot.from - start()).num_minutes()).collect();
+    assert_eq!(froms, [30, 90, 150]);
+    assert_eq!(slots[2].intensity, 90);
+    assert!((slots[2].energy_kwh - 1.0).abs() < 1e-9);
+    assert_eq!(slots[2].to - slots[2].from, TimeDelta::seconds(515));
+    let total: f64 = slots.iter().map(|slot| slot.emissions_g()).sum();
+    assert!((total - (3.5 * 90.0 + 3.5 * 80.0 + 90.0)).abs() < 1e-6);
+
+    // Charging straight away takes the first periods in order
+    let immediate = immediate_charge(&points, start(), end, 8.0, 7.0).unwrap();
+    let intensities: Vec<_> = immediate.iter().map(|slot| slot.intensity).collect();
+    assert_eq!(intensities, [200, 90, 150]);
+}
+
+#[test]
+fn charging_stays_between_now_and_the_deadline() {
+    let points = periods(&[50, 300, 300, 40]);
+    // Starting 10 minutes into the first period, ready by the start of the
+    // last: the greenest periods are only partly or not at all available
+    let from = start() + TimeDelta::minutes(10);
+    let to = start() + TimeDelta::minutes(90);
+    let slots = greenest_charge(&points, from, to, 5.0, 6.0).unwrap();
+    assert_eq!(slots[0].from, from);
+    assert_eq!(slots[0].to, start() + SETTLEMENT_PERIOD);
+    assert!((slots[0].energy_kwh - 2.0).abs() < 1e-9);
+    assert!(slots.iter().all(|slot| slot.to <= to));
+
+    // More than fits in the time left
+    assert_eq!(greenest_charge(&points, from, to, 9.0, 6.0), None);
+}
@@ -0,0 +1,195 @@
+//! The dashboard's SVG charts and the figures behind them
+
+mod common;
+
+use carbon_vibe::chart::{
+    Rect, SPARKLINE_HISTORY, YScale, fuel_series, moving_average, render_mix_treemap, sparkline_path, squarify,
+    trailing_periods, x_axis_ticks,
+};
+use carbon_vibe::mix::FuelSourceWithIntensity;
+use carbon_vibe::model::{FuelSource, GenerationMixPeriod};
+use carbon_vibe::cli::timezone::TimezoneArgs;
+use chrono::{DateTime, TimeDelta, Utc};
+use clap::Parser;
+use common::utc;
+
+#[derive(Parser)]
+struct Cli {
+    #[command(flatten)]
+    timezone: TimezoneArgs,
+}
+
+fn assert_near(actual: f64, expected: f64) {
+    assert!((actual - expected).abs() < 1e-9, "{actual} != {expected}");
+}
+
+#[test]
+fn moving_average_weights_the_middle_and_renormalises_at_the_ends() {
+    let values = [10, 20, 30, 40, 50, 60, 100];
+    let pinned = |window| {
+        let average = moving_average(&values, window);
+        assert_eq!(average.len(), values.len());
+        [average[0], average[3], average[6]]
+    };
+
+    // A window of 1 is the data itself
+    assert_eq!(pinned(1), [10.0, 40.0, 100.0]);
+
+    // 2 reaches one ahead: weights 2, 1, and the last point has nothing
+    // ahead of it
+    let [first, middle, last] = pinned(2);
+    assert_near(first, (2.0 * 10.0 + 20.0) / 3.0);
+    assert_near(middle, (2.0 * 40.0 + 50.0) / 3.0);
+    assert_near(last, 100.0);
+
+    // 5: weights 1, 2, 3, 2, 1, with those past either end dropped
+    let [first, middle, last] = pinned(5);
+    assert_near(first, (3.0 * 10.0 + 2.0 * 20.0 + 30.0) / 6.0);
+    assert_near(middle, (20.0 + 2.0 * 30.0 + 3.0 * 40.0 + 2.0 * 50.0 + 60.0) / 9.0);
+    assert_near(last, (50.0 + 2.0 * 60.0 + 3.0 * 100.0) / 6.0);
+
+    assert!(moving_average(&values, 0).is_empty());
+    assert!(moving_average(&[], 5).is_empty());
+}
+
+/// Half-hour period starts from 00:00 UTC on Thursday 26 June 2025,
+/// spanning `hours`
+fn half_hours(hours: i64) -> Vec<DateTime<Utc>> {
+    let start = utc("2025-06-26T00:00:00Z");
+    (0..=hours * 2).map(|i| start + TimeDelta::minutes(30 * i)).collect()
+}
+
+#[test]
+fn x_axis_ticks_are_spaced_to_the_window_and_name_the_day_past_24_hours() {
+    Cli::parse_from(["carbon", "--timezone", "UTC"]).timezone.apply();
+
+    // 25 points: every third, from one end to the other
+    let ticks = x_axis_ticks(&half_hours(12), 12);
+    assert_eq!(ticks.len(), 9);
+    assert_eq!(ticks[0], (0.0, "00:00".to_string()));
+    assert_eq!(ticks[1], (3.0 / 24.0, "01:30".to_string()));
+    assert_eq!(ticks[8], (1.0, "12:00".to_string()));
+
+    // 49 points, every fifth; exactly a day still has times alone
+    let ticks = x_axis_ticks(&half_hours(24), 12);
+    assert_eq!(ticks.len(), 10);
+    assert_eq!(ticks[1], (5.0 / 48.0, "02:30".to_string()));
+    assert_eq!(ticks[9], (45.0 / 48.0, "22:30".to_string()));
+
+    // 97 points, every ninth, labelled with the weekday
+    let ticks = x_axis_ticks(&half_hours(48), 12);
+    assert_eq!(ticks.len(), 11);
+    assert_eq!(ticks[0].1, "Thu 00:00");
+    assert_eq!(ticks[10], (90.0 / 96.0, "Fri 21:00".to_string()));
+
+    assert!(x_axis_ticks(&[], 12).is_empty());
+    assert!(x_axis_ticks(&half_hours(12), 0).is_empty());
+}
+
+#[test]
+fn fixed_y_bounds_replace_the_data_range_and_clamp_outliers() {
+    let values = [150, 250];
+    // 100 pixels high, 20 down from the top
+    let scale = |y_min, y_max| YScale::new(&values, y_min, y_max, 20.0, 100.0);
+
+    let data = scale(None, None).unwrap();
+    assert_eq!((data.min, data.max), (150.0, 250.0));
+    assert_eq!([data.y(150.0), data.y(200.0), data.y(250.0)], [120.0, 70.0, 20.0]);
+
+    let fixed = scale(Some(0.0), Some(400.0)).unwrap();
+    assert_eq!((fixed.min, fixed.max), (0.0, 400.0));
+    assert_eq!(fixed.y(200.0), 70.0);
+    // Outside the domain values sit on the edge, but axis labels needn't
+    assert_eq!([fixed.y(500.0), fixed.y(-10.0)], [20.0, 120.0]);
+    assert_eq!(fixed.unclamped_y(500.0), -5.0);
+
+    // One bound fixed, the other from the data
+    assert_eq!(scale(None, Some(400.0)).map(|s| (s.min, s.max)), Some((150.0, 400.0)));
+
+    assert!(YScale::new(&[200, 200], None, None, 20.0, 100.0).is_none());
+    assert!(scale(Some(300.0), Some(300.0)).is_none());
+}
+
+#[test]
+fn sparklines_follow_one_fuel_over_the_last_12_hours_and_draw_flat_series_through_the_middle() {
+    let period = |from: &str, mix: &[(&str, f64)]| GenerationMixPeriod {
+        from: utc(from),
+        to: utc(from) + TimeDelta::minutes(30),
+        generation_mix: mix
+            .iter()
+            .map(|&(fuel, perc)| FuelSource { fuel: fuel.to_string(), perc })
+            .collect(),
+    };
+    let history = [
+        period("2025-06-26T12:00:00Z", &[("gas", 30.0), ("solar", 20.0)]),
+        // Not reported at all rather than at 0%
+        period("2025-06-26T12:30:00Z", &[("gas", 40.0)]),
+        period("2025-06-26T13:00:00Z", &[("gas", 50.0), ("solar", 10.0)]),
+    ];
+
+    assert_eq!(fuel_series(&history, "solar"), [20.0, 0.0, 10.0]);
+    assert_eq!(fuel_series(&history, "coal"), [0.0, 0.0, 0.0]);
+    assert!(fuel_series(&[], "gas").is_empty());
+
+    // Scaled from the lowest value at the bottom to the highest at the top
+    assert_eq!(
+        sparkline_path(&fuel_series(&history, "solar"), 60.0, 16.0),
+        "M 0.0 0.0 L 30.0 16.0 L 60.0 8.0"
+    );
+    assert_eq!(sparkline_path(&[25.0, 25.0, 25.0], 60.0, 16.0), "M 0.0 8.0 L 30.0 8.0 L 60.0 8.0");
+    // A single point is no line
+    assert_eq!(sparkline_path(&[25.0], 60.0, 16.0), "");
+
+    // Of a day's history, the half-hours from 12 hours before the latest
+    // ends
+    let start = utc("2025-06-26T00:00:00Z");
+    let day: Vec<GenerationMixPeriod> = (0..48)
+        .map(|i| period(&(start + TimeDelta::minutes(30 * i)).to_rfc3339(), &[("gas", i as f64)]))
+        .collect();
+    let recent = trailing_periods(&day, SPARKLINE_HISTORY);
+    assert_eq!(recent.len(), 24);
+    assert_eq!(recent[0].from, utc("2025-06-26T12:00:00Z"));
+    assert_eq!(trailing_periods(&day[..10], SPARKLINE_HISTORY).len(), 10);
+    assert!(trailing_periods(&[], SPARKLINE_HISTORY).is_empty());
+}
+
+#[test]
+fn treemap_tiles_have_areas_in_proportion_and_fill_the_chart() {
+    let bounds = Rect { x: 0.0, y: 0.0, width: 500.0, height: 300.0 };
+    let values = [40.5, 22.0, 15.0, 9.5, 6.0, 4.0, 2.0, 1.0, 0.0];
+    let tiles = squarify(&values, bounds);
+    assert_eq!(tiles.len(), values.len());
+
+    let total: f64 = values.iter().sum();
+    for (value, tile) in values.iter().zip(&tiles) {
+        assert_near(tile.width * tile.height, value / total * 500.0 * 300.0);
+        // Inside the chart
+        assert!(tile.x >= 0.0 && tile.y >= 0.0, "{tile:?}");
+        assert!(tile.x + tile.width <= 500.0 + 1e-9 && tile.y + tile.height <= 300.0 + 1e-9, "{tile:?}");
+    }
+    // Squarified: the big tiles aren't slivers
+    for tile in &tiles[..4] {
+        assert!(tile.width.max(tile.height) / tile.width.min(tile.height) < 3.0, "{tile:?}");
+    }
+    // The first strip is a column down the left of the wider-than-tall chart
+    assert_eq!((tiles[0].x, tiles[0].y, tiles[0].height), (0.0, 0.0, 300.0));
+    // Nothing overlaps
+    for (i, a) in tiles.iter().enumerate() {
+        for b in &tiles[i + 1..] {
+            let overlap = (a.x + a.width).min(b.x + b.width) - a.x.max(b.x);
+            let overlap = overlap.min((a.y + a.height).min(b.y + b.height) - a.y.max(b.y));
+            assert!(overlap < 1e-9, "{a:?} overlaps {b:?}");
+        }
+    }
+    assert!(squarify(&[0.0, 0.0], bounds).iter().all(|tile| tile.width == 0.0));
+
+    // Largest first, labelled where there's room
+    let mix = [("solar", 1.0), ("wind", 60.0), ("gas", 39.0)]
+        .map(|(fuel, perc)| FuelSourceWithIntensity { fuel: fuel.to_string(), perc, carbon_intensity: None });
+    let svg = render_mix_treemap(&mix, (500.0, 300.0));
+    assert!(svg.starts_with(r#"<svg xmlns="http://www.w3.org/2000/svg" width="500" height="300""#), "{svg}");
+    assert!(svg.find("<title>wind: 60.0%</title>") < svg.find("<title>gas: 39.0%</title>"));
+    assert!(svg.contains(r#">wind<tspan"#));
+    assert!(svg.contains("<title>solar: 1.0%</title>") && !svg.contains(">solar<tspan"));
+    assert_eq!(render_mix_treemap(&[], (500.0, 300.0)), "");
+}
@@ -0,0 +1,95 @@
+//! The `carbon` command line as a whole: its definition, and the completion
+//! scripts and man pages generated from it, settings from the config file
+//! and environment, and the systemd units it writes
+
+use carbon_vibe::cli::Cli;
+use carbon_vibe::cli::config::{Config, env_settings};
+use clap::{CommandFactory, Parser};
+use clap_complete::Shell;
+use std::ffi::OsString;
+use std::time::Duration;
+
+#[test]
+fn completions_and_man_pages_come_from_the_argument_definitions() {
+    // Conflicting names, bad defaults and the like, across every command
+    Cli::command().debug_assert();
+
+    let mut fish = Vec::new();
+    clap_complete::generate(Shell::Fish, &mut Cli::command(), "carbon", &mut fish);
+    let fish = String::from_utf8(fish).unwrap();
+    assert!(fish.contains(r#"-a "history""#), "{fish}");
+    // Region ids are offered with their names
+    assert!(fish.contains(r"13\t'London'"), "{fish}");
+
+    let mut page = Vec::new();
+    clap_mangen::Man::new(Cli::command().find_subcommand("calendar").unwrap().clone())
+        .render(&mut page)
+        .unwrap();
+    let page = String::from_utf8(page).unwrap();
+    assert!(page.contains(".TH calendar 1"), "{page}");
+    assert!(page.contains(r"\-\-below"), "{page}");
+}
+
+#[test]
+fn environment_settings_beat_the_config_file() {
+    let config = Config::parse(
+        "config.toml".into(),
+        "postcode = \"SW1A\"\n[web]\ncache-ttl = 5\nbind = \"0.0.0.0:3000\"\n",
+    )
+    .unwrap();
+    let env = env_settings(
+        [
+            ("CARBON_VIBE_CACHE_TTL", "60"),
+            ("CARBON_VIBE_REGION", "13"),
+            ("CARBON_VIBE_WEBHOOK", "http://a.example, http://b.example"),
+            // Set by Kubernetes for a service of that name, not an option
+            ("CARBON_VIBE_SERVICE_HOST", "10.0.0.1"),
+            ("PATH", "/usr/bin"),
+        ]
+        .map(|(name, value)| (OsString::from(name), OsString::from(value))),
+    );
+    assert_eq!(env.len(), 3, "{env:?}");
+
+    let command = config.apply(Cli::command(), None, &env).unwrap();
+    let matches = command.clone().get_matches_from(["carbon", "web"]);
+    let web = matches.subcommand_matches("web").unwrap();
+    assert_eq!(web.get_one::<Duration>("cache_ttl"), Some(&Duration::from_secs(60)));
+    assert_eq!(web.get_one::<std::net::SocketAddr>("bind").unwrap().to_string(), "0.0.0.0:3000");
+    // A region from the environment replaces the config file's postcode
+    assert_eq!(web.get_one::<u32>("region"), Some(&13));
+    assert!(web.get_raw("postcode").is_none());
+
+    let matches = command.get_matches_from(["carbon", "watch", "--below", "100"]);
+    let watch = matches.subcommand_matches("watch").unwrap();
+    let webhooks: Vec<&String> = watch.get_many("webhooks").unwrap().collect();
+    assert_eq!(webhooks, ["http://a.example", "http://b.example"]);
+}
+
+#[tokio::test]
+async fn install_service_writes_a_notify_unit_and_its_socket() {
+    let dir = std::env::temp_dir().join(format!("carbon-units-{pid}", pid = std::process::id()));
+    let cli = Cli::try_parse_from([
+        "carbon",
+        "install-service",
+        "--command",
+        "web",
+        "--out-dir",
+        dir.to_str().unwrap(),
+        "--socket",
+        "0.0.0.0:3000",
+        "--",
+        "--factors",
+        "/etc/carbon vibe/100%.toml",
+    ])
+    .unwrap();
+    cli.command.run().await.unwrap();
+
+    let service = std::fs::read_to_string(dir.join("carbon-vibe.service")).unwrap();
+    let socket = std::fs::read_to_string(dir.join("carbon-vibe.socket")).unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+    assert!(service.contains("Type=notify\n"), "{service}");
+    assert!(service.contains(r#" web --factors "/etc/carbon vibe/100%%.toml""#), "{service}");
+    assert!(service.contains("WatchdogSec=60\n"), "{service}");
+    assert!(service.contains("Requires=carbon-vibe.socket\n"), "{service}");
+    assert!(socket.contains("ListenStream=0.0.0.0:3000\n"), "{socket}");
+}
@@ -0,0 +1,321 @@
+//! `CarbonClient` against a mock API: response parsing, range queries,
+//! retries and the in-flight limit, and the timestamp forms it reads
+
+mod common;
+
+use carbon_vibe::CarbonError;
+use carbon_vibe::model::{IntensityIndex, parse_api_datetime, parse_datetime_arg};
+use chrono::TimeDelta;
+use common::{client, json, mock_api, utc};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, Request, Respond, ResponseTemplate};
+
+#[tokio::test]
+async fn current_period_prefers_actual_over_forecast() {
+    let server = mock_api().await;
+
+    let period = client(&server).current_period().await.unwrap();
+
+    assert_eq!(period.from, utc("2025-01-15T12:00:00Z"));
+    assert_eq!(period.to, utc("2025-01-15T12:30:00Z"));
+    assert_eq!(period.intensity, 190);
+    assert_eq!(period.actual, Some(190));
+    assert_eq!(period.forecast, Some(187));
+    assert_eq!(period.index, Some(IntensityIndex::Moderate));
+}
+
+#[tokio::test]
+async fn intensity_between_falls_back_to_forecast_and_skips_empty_periods() {
+    let server = mock_api().await;
+
+    let points = client(&server)
+        .intensity_between(utc("2025-01-15T00:00:00Z"), utc("2025-01-15T06:00:00Z"))
+        .await
+        .unwrap();
+
+    // 23:30 to 05:30; the unforecast 06:00 period is dropped
+    assert_eq!(points.len(), 13);
+    assert!(points.windows(2).all(|pair| pair[0].from < pair[1].from));
+    let last = points.last().unwrap();
+    assert_eq!(last.from, utc("2025-01-15T05:30:00Z"));
+    assert!(last.is_forecast());
+    assert_eq!(last.intensity, 238);
+    assert_eq!(last.index, Some(IntensityIndex::High));
+}
+
+/// Paths `server` has been asked for, in order
+async fn requested_paths(server: &MockServer) -> Vec<String> {
+    server
+        .received_requests()
+        .await
+        .unwrap()
+        .iter()
+        .map(|request| request.url.path().to_string())
+        .collect()
+}
+
+#[tokio::test]
+async fn intensity_range_for_splits_long_ranges_and_trims_to_the_range() {
+    let server = mock_api().await;
+    let from = utc("2025-01-15T00:00:00Z");
+
+    // Up to the API's 14-day limit is a single request
+    client(&server)
+        .intensity_range_for(None, from, from + TimeDelta::days(10))
+        .await
+        .unwrap();
+    assert_eq!(requested_paths(&server).await, ["/intensity/2025-01-15T00:00Z/2025-01-25T00:00Z"]);
+
+    let server = mock_api().await;
+    let points = client(&server)
+        .intensity_range_for(None, from, from + TimeDelta::days(20))
+        .await
+        .unwrap();
+
+    assert_eq!(
+        requested_paths(&server).await,
+        [
+            "/intensity/2025-01-15T00:00Z/2025-01-29T00:00Z",
+            "/intensity/2025-01-29T00:00Z/2025-02-04T00:00Z",
+        ]
+    );
+    // Both chunks got the same recorded periods: the overlap before `from`
+    // is dropped and the repeats are kept once
+    assert_eq!(points.len(), 12);
+    assert_eq!(points[0].from, from);
+}
+
+#[tokio::test]
+async fn chunk_boundaries_keep_the_measured_copy_of_a_repeated_period() {
+    let server = MockServer::start().await;
+    let period = |from: &str, to: &str, actual: Option<i32>| {
+        serde_json::json!({ "from": from, "to": to, "intensity": { "forecast": 200, "actual": actual, "index": "moderate" } })
+    };
+    // The first chunk was fetched before the boundary period was measured
+    let chunks = [
+        (
+            "/intensity/2025-01-15T00:00Z/2025-01-29T00:00Z",
+            [period("2025-01-28T23:30Z", "2025-01-29T00:00Z", Some(210)), period("2025-01-29T00:00Z", "2025-01-29T00:30Z", None)],
+        ),
+        (
+            "/intensity/2025-01-29T00:00Z/2025-02-04T00:00Z",
+            [period("2025-01-29T00:00Z", "2025-01-29T00:30Z", Some(190)), period("2025-01-29T00:30Z", "2025-01-29T01:00Z", None)],
+        ),
+    ];
+    for (chunk, data) in chunks {
+        Mock::given(method("GET"))
+            .and(path(chunk))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({ "data": data })))
+            .mount(&server)
+            .await;
+    }
+
+    let from = utc("2025-01-15T00:00:00Z");
+    let points = client(&server)
+        .intensity_range_for(None, from, from + TimeDelta::days(20))
+        .await
+        .unwrap();
+
+    let figures: Vec<_> = points.iter().map(|point| (point.from, point.actual)).collect();
+    assert_eq!(
+        figures,
+        [
+            (utc("2025-01-28T23:30:00Z"), Some(210)),
+            (utc("2025-01-29T00:00:00Z"), Some(190)),
+            (utc("2025-01-29T00:30:00Z"), None),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn generation_mix_and_history_parse() {
+    let server = mock_api().await;
+    let client = client(&server);
+
+    let mix = client.generation_mix().await.unwrap();
+    assert_eq!(mix.len(), 9);
+    assert_eq!(mix[3].fuel, "gas");
+    assert_eq!(mix[3].perc, 38.5);
+
+    let history = client
+        .generation_between(utc("2025-01-15T10:00:00Z"), utc("2025-01-15T12:00:00Z"))
+        .await
+        .unwrap();
+    assert_eq!(history.len(), 4);
+    assert_eq!(history[0].from, utc("2025-01-15T10:00:00Z"));
+    for period in &history {
+        let total: f64 = period.generation_mix.iter().map(|fuel| fuel.perc).sum();
+        assert!((total - 100.0).abs() < 0.05, "mix sums to {total}");
+    }
+}
+
+#[tokio::test]
+async fn a_postcode_resolves_to_its_region() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/regional/postcode/RG10"))
+        .respond_with(json("regional_postcode.json"))
+        .mount(&server)
+        .await;
+    // An unknown outward code comes back as an empty list
+    Mock::given(method("GET"))
+        .and(path("/regional/postcode/ZZ99"))
+        .respond_with(ResponseTemplate::new(200).set_body_raw(r#"{"data": []}"#, "application/json"))
+        .mount(&server)
+        .await;
+    let client = client(&server);
+
+    // Only the outward code is looked up
+    let reading = client.regional_intensity("rg10 9aa").await.unwrap();
+    assert_eq!(reading.postcode.as_deref(), Some("RG10"));
+    assert_eq!(reading.region.region_id, 12);
+    assert_eq!(reading.region.short_name, "South England");
+    assert_eq!(reading.region.dno_region.as_deref(), Some("SSE South"));
+    assert_eq!(reading.region.intensity.best(), Some(236));
+    assert_eq!(reading.from, utc("2025-01-15T12:00:00Z"));
+
+    let error = client.regional_intensity("ZZ99 1AA").await.unwrap_err();
+    assert!(matches!(error, CarbonError::UnknownRegion(_)), "{error:?}");
+    assert_eq!(error.to_string(), "No region found for postcode 'ZZ99'");
+}
+
+#[tokio::test]
+async fn factors_parse_without_the_unpublished_interconnectors() {
+    let server = mock_api().await;
+
+    let factors = client(&server).factors().await.unwrap();
+
+    assert_eq!(factors.gas_combined_cycle, 394);
+    assert_eq!(factors.french_imports, 53);
+    assert_eq!(factors.belgian_imports, None);
+    assert_eq!(factors.norwegian_imports, None);
+}
+
+#[tokio::test]
+async fn server_errors_are_retried() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/intensity"))
+        .respond_with(ResponseTemplate::new(503))
+        .up_to_n_times(2)
+        .with_priority(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/intensity"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_raw(common::fixture("intensity.json"), "application/json"),
+        )
+        .mount(&server)
+        .await;
+
+    let intensity = client(&server).current_intensity().await.unwrap();
+
+    assert_eq!(intensity, 190);
+    assert_eq!(server.received_requests().await.unwrap().len(), 3);
+}
+
+/// Answers with the current intensity after `delay`, noting when each
+/// request arrived
+struct Slow {
+    delay: Duration,
+    arrivals: Arc<Mutex<Vec<Instant>>>,
+}
+
+impl Respond for Slow {
+    fn respond(&self, _: &Request) -> ResponseTemplate {
+        self.arrivals.lock().unwrap().push(Instant::now());
+        json("intensity.json").set_delay(self.delay)
+    }
+}
+
+/// The most of `requests` sent together that the server was answering at
+/// once, with the client limited to `limit`: each is open from its arrival
+/// until `delay` later
+async fn most_in_flight(limit: Option<usize>, requests: usize) -> usize {
+    let server = MockServer::start().await;
+    let delay = Duration::from_millis(200);
+    let arrivals = Arc::new(Mutex::new(Vec::new()));
+    Mock::given(method("GET"))
+        .and(path("/intensity"))
+        .respond_with(Slow { delay, arrivals: arrivals.clone() })
+        .mount(&server)
+        .await;
+    let client = match limit {
+        Some(limit) => client(&server).with_max_in_flight(limit),
+        None => client(&server),
+    };
+
+    let results = futures_util::future::join_all((0..requests).map(|_| client.current_intensity())).await;
+    assert!(results.iter().all(Result::is_ok));
+
+    let arrivals = arrivals.lock().unwrap();
+    assert_eq!(arrivals.len(), requests);
+    arrivals
+        .iter()
+        .map(|&start| arrivals.iter().filter(|&&other| other >= start && other < start + delay).count())
+        .max()
+        .unwrap()
+}
+
+#[tokio::test]
+async fn requests_beyond_the_in_flight_limit_wait_for_a_slot() {
+    assert_eq!(most_in_flight(None, 6).await, 6);
+    assert_eq!(most_in_flight(Some(2), 6).await, 2);
+}
+
+#[tokio::test]
+async fn client_errors_are_not_retried() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(400))
+        .mount(&server)
+        .await;
+
+    let error = client(&server).current_intensity().await.unwrap_err();
+
+    assert!(
+        matches!(error, CarbonError::Upstream { status, .. } if status == 400),
+        "{error:?}"
+    );
+    assert_eq!(server.received_requests().await.unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn unexpected_json_is_a_decode_error() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/intensity"))
+        .respond_with(
+            ResponseTemplate::new(200).set_body_raw(r#"{"error": "oops"}"#, "application/json"),
+        )
+        .mount(&server)
+        .await;
+
+    let error = client(&server).current_intensity().await.unwrap_err();
+
+    assert!(matches!(error, CarbonError::Decode { .. }), "{error:?}");
+}
+
+#[test]
+fn timestamps_parse_in_each_form_the_api_uses() {
+    let expected = utc("2025-06-26T14:30:00Z");
+    for value in [
+        "2025-06-26T14:30Z",
+        "2025-06-26T14:30:00Z",
+        "2025-06-26T14:30:00.000Z",
+        "2025-06-26T15:30+01:00",
+        "2025-06-26T14:30",
+        "2025-06-26 14:30:00",
+    ] {
+        assert_eq!(parse_api_datetime(value).unwrap(), expected, "{value}");
+    }
+    assert!(matches!(parse_api_datetime("26/06/2025 14:30"), Err(CarbonError::DateParse(_))));
+
+    // The command line also takes a bare date
+    assert_eq!(parse_datetime_arg("2025-06-26").unwrap(), utc("2025-06-26T00:00:00Z"));
+    assert_eq!(parse_datetime_arg("2025-06-26T15:30+01:00").unwrap(), expected);
+}
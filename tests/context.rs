@@ -0,0 +1,50 @@
+//! Intensity context: the share of stored history a figure is greener or
+//! dirtier than
+
+mod common;
+
+use carbon_vibe::context::IntensityContext;
+use carbon_vibe::model::IntensityPoint;
+use chrono::TimeDelta;
+use common::utc;
+
+/// `intensities` as consecutive periods from midnight on 1 June
+fn history(intensities: impl IntoIterator<Item = i32>) -> Vec<IntensityPoint> {
+    let start = utc("2025-06-01T00:00:00Z");
+    intensities
+        .into_iter()
+        .zip(0..)
+        .map(|(intensity, n)| {
+            let from = start + TimeDelta::minutes(30 * n);
+            IntensityPoint {
+                from,
+                to: from + TimeDelta::minutes(30),
+                intensity,
+                actual: Some(intensity),
+                forecast: None,
+                index: None,
+            }
+        })
+        .collect()
+}
+
+#[test]
+fn a_figure_is_ranked_against_stored_history() {
+    // 100 periods at 1..=100
+    let history = history(1..=100);
+    let context = IntensityContext::of(30, &history).unwrap();
+    assert_eq!((context.greener_than, context.dirtier_than, context.periods), (70, 29, 100));
+    assert_eq!(context.since, utc("2025-06-01T00:00:00Z"));
+    assert_eq!(
+        context.describe(utc("2025-07-01T00:00:00Z")),
+        "greener than 70% of the past 30 days"
+    );
+    assert_eq!(
+        IntensityContext::of(95, &history).unwrap().describe(utc("2026-06-01T00:00:00Z")),
+        "dirtier than 94% of the past year"
+    );
+
+    // Less than a day isn't enough to say
+    assert_eq!(IntensityContext::of(30, &history[..47]), None);
+    assert_eq!(IntensityContext::of(30, &[]), None);
+}
@@ -0,0 +1,128 @@
+//! `ElectricityMapsClient` against a mock Electricity Maps API: the key and
+//! zone sent, range chunking and grouping production types into fuels
+
+mod common;
+
+use carbon_vibe::electricitymaps::ElectricityMapsClient;
+use carbon_vibe::model::{FuelSource, IntensityIndex};
+use chrono::{DateTime, TimeDelta, Utc};
+use common::{client, json, utc};
+use reqwest::header::HeaderValue;
+use wiremock::matchers::{header, method, path, query_param};
+use wiremock::{Mock, MockServer};
+
+const TOKEN: &str = "test-api-key";
+
+fn electricity_maps(server: &MockServer) -> ElectricityMapsClient {
+    ElectricityMapsClient::new(client(server), HeaderValue::from_static(TOKEN), "DE")
+}
+
+#[tokio::test]
+async fn current_period_sends_the_key_and_zone() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/v3/carbon-intensity/latest"))
+        .and(header("auth-token", TOKEN))
+        .and(query_param("zone", "DE"))
+        .respond_with(json("electricitymaps_intensity_latest.json"))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let period = electricity_maps(&server).current_period().await.unwrap();
+
+    assert_eq!(period.from, utc("2025-06-01T14:00:00Z"));
+    assert_eq!(period.to, utc("2025-06-01T15:00:00Z"));
+    assert_eq!(period.intensity, 302);
+    assert_eq!(period.forecast, None);
+    assert_eq!(period.index, Some(IntensityIndex::VeryHigh));
+}
+
+#[tokio::test]
+async fn intensity_between_fetches_ten_days_at_a_time_and_skips_gaps() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/v3/carbon-intensity/past-range"))
+        .respond_with(json("electricitymaps_intensity_range.json"))
+        .expect(2)
+        .mount(&server)
+        .await;
+    let from = utc("2025-06-01T00:00:00Z");
+
+    let points = electricity_maps(&server)
+        .intensity_between(from, from + TimeDelta::days(15))
+        .await
+        .unwrap();
+
+    let ranges: Vec<Option<String>> = server
+        .received_requests()
+        .await
+        .unwrap()
+        .iter()
+        .map(|request| request.url.query().map(str::to_string))
+        .collect();
+    assert_eq!(
+        ranges,
+        [
+            Some("zone=DE&start=2025-06-01T00%3A00%3A00Z&end=2025-06-11T00%3A00%3A00Z".to_string()),
+            Some("zone=DE&start=2025-06-11T00%3A00%3A00Z&end=2025-06-16T00%3A00%3A00Z".to_string()),
+        ]
+    );
+    // Both chunks got the same recorded hours, which are kept once each;
+    // the hour without a value is dropped
+    let hours: Vec<(DateTime<Utc>, i32)> = points
+        .iter()
+        .map(|point| (point.from, point.intensity))
+        .collect();
+    assert_eq!(
+        hours,
+        [
+            (utc("2025-06-01T00:00:00Z"), 341),
+            (utc("2025-06-01T02:00:00Z"), 318)
+        ]
+    );
+}
+
+#[tokio::test]
+async fn generation_mix_groups_production_types_into_fuels() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/v3/power-breakdown/latest"))
+        .respond_with(json("electricitymaps_breakdown_latest.json"))
+        .mount(&server)
+        .await;
+
+    let mix = electricity_maps(&server).generation_mix().await.unwrap();
+
+    // Storage charging (negative), missing and zero values are left out
+    let expected = [
+        ("biomass", 9.8),
+        ("coal", 14.6),
+        ("gas", 7.3),
+        ("hydro", 3.7),
+        ("other", 1.2),
+        ("solar", 34.1),
+        ("wind", 29.3),
+    ]
+    .map(|(fuel, perc)| FuelSource {
+        fuel: fuel.to_string(),
+        perc,
+    });
+    assert_eq!(mix, expected);
+}
+
+#[tokio::test]
+async fn marginal_period_reads_the_marginal_signal() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/v3/marginal-carbon-intensity/latest"))
+        .and(query_param("zone", "DE"))
+        .respond_with(json("electricitymaps_marginal_latest.json"))
+        .mount(&server)
+        .await;
+
+    let period = electricity_maps(&server).marginal_period().await.unwrap();
+
+    assert_eq!(period.from, utc("2025-06-01T14:00:00Z"));
+    assert_eq!(period.intensity, 512);
+}
@@ -0,0 +1,124 @@
+//! `EntsoeClient` against a mock ENTSO-E API: the query sent, grouping
+//! production types into fuels, and responses without data
+
+mod common;
+
+use carbon_vibe::CarbonError;
+use carbon_vibe::entsoe::{Area, EntsoeClient};
+use carbon_vibe::model::{FuelSource, IntensityIndex};
+use common::{client, fixture, utc};
+use wiremock::matchers::{method, path, query_param};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+const TOKEN: &str = "00000000-test-token";
+
+fn xml(name: &str) -> ResponseTemplate {
+    ResponseTemplate::new(200).set_body_raw(fixture(name), "text/xml")
+}
+
+fn entsoe(server: &MockServer) -> EntsoeClient {
+    EntsoeClient::new(client(server), TOKEN, Area::lookup("nl").unwrap())
+}
+
+fn fuel(fuel: &str, perc: f64) -> FuelSource {
+    FuelSource {
+        fuel: fuel.to_string(),
+        perc,
+    }
+}
+
+#[tokio::test]
+async fn generation_between_sums_complete_intervals_into_fuels() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/api"))
+        .and(query_param("securityToken", TOKEN))
+        .and(query_param("documentType", "A75"))
+        .and(query_param("processType", "A16"))
+        .and(query_param("in_Domain", "10YNL----------L"))
+        .and(query_param("periodStart", "202506010000"))
+        .and(query_param("periodEnd", "202506010300"))
+        .respond_with(xml("entsoe_generation.xml"))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let periods = entsoe(&server)
+        .generation_between(utc("2025-06-01T00:00:00Z"), utc("2025-06-01T03:00:00Z"))
+        .await
+        .unwrap();
+
+    // 02:00 has no wind figure yet, so it's left out; pumping (a series out
+    // of the zone) isn't generation
+    assert_eq!(periods.len(), 2);
+    assert_eq!(periods[0].from, utc("2025-06-01T00:00:00Z"));
+    assert_eq!(periods[0].to, utc("2025-06-01T01:00:00Z"));
+    assert_eq!(
+        periods[0].generation_mix,
+        vec![fuel("gas", 60.0), fuel("solar", 20.0), fuel("wind", 20.0)]
+    );
+    // Gas has no point at position 2, so repeats position 1's 600 MW
+    assert_eq!(
+        periods[1].generation_mix,
+        vec![fuel("gas", 42.9), fuel("solar", 28.6), fuel("wind", 28.6)]
+    );
+}
+
+#[tokio::test]
+async fn intensity_between_weights_the_mix_by_fuel_factors() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/api"))
+        .respond_with(xml("entsoe_generation.xml"))
+        .mount(&server)
+        .await;
+
+    let points = entsoe(&server)
+        .intensity_between(utc("2025-06-01T00:00:00Z"), utc("2025-06-01T03:00:00Z"))
+        .await
+        .unwrap();
+
+    // 60% gas at 394 gCO₂/kWh
+    assert_eq!(points[0].intensity, 236);
+    assert_eq!(points[0].actual, Some(236));
+    assert_eq!(points[0].forecast, None);
+    assert_eq!(points[0].index, Some(IntensityIndex::High));
+    assert_eq!(points[1].intensity, 169);
+    assert_eq!(points[1].index, Some(IntensityIndex::Moderate));
+}
+
+#[tokio::test]
+async fn acknowledgement_is_reported_as_no_data_with_its_reason() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/api"))
+        .respond_with(xml("entsoe_acknowledgement.xml"))
+        .mount(&server)
+        .await;
+
+    let error = entsoe(&server)
+        .generation_between(utc("2025-06-01T00:00:00Z"), utc("2025-06-01T03:00:00Z"))
+        .await
+        .unwrap_err();
+
+    assert!(matches!(error, CarbonError::EmptyData(_)));
+    assert!(error.to_string().contains("No matching data found"), "{error}");
+}
+
+#[tokio::test]
+async fn errors_leave_out_the_token() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/api"))
+        .respond_with(ResponseTemplate::new(401))
+        .mount(&server)
+        .await;
+
+    let error = entsoe(&server)
+        .generation_between(utc("2025-06-01T00:00:00Z"), utc("2025-06-01T03:00:00Z"))
+        .await
+        .unwrap_err();
+
+    assert!(matches!(error, CarbonError::Upstream { .. }));
+    assert!(!error.to_string().contains(TOKEN), "{error}");
+}
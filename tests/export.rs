@@ -0,0 +1,78 @@
+//! `export`: typed Parquet columns with UTC timestamps that read back intact
+
+mod common;
+
+use arrow_array::Array;
+use arrow_array::cast::AsArray;
+use arrow_array::types::{Float64Type, Int32Type, TimestampMillisecondType};
+use arrow_schema::{DataType, TimeUnit};
+use carbon_vibe::cli::export::{ExportFormat, generation_mix_batch, intensity_batch, write};
+use carbon_vibe::model::{FuelSource, GenerationMixPeriod, IntensityIndex, IntensityPoint};
+use common::utc;
+use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+use std::fs::File;
+
+#[test]
+fn parquet_export_keeps_types_and_utc_timestamps() {
+    let points = vec![
+        IntensityPoint {
+            from: utc("2025-01-01T00:00:00Z"),
+            to: utc("2025-01-01T00:30:00Z"),
+            intensity: 120,
+            actual: Some(120),
+            forecast: Some(131),
+            index: Some(IntensityIndex::Moderate),
+        },
+        IntensityPoint {
+            from: utc("2025-01-01T00:30:00Z"),
+            to: utc("2025-01-01T01:00:00Z"),
+            intensity: 118,
+            actual: None,
+            forecast: Some(118),
+            index: None,
+        },
+    ];
+    let periods = vec![GenerationMixPeriod {
+        from: utc("2025-01-01T00:00:00Z"),
+        to: utc("2025-01-01T00:30:00Z"),
+        generation_mix: vec![
+            FuelSource { fuel: "gas".into(), perc: 31.5 },
+            FuelSource { fuel: "wind".into(), perc: 40.2 },
+        ],
+    }];
+    let dir = std::env::temp_dir().join(format!("carbon-export-{pid}", pid = std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let intensity_path = dir.join("intensity.parquet");
+    let mix_path = dir.join("generation_mix.parquet");
+    write(&intensity_path, &intensity_batch(&points), ExportFormat::Parquet).unwrap();
+    write(&mix_path, &generation_mix_batch(&periods), ExportFormat::Parquet).unwrap();
+
+    let read = |path| {
+        ParquetRecordBatchReaderBuilder::try_new(File::open(path).unwrap())
+            .unwrap()
+            .build()
+            .unwrap()
+            .next()
+            .unwrap()
+            .unwrap()
+    };
+    let intensity = read(&intensity_path);
+    let mix = read(&mix_path);
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(
+        intensity.schema().field_with_name("from").unwrap().data_type(),
+        &DataType::Timestamp(TimeUnit::Millisecond, Some("UTC".into()))
+    );
+    let from = intensity.column_by_name("from").unwrap().as_primitive::<TimestampMillisecondType>();
+    assert_eq!(from.value(1), utc("2025-01-01T00:30:00Z").timestamp_millis());
+    let actual = intensity.column_by_name("actual").unwrap().as_primitive::<Int32Type>();
+    assert_eq!((actual.value(0), actual.is_null(1)), (120, true));
+    let index = intensity.column_by_name("index").unwrap().as_string::<i32>();
+    assert_eq!((index.value(0), index.is_null(1)), ("moderate", true));
+
+    // Long form: a row per fuel
+    assert_eq!(mix.num_rows(), 2);
+    assert_eq!(mix.column_by_name("fuel").unwrap().as_string::<i32>().value(1), "wind");
+    assert_eq!(mix.column_by_name("perc").unwrap().as_primitive::<Float64Type>().value(0), 31.5);
+}
@@ -0,0 +1,46 @@
+//! `--max-failures`: counting recording passes that fail in a row, marking
+//! each run as a gap in the archive, and giving up
+
+mod common;
+
+use carbon_vibe::CarbonError;
+use carbon_vibe::cli::failures::FailureCount;
+use carbon_vibe::store::Store;
+use chrono::TimeDelta;
+use common::utc;
+
+fn outage() -> Result<(), CarbonError> {
+    Err(CarbonError::EmptyData("intensity data".into()))
+}
+
+#[test]
+fn consecutive_failures_are_marked_as_gaps_and_end_the_poller() {
+    let mut store = Store::open_in_memory().unwrap();
+    let start = utc("2025-01-15T12:00:00Z");
+    let pass = |n: i64| start + TimeDelta::minutes(30 * n);
+    let mut failures = FailureCount::new(Some(3));
+
+    // Two failures, then a success: one gap, and polling carries on
+    assert!(failures.record(&mut store, pass(0), outage()).is_ok());
+    assert!(failures.record(&mut store, pass(1), outage()).is_ok());
+    assert!(failures.record(&mut store, pass(2), Ok(())).is_ok());
+    // The success reset the count, so it's the third in this run that gives up
+    assert!(failures.record(&mut store, pass(3), outage()).is_ok());
+    assert!(failures.record(&mut store, pass(4), outage()).is_ok());
+    let error = failures.record(&mut store, pass(5), outage()).unwrap_err();
+    assert!(matches!(error, CarbonError::TooManyFailures { failures: 3, .. }), "{error:?}");
+    assert_eq!(error.to_string(), "Giving up after 3 failed passes in a row: API returned no intensity data");
+    // With the last failure's status
+    assert_eq!(error.exit_code(), 6);
+
+    let gaps = store.gaps_between(start, pass(6)).unwrap();
+    let spans: Vec<_> = gaps.iter().map(|gap| (gap.from, gap.to, gap.failures)).collect();
+    assert_eq!(spans, [(pass(0), pass(1), 2), (pass(3), pass(5), 3)]);
+    assert_eq!(gaps[1].error, "API returned no intensity data");
+
+    // Without --max-failures it never gives up
+    let mut failures = FailureCount::new(None);
+    for n in 0..100 {
+        assert!(failures.record(&mut store, pass(10 + n), outage()).is_ok());
+    }
+}
@@ -0,0 +1,80 @@
+//! The badge, e-ink screen and rasteriser, drawn directly rather than
+//! through the dashboard's routes
+
+mod common;
+
+use carbon_vibe::badge::render_badge;
+use carbon_vibe::cli::timezone::TimezoneArgs;
+use carbon_vibe::eink::{EinkScreen, describe_green_window, render_eink};
+use carbon_vibe::model::IntensityIndex;
+use carbon_vibe::raster::{encode_monochrome, rasterise, system_fonts};
+use carbon_vibe::schedule::Window;
+use clap::Parser;
+use common::utc;
+use std::sync::Arc;
+
+#[derive(Parser)]
+struct Cli {
+    #[command(flatten)]
+    timezone: TimezoneArgs,
+}
+
+#[test]
+fn badge_is_sized_to_its_escaped_text() {
+    let badge = render_badge("R&D carbon", "190 gCO₂/kWh", "#e6a100");
+
+    assert!(badge.contains("R&amp;D carbon: 190 gCO₂/kWh"));
+    // 7px a character plus 10px padding: 10 characters of label and 12 of
+    // message
+    assert!(badge.contains(r#"width="174""#));
+    assert!(badge.contains(r##"<rect x="80" width="94" height="20" fill="#e6a100"/>"##));
+}
+
+#[test]
+fn eink_screen_describes_the_next_green_window_and_rasterises_to_one_bit() {
+    Cli::parse_from(["carbon", "--timezone", "UTC"]).timezone.apply();
+    let now = utc("2025-06-26T10:10:00Z");
+    let window = |from: &str, to: &str| Window { from: utc(from), to: utc(to), average: 80.0 };
+
+    assert_eq!(
+        describe_green_window(&[window("2025-06-26T09:00:00Z", "2025-06-26T11:00:00Z")], now),
+        "Green now until 11:00"
+    );
+    // Windows already over are skipped
+    assert_eq!(
+        describe_green_window(
+            &[
+                window("2025-06-26T02:00:00Z", "2025-06-26T04:00:00Z"),
+                window("2025-06-26T13:00:00Z", "2025-06-26T16:30:00Z"),
+            ],
+            now
+        ),
+        "Next green window 13:00–16:30"
+    );
+    assert_eq!(
+        describe_green_window(&[window("2025-06-27T02:00:00Z", "2025-06-27T05:30:00Z")], now),
+        "Next green window Fri 02:00–05:30"
+    );
+    assert_eq!(describe_green_window(&[], now), "No green window in the next 48 hours");
+
+    // Half the design size: the same layout, scaled
+    let screen = EinkScreen {
+        area: "South Wales".to_string(),
+        intensity: Some(190),
+        index: Some(IntensityIndex::Moderate),
+        green_window: "Next green window 13:00–16:30".to_string(),
+        updated: now,
+    };
+    let svg = render_eink(&screen, (400, 240), false);
+    assert!(svg.contains(r#"width="400" height="240" viewBox="0 0 800 480""#));
+    assert!(svg.contains(">190<"));
+    assert!(svg.contains(">MODERATE<"));
+
+    let fonts = Arc::new(system_fonts());
+    let pixmap = rasterise(&svg, "#FFFFFF", Default::default(), (400, 240), &fonts).unwrap();
+    let png = encode_monochrome(&pixmap).unwrap();
+    // Width and height, then bit depth 1, greyscale
+    assert_eq!(&png[16..24], &[0, 0, 1, 144, 0, 0, 0, 240]);
+    assert_eq!(&png[24..26], &[1, 0]);
+    assert!(rasterise(&svg, "white", Default::default(), (400, 240), &fonts).is_err());
+}
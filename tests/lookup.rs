@@ -0,0 +1,55 @@
+//! `intensity-at`: the settlement period containing a moment, or an
+//! estimate between period midpoints
+
+mod common;
+
+use carbon_vibe::lookup::{containing_period, interpolated_intensity};
+use carbon_vibe::model::IntensityPoint;
+use chrono::TimeDelta;
+use common::utc;
+
+fn periods() -> Vec<IntensityPoint> {
+    [("2025-06-26T13:30:00Z", 140), ("2025-06-26T14:00:00Z", 200), ("2025-06-26T14:30:00Z", 260)]
+        .into_iter()
+        .map(|(from, intensity)| IntensityPoint {
+            from: utc(from),
+            to: utc(from) + TimeDelta::minutes(30),
+            intensity,
+            actual: Some(intensity),
+            forecast: None,
+            index: None,
+        })
+        .collect()
+}
+
+#[test]
+fn step_lookup_finds_the_period_starting_at_or_before_the_moment() {
+    let periods = periods();
+    let intensity_at = |at| containing_period(&periods, utc(at)).map(|period| period.intensity);
+
+    assert_eq!(intensity_at("2025-06-26T14:17:00Z"), Some(200));
+    // Periods include their start and end where the next begins
+    assert_eq!(intensity_at("2025-06-26T14:00:00Z"), Some(200));
+    assert_eq!(intensity_at("2025-06-26T14:30:00Z"), Some(260));
+    assert_eq!(intensity_at("2025-06-26T15:00:00Z"), None);
+}
+
+#[test]
+fn interpolation_runs_between_neighbouring_midpoints() {
+    let periods = periods();
+
+    // Two minutes into the 30 from 14:15 to 14:45, 200 rising to 260
+    let (value, before, after) = interpolated_intensity(&periods, utc("2025-06-26T14:17:00Z")).unwrap();
+    assert!((value - 204.0).abs() < 1e-9, "{value}");
+    assert_eq!((before, after), (utc("2025-06-26T14:15:00Z"), utc("2025-06-26T14:45:00Z")));
+
+    // On a midpoint it's that period's own figure
+    let (value, ..) = interpolated_intensity(&periods, utc("2025-06-26T14:15:00Z")).unwrap();
+    assert_eq!(value, 200.0);
+    let (value, ..) = interpolated_intensity(&periods, utc("2025-06-26T14:45:00Z")).unwrap();
+    assert_eq!(value, 260.0);
+
+    // Outside the first and last midpoints there's nothing to go between
+    assert!(interpolated_intensity(&periods, utc("2025-06-26T13:40:00Z")).is_none());
+    assert!(interpolated_intensity(&periods, utc("2025-06-26T14:50:00Z")).is_none());
+}
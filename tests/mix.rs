@@ -0,0 +1,127 @@
+//! Attaching carbon factors to a recorded generation mix
+
+mod common;
+
+use carbon_vibe::mix::{FactorOverrides, UNKNOWN_FUEL, enrich_generation_mix, weighted_intensity};
+use carbon_vibe::model::{CarbonFactors, CarbonFactorsData, FuelSource, GenerationMixData};
+use carbon_vibe::quality::{DataWarning, Warnings};
+use common::parse_fixture;
+
+fn factors() -> CarbonFactors {
+    let mut response: CarbonFactorsData = parse_fixture("intensity_factors.json");
+    response.data.remove(0)
+}
+
+fn generation_mix() -> Vec<FuelSource> {
+    let response: GenerationMixData = parse_fixture("generation.json");
+    response.data.generation_mix
+}
+
+#[test]
+fn fuels_take_their_factors() {
+    let mix = enrich_generation_mix(generation_mix(), &factors(), false, &mut Warnings::default());
+
+    let factor = |name: &str| {
+        mix.iter()
+            .find(|fuel| fuel.fuel == name)
+            .and_then(|fuel| fuel.carbon_intensity)
+    };
+    assert_eq!(mix.len(), 9);
+    assert_eq!(factor("gas"), Some(394));
+    assert_eq!(factor("biomass"), Some(120));
+    assert_eq!(factor("wind"), Some(0));
+    assert_eq!(factor("other"), Some(300));
+    // Capacity-weighted across the interconnectors, with Belgium and Norway
+    // estimated
+    assert_eq!(factor("imports"), Some(184));
+}
+
+#[test]
+fn fuels_without_a_factor_are_unknown_rather_than_zero_carbon() {
+    let fuel = |name: &str, perc| FuelSource {
+        fuel: name.to_string(),
+        perc,
+    };
+    let mix = vec![
+        fuel("gas", 50.0),
+        fuel("fusion", 10.0),
+        fuel("battery", 15.0),
+        fuel("pumped storage", 5.0),
+        fuel("tidal", 20.0),
+    ];
+
+    let mut warnings = Warnings::default();
+    let enriched = enrich_generation_mix(mix.clone(), &factors(), false, &mut warnings);
+    let names: Vec<_> = enriched.iter().map(|fuel| (fuel.fuel.as_str(), fuel.perc, fuel.carbon_intensity)).collect();
+    assert_eq!(
+        names,
+        [
+            ("gas", 50.0, Some(394)),
+            (UNKNOWN_FUEL, 30.0, None),
+            ("battery", 15.0, None),
+            ("pumped storage", 5.0, Some(0)),
+        ]
+    );
+    // Only the shares with a factor are weighed
+    assert_eq!(weighted_intensity(&enriched), Some(394.0 * 50.0 / 55.0));
+    // Each unknown fuel is raised, but without --strict only logged
+    let unknown = |fuel: &str| DataWarning::UnknownFuel { fuel: fuel.to_string() };
+    assert_eq!(warnings.raised(), [unknown("fusion"), unknown("tidal")]);
+    assert!(warnings.check().is_ok());
+
+    // Until they're given one
+    let overrides: FactorOverrides = toml::from_str("battery = 200\n[fuels]\ntidal = 0\n").unwrap();
+    let enriched = enrich_generation_mix(mix, &overrides.apply(factors()), false, &mut Warnings::default());
+    let factor = |name: &str| enriched.iter().find(|fuel| fuel.fuel == name).unwrap().carbon_intensity;
+    assert_eq!((factor("battery"), factor("tidal"), factor(UNKNOWN_FUEL)), (Some(200), Some(0), None));
+}
+
+#[test]
+fn split_imports_keep_the_share_and_overall_intensity() {
+    let whole = enrich_generation_mix(generation_mix(), &factors(), false, &mut Warnings::default());
+    let split = enrich_generation_mix(generation_mix(), &factors(), true, &mut Warnings::default());
+
+    let imports: Vec<_> = split
+        .iter()
+        .filter(|fuel| fuel.fuel.ends_with(" imports"))
+        .collect();
+    let names: Vec<&str> = imports.iter().map(|fuel| fuel.fuel.as_str()).collect();
+    assert_eq!(
+        names,
+        [
+            "french imports",
+            "dutch imports",
+            "belgian imports",
+            "norwegian imports",
+            "irish imports"
+        ]
+    );
+    let share: f64 = imports.iter().map(|fuel| fuel.perc).sum();
+    assert!((share - 14.8).abs() < 1e-9, "imports share {share}");
+    assert_eq!(split.len(), whole.len() + 4);
+    // Only differs by rounding the combined factor
+    assert!((weighted_intensity(&split).unwrap() - weighted_intensity(&whole).unwrap()).abs() < 0.1);
+}
+
+#[test]
+fn overrides_replace_the_published_factors() {
+    let overrides: FactorOverrides =
+        toml::from_str("gas = 490\nbiomass = 230\nnorwegian-imports = 20\n").unwrap();
+
+    let mix = enrich_generation_mix(generation_mix(), &overrides.apply(factors()), true, &mut Warnings::default());
+
+    let factor = |name: &str| {
+        mix.iter()
+            .find(|fuel| fuel.fuel == name)
+            .and_then(|fuel| fuel.carbon_intensity)
+    };
+    assert_eq!(factor("gas"), Some(490));
+    assert_eq!(factor("biomass"), Some(230));
+    assert_eq!(factor("norwegian imports"), Some(20));
+    // The rest keep theirs
+    assert_eq!(factor("other"), Some(300));
+    assert_eq!(factor("french imports"), Some(53));
+
+    // A misspelt fuel is an error rather than silently ignored
+    assert!(toml::from_str::<FactorOverrides>("gass = 490").is_err());
+}
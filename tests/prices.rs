@@ -0,0 +1,115 @@
+//! Agile prices from a mock Octopus API, and weighing them against carbon
+//! when picking a window
+
+mod common;
+
+use carbon_vibe::model::IntensityPoint;
+use carbon_vibe::octopus::{OctopusClient, UnitRate};
+use carbon_vibe::schedule::cheap_green_window;
+use chrono::{DateTime, TimeDelta, Utc};
+use common::{client, json, utc};
+use wiremock::matchers::{method, path, query_param};
+use wiremock::{Mock, MockServer};
+
+const RATES_PATH: &str =
+    "/v1/products/AGILE-24-10-01/electricity-tariffs/E-1R-AGILE-24-10-01-C/standard-unit-rates/";
+
+async fn mock_octopus() -> MockServer {
+    let server = MockServer::start().await;
+    for (page, name) in [
+        ("1", "octopus_agile_rates_page1.json"),
+        ("2", "octopus_agile_rates_page2.json"),
+    ] {
+        Mock::given(method("GET"))
+            .and(path(RATES_PATH))
+            .and(query_param("period_from", "2025-06-26T00:00:00Z"))
+            .and(query_param("period_to", "2025-06-26T03:00:00Z"))
+            .and(query_param("page", page))
+            .respond_with(json(name))
+            .expect(1)
+            .mount(&server)
+            .await;
+    }
+    server
+}
+
+async fn rates() -> Vec<UnitRate> {
+    let server = mock_octopus().await;
+    OctopusClient::new(client(&server), "AGILE-24-10-01", 'C')
+        .unit_rates(utc("2025-06-26T00:00:00Z"), utc("2025-06-26T03:00:00Z"))
+        .await
+        .unwrap()
+}
+
+/// Half-hourly points from midnight with these intensities
+fn points(intensities: &[i32]) -> Vec<IntensityPoint> {
+    let midnight = utc("2025-06-26T00:00:00Z");
+    intensities
+        .iter()
+        .zip(0..)
+        .map(|(&intensity, i)| IntensityPoint {
+            from: midnight + TimeDelta::minutes(30 * i),
+            to: midnight + TimeDelta::minutes(30 * (i + 1)),
+            intensity,
+            actual: None,
+            forecast: Some(intensity),
+            index: None,
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn unit_rates_follows_pages_and_sorts_oldest_first() {
+    let rates = rates().await;
+
+    let prices: Vec<(DateTime<Utc>, f64)> =
+        rates.iter().map(|rate| (rate.from, rate.price)).collect();
+    assert_eq!(
+        prices,
+        [
+            (utc("2025-06-26T00:00:00Z"), 16.8),
+            (utc("2025-06-26T00:30:00Z"), 15.75),
+            (utc("2025-06-26T01:00:00Z"), -2.1),
+            (utc("2025-06-26T01:30:00Z"), 4.2),
+            (utc("2025-06-26T02:00:00Z"), 18.9),
+            (utc("2025-06-26T02:30:00Z"), 21.0),
+        ]
+    );
+    assert_eq!(rates[0].to, utc("2025-06-26T00:30:00Z"));
+}
+
+#[tokio::test]
+async fn price_weight_moves_the_window_between_greenest_and_cheapest() {
+    let rates = rates().await;
+    // Greenest late on, cheapest in the middle
+    let points = points(&[50, 60, 200, 210, 55, 50]);
+    let window_from = |price_weight| {
+        cheap_green_window(&points, &rates, TimeDelta::hours(1), price_weight)
+            .unwrap()
+            .window
+            .from
+    };
+
+    assert_eq!(window_from(0.0), utc("2025-06-26T02:00:00Z"));
+    assert_eq!(window_from(1.0), utc("2025-06-26T01:00:00Z"));
+    // Fairly green and middling on price beats either extreme
+    let balanced = cheap_green_window(&points, &rates, TimeDelta::hours(1), 0.5).unwrap();
+    assert_eq!(balanced.window.from, utc("2025-06-26T00:00:00Z"));
+    assert_eq!(balanced.window.average, 55.0);
+    assert!((balanced.average_price - 16.275).abs() < 1e-9);
+}
+
+#[test]
+fn periods_without_a_price_are_left_out() {
+    let points = points(&[50, 60, 70]);
+    let rates = [UnitRate {
+        from: utc("2025-06-26T00:30:00Z"),
+        to: utc("2025-06-26T01:00:00Z"),
+        price: 10.0,
+    }];
+
+    assert_eq!(
+        cheap_green_window(&points, &rates, TimeDelta::hours(1), 0.5),
+        None
+    );
+}
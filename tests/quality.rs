@@ -0,0 +1,128 @@
+//! `--strict`: every data-quality warning becomes an error, for the fetch or
+//! command that raised it. Without `--strict` they're only logged; see
+//! `tests/mix.rs`.
+
+mod common;
+
+use axum::Router;
+use axum::http::StatusCode;
+use carbon_vibe::CarbonError;
+use carbon_vibe::cli::web::router;
+use carbon_vibe::mix::enrich_generation_mix;
+use carbon_vibe::model::FuelSource;
+use carbon_vibe::provider::FUEL_FACTORS;
+use carbon_vibe::quality::{self, DataWarning, StrictArgs, Warnings};
+use clap::Parser;
+use common::{body_text, dashboard_options, get, mock_api};
+use serde_json::Value;
+use std::time::Duration;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+#[derive(Parser)]
+struct Cli {
+    #[command(flatten)]
+    strict: StrictArgs,
+}
+
+/// Apply `--strict`, as every test here runs under it
+fn strict() {
+    Cli::parse_from(["carbon", "--strict"]).strict.apply();
+    assert!(quality::strict());
+}
+
+/// Warnings from enriching a mix of `shares`
+fn mix_warnings(shares: &[(&str, f64)]) -> Warnings {
+    let mix = shares
+        .iter()
+        .map(|&(fuel, perc)| FuelSource { fuel: fuel.to_string(), perc })
+        .collect();
+    let mut warnings = Warnings::default();
+    enrich_generation_mix(mix, &FUEL_FACTORS, false, &mut warnings);
+    warnings
+}
+
+/// `/api/data`'s status and body
+async fn api_data(app: &Router) -> (StatusCode, Value) {
+    let response = get(app.clone(), "/api/data").await;
+    let status = response.status();
+    (status, serde_json::from_str(&body_text(response).await).unwrap())
+}
+
+#[test]
+fn unknown_fuels_and_mixes_off_100_percent_fail() {
+    strict();
+
+    let Err(CarbonError::DataQuality(warnings)) = mix_warnings(&[("wind", 60.0), ("tidal", 40.0)]).check() else {
+        panic!("an unknown fuel should fail under --strict");
+    };
+    assert_eq!(warnings, [DataWarning::UnknownFuel { fuel: "tidal".to_string() }]);
+
+    let error = mix_warnings(&[("wind", 60.0), ("gas", 30.0)]).check().unwrap_err();
+    assert_eq!(error.to_string(), "--strict: generation mix adds up to 90.0%, not 100%");
+    assert_eq!(error.exit_code(), 8);
+
+    // A small rounding difference is fine
+    assert!(mix_warnings(&[("wind", 60.1), ("gas", 40.0)]).check().is_ok());
+}
+
+#[test]
+fn warnings_only_fail_the_fetch_or_command_that_raised_them() {
+    strict();
+
+    // Two fetches at once: one's warnings don't fail the other
+    let degraded = mix_warnings(&[("tidal", 100.0)]);
+    assert!(mix_warnings(&[("wind", 100.0)]).check().is_ok());
+    assert!(degraded.check().is_err());
+
+    // Deferred ones fail the command once, when it's done
+    mix_warnings(&[("tidal", 100.0)]).defer();
+    assert!(quality::check_deferred().is_err());
+    assert!(quality::check_deferred().is_ok());
+}
+
+#[tokio::test]
+async fn a_failing_palette_stops_the_dashboard_starting() {
+    strict();
+    let server = mock_api().await;
+
+    assert!(router(dashboard_options(&server, &[])).is_ok());
+    let error = router(dashboard_options(&server, &["--min-contrast", "21"])).unwrap_err().to_string();
+    assert!(error.starts_with("--strict: light theme: "), "{error}");
+}
+
+#[tokio::test]
+async fn mix_times_factors_too_far_from_the_reported_figure_fails_the_refresh() {
+    strict();
+    let server = mock_api().await;
+
+    assert_eq!(api_data(&router(dashboard_options(&server, &[])).unwrap()).await.0, StatusCode::OK);
+    let app = router(dashboard_options(&server, &["--mix-check-threshold", "0"])).unwrap();
+    let (status, body) = api_data(&app).await;
+    assert_eq!(status, StatusCode::BAD_GATEWAY);
+    let error = body["error"].as_str().unwrap();
+    assert!(error.starts_with("--strict: mix × factors gives 188 gCO₂/kWh against 190 reported"), "{error}");
+}
+
+#[tokio::test]
+async fn a_stale_panel_fails_the_refresh_and_the_cache_keeps_good_data() {
+    strict();
+    let server = mock_api().await;
+    let app = router(dashboard_options(&server, &["--cache-ttl", "1"])).unwrap();
+    assert_eq!(api_data(&app).await.0, StatusCode::OK);
+
+    Mock::given(method("GET"))
+        .and(path("/generation"))
+        .respond_with(ResponseTemplate::new(500))
+        .with_priority(1)
+        .expect(1..)
+        .mount(&server)
+        .await;
+    tokio::time::sleep(Duration::from_millis(1100)).await;
+    // Served from the cache while it refreshes in the background
+    assert_eq!(api_data(&app).await.0, StatusCode::OK);
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    let (status, body) = api_data(&app).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(body["errors"].get("mix").is_none(), "{body}");
+}
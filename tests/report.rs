@@ -0,0 +1,83 @@
+//! `report`: the last whole week against the one before, its days, mix
+//! trends and the saving from shifting load
+
+mod common;
+
+use carbon_vibe::cli::report::{ReportPeriod, ShiftableLoad, compile};
+use carbon_vibe::model::{FuelSource, GenerationMixPeriod, IntensityPoint};
+use chrono::{NaiveDate, TimeDelta};
+use common::utc;
+
+/// Half-hourly periods for `days` days from `from`, at `base`, plus 100 for
+/// an evening peak from 16 to 20 hours into each day
+fn points(from: &str, days: i64, base: i32) -> Vec<IntensityPoint> {
+    let from = utc(from);
+    (0..days * 48)
+        .map(|n| {
+            let from = from + TimeDelta::minutes(30 * n);
+            let peak = (32..40).contains(&(n % 48));
+            IntensityPoint {
+                from,
+                to: from + TimeDelta::minutes(30),
+                intensity: base + if peak { 100 } else { 0 },
+                actual: None,
+                forecast: None,
+                index: None,
+            }
+        })
+        .collect()
+}
+
+fn mix(from: &str, wind: f64) -> GenerationMixPeriod {
+    let from = utc(from);
+    GenerationMixPeriod {
+        from,
+        to: from + TimeDelta::minutes(30),
+        generation_mix: vec![
+            FuelSource { fuel: "gas".into(), perc: 100.0 - wind },
+            FuelSource { fuel: "wind".into(), perc: wind },
+        ],
+    }
+}
+
+#[test]
+fn weekly_report_compares_days_fuels_and_shifted_load() {
+    // Wednesday 25 June 2025: the last whole week is Mon 16 to Sun 22 June,
+    // which is BST, so UK days start at 23:00 UTC the evening before
+    let now = utc("2025-06-25T12:00:00Z");
+    let mut history = points("2025-06-08T23:00:00Z", 7, 200);
+    history.extend(points("2025-06-15T23:00:00Z", 7, 100));
+    // Sunday was the greenest day
+    for point in history.iter_mut().rev().take(48) {
+        point.intensity -= 50;
+    }
+    let periods = vec![mix("2025-06-10T12:00:00Z", 20.0), mix("2025-06-18T12:00:00Z", 45.0)];
+    let load = ShiftableLoad {
+        kwh_per_day: 10.0,
+        duration: TimeDelta::hours(3),
+    };
+
+    let report = compile(ReportPeriod::Weekly, now, &history, &periods, load).unwrap();
+    assert_eq!(report.title, "Week of 16 June 2025");
+    assert_eq!((report.from, report.to), (utc("2025-06-15T23:00:00Z"), utc("2025-06-22T23:00:00Z")));
+    assert_eq!(report.days.len(), 7);
+    assert_eq!(report.best_day().date, NaiveDate::from_ymd_opt(2025, 6, 22).unwrap());
+    // Each day averages base + 100 * 8/48
+    assert!((report.previous_mean.unwrap() - (200.0 + 100.0 / 6.0)).abs() < 1e-9);
+    assert_eq!(report.mix[0].fuel, "gas");
+    assert_eq!((report.mix[1].share, report.mix[1].previous), (45.0, Some(20.0)));
+
+    // Shifting out of the evening peak saves 100/6 g per kWh each day
+    let saving = report.saving.unwrap();
+    assert_eq!(saving.days, 7);
+    assert!((saving.saved_g() - 7.0 * 10.0 * 100.0 / 6.0).abs() < 1e-9);
+
+    let text = report.text();
+    assert!(text.contains("% lower than the week before"), "{text}");
+    assert!(text.contains("Greenest day: Sun 22 Jun at 67 gCO₂/kWh"), "{text}");
+    assert!(text.contains("wind 45.0% (+25.0 points)"), "{text}");
+    assert!(report.html().contains("Week of 16 June 2025"));
+
+    // Nothing in the week itself
+    assert!(compile(ReportPeriod::Weekly, now, &history[..336], &periods, load).is_none());
+}
@@ -0,0 +1,102 @@
+//! `--seed-history`: priming the archive from a CSV export or another archive,
+//! keeping what it already holds
+
+mod common;
+
+use carbon_vibe::cli::seed::{SeedSummary, seed_history};
+use carbon_vibe::model::{FuelSource, GenerationMixPeriod, IntensityIndex, IntensityPoint};
+use carbon_vibe::store::Store;
+use common::utc;
+use std::path::PathBuf;
+
+fn point(from: &str, to: &str, actual: Option<i32>, forecast: Option<i32>) -> IntensityPoint {
+    IntensityPoint {
+        from: utc(from),
+        to: utc(to),
+        intensity: actual.or(forecast).unwrap(),
+        actual,
+        forecast,
+        index: Some(IntensityIndex::Moderate),
+    }
+}
+
+/// `name` in a directory of its own under the temporary directory
+fn temp_path(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("carbon-seed-{pid}", pid = std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    dir.join(name)
+}
+
+fn seed_file(name: &str, contents: &str) -> PathBuf {
+    let path = temp_path(name);
+    std::fs::write(&path, contents).unwrap();
+    path
+}
+
+#[test]
+fn a_seed_fills_in_what_the_archive_lacks() {
+    let mut store = Store::open_in_memory().unwrap();
+    // Already recorded, with its actual: a seed mustn't overwrite it
+    let recorded = point("2025-01-15T12:30:00Z", "2025-01-15T13:00:00Z", Some(180), Some(190));
+    store.record_intensity(std::slice::from_ref(&recorded)).unwrap();
+
+    // `history export`
+    let export = seed_file(
+        "history.csv",
+        "from,to,actual,forecast,index\n\
+         2025-01-15T12:00:00Z,2025-01-15T12:30:00Z,175,185,moderate\n\
+         2025-01-15T12:30:00Z,2025-01-15T13:00:00Z,,190,moderate\n",
+    );
+    let seeded = seed_history(&mut store, &export).unwrap();
+    assert_eq!(seeded, SeedSummary { intensity_periods: 1, generation_periods: 0 });
+    let stored = store
+        .intensity_between(utc("2025-01-15T00:00:00Z"), utc("2025-01-16T00:00:00Z"))
+        .unwrap();
+    assert_eq!(
+        stored,
+        [point("2025-01-15T12:00:00Z", "2025-01-15T12:30:00Z", Some(175), Some(185)), recorded]
+    );
+    // So it can be given on every start
+    assert_eq!(seed_history(&mut store, &export).unwrap(), SeedSummary::default());
+
+    // `archive mix`, one row per fuel
+    let mix = seed_file(
+        "mix.csv",
+        "from,to,fuel,perc\n\
+         2025-01-15T12:00:00Z,2025-01-15T12:30:00Z,gas,40\n\
+         2025-01-15T12:00:00Z,2025-01-15T12:30:00Z,wind,60\n",
+    );
+    assert_eq!(seed_history(&mut store, &mix).unwrap().generation_periods, 1);
+
+    // Another archive
+    let path = temp_path("other.db");
+    let _ = std::fs::remove_file(&path);
+    let mut other = Store::open(&path).unwrap();
+    let older = point("2025-01-14T12:00:00Z", "2025-01-14T12:30:00Z", Some(210), None);
+    other.record_intensity(std::slice::from_ref(&older)).unwrap();
+    other
+        .record_generation(&[GenerationMixPeriod {
+            from: older.from,
+            to: older.to,
+            generation_mix: vec![FuelSource { fuel: "gas".into(), perc: 100.0 }],
+        }])
+        .unwrap();
+    drop(other);
+    let seeded = seed_history(&mut store, &path).unwrap();
+    assert_eq!(seeded, SeedSummary { intensity_periods: 1, generation_periods: 1 });
+    assert_eq!(store.summary().unwrap().earliest, Some(older.from));
+
+    // Figures exported in another unit aren't misread
+    let converted = seed_file(
+        "converted.csv",
+        "from,to,actual,forecast,index\n2025-01-15T11:30:00Z,2025-01-15T12:00:00Z,0.4,0.4,moderate\n",
+    );
+    let error = seed_history(&mut store, &converted).unwrap_err();
+    assert!(
+        error
+            .to_string()
+            .ends_with("line 2: '0.4' isn't a whole gCO₂/kWh figure; export with the default --units"),
+        "{error}"
+    );
+    assert_eq!(error.exit_code(), 2);
+}
@@ -0,0 +1,53 @@
+//! Settlement periods: numbering within the UK day, including the days the
+//! clocks change, and when pollers wake for the next one
+
+mod common;
+
+use carbon_vibe::settlement::{SettlementPeriod, next_poll, until_next_period};
+use chrono::{NaiveDate, TimeDelta};
+use common::utc;
+
+#[test]
+fn periods_are_numbered_from_uk_midnight_and_polls_wait_for_the_next() {
+    // 14:30 BST
+    let period = SettlementPeriod::containing(utc("2025-06-26T13:42:10Z"));
+    assert_eq!(period.from(), utc("2025-06-26T13:30:00Z"));
+    assert_eq!(period.to(), utc("2025-06-26T14:00:00Z"));
+    assert_eq!(period.to_string(), "2025-06-26 SP30");
+    // Midnight BST is 23:00 UTC the day before
+    let first = SettlementPeriod::containing(utc("2025-06-25T23:00:00Z"));
+    assert_eq!(first.date(), NaiveDate::from_ymd_opt(2025, 6, 26).unwrap());
+    assert_eq!(first.number(), 1);
+    assert_eq!(first.previous().number(), 48);
+
+    // The spring-forward day is 23 hours long, the fall-back day 25
+    let spring = SettlementPeriod::containing(utc("2025-03-30T22:30:00Z"));
+    assert_eq!(spring.to_string(), "2025-03-30 SP46");
+    assert_eq!(spring.next().to_string(), "2025-03-31 SP1");
+    let autumn = SettlementPeriod::containing(utc("2025-10-26T23:30:00Z"));
+    assert_eq!(autumn.to_string(), "2025-10-26 SP50");
+    assert_eq!(autumn.next().to_string(), "2025-10-27 SP1");
+
+    assert_eq!(
+        until_next_period(utc("2025-06-26T13:45:00Z")),
+        TimeDelta::minutes(15)
+    );
+    let half_hour = TimeDelta::minutes(30);
+    assert_eq!(
+        next_poll(utc("2025-06-26T13:45:00Z"), half_hour),
+        utc("2025-06-26T14:01:00Z")
+    );
+    // Not yet published at the boundary itself, so still wait for it
+    assert_eq!(
+        next_poll(utc("2025-06-26T14:00:30Z"), half_hour),
+        utc("2025-06-26T14:01:00Z")
+    );
+    assert_eq!(
+        next_poll(utc("2025-06-26T14:01:00Z"), half_hour),
+        utc("2025-06-26T14:31:00Z")
+    );
+    assert_eq!(
+        next_poll(utc("2025-06-26T14:01:00Z"), TimeDelta::hours(1)),
+        utc("2025-06-26T15:01:00Z")
+    );
+}
@@ -0,0 +1,28 @@
+//! Palette contrast against WCAG 2.1
+
+use carbon_vibe::theme::{DEFAULT_MIN_CONTRAST, THEMES, contrast_ratio, palette_contrast_warnings};
+
+#[test]
+fn contrast_ratios_match_wcag_for_known_pairs() {
+    assert_eq!(contrast_ratio("#000000", "#FFFFFF"), Some(21.0));
+    assert_eq!(contrast_ratio("#FFFFFF", "#000000"), Some(21.0));
+    assert_eq!(contrast_ratio("#336699", "#336699"), Some(1.0));
+    // The lightest grey that used to pass for body text
+    let grey = contrast_ratio("#777777", "#FFFFFF").unwrap();
+    assert!((grey - 4.48).abs() < 0.01, "{grey}");
+    assert_eq!(contrast_ratio("white", "#FFFFFF"), None);
+
+    // Black against white passes, but two blacks side by side don't
+    let warnings = palette_contrast_warnings(&["#000000", "#000000", "#FFFFFF"], "#FFFFFF", 3.0);
+    assert_eq!(warnings.len(), 2, "{warnings:?}");
+    assert!(warnings[0].starts_with("1 palette colours are below 3.0:1"), "{}", warnings[0]);
+    assert!(warnings[1].contains("#000000/#000000 (1.00:1)"), "{}", warnings[1]);
+}
+
+#[test]
+fn built_in_palettes_pass_the_default_contrast() {
+    for theme in THEMES {
+        let warnings = palette_contrast_warnings(&theme.palette, theme.card_background, DEFAULT_MIN_CONTRAST);
+        assert!(warnings.is_empty(), "{name}: {warnings:?}", name = theme.name);
+    }
+}
@@ -0,0 +1,33 @@
+//! `--timezone`: times shown on the chosen zone's clock, and hours that
+//! begin on the hour there
+
+mod common;
+
+use carbon_vibe::cli::timezone::{TimezoneArgs, display_zone, floor, local, start_of_day};
+use chrono::{NaiveDate, TimeDelta};
+use clap::Parser;
+use common::utc;
+
+#[derive(Parser)]
+struct Cli {
+    #[command(flatten)]
+    timezone: TimezoneArgs,
+}
+
+#[test]
+fn times_are_shown_and_grouped_in_the_chosen_zone() {
+    assert!(Cli::try_parse_from(["carbon", "--timezone", "Mars/Olympus"]).is_err());
+
+    // Half an hour off the UTC hour
+    Cli::parse_from(["carbon", "--timezone", "Asia/Kolkata"]).timezone.apply();
+    assert_eq!(display_zone(), chrono_tz::Asia::Kolkata);
+
+    let time = utc("2025-06-26T13:42:10Z");
+    assert_eq!(local(time).format("%H:%M %Z").to_string(), "19:12 IST");
+    assert_eq!(floor(time, TimeDelta::hours(1)), utc("2025-06-26T13:30:00Z"));
+    assert_eq!(floor(time, TimeDelta::minutes(30)), utc("2025-06-26T13:30:00Z"));
+    assert_eq!(
+        start_of_day(NaiveDate::from_ymd_opt(2025, 6, 26).unwrap()),
+        utc("2025-06-25T18:30:00Z")
+    );
+}
@@ -0,0 +1,28 @@
+//! `today`: the rest of the day's forecast and its cleanest period
+
+mod common;
+
+use carbon_vibe::cli::today::{cleanest_period, forecast_periods};
+use carbon_vibe::model::{CarbonIntensityData, IntensityPoint};
+use common::{parse_fixture, utc};
+
+#[test]
+fn cleanest_period_is_the_lowest_forecast_and_the_earliest_of_a_tie() {
+    let response: CarbonIntensityData = parse_fixture("intensity_range.json");
+    let points = response.data.into_iter().filter_map(IntensityPoint::from_entry).collect();
+    let mut periods = forecast_periods(points);
+    // The last entry has neither figure
+    assert_eq!(periods.len(), 13);
+
+    // By forecast, not the actual: 187 at 02:30 though 01:30 measured lower
+    let cleanest = cleanest_period(&periods).unwrap();
+    assert_eq!((cleanest.from, cleanest.forecast), (utc("2025-01-15T02:30:00Z"), 187));
+    assert_eq!(cleanest.index, "moderate");
+
+    // 04:00 forecast as low is later, so 02:30 keeps it
+    let later = periods.iter_mut().find(|p| p.from == utc("2025-01-15T04:00:00Z")).unwrap();
+    later.forecast = 187;
+    assert_eq!(cleanest_period(&periods).unwrap().from, utc("2025-01-15T02:30:00Z"));
+
+    assert!(cleanest_period(&[]).is_none());
+}
@@ -0,0 +1,57 @@
+//! `--units`: intensity shown and serialized in the chosen unit, while
+//! everything underneath stays in gCO₂/kWh
+
+mod common;
+
+use carbon_vibe::calendar::to_ics;
+use carbon_vibe::model::IntensityPoint;
+use carbon_vibe::schedule::Window;
+use carbon_vibe::units::{UnitsArgs, display_unit, figure, label};
+use clap::Parser;
+use common::{body_text, dashboard, get, mock_api};
+use serde_json::Value;
+
+#[derive(Parser)]
+struct Cli {
+    #[command(flatten)]
+    units: UnitsArgs,
+}
+
+#[tokio::test]
+async fn intensity_is_shown_in_the_chosen_unit() {
+    assert!(Cli::try_parse_from(["carbon", "--units", "tonnes"]).is_err());
+    assert_eq!(display_unit().label(), "gCO₂/kWh");
+
+    Cli::parse_from(["carbon", "--units", "lb-mwh"]).units.apply();
+    assert_eq!(label(), "lbCO₂/MWh");
+    assert_eq!(figure(143), "315");
+    let window = Window {
+        from: "2025-06-26T13:30:00Z".parse().unwrap(),
+        to: "2025-06-26T15:00:00Z".parse().unwrap(),
+        average: 143.0,
+    };
+    let ics = to_ics(&[window], 150, None, "2025-06-26T12:00:00Z".parse().unwrap());
+    assert!(ics.contains("SUMMARY:Green hours: 315 lbCO₂/MWh\r\n"), "{ics}");
+
+    // The library's records stay in gCO₂/kWh...
+    let point = IntensityPoint {
+        from: "2025-06-26T13:30:00Z".parse().unwrap(),
+        to: "2025-06-26T14:00:00Z".parse().unwrap(),
+        intensity: 143,
+        actual: Some(143),
+        forecast: Some(150),
+        index: None,
+    };
+    let json = serde_json::to_value(&point).unwrap();
+    assert_eq!(json["intensity"], 143);
+    assert_eq!(json["forecast"], 150);
+
+    // ...and are converted where they're shown
+    let server = mock_api().await;
+    let response = get(dashboard(&server, &[]), "/api/timeline").await;
+    let timeline: Value = serde_json::from_str(&body_text(response).await).unwrap();
+    let first = &timeline["points"][0];
+    assert_eq!(first["from"], "2025-01-14T23:30:00Z");
+    assert_eq!(first["actual"], 474.0);
+    assert_eq!(first["forecast"], 467.4);
+}
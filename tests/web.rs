@@ -0,0 +1,831 @@
+//! The web dashboard's routes against a mock API: data, chart rendering and
+//! upstream failures
+
+mod common;
+
+use axum::body::{Body, to_bytes};
+use axum::http::{Request, StatusCode, header};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use carbon_vibe::CarbonError;
+use carbon_vibe::cli::config::Config;
+use carbon_vibe::cli::timezone::TimezoneArgs;
+use carbon_vibe::cli::web::{Baseline, baseline_reference, describe_baseline_delta, router, serve_on};
+use carbon_vibe::model::IntensityPoint;
+use carbon_vibe::settlement::SettlementPeriod;
+use chrono::{NaiveDate, SecondsFormat, TimeDelta, Utc};
+use clap::Parser;
+use common::{CLIENT_ADDR, Web, body_text, dashboard, dashboard_options, from_client, get, get_from, mock_api, utc};
+use futures_util::StreamExt;
+use serde_json::Value;
+use std::net::SocketAddr;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tower::Service;
+use wiremock::matchers::{method, path, path_regex};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+#[derive(Parser)]
+struct Zone {
+    #[command(flatten)]
+    timezone: TimezoneArgs,
+}
+
+/// The whole server, as `web` runs it, on a free local port
+async fn serve(server: &MockServer, args: &[&str]) -> SocketAddr {
+    let base = ["web", "--api-url", &server.uri(), "--cache-ttl", "0"];
+    let options = Web::parse_from(base.iter().chain(args)).options;
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(serve_on(listener, options, std::future::pending()));
+    addr
+}
+
+#[tokio::test]
+async fn api_data_has_enriched_mix_and_rendered_charts() {
+    let server = mock_api().await;
+
+    let response = get(dashboard(&server, &[]), "/api/data").await;
+
+    assert_eq!(response.status(), StatusCode::OK);
+    let data: Value = serde_json::from_str(&body_text(response).await).unwrap();
+    assert_eq!(data["intensity"], 190);
+    assert_eq!(data["index"], "moderate");
+    assert_eq!(data["timeline_points"].as_array().unwrap().len(), 13);
+    let gas = data["generation_mix"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|fuel| fuel["fuel"] == "gas")
+        .unwrap();
+    assert_eq!(gas["carbon_intensity"], 394);
+
+    let sections = &data["sections"];
+    for section in ["intensity_chart", "generation_mix", "mix_history_chart"] {
+        let html = sections[section].as_str().unwrap();
+        assert!(html.contains("<svg"), "{section} has no chart: {html}");
+    }
+    assert!(
+        sections["intensity_chart"]
+            .as_str()
+            .unwrap()
+            .contains("<path")
+    );
+    assert!(sections["generation_mix"].as_str().unwrap().contains("gas"));
+}
+
+#[tokio::test]
+async fn intensity_chart_shades_bands_and_overlays_the_forecast() {
+    let server = mock_api().await;
+
+    let response = get(dashboard(&server, &[]), "/api/data").await;
+
+    let data: Value = serde_json::from_str(&body_text(response).await).unwrap();
+    let chart = data["sections"]["intensity_chart"].as_str().unwrap();
+    // The data runs from 183 to 238, across the moderate and high bands only
+    assert!(chart.contains("var(--band-moderate)"), "{chart}");
+    assert!(chart.contains("var(--band-high)"), "{chart}");
+    assert!(!chart.contains("var(--band-low)"), "{chart}");
+    assert!(chart.contains("<title>01:00–01:30: 190 gCO₂/kWh (forecast 193)</title>"));
+    assert!(chart.contains("<title>05:00–05:30: 226 gCO₂/kWh (forecast)</title>"));
+    // Forecasts for the ten periods with actuals were off by 2.7 on average
+    assert!(chart.contains(r#"class="published-forecast""#));
+    assert!(
+        chart.contains("Forecast off by 3 gCO₂/kWh on average"),
+        "{chart}"
+    );
+}
+
+#[tokio::test]
+async fn split_imports_show_each_interconnector() {
+    let server = mock_api().await;
+
+    let response = get(dashboard(&server, &["--split-imports"]), "/api/data").await;
+
+    let data: Value = serde_json::from_str(&body_text(response).await).unwrap();
+    let fuels: Vec<&str> = data["generation_mix"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|fuel| fuel["fuel"].as_str().unwrap())
+        .collect();
+    assert!(fuels.contains(&"norwegian imports"), "{fuels:?}");
+    assert!(!fuels.contains(&"imports"), "{fuels:?}");
+}
+
+#[tokio::test]
+async fn page_shows_the_headline_intensity() {
+    let server = mock_api().await;
+
+    let response = get(dashboard(&server, &[]), "/?theme=dark").await;
+
+    assert_eq!(response.status(), StatusCode::OK);
+    let page = body_text(response).await;
+    assert!(page.contains("190"));
+    assert!(page.contains("color-scheme: dark"));
+    assert!(page.contains(r#"href="/static/dashboard.css""#));
+}
+
+#[tokio::test]
+async fn page_load_time_is_split_into_fetch_and_render() {
+    let server = mock_api().await;
+
+    let response = get(dashboard(&server, &[]), "/").await;
+
+    assert_eq!(response.status(), StatusCode::OK);
+    let timing = response.headers()["server-timing"].to_str().unwrap();
+    let metrics: Vec<&str> = timing.split(", ").collect();
+    assert_eq!(metrics.len(), 2, "{timing}");
+    for (metric, name) in metrics.iter().zip(["fetch;", "render;"]) {
+        assert!(metric.starts_with(name), "{timing}");
+        let duration = metric.rsplit_once(";dur=").unwrap().1;
+        assert!(duration.parse::<f64>().is_ok_and(|ms| ms >= 0.0), "{timing}");
+    }
+}
+
+#[tokio::test]
+async fn no_legend_leaves_the_pie_chart_alone() {
+    let server = mock_api().await;
+
+    let page = body_text(get(dashboard(&server, &[]), "/").await).await;
+    assert!(page.contains(r#"<div class="legend">"#));
+    assert!(page.contains(r#"<div class="legend-item">"#));
+
+    let page = body_text(get(dashboard(&server, &["--no-legend"]), "/").await).await;
+    assert!(page.contains(r#"<div class="chart-container">"#));
+    assert!(!page.contains(r#"class="legend"#), "{page}");
+    assert!(!page.contains("legend-right"));
+}
+
+#[tokio::test]
+async fn static_assets_revalidate_with_their_etag() {
+    let server = mock_api().await;
+    let mut app = dashboard(&server, &[]);
+
+    let response = get(app.clone(), "/static/dashboard.css").await;
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.headers()[header::CONTENT_TYPE],
+        "text/css; charset=utf-8"
+    );
+    let etag = response.headers()[header::ETAG].clone();
+    assert!(body_text(response).await.contains(".intensity-value"));
+
+    let revalidate = Request::get("/static/dashboard.css")
+        .header(header::IF_NONE_MATCH, etag)
+        .body(Body::empty())
+        .unwrap();
+    let response = app.call(revalidate).await.unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+    assert!(body_text(response).await.is_empty());
+
+    let missing = get(app, "/static/missing.js").await;
+    assert_eq!(missing.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn responses_are_compressed_for_clients_that_accept_it() {
+    let server = mock_api().await;
+    let mut app = dashboard(&server, &[]);
+    let accepting = |uri: &str, encodings: &str| {
+        let request = Request::get(uri).header(header::ACCEPT_ENCODING, encodings).body(Body::empty()).unwrap();
+        from_client(request, CLIENT_ADDR)
+    };
+
+    let response = app.call(accepting("/", "gzip")).await.unwrap();
+    assert_eq!(response.headers()[header::CONTENT_ENCODING], "gzip");
+    let compressed = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let mut page = String::new();
+    std::io::Read::read_to_string(&mut flate2::read::GzDecoder::new(&compressed[..]), &mut page).unwrap();
+    assert!(page.contains("<svg"), "{page}");
+
+    // Static assets are precompressed, preferring Brotli, with a tag of
+    // their own per encoding
+    let response = app.call(accepting("/static/dashboard.css", "gzip, deflate, br")).await.unwrap();
+    assert_eq!(response.headers()[header::CONTENT_ENCODING], "br");
+    assert_eq!(response.headers()[header::VARY], "accept-encoding");
+    assert!(response.headers()[header::ETAG].to_str().unwrap().ends_with("-br\""));
+    let response = app.call(accepting("/static/dashboard.css", "br;q=0, gzip")).await.unwrap();
+    assert_eq!(response.headers()[header::CONTENT_ENCODING], "gzip");
+}
+
+#[tokio::test]
+async fn badge_shows_intensity_in_its_band_colour() {
+    let server = mock_api().await;
+
+    let response = get(dashboard(&server, &[]), "/badge.svg").await;
+
+    assert_eq!(response.headers()[header::CONTENT_TYPE], "image/svg+xml");
+    let cache_control = response.headers()[header::CACHE_CONTROL]
+        .to_str()
+        .unwrap()
+        .to_string();
+    assert!(
+        cache_control.starts_with("public, max-age="),
+        "{cache_control}"
+    );
+    let badge = body_text(response).await;
+    assert!(badge.contains("190 gCO₂/kWh"));
+    assert!(badge.contains(carbon_vibe::theme::LIGHT.bands[2]));
+}
+
+#[tokio::test]
+async fn calendar_has_an_event_per_low_carbon_window() {
+    let server = mock_api().await;
+
+    let response = get(dashboard(&server, &[]), "/calendar.ics?below=188").await;
+
+    assert_eq!(
+        response.headers()[header::CONTENT_TYPE],
+        "text/calendar; charset=utf-8"
+    );
+    let calendar = body_text(response).await;
+    assert!(calendar.starts_with("BEGIN:VCALENDAR\r\nVERSION:2.0\r\n"));
+    assert!(calendar.ends_with("END:VCALENDAR\r\n"));
+    // 186, 185 and 183 from 01:30 are the only run below 188
+    assert_eq!(calendar.matches("BEGIN:VEVENT").count(), 1, "{calendar}");
+    assert!(calendar.contains("\r\nDTSTART:20250115T013000Z\r\nDTEND:20250115T030000Z\r\n"));
+    assert!(calendar.contains("\r\nSUMMARY:Green hours: 185 gCO₂/kWh\r\n"));
+    assert!(calendar.contains("UID:20250115T013000Z-below-188-gb@carbon-vibe"));
+    // Long lines are folded at 75 octets
+    assert!(
+        calendar.split("\r\n").all(|line| line.len() <= 75),
+        "{calendar}"
+    );
+}
+
+#[tokio::test]
+async fn regions_page_maps_and_ranks_every_region_by_intensity() {
+    let server = mock_api().await;
+
+    let response = get(dashboard(&server, &[]), "/regions").await;
+
+    assert_eq!(response.status(), StatusCode::OK);
+    let page = body_text(response).await;
+    assert_eq!(page.matches("<rect x=\"160\"").count(), 17, "{page}");
+    assert!(page.contains("North Scotland: 12 gCO₂/kWh (very low)"));
+    assert!(page.contains("South East England: 250 gCO₂/kWh (high)"));
+    // The map has a shape per DNO region, filled with its band's colour
+    assert_eq!(page.matches("<path class=\"region\"").count(), 14);
+    assert!(page.contains("<title>London: 228 gCO₂/kWh (high)</title></path>"));
+    let position = |name: &str| page.find(&format!("<td>{name}</td>")).unwrap();
+    assert!(position("North Scotland") < position("Scotland"));
+    assert!(position("Scotland") < position("London"));
+    assert!(position("London") < position("South East England"));
+}
+
+#[tokio::test]
+async fn upstream_failures_are_reported_and_not_cached() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(500))
+        .mount(&server)
+        .await;
+    let app = dashboard(&server, &[]);
+
+    let response = get(app.clone(), "/api/data").await;
+    assert_eq!(response.status(), StatusCode::BAD_GATEWAY);
+    assert_eq!(response.headers()[header::CACHE_CONTROL], "no-store");
+
+    let badge = get(app, "/badge.svg").await;
+    assert_eq!(badge.status(), StatusCode::OK);
+    assert_eq!(badge.headers()[header::CACHE_CONTROL], "no-store");
+    assert!(body_text(badge).await.contains("unavailable"));
+}
+
+#[tokio::test]
+async fn a_failing_panel_leaves_the_rest_of_the_dashboard() {
+    let server = mock_api().await;
+    Mock::given(method("GET"))
+        .and(path("/generation"))
+        .respond_with(ResponseTemplate::new(500))
+        .with_priority(1)
+        .mount(&server)
+        .await;
+    let app = dashboard(&server, &[]);
+
+    let response = get(app.clone(), "/api/data").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let data: Value = serde_json::from_str(&body_text(response).await).unwrap();
+    assert_eq!(data["intensity"], 190);
+    assert_eq!(data["timeline_points"].as_array().unwrap().len(), 13);
+    assert_eq!(data["generation_mix"], serde_json::json!([]));
+    assert!(
+        data["errors"]["mix"]["error"]
+            .as_str()
+            .unwrap()
+            .contains("500")
+    );
+    assert_eq!(data["errors"]["mix"]["stale_since"], Value::Null);
+    assert!(data["errors"].get("timeline").is_none());
+    let sections = &data["sections"];
+    assert!(
+        sections["generation_mix"]
+            .as_str()
+            .unwrap()
+            .starts_with(r#"<div class="panel-error""#)
+    );
+    assert!(
+        sections["intensity_chart"]
+            .as_str()
+            .unwrap()
+            .contains("<svg")
+    );
+
+    let page = body_text(get(app.clone(), "/").await).await;
+    assert!(page.contains("Generation mix unavailable"), "{page}");
+    assert!(page.contains(r#"<span id="intensity-value">190</span>"#));
+
+    // Readiness still holds, but the failure is reported
+    let ready = get(app, "/readyz").await;
+    assert_eq!(ready.status(), StatusCode::OK);
+    let health: Value = serde_json::from_str(&body_text(ready).await).unwrap();
+    assert!(
+        health["upstream"]["last_error"]["error"]
+            .as_str()
+            .unwrap()
+            .starts_with("mix: ")
+    );
+}
+
+#[tokio::test]
+async fn clients_over_the_rate_limit_are_told_when_to_retry() {
+    let server = mock_api().await;
+    let app = dashboard(&server, &["--rate-limit", "60", "--rate-limit-burst", "2"]);
+
+    for _ in 0..2 {
+        let response = get(app.clone(), "/api/intensity").await;
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+    let limited = get(app.clone(), "/api/intensity").await;
+    assert_eq!(limited.status(), StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(limited.headers()[header::RETRY_AFTER], "1");
+
+    // Other clients and the health probes are unaffected
+    let other = get_from(app.clone(), "/api/intensity", "198.51.100.7:40000").await;
+    assert_eq!(other.status(), StatusCode::OK);
+    assert_eq!(get(app.clone(), "/healthz").await.status(), StatusCode::OK);
+
+    let metrics = body_text(get_from(app, "/metrics", "198.51.100.7:40000").await).await;
+    assert!(metrics.contains(r#"carbon_requests_rejected_total{reason="rate_limit"} 1"#));
+}
+
+#[tokio::test]
+async fn requests_over_the_concurrency_cap_are_turned_away() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(500).set_delay(Duration::from_millis(300)))
+        .mount(&server)
+        .await;
+    let app = dashboard(&server, &["--max-concurrent-requests", "1"]);
+
+    let (first, second) = tokio::join!(get(app.clone(), "/api/data"), async {
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        get(app.clone(), "/api/data").await
+    });
+
+    assert_eq!(first.status(), StatusCode::BAD_GATEWAY);
+    assert_eq!(second.status(), StatusCode::SERVICE_UNAVAILABLE);
+    assert!(second.headers().contains_key(header::RETRY_AFTER));
+}
+
+#[tokio::test]
+async fn chart_is_rasterised_at_the_requested_size() {
+    let server = mock_api().await;
+
+    let response = get(dashboard(&server, &[]), "/chart.png?hours=12&width=1000&height=360&theme=dark").await;
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()[header::CONTENT_TYPE], "image/png");
+    let png = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    assert_eq!(&png[..8], b"\x89PNG\r\n\x1a\n");
+    // IHDR: width then height, big-endian
+    assert_eq!(&png[16..24], &[0, 0, 3, 232, 0, 0, 1, 104]);
+
+    let response = get(dashboard(&server, &[]), "/chart.jpg").await;
+    assert_eq!(response.headers()[header::CONTENT_TYPE], "image/jpeg");
+    let jpeg = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    assert_eq!(&jpeg[..2], &[0xFF, 0xD8]);
+}
+
+#[tokio::test]
+async fn chart_as_a_data_uri_decodes_back_to_the_image() {
+    let server = mock_api().await;
+    let app = dashboard(&server, &[]);
+
+    let png = to_bytes(get(app.clone(), "/chart.png?hours=12").await.into_body(), usize::MAX).await.unwrap();
+    let response = get(app.clone(), "/chart.png?hours=12&data_uri=true").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()[header::CONTENT_TYPE], "text/plain; charset=utf-8");
+    let uri = body_text(response).await;
+    let encoded = uri.strip_prefix("data:image/png;base64,").unwrap();
+    assert_eq!(BASE64.decode(encoded).unwrap(), png);
+
+    let uri = body_text(get(app, "/chart.jpg?data_uri=true").await).await;
+    let encoded = uri.strip_prefix("data:image/jpeg;base64,").unwrap();
+    assert_eq!(&BASE64.decode(encoded).unwrap()[..2], &[0xFF, 0xD8]);
+}
+
+#[tokio::test]
+async fn eink_screen_is_a_one_bit_png_at_the_panel_size() {
+    let server = mock_api().await;
+
+    for (uri, size) in [
+        ("/eink", [0, 0, 3, 32, 0, 0, 1, 224]),
+        // A portrait panel keeps its own size, whichever way the content is turned
+        ("/eink?width=480&height=800&rotate=270&large=true", [0, 0, 1, 224, 0, 0, 3, 32]),
+    ] {
+        let response = get(dashboard(&server, &[]), uri).await;
+
+        assert_eq!(response.headers()[header::CONTENT_TYPE], "image/png");
+        let png = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(&png[16..24], &size, "{uri}");
+        // Bit depth 1, greyscale
+        assert_eq!(&png[24..26], &[1, 0], "{uri}");
+    }
+}
+
+#[tokio::test]
+async fn openapi_spec_describes_every_api_route() {
+    let server = mock_api().await;
+
+    let response = get(dashboard(&server, &[]), "/api/openapi.json").await;
+
+    assert_eq!(response.status(), StatusCode::OK);
+    let spec: Value = serde_json::from_str(&body_text(response).await).unwrap();
+    assert_eq!(spec["openapi"], "3.1.0");
+    for route in ["/api/data", "/api/intensity", "/api/generation", "/api/timeline", "/api/stream"] {
+        assert!(spec["paths"][route]["get"]["responses"]["200"].is_object(), "{route}");
+    }
+    let point = &spec["components"]["schemas"]["ApiPeriod"]["properties"];
+    assert_eq!(point["from"]["format"], "date-time");
+    // --units lb-mwh gives fractional figures
+    assert_eq!(point["intensity"]["type"], "number");
+    assert!(spec["components"]["schemas"]["IntensityIndex"]["enum"]
+        .as_array()
+        .unwrap()
+        .contains(&Value::from("very low")));
+
+    let docs = body_text(get(dashboard(&server, &[]), "/api/docs").await).await;
+    assert!(docs.contains("/api/openapi.json"));
+}
+
+#[tokio::test]
+async fn unchanged_page_and_api_data_are_not_modified() {
+    let server = mock_api().await;
+    let conditional = |uri: &str, header: header::HeaderName, value: &str| {
+        from_client(Request::get(uri).header(header, value).body(Body::empty()).unwrap(), CLIENT_ADDR)
+    };
+
+    for uri in ["/", "/api/data", "/api/timeline"] {
+        let response = get(dashboard(&server, &[]), uri).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let etag = response.headers()[header::ETAG].to_str().unwrap().to_string();
+        assert!(etag.starts_with("W/\""), "{etag}");
+        let last_modified = response.headers()[header::LAST_MODIFIED].to_str().unwrap().to_string();
+
+        // The same data again is a 304 with no body, by either validator
+        let mut app = dashboard(&server, &[]);
+        let response = app.call(conditional(uri, header::IF_NONE_MATCH, &etag)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED, "{uri}");
+        assert_eq!(response.headers()[header::ETAG], etag.as_str());
+        assert!(body_text(response).await.is_empty());
+        let response = app.call(conditional(uri, header::IF_MODIFIED_SINCE, &last_modified)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED, "{uri}");
+
+        // A tag from other data gets the full response
+        let response = app.call(conditional(uri, header::IF_NONE_MATCH, "W/\"0\"")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK, "{uri}");
+    }
+}
+
+#[tokio::test]
+async fn credentials_are_required_when_configured() {
+    let server = mock_api().await;
+    let app = dashboard(
+        &server,
+        &["--auth-token", "letmein", "--auth-user", "phone", "--auth-password", "s3cret"],
+    );
+    let authorised = |uri: &str, authorization: &str| {
+        let request = Request::get(uri)
+            .header(header::AUTHORIZATION, authorization)
+            .body(Body::empty())
+            .unwrap();
+        app.clone().call(from_client(request, CLIENT_ADDR))
+    };
+
+    let refused = get(app.clone(), "/api/intensity").await;
+    assert_eq!(refused.status(), StatusCode::UNAUTHORIZED);
+    let challenges: Vec<_> = refused.headers().get_all(header::WWW_AUTHENTICATE).iter().collect();
+    assert!(challenges[0].to_str().unwrap().starts_with("Basic realm="));
+    assert_eq!(challenges.len(), 2);
+
+    // The token as a bearer or a basic-auth password, or the user's password
+    for authorization in ["Bearer letmein", "Basic OmxldG1laW4=", "basic cGhvbmU6czNjcmV0"] {
+        let response = authorised("/api/intensity", authorization).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK, "{authorization}");
+    }
+    for authorization in ["Bearer letmeout", "Basic cGhvbmU6d3Jvbmc=", "Basic !!!", "letmein"] {
+        let response = authorised("/", authorization).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED, "{authorization}");
+    }
+
+    // Probes and the page's assets stay open
+    assert_eq!(get(app.clone(), "/healthz").await.status(), StatusCode::OK);
+    assert_eq!(get(app.clone(), "/static/dashboard.css").await.status(), StatusCode::OK);
+
+    let metrics = body_text(authorised("/metrics", "Bearer letmein").await.unwrap()).await;
+    assert!(metrics.contains(r#"carbon_requests_rejected_total{reason="unauthorised"} 5"#), "{metrics}");
+}
+
+#[tokio::test]
+async fn factors_are_fetched_once_and_overridden_from_a_file() {
+    let server = mock_api().await;
+    let path = std::env::temp_dir().join(format!("carbon-factors-{pid}.toml", pid = std::process::id()));
+    std::fs::write(&path, "gas = 490\n").unwrap();
+    let app = dashboard(&server, &["--factors", path.to_str().unwrap()]);
+    std::fs::remove_file(&path).unwrap();
+
+    for _ in 0..2 {
+        let response = get(app.clone(), "/api/data").await;
+        let data: Value = serde_json::from_str(&body_text(response).await).unwrap();
+        let gas = data["generation_mix"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|fuel| fuel["fuel"] == "gas")
+            .unwrap();
+        assert_eq!(gas["carbon_intensity"], 490);
+    }
+
+    // The data is uncached, but the factors aren't fetched again
+    let requests = server.received_requests().await.unwrap();
+    let fetches = |path: &str| requests.iter().filter(|request| request.url.path() == path).count();
+    assert_eq!(fetches("/intensity"), 2);
+    assert_eq!(fetches("/intensity/factors"), 1);
+}
+
+#[tokio::test]
+async fn mix_times_factors_is_shown_beside_the_reported_intensity() {
+    let server = mock_api().await;
+
+    let response = get(dashboard(&server, &[]), "/api/intensity").await;
+    let data: Value = serde_json::from_str(&body_text(response).await).unwrap();
+    assert_eq!((data["intensity"].as_i64(), data["mix_intensity"].as_i64()), (Some(190), Some(188)));
+
+    // Within the threshold it's shown plainly; past it, flagged
+    let check = |args: &'static [&'static str]| {
+        let app = dashboard(&server, args);
+        async move {
+            let data: Value = serde_json::from_str(&body_text(get(app, "/api/data").await).await).unwrap();
+            data["sections"]["mix_check"].as_str().unwrap().to_string()
+        }
+    };
+    let plain = check(&[]).await;
+    assert!(plain.contains(r#"class="mix-check""#), "{plain}");
+    assert!(plain.contains("Mix × factors: 188 gCO₂/kWh"), "{plain}");
+    let flagged = check(&["--mix-check-threshold", "0"]).await;
+    assert!(flagged.contains("mix-check diverges"), "{flagged}");
+    assert!(flagged.contains(", 2 below the reported figure"), "{flagged}");
+}
+
+#[tokio::test]
+async fn stream_sends_the_current_period_as_a_json_line_and_stays_open() {
+    let server = mock_api().await;
+
+    let request = Request::get("/api/stream")
+        .header(header::ACCEPT_ENCODING, "gzip")
+        .body(Body::empty())
+        .unwrap();
+    let request = from_client(request, CLIENT_ADDR);
+    let response = dashboard(&server, &[]).call(request).await.unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()[header::CONTENT_TYPE], "application/x-ndjson");
+    // Compression would hold lines back
+    assert!(response.headers().get(header::CONTENT_ENCODING).is_none());
+
+    let mut body = response.into_body().into_data_stream();
+    let first = tokio::time::timeout(Duration::from_secs(5), body.next())
+        .await
+        .unwrap()
+        .unwrap()
+        .unwrap();
+    let line = std::str::from_utf8(&first).unwrap();
+    assert!(line.ends_with('\n') && line.matches('\n').count() == 1, "{line:?}");
+    let line: Value = serde_json::from_str(line).unwrap();
+    assert!(line["period"]["from"].is_string());
+    assert!(line["period"]["intensity"].is_i64());
+    assert_eq!(line["mix_intensity"], 188);
+    assert!(line["generation_mix"].as_array().is_some_and(|mix| !mix.is_empty()));
+
+    // Nothing more until the next period begins
+    assert!(tokio::time::timeout(Duration::from_millis(200), body.next()).await.is_err());
+}
+
+#[tokio::test]
+async fn websocket_clients_get_the_current_figures_on_connecting_and_each_refresh() {
+    let server = mock_api().await;
+    let rules = std::env::temp_dir().join(format!("carbon-ws-alerts-{pid}.toml", pid = std::process::id()));
+    std::fs::write(&rules, "[[rules]]\nname = \"Clean\"\nwhen = \"intensity < 100\"\nchannels = [\"stdout\"]\n").unwrap();
+    let addr = serve(&server, &["--alerts", rules.to_str().unwrap(), "--refresh-interval", "1"]).await;
+
+    let (mut socket, _) = tokio_tungstenite::connect_async(format!("ws://{addr}/ws")).await.unwrap();
+    let mut next_update = async || loop {
+        let message = tokio::time::timeout(Duration::from_secs(5), socket.next())
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+        let message: Value = serde_json::from_str(message.to_text().unwrap()).unwrap();
+        if message["type"] == "update" {
+            return message;
+        }
+    };
+    let message = next_update().await;
+    // The fixture's latest period
+    assert_eq!(message["period"]["intensity"], 238);
+    assert_eq!(message["mix_intensity"], 188);
+    assert!(message["generation_mix"].as_array().is_some_and(|mix| !mix.is_empty()));
+
+    // The next refresh finds a revised forecast and pushes it unasked
+    let revised = common::fixture("intensity_range.json").replace("\"forecast\": 238", "\"forecast\": 150");
+    Mock::given(method("GET"))
+        .and(path_regex(r"^/intensity/[^/]+Z/[^/]+Z$"))
+        .respond_with(ResponseTemplate::new(200).set_body_raw(revised, "application/json"))
+        .with_priority(1)
+        .mount(&server)
+        .await;
+    let mut message = next_update().await;
+    // A refresh already under way may repeat the first figures
+    if message["period"]["intensity"] == 238 {
+        message = next_update().await;
+    }
+    assert_eq!(message["period"]["intensity"], 150);
+    std::fs::remove_file(&rules).unwrap();
+
+    // A rules file that doesn't parse stops the server starting
+    let path = std::env::temp_dir().join(format!("carbon-ws-bad-alerts-{pid}.toml", pid = std::process::id()));
+    std::fs::write(&path, "[[rules]]\nname = \"Bad\"\nwhen = \"intensity <\"\nchannels = [\"stdout\"]\n").unwrap();
+    let options = dashboard_options(&server, &["--alerts", path.to_str().unwrap()]);
+    std::fs::remove_file(&path).unwrap();
+    assert!(matches!(router(options), Err(CarbonError::AlertRules { .. })));
+}
+
+#[tokio::test]
+async fn concurrent_page_loads_share_one_upstream_fetch() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(500).set_delay(Duration::from_millis(200)))
+        .mount(&server)
+        .await;
+    // Uncached, and failing, so nothing is kept between loads
+    let app = dashboard(&server, &[]);
+
+    let loads = (0..10).map(|_| get(app.clone(), "/api/data"));
+    for response in futures_util::future::join_all(loads).await {
+        assert_eq!(response.status(), StatusCode::BAD_GATEWAY);
+    }
+
+    // One page load's worth: the current period, generation mix and
+    // timeline, rather than ten of each
+    let requests = server.received_requests().await.unwrap();
+    assert_eq!(requests.len(), 3, "{requests:?}");
+}
+
+#[tokio::test]
+async fn advice_waits_for_a_greener_time_in_the_appliances_window() {
+    // 24 hours from the current period at 200 gCO₂/kWh, with two periods at
+    // 100 five hours in
+    let start = SettlementPeriod::containing(Utc::now()).from();
+    let periods: Vec<Value> = (0..48)
+        .map(|i| {
+            let from = start + TimeDelta::minutes(30 * i);
+            let intensity = if (10..12).contains(&i) { 100 } else { 200 };
+            serde_json::json!({
+                "from": from.format("%Y-%m-%dT%H:%MZ").to_string(),
+                "to": (from + TimeDelta::minutes(30)).format("%Y-%m-%dT%H:%MZ").to_string(),
+                "intensity": { "forecast": intensity, "actual": null, "index": "moderate" }
+            })
+        })
+        .collect();
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({ "data": periods })))
+        .mount(&server)
+        .await;
+
+    // Appliances sit beside the command tables without upsetting them
+    let config = Config::parse(
+        "config.toml".into(),
+        "[web]\ncache-ttl = 0\n\n[appliances.washing-machine]\nkwh = 1.5\nduration = \"1h\"\nwithin = \"12h\"\n",
+    )
+    .unwrap();
+    let appliances = config.appliances().unwrap();
+    let options = dashboard_options(&server, &[]);
+    let app = router(options.with_appliances(appliances)).unwrap();
+
+    let response = get(app.clone(), "/api/advice/washing-machine").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let advice: Value = serde_json::from_str(&body_text(response).await).unwrap();
+    assert_eq!(advice["verdict"], "wait", "{advice}");
+    assert_eq!(advice["now"]["emissions_g"], 300.0);
+    let greener = start + TimeDelta::hours(5);
+    assert_eq!(advice["greenest"]["from"], greener.to_rfc3339_opts(SecondsFormat::Secs, true));
+    assert_eq!(advice["greenest"]["emissions_g"], 150.0);
+    assert_eq!(advice["greenest"]["saving_percent"], 50.0);
+
+    let response = get(app, "/api/advice/dryer").await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    // A profile that can't finish inside the forecast is refused up front
+    let config = Config::parse(
+        "config.toml".into(),
+        "[appliances.dryer]\nkwh = 2\nduration = \"3h\"\nwithin = \"48h\"\n",
+    )
+    .unwrap();
+    let error = config.appliances().unwrap_err();
+    assert!(error.contains("[appliances.dryer]"), "{error}");
+}
+
+#[test]
+fn headline_compares_against_todays_actuals_or_a_fixed_baseline() {
+    Zone::parse_from(["carbon", "--timezone", "UTC"]).timezone.apply();
+    let point = |from: &str, intensity: i32, actual: bool| IntensityPoint {
+        from: utc(from),
+        to: utc(from) + TimeDelta::minutes(30),
+        intensity,
+        actual: actual.then_some(intensity),
+        forecast: Some(intensity),
+        index: None,
+    };
+    let today = NaiveDate::from_ymd_opt(2025, 6, 26).unwrap();
+    let timeline = [
+        point("2025-06-25T23:30:00Z", 500, true),
+        point("2025-06-26T00:00:00Z", 100, true),
+        point("2025-06-26T00:30:00Z", 300, true),
+        point("2025-06-26T01:00:00Z", 900, false),
+    ];
+
+    // Yesterday's reading and the forecast are left out of the average
+    let (value, description) = baseline_reference(&Baseline::Daily, &timeline, today).unwrap();
+    assert_eq!(value, 200.0);
+    assert_eq!(description, "today's average");
+    assert_eq!(
+        describe_baseline_delta(176, value, &description).as_deref(),
+        Some("12% below today's average")
+    );
+    assert!(baseline_reference(&Baseline::Daily, &timeline[3..], today).is_none());
+
+    let (value, description) = baseline_reference(&Baseline::Fixed(150), &[], today).unwrap();
+    assert_eq!(value, 150.0);
+    assert_eq!(
+        describe_baseline_delta(180, value, &description).as_deref(),
+        Some("20% above the 150 gCO₂/kWh baseline")
+    );
+    assert_eq!(
+        describe_baseline_delta(150, value, &description).as_deref(),
+        Some("level with the 150 gCO₂/kWh baseline")
+    );
+    assert!(describe_baseline_delta(150, 0.0, "a zero baseline").is_none());
+}
+
+#[tokio::test]
+async fn palettes_below_the_minimum_contrast_only_warn() {
+    let server = mock_api().await;
+
+    // No palette has every colour at 21:1, so each theme warns
+    assert!(router(dashboard_options(&server, &["--min-contrast", "21"])).is_ok());
+}
+
+#[tokio::test]
+async fn idle_connections_close_at_the_header_read_timeout_and_keep_alive_can_be_off() {
+    let server = mock_api().await;
+    // What the server sends on a new connection after one request, if it
+    // closes the connection within `wait` seconds
+    let closed_within = |addr, wait| async move {
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream.write_all(b"GET /healthz HTTP/1.1\r\nHost: localhost\r\n\r\n").await.unwrap();
+        let mut received = Vec::new();
+        tokio::time::timeout(Duration::from_secs(wait), stream.read_to_end(&mut received))
+            .await
+            .is_ok()
+            .then(|| String::from_utf8(received).unwrap())
+    };
+
+    // Kept open for the next request until it's overdue
+    let addr = serve(&server, &["--header-read-timeout", "1"]).await;
+    let response = closed_within(addr, 5).await.unwrap();
+    assert!(response.starts_with("HTTP/1.1 200 OK"), "{response}");
+    assert!(!response.contains("connection: close"), "{response}");
+    let addr = serve(&server, &[]).await;
+    assert_eq!(closed_within(addr, 2).await, None);
+
+    // Closed straight after the response
+    let addr = serve(&server, &["--no-keep-alive"]).await;
+    let response = closed_within(addr, 5).await.unwrap();
+    assert!(response.starts_with("HTTP/1.1 200 OK"), "{response}");
+    assert!(response.contains("connection: close"), "{response}");
+}